@@ -1,5 +1,8 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use epub::doc::EpubDoc;
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
 
 fn epub_open_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("Epub Open");
@@ -27,6 +30,149 @@ fn epub_grab_resource_benchmark(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, epub_open_benchmark, epub_grab_resource_benchmark);
+fn epub_read_all_chapters_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Epub Read All Chapters");
+    let doc = EpubDoc::new("tests/docs/charles-dickens_a-christmas-carol.epub").unwrap();
+
+    group.bench_function("Fresh buffers", |bencher| {
+        bencher.iter(|| {
+            for item in doc.spine() {
+                let _ = doc.get_resource(&item.idref).unwrap();
+            }
+        })
+    });
+
+    group.bench_function("Reused buffer", |bencher| {
+        let mut buf = Vec::new();
+        bencher.iter(|| {
+            for item in doc.spine() {
+                let _ = doc.get_resource_into(&item.idref, &mut buf).unwrap();
+            }
+        })
+    });
+
+    group.finish();
+}
+
+/// An in-memory epub with `chapters` chapters and a ToC entry for each of them.
+fn synthetic_epub(chapters: usize) -> Vec<u8> {
+    let (mut manifest, mut spine, mut nav_map) = (String::new(), String::new(), String::new());
+    for i in 0..chapters {
+        let _ = write!(
+            manifest,
+            r#"<item id="c{i}" href="c{i}.xhtml" media-type="application/xhtml+xml"/>"#
+        );
+        let _ = write!(spine, r#"<itemref idref="c{i}"/>"#);
+        let _ = write!(
+            nav_map,
+            r#"<navPoint id="n{i}" playOrder="{i}"><navLabel><text>{i}</text></navLabel><content src="c{i}.xhtml"/></navPoint>"#
+        );
+    }
+    let opf = format!(
+        r#"<package xmlns="http://www.idpf.org/2007/opf" version="2.0" unique-identifier="uid">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:identifier id="uid">bench</dc:identifier></metadata>
+<manifest><item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>{manifest}</manifest>
+<spine toc="ncx">{spine}</spine>
+</package>"#
+    );
+    let ncx = format!(
+        r#"<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1"><navMap>{nav_map}</navMap></ncx>"#
+    );
+    let container = r#"<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#;
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for (name, content) in [
+        ("mimetype", "application/epub+zip"),
+        ("META-INF/container.xml", container),
+        ("OEBPS/content.opf", &opf),
+        ("OEBPS/toc.ncx", &ncx),
+    ] {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+    }
+
+    zip.finish().unwrap().into_inner()
+}
+
+fn epub_toc_spine_map_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Epub ToC Spine Map");
+
+    for size in [500, 1000, 2000] {
+        let doc = EpubDoc::from_reader(Cursor::new(synthetic_epub(size))).unwrap();
+
+        group.bench_with_input(
+            BenchmarkId::new("toc_spine_map", size),
+            &doc,
+            |bencher, doc| bencher.iter(|| doc.toc_spine_map()),
+        );
+        group.bench_with_input(BenchmarkId::new("Per entry", size), &doc, |bencher, doc| {
+            bencher.iter(|| {
+                doc.toc_flat()
+                    .filter_map(|(_, nav)| nav.content.as_ref())
+                    .map(|content| doc.resource_uri_to_chapter(content))
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn epub_path_lookup_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Epub Path Lookup");
+
+    let size = 5000;
+    let doc = EpubDoc::from_reader(Cursor::new(synthetic_epub(size))).unwrap();
+    let paths: Vec<_> = doc
+        .resources()
+        .values()
+        .map(|item| item.path.clone())
+        .collect();
+
+    group.bench_with_input(
+        BenchmarkId::new("resource_id_by_path", size),
+        &paths,
+        |bencher, paths| {
+            bencher.iter(|| {
+                paths
+                    .iter()
+                    .map(|path| doc.resource_id_by_path(path))
+                    .collect::<Vec<_>>()
+            })
+        },
+    );
+    // What the lookups used to do
+    group.bench_with_input(
+        BenchmarkId::new("Linear scan", size),
+        &paths,
+        |bencher, paths| {
+            bencher.iter(|| {
+                paths
+                    .iter()
+                    .map(|path| {
+                        doc.resources()
+                            .iter()
+                            .find(|(_, item)| item.path == *path)
+                            .map(|(id, _)| id.as_str())
+                    })
+                    .collect::<Vec<_>>()
+            })
+        },
+    );
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    epub_open_benchmark,
+    epub_grab_resource_benchmark,
+    epub_read_all_chapters_benchmark,
+    epub_toc_spine_map_benchmark,
+    epub_path_lookup_benchmark
+);
 
 criterion_main!(benches);
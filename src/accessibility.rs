@@ -0,0 +1,200 @@
+//! The accessibility metadata of a book, as described by the EPUB Accessibility specification.
+//!
+//! Publishers declare how a book can be perceived (`schema:accessMode`), what helps (`schema:accessibilityFeature`),
+//! what could harm (`schema:accessibilityHazard`) and who checked it (`a11y:certifiedBy`), see
+//! [EpubDoc::accessibility].
+
+use crate::doc::EpubDoc;
+use std::io::{Read, Seek};
+
+/// The accessibility metadata of a book, see [EpubDoc::accessibility].
+///
+/// Every list is in document order. Values are kept as written, apart from surrounding whitespace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccessibilityMetadata {
+    /// `schema:accessMode`, the ways the content can be perceived
+    pub access_modes: Vec<AccessMode>,
+    /// `schema:accessModeSufficient`, each a set of access modes which suffices to consume the whole book
+    pub access_modes_sufficient: Vec<Vec<AccessMode>>,
+    /// `schema:accessibilityFeature`, e.g. `alternativeText` or `structuralNavigation`
+    pub features: Vec<String>,
+    /// `schema:accessibilityHazard`
+    pub hazards: Vec<Hazard>,
+    /// `schema:accessibilitySummary`, a human readable description
+    pub summary: Option<String>,
+    /// `a11y:certifiedBy`, the parties which evaluated the book
+    pub certified_by: Vec<String>,
+    /// `dcterms:conformsTo`, the accessibility standards the book conforms to, as a name or a link
+    pub conforms_to: Vec<String>,
+    /// Any other `a11y:` or `schema:access...` properties, as (property, value), sorted by property
+    pub other: Vec<(String, String)>,
+}
+
+/// A way to perceive content, see [AccessibilityMetadata::access_modes].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AccessMode {
+    Auditory,
+    Tactile,
+    Textual,
+    Visual,
+    /// A more specific mode from the schema.org vocabulary, e.g. `chartOnVisual`, or an unknown mode
+    Other(String),
+}
+
+impl AccessMode {
+    fn parse(mode: &str) -> Self {
+        match mode {
+            "auditory" => AccessMode::Auditory,
+            "tactile" => AccessMode::Tactile,
+            "textual" => AccessMode::Textual,
+            "visual" => AccessMode::Visual,
+            _ => AccessMode::Other(mode.to_string()),
+        }
+    }
+}
+
+/// A physiological hazard, see [AccessibilityMetadata::hazards].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Hazard {
+    Flashing,
+    NoFlashingHazard,
+    MotionSimulation,
+    NoMotionSimulationHazard,
+    Sound,
+    NoSoundHazard,
+    /// `none`, there are no hazards at all
+    None,
+    /// `unknown`, the book wasn't checked for hazards
+    Unknown,
+    Other(String),
+}
+
+impl Hazard {
+    fn parse(hazard: &str) -> Self {
+        match hazard {
+            "flashing" => Hazard::Flashing,
+            "noFlashingHazard" => Hazard::NoFlashingHazard,
+            "motionSimulation" => Hazard::MotionSimulation,
+            "noMotionSimulationHazard" => Hazard::NoMotionSimulationHazard,
+            "sound" => Hazard::Sound,
+            "noSoundHazard" => Hazard::NoSoundHazard,
+            "none" => Hazard::None,
+            "unknown" => Hazard::Unknown,
+            _ => Hazard::Other(hazard.to_string()),
+        }
+    }
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns the accessibility metadata of the book, from both EPUB3 `property` and EPUB2 `name` metas.
+    ///
+    /// The values of the access modes, features and hazards are split on commas and whitespace, as some publishers
+    /// list several in one `meta`. Each `schema:accessModeSufficient` is a comma separated set in its own right.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let accessibility = doc.accessibility();
+    /// assert!(accessibility.access_modes.is_empty());
+    /// assert_eq!(None, accessibility.summary);
+    /// ```
+    pub fn accessibility(&self) -> AccessibilityMetadata {
+        let metadata = self.metadata();
+        let values = |name: &str| {
+            metadata
+                .get(name)
+                .into_iter()
+                .flatten()
+                .map(|node| node.content.trim())
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<_>>()
+        };
+        let list = |name: &str| {
+            values(name)
+                .into_iter()
+                .flat_map(split_list)
+                .collect::<Vec<_>>()
+        };
+
+        // EPUB 3.0 books link to the conformance document instead
+        let conformance_links = metadata
+            .get("link")
+            .into_iter()
+            .flatten()
+            .filter(|link| {
+                link.find_attr("rel").is_some_and(|rel| {
+                    rel.split_ascii_whitespace()
+                        .any(|rel| rel == "dcterms:conformsTo")
+                })
+            })
+            .filter_map(|link| link.find_attr("href"));
+
+        let mut other: Vec<_> = metadata
+            .iter()
+            .filter(|(name, _)| {
+                (name.starts_with("a11y:") || name.starts_with("schema:access"))
+                    && !KNOWN_PROPERTIES.contains(&name.as_str())
+            })
+            .flat_map(|(name, nodes)| {
+                nodes
+                    .iter()
+                    .map(move |node| (name.clone(), node.content.trim().to_string()))
+            })
+            .collect();
+        // Stable, so the values of a property stay in document order
+        other.sort_by(|a, b| a.0.cmp(&b.0));
+
+        AccessibilityMetadata {
+            access_modes: list("schema:accessMode")
+                .into_iter()
+                .map(AccessMode::parse)
+                .collect(),
+            access_modes_sufficient: values("schema:accessModeSufficient")
+                .into_iter()
+                .map(|set| split_list(set).map(AccessMode::parse).collect())
+                .collect(),
+            features: list("schema:accessibilityFeature")
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            hazards: list("schema:accessibilityHazard")
+                .into_iter()
+                .map(Hazard::parse)
+                .collect(),
+            summary: values("schema:accessibilitySummary")
+                .first()
+                .map(|summary| summary.to_string()),
+            certified_by: values("a11y:certifiedBy")
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            conforms_to: values("dcterms:conformsTo")
+                .into_iter()
+                .chain(conformance_links)
+                .map(str::to_string)
+                .collect(),
+            other,
+        }
+    }
+}
+
+/// The properties with a field in [AccessibilityMetadata].
+const KNOWN_PROPERTIES: &[&str] = &[
+    "schema:accessMode",
+    "schema:accessModeSufficient",
+    "schema:accessibilityFeature",
+    "schema:accessibilityHazard",
+    "schema:accessibilitySummary",
+    "a11y:certifiedBy",
+];
+
+fn split_list(values: &str) -> impl Iterator<Item = &str> {
+    values
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|value| !value.is_empty())
+}
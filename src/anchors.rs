@@ -0,0 +1,137 @@
+//! The `id` attributes of a chapter, which fragments (`chapter.xhtml#note-1`) point at.
+//!
+//! [EpubDoc::chapter_anchors] scans a chapter once and caches the result, so resolving many links into the same
+//! chapter stays cheap.
+
+use crate::doc::EpubDoc;
+use crate::error::Result;
+use crate::xmlutils::XMLError;
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use std::sync::{Arc, PoisonError};
+
+/// An element with an `id` attribute, see [EpubDoc::chapter_anchors].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnchorInfo {
+    /// The (lowercase) name of the element, e.g. `aside`
+    pub element_name: String,
+    /// The number of characters of text content before the element, as written in the source
+    pub char_offset: usize,
+}
+
+/// The anchors of a single chapter, as cached by the [EpubDoc].
+#[derive(Debug, Default)]
+pub(crate) struct ChapterAnchors {
+    anchors: HashMap<String, AnchorInfo>,
+    /// The ids which occurred more than once, in document order
+    duplicates: Vec<String>,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns every `id` in the chapter at `spine_index`, with the element it is on.
+    ///
+    /// The chapter is only scanned the first time, later calls are served from a cache. Duplicate ids (invalid,
+    /// but common) keep their first occurrence, see [EpubDoc::duplicate_anchors].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// let anchors = doc.chapter_anchors(3).unwrap();
+    /// assert!(anchors.values().all(|anchor| !anchor.element_name.is_empty()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::InvalidPosition](crate::error::ArchiveError::InvalidPosition) if `spine_index` is out
+    /// of range, or an error if the chapter can't be read.
+    pub fn chapter_anchors(&self, spine_index: usize) -> Result<HashMap<String, AnchorInfo>> {
+        Ok(self.cached_anchors(spine_index)?.anchors.clone())
+    }
+
+    /// Returns the ids which occur more than once in the chapter at `spine_index`, in document order.
+    ///
+    /// # Errors
+    ///
+    /// See [EpubDoc::chapter_anchors].
+    pub fn duplicate_anchors(&self, spine_index: usize) -> Result<Vec<String>> {
+        Ok(self.cached_anchors(spine_index)?.duplicates.clone())
+    }
+
+    /// Returns whether the chapter at `spine_index` has an element with the given `id`.
+    ///
+    /// # Errors
+    ///
+    /// See [EpubDoc::chapter_anchors].
+    pub fn has_anchor(&self, spine_index: usize, id: &str) -> Result<bool> {
+        Ok(self.cached_anchors(spine_index)?.anchors.contains_key(id))
+    }
+
+    pub(crate) fn cached_anchors(&self, spine_index: usize) -> Result<Arc<ChapterAnchors>> {
+        let cache = self
+            .anchor_cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(anchors) = cache.get(&spine_index) {
+            return Ok(anchors.clone());
+        }
+        // Don't hold the lock while reading the chapter
+        drop(cache);
+
+        let (_, content) = self.chapter_content(spine_index)?;
+        let anchors = Arc::new(scan_anchors(&content)?);
+        self.anchor_cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(spine_index, anchors.clone());
+
+        Ok(anchors)
+    }
+}
+
+/// Collect the anchors of a (X)HTML document in a single pass.
+fn scan_anchors(html: &str) -> Result<ChapterAnchors, XMLError> {
+    let chapter = RefCell::new(ChapterAnchors::default());
+    let chars = Cell::new(0);
+
+    let settings = lol_html::Settings {
+        element_content_handlers: vec![lol_html::element!("[id]", |el| {
+            let Some(id) = el.get_attribute("id") else {
+                return Ok(());
+            };
+            let mut chapter = chapter.borrow_mut();
+            let ChapterAnchors {
+                anchors,
+                duplicates,
+            } = &mut *chapter;
+            match anchors.entry(id) {
+                Entry::Vacant(entry) => {
+                    entry.insert(AnchorInfo {
+                        element_name: el.tag_name().to_ascii_lowercase(),
+                        char_offset: chars.get(),
+                    });
+                }
+                Entry::Occupied(entry) if !duplicates.contains(entry.key()) => {
+                    duplicates.push(entry.key().clone());
+                }
+                Entry::Occupied(_) => {}
+            }
+            Ok(())
+        })],
+        document_content_handlers: vec![lol_html::doc_text!(|text| {
+            chars.set(chars.get() + text.as_str().chars().count());
+            Ok(())
+        })],
+        ..lol_html::Settings::default()
+    };
+
+    let mut rewriter = lol_html::HtmlRewriter::new(settings, |_: &[u8]| {});
+    rewriter.write(html.as_bytes())?;
+    rewriter.end()?;
+
+    Ok(chapter.into_inner())
+}
@@ -0,0 +1,207 @@
+//! Diagnostics for the hrefs used throughout an epub.
+//!
+//! Plenty of books in the wild use hrefs which are not quite spec compliant (backslashes, absolute paths, ...).
+//! The path resolution of this crate tries to work around those, [EpubDoc::path_anomalies] reports which
+//! workarounds were needed for a given book.
+
+use crate::doc::EpubDoc;
+use crate::error::Result;
+use crate::utils;
+use crate::xmlutils;
+use crate::xmlutils::RoxmlNodeExt;
+use std::io::{Read, Seek};
+use std::path::{Component, Path};
+
+/// The part of the epub an href was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HrefLocation {
+    /// An `item` in the `content.opf` manifest
+    Manifest,
+    /// An entry in the NCX or EPUB3 navigation document
+    Toc,
+    /// A `reference` in the `content.opf` guide
+    Guide,
+}
+
+/// Classification of a single href.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathAnomalyKind {
+    /// A plain relative href, no workarounds needed
+    Clean,
+    /// Uses `\` as a path separator
+    Backslash,
+    /// Starts with a `/`, and is therefore relative to the container root instead of the referencing document
+    Absolute,
+    /// Has more `..` segments than there are parent directories, pointing outside the container
+    EscapesRoot,
+    /// Contains percent-encoded characters, which may or may not be encoded in the zip entry name
+    PercentEncoded,
+    /// An absolute URL pointing outside the epub
+    Remote,
+}
+
+impl PathAnomalyKind {
+    /// Classify the given `href`, as found in a document located in the `base` directory.
+    ///
+    /// When an href exhibits several anomalies the most severe one is reported, in the order
+    /// [Remote](Self::Remote), [Absolute](Self::Absolute), [Backslash](Self::Backslash),
+    /// [EscapesRoot](Self::EscapesRoot), and [PercentEncoded](Self::PercentEncoded).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::anomalies::PathAnomalyKind;
+    /// assert_eq!(PathAnomalyKind::Clean, PathAnomalyKind::classify("Text/001.xhtml", "OEBPS"));
+    /// assert_eq!(PathAnomalyKind::Backslash, PathAnomalyKind::classify("Text\\001.xhtml", "OEBPS"));
+    /// assert_eq!(PathAnomalyKind::EscapesRoot, PathAnomalyKind::classify("../../001.xhtml", "OEBPS"));
+    /// ```
+    pub fn classify(href: &str, base: impl AsRef<Path>) -> PathAnomalyKind {
+        let (href, _) = crate::utils::split_fragment(href);
+
+        if utils::is_remote(href) {
+            PathAnomalyKind::Remote
+        } else if href.starts_with('/') || href.starts_with('\\') {
+            PathAnomalyKind::Absolute
+        } else if href.contains('\\') {
+            PathAnomalyKind::Backslash
+        } else if escapes_root(base.as_ref(), href) {
+            PathAnomalyKind::EscapesRoot
+        } else if has_percent_escape(href) {
+            PathAnomalyKind::PercentEncoded
+        } else {
+            PathAnomalyKind::Clean
+        }
+    }
+}
+
+/// An href which needed a workaround to be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathAnomaly {
+    /// Where the href was found
+    pub location: HrefLocation,
+    /// The href, exactly as written in the epub
+    pub href: String,
+    /// What is wrong with it
+    pub kind: PathAnomalyKind,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Scans every manifest, guide, and ToC href of this epub and reports those which are not [PathAnomalyKind::Clean].
+    ///
+    /// This re-reads the package and ToC documents, so it's intended for diagnostics rather than hot paths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use epub::anomalies::PathAnomalyKind;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let anomalies = doc.path_anomalies().unwrap();
+    /// assert!(anomalies.iter().all(|a| a.kind == PathAnomalyKind::PercentEncoded));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the package document can't be read or parsed.
+    pub fn path_anomalies(&self) -> Result<Vec<PathAnomaly>> {
+        let mut output = Vec::new();
+        let root_base = self.root_base.as_path();
+
+        let content = self.get_resource_by_path(&self.root_file)?;
+        let txt = xmlutils::ensure_utf8(&content)?;
+        let recovered;
+        let root = match xmlutils::parse_xml(&txt) {
+            Ok(root) => root,
+            // The book only opened once its escaped package document was recovered, see ParseMode::Lenient
+            Err(e) => match xmlutils::unescape_document(&txt) {
+                Some((unescaped, _)) => {
+                    recovered = unescaped;
+                    xmlutils::parse_xml(&recovered)?
+                }
+                None => return Err(e),
+            },
+        };
+
+        let mut push = |location, href: &str, base: &Path| {
+            let kind = PathAnomalyKind::classify(href, base);
+            if kind != PathAnomalyKind::Clean {
+                output.push(PathAnomaly {
+                    location,
+                    href: href.to_string(),
+                    kind,
+                });
+            }
+        };
+
+        for item in root.descendants().filter(|r| r.has_tag_name("item")) {
+            if let Some(href) = item.attribute("href") {
+                push(HrefLocation::Manifest, href, root_base);
+            }
+        }
+
+        for reference in root.descendants().filter(|r| r.has_tag_name("reference")) {
+            if let Some(href) = reference.attribute("href") {
+                push(HrefLocation::Guide, href, root_base);
+            }
+        }
+
+        // The ToC documents the parser settled on, including the NCX and nav found without being declared as such
+        let toc_paths = [self.toc_id(), self.nav_id()]
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.ctx().resources.get(id))
+            .map(|item| item.path.as_path());
+        for toc_path in toc_paths {
+            let Ok(toc) = self.get_resource_by_path(toc_path) else {
+                continue;
+            };
+            let toc_base = toc_path.parent().unwrap_or(root_base);
+            let Ok(txt) = xmlutils::ensure_utf8(&toc) else {
+                continue;
+            };
+            let Ok(toc_root) = xmlutils::parse_xml(&txt) else {
+                continue;
+            };
+
+            for node in toc_root.descendants() {
+                let href = if node.has_tag_name("content") {
+                    node.attribute("src")
+                } else if node.has_tag_name("a") {
+                    node.attr_no_namespace("href")
+                } else {
+                    None
+                };
+
+                if let Some(href) = href {
+                    push(HrefLocation::Toc, href, toc_base);
+                }
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+fn has_percent_escape(href: &str) -> bool {
+    href.as_bytes()
+        .windows(3)
+        .any(|w| w[0] == b'%' && w[1].is_ascii_hexdigit() && w[2].is_ascii_hexdigit())
+}
+
+fn escapes_root(base: &Path, href: &str) -> bool {
+    let mut depth = base
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .count();
+
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." if depth == 0 => return true,
+            ".." => depth -= 1,
+            _ => depth += 1,
+        }
+    }
+
+    false
+}
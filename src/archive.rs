@@ -3,17 +3,70 @@
 //! Provides easy methods to navigate througth the epub parts and to get
 //! the content as string.
 
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
 
 use crate::error::{ArchiveError, Result};
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, SeekFrom};
+use zip::result::ZipError;
+
+/// The most [EpubArchive::read_entry_into] reserves up front, whatever size the zip directory claims.
+const MAX_RESERVE_HINT: u64 = 1024 * 1024;
+
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+pub(crate) const CENTRAL_HEADER_LEN: usize = 46;
+pub(crate) const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_END_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+const ZIP64_END_LOCATOR_LEN: usize = 20;
+
+/// The characters of the upper half of code page 437, the encoding of names without the UTF-8 flag.
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
 
 /// Epub archive struct. Here it's stored the file path and the list of
 /// files in the zip archive.
 pub struct EpubArchive<R: Read + Seek> {
-    zip: zip::ZipArchive<R>,
+    /// Only `None` while [EpubArchive::with_reader] has taken the reader, or if it failed to reopen the archive.
+    zip: Option<zip::ZipArchive<R>>,
+    /// The central directory record of every entry by name, read once when opening the archive
+    records: HashMap<String, EntryRecord>,
+}
+
+/// The sizes, checksum and modification time of an entry, as recorded in the central directory.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EntryRecord {
+    pub size: u64,
+    pub compressed_size: u64,
+    pub crc32: u32,
+    pub last_modified: zip::DateTime,
+}
+
+impl<R: Read + Seek> std::fmt::Debug for EpubArchive<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EpubArchive")
+            .field("entries", &self.zip.as_ref().map(|zip| zip.len()))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Location and size of a single entry within the zip archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryLayout {
+    /// The name of the entry, as stored in the zip archive
+    pub name: String,
+    /// Offset of the local file header
+    pub header_offset: u64,
+    /// Offset of the (compressed) data, directly after the local file header
+    pub data_offset: u64,
+    /// Offset of the entry's record in the central directory
+    pub central_header_offset: u64,
+    pub compressed_size: u64,
+    pub size: u64,
+    pub crc32: u32,
+    pub compression: zip::CompressionMethod,
 }
 
 impl EpubArchive<BufReader<File>> {
@@ -37,10 +90,116 @@ impl<R: Read + Seek> EpubArchive<R> {
     /// # Errors
     ///
     /// Returns an error if the zip is broken.
-    pub fn from_reader(reader: R) -> Result<Self> {
-        let zip = zip::ZipArchive::new(reader)?;
+    pub fn from_reader(mut reader: R) -> Result<Self> {
+        // The zip crate only exposes the records together with the local headers, which would mean a seek per entry
+        let records = read_central_directory(&mut reader);
+        let mut zip = zip::ZipArchive::new(reader)?;
+        let records = records.unwrap_or_else(|_| records_of_entries(&mut zip));
+
+        Ok(EpubArchive {
+            zip: Some(zip),
+            records,
+        })
+    }
+
+    /// Unwraps the archive, returning the underlying reader.
+    ///
+    /// The position of the reader is undefined.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the archive couldn't be reopened after the reader was used directly, in which case the reader is
+    /// lost, see [ArchiveError::ZipUnavailable].
+    pub fn into_inner(self) -> R {
+        self.zip
+            .expect("The archive failed to reopen, dropping the reader")
+            .into_inner()
+    }
 
-        Ok(EpubArchive { zip })
+    fn zip(&mut self) -> Result<&mut zip::ZipArchive<R>> {
+        self.zip.as_mut().ok_or(ArchiveError::ZipUnavailable)
+    }
+
+    /// Returns the layout of every entry, in the order of the central directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the local header of an entry can't be read.
+    pub fn layout(&mut self) -> Result<Vec<EntryLayout>> {
+        let zip = self.zip()?;
+
+        (0..zip.len())
+            .map(|i| {
+                let file = zip.by_index_raw(i)?;
+
+                Ok(EntryLayout {
+                    name: file.name().to_string(),
+                    header_offset: file.header_start(),
+                    data_offset: file.data_start(),
+                    central_header_offset: file.central_header_start(),
+                    compressed_size: file.compressed_size(),
+                    size: file.size(),
+                    crc32: file.crc32(),
+                    compression: file.compression(),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the uncompressed and compressed size of every entry, by name, as recorded in the central directory.
+    pub fn entry_sizes(&self) -> HashMap<String, (u64, u64)> {
+        self.records
+            .iter()
+            .map(|(name, record)| (name.clone(), (record.size, record.compressed_size)))
+            .collect()
+    }
+
+    /// Returns the central directory record of the file by the `name`, using the same percent-decoding fallback as
+    /// [EpubArchive::get_entry].
+    pub(crate) fn entry_record(&self, name: impl AsRef<Path>) -> Option<&EntryRecord> {
+        let name = name.as_ref().to_string_lossy();
+
+        self.records.get(name.as_ref()).or_else(|| {
+            let name = percent_encoding::percent_decode(name.as_bytes())
+                .decode_utf8()
+                .ok()?;
+            self.records.get(name.as_ref())
+        })
+    }
+
+    /// Returns the comment of the zip archive.
+    pub fn comment(&mut self) -> Vec<u8> {
+        self.zip
+            .as_ref()
+            .map(|zip| zip.comment().to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Gives `f` direct access to the underlying reader, e.g. to copy raw bytes of the archive.
+    ///
+    /// The zip archive is reopened afterwards, even if `f` fails or panics.
+    pub(crate) fn with_reader<T>(&mut self, f: impl FnOnce(&mut R) -> Result<T>) -> Result<T> {
+        let zip = self.zip.take().ok_or(ArchiveError::ZipUnavailable)?;
+        let mut guard = ReopenGuard {
+            zip: &mut self.zip,
+            reader: Some(zip.into_inner()),
+        };
+        let result = f(guard.reader.as_mut().ok_or(ArchiveError::ZipUnavailable)?);
+        guard.reopen()?;
+
+        result
+    }
+
+    /// Returns the name, compression method, and decompressed content of the entry at `index`.
+    pub(crate) fn entry_by_index(
+        &mut self,
+        index: usize,
+    ) -> Result<(String, zip::CompressionMethod, Vec<u8>)> {
+        let mut file = self.zip()?.by_index(index)?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+
+        Ok((file.name().to_string(), file.compression(), content))
     }
 
     /// Returns the content of the file by the `name` as `Vec<u8>`.
@@ -50,25 +209,99 @@ impl<R: Read + Seek> EpubArchive<R> {
     /// Returns an error if the name doesn't exists in the zip archive.
     pub fn get_entry(&mut self, name: impl AsRef<Path>) -> Result<Vec<u8>> {
         let mut entry = Vec::new();
-        let path = name.as_ref();
-        let name = path.to_string_lossy();
+        self.read_entry_into(name, &mut entry)?;
+        Ok(entry)
+    }
 
-        match self.zip.by_name(&name) {
-            Ok(mut zipfile) => {
-                zipfile.read_to_end(&mut entry)?;
-                return Ok(entry);
-            }
+    /// Reads the content of the file by the `name` into `buf`, returning the number of bytes read.
+    ///
+    /// `buf` is cleared first, and space for the file is reserved up front, which allows a single
+    /// buffer to be reused for many entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name doesn't exists in the zip archive.
+    pub fn read_entry_into(&mut self, name: impl AsRef<Path>, buf: &mut Vec<u8>) -> Result<usize> {
+        buf.clear();
+
+        let read = self.with_entry(name, |file| {
+            // The size comes from the zip directory and can't be trusted, so only a bounded hint is
+            // reserved and `read_to_end` grows the buffer past it when needed
+            let hint = file.size().min(MAX_RESERVE_HINT) as usize;
+            let _ = buf.try_reserve(hint);
+            file.read_to_end(buf)
+        })??;
+
+        Ok(read)
+    }
+
+    /// Returns at most the first `len` bytes of the file by the `name`, only decompressing what is needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name doesn't exists in the zip archive.
+    pub fn get_entry_prefix(&mut self, name: impl AsRef<Path>, len: usize) -> Result<Vec<u8>> {
+        let mut prefix = Vec::new();
+        self.with_entry(name, |file| {
+            file.take(u64::try_from(len).unwrap_or(u64::MAX))
+                .read_to_end(&mut prefix)
+        })??;
+
+        Ok(prefix)
+    }
+
+    /// Returns the uncompressed size of the file by the `name`, without decompressing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name doesn't exists in the zip archive.
+    pub fn get_entry_size(&mut self, name: impl AsRef<Path>) -> Result<u64> {
+        self.with_entry(name, |file| file.size())
+    }
+
+    /// Returns the CRC-32 of the file by the `name` as recorded in the zip directory, without decompressing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name doesn't exists in the zip archive.
+    pub fn get_entry_crc32(&mut self, name: impl AsRef<Path>) -> Result<u32> {
+        self.with_entry(name, |file| file.crc32())
+    }
+
+    /// Looks up the file by the `name` (with the percent-decoding fallback) without reading its data.
+    fn with_entry<T>(
+        &mut self,
+        name: impl AsRef<Path>,
+        f: impl FnOnce(&mut zip::read::ZipFile<'_>) -> T,
+    ) -> Result<T> {
+        let name = name.as_ref().to_string_lossy();
+
+        match self.zip()?.by_name(&name) {
+            Ok(mut zipfile) => return Ok(f(&mut zipfile)),
             Err(zip::result::ZipError::FileNotFound) => {}
-            Err(e) => {
-                return Err(e.into());
-            }
+            Err(e) => return Err(e.into()),
         };
 
         // try percent encoding
         let name = percent_encoding::percent_decode(name.as_bytes()).decode_utf8()?;
-        let mut zipfile = self.zip.by_name(&name)?;
-        zipfile.read_to_end(&mut entry)?;
-        Ok(entry)
+        let mut zipfile = self.zip()?.by_name(&name)?;
+        Ok(f(&mut zipfile))
+    }
+
+    /// Returns whether the file by the `name` exists, without reading it.
+    ///
+    /// Uses the same percent-decoding fallback as [EpubArchive::get_entry].
+    pub fn contains(&self, name: impl AsRef<Path>) -> bool {
+        let Some(zip) = self.zip.as_ref() else {
+            return false;
+        };
+        let name = name.as_ref().to_string_lossy();
+        let exists = |name: &str| zip.file_names().any(|n| n == name);
+
+        exists(&name)
+            || percent_encoding::percent_decode(name.as_bytes())
+                .decode_utf8()
+                .is_ok_and(|decoded| exists(&decoded))
     }
 
     /// Returns the content of the file by the `name` as `String`.
@@ -90,3 +323,181 @@ impl<R: Read + Seek> EpubArchive<R> {
         self.get_entry("META-INF/container.xml")
     }
 }
+
+pub(crate) fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn invalid(reason: &'static str) -> ArchiveError {
+    ZipError::InvalidArchive(reason).into()
+}
+
+/// Puts the reader taken by [EpubArchive::with_reader] back into a zip archive, also when unwinding from a panic.
+struct ReopenGuard<'a, R: Read + Seek> {
+    zip: &'a mut Option<zip::ZipArchive<R>>,
+    reader: Option<R>,
+}
+
+impl<R: Read + Seek> ReopenGuard<'_, R> {
+    fn reopen(&mut self) -> Result<()> {
+        if let Some(reader) = self.reader.take() {
+            *self.zip = Some(zip::ZipArchive::new(reader)?);
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Drop for ReopenGuard<'_, R> {
+    fn drop(&mut self) {
+        // An error leaves the archive unavailable, which the accessors report
+        let _ = self.reopen();
+    }
+}
+
+/// Reads the record of every entry from the central directory in a single pass, without touching the local headers.
+///
+/// Like the zip crate, data prepended to the archive is accounted for, except for ZIP64 archives.
+fn read_central_directory(reader: &mut (impl Read + Seek)) -> Result<HashMap<String, EntryRecord>> {
+    // The end record is followed by a comment of at most `u16::MAX` bytes
+    let length = reader.seek(SeekFrom::End(0))?;
+    let tail_len = length.min((END_OF_CENTRAL_DIRECTORY_LEN + usize::from(u16::MAX)) as u64);
+    let tail_start = length - tail_len;
+    let mut tail = vec![0; tail_len as usize];
+    reader.seek(SeekFrom::Start(tail_start))?;
+    reader.read_exact(&mut tail)?;
+
+    let end = (0..=tail.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_LEN))
+        .rev()
+        .find(|&pos| {
+            pos + END_OF_CENTRAL_DIRECTORY_LEN <= tail.len()
+                && read_u32(&tail, pos) == END_OF_CENTRAL_DIRECTORY_SIGNATURE
+        })
+        .ok_or_else(|| invalid("Could not find central directory end"))?;
+    let mut directory_end = tail_start + end as u64;
+    let mut size = u64::from(read_u32(&tail, end + 12));
+    let mut offset = u64::from(read_u32(&tail, end + 16));
+
+    // A ZIP64 archive has a locator right before the end record, pointing at the ZIP64 end record
+    if end >= ZIP64_END_LOCATOR_LEN
+        && read_u32(&tail, end - ZIP64_END_LOCATOR_LEN) == ZIP64_END_LOCATOR_SIGNATURE
+    {
+        let zip64_end = read_u64(&tail, end - ZIP64_END_LOCATOR_LEN + 8);
+        let mut record = [0; 56];
+        reader.seek(SeekFrom::Start(zip64_end))?;
+        reader.read_exact(&mut record)?;
+        if read_u32(&record, 0) != ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+            return Err(invalid("Invalid ZIP64 central directory end"));
+        }
+        directory_end = zip64_end;
+        size = read_u64(&record, 40);
+        offset = read_u64(&record, 48);
+    }
+
+    // The central directory directly precedes its end record, anything before its recorded offset was prepended
+    let archive_offset = directory_end
+        .checked_sub(size)
+        .and_then(|start| start.checked_sub(offset))
+        .ok_or_else(|| invalid("Invalid central directory size or offset"))?;
+    let mut directory = vec![0; size as usize];
+    reader.seek(SeekFrom::Start(archive_offset + offset))?;
+    reader.read_exact(&mut directory)?;
+
+    let mut records = HashMap::new();
+    let mut pos = 0;
+    while pos < directory.len() {
+        if pos + CENTRAL_HEADER_LEN > directory.len()
+            || read_u32(&directory, pos) != CENTRAL_HEADER_SIGNATURE
+        {
+            return Err(invalid("Invalid central directory header"));
+        }
+        let name_start = pos + CENTRAL_HEADER_LEN;
+        let extra_start = name_start + usize::from(read_u16(&directory, pos + 28));
+        let comment_start = extra_start + usize::from(read_u16(&directory, pos + 30));
+        let next = comment_start + usize::from(read_u16(&directory, pos + 32));
+        if next > directory.len() {
+            return Err(invalid("Invalid central directory header"));
+        }
+
+        let is_utf8 = read_u16(&directory, pos + 8) & (1 << 11) != 0;
+        let name = decode_name(&directory[name_start..extra_start], is_utf8);
+        let mut record = EntryRecord {
+            size: u64::from(read_u32(&directory, pos + 24)),
+            compressed_size: u64::from(read_u32(&directory, pos + 20)),
+            crc32: read_u32(&directory, pos + 16),
+            last_modified: zip::DateTime::from_msdos(
+                read_u16(&directory, pos + 14),
+                read_u16(&directory, pos + 12),
+            ),
+        };
+        read_zip64_sizes(&directory[extra_start..comment_start], &mut record);
+
+        records.insert(name, record);
+        pos = next;
+    }
+
+    Ok(records)
+}
+
+/// Replaces the sizes which don't fit in 32 bits by those of the ZIP64 extra field, if any.
+fn read_zip64_sizes(mut extra: &[u8], record: &mut EntryRecord) {
+    while extra.len() >= 4 {
+        let kind = read_u16(extra, 0);
+        let len = usize::from(read_u16(extra, 2));
+        let Some(field) = extra.get(4..4 + len) else {
+            return;
+        };
+
+        if kind == 0x0001 {
+            // The fields are only present if the 32 bits ones are saturated, in this order
+            let mut values = field.chunks_exact(8).map(|value| read_u64(value, 0));
+            if record.size == u64::from(u32::MAX) {
+                record.size = values.next().unwrap_or(record.size);
+            }
+            if record.compressed_size == u64::from(u32::MAX) {
+                record.compressed_size = values.next().unwrap_or(record.compressed_size);
+            }
+        }
+        extra = &extra[4 + len..];
+    }
+}
+
+/// Decodes a raw entry name like the zip crate does, as UTF-8 if flagged so, and as code page 437 otherwise.
+fn decode_name(raw: &[u8], is_utf8: bool) -> String {
+    if is_utf8 || raw.is_ascii() {
+        String::from_utf8_lossy(raw).into_owned()
+    } else {
+        raw.iter()
+            .map(|&b| match b {
+                0..=0x7f => char::from(b),
+                _ => CP437_HIGH.chars().nth(usize::from(b - 0x80)).unwrap_or('?'),
+            })
+            .collect()
+    }
+}
+
+/// The records of the entries the zip crate could read the local header of, for archives whose central directory
+/// [read_central_directory] can't make sense of.
+fn records_of_entries<R: Read + Seek>(
+    zip: &mut zip::ZipArchive<R>,
+) -> HashMap<String, EntryRecord> {
+    (0..zip.len())
+        .filter_map(|i| {
+            let file = zip.by_index_raw(i).ok()?;
+            let record = EntryRecord {
+                size: file.size(),
+                compressed_size: file.compressed_size(),
+                crc32: file.crc32(),
+                last_modified: file.last_modified(),
+            };
+            Some((file.name().to_string(), record))
+        })
+        .collect()
+}
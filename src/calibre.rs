@@ -0,0 +1,85 @@
+//! The metadata calibre adds to the books it manages.
+//!
+//! Besides the series (see [EpubDoc::series]), calibre records when a book was added to the library, its rating,
+//! the sort title, and the values of the user defined columns, see [EpubDoc::calibre_metadata].
+
+use crate::doc::EpubDoc;
+use crate::metadata::PartialDate;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+
+/// The prefix of the metadata names of the user defined columns.
+const USER_METADATA_PREFIX: &str = "calibre:user_metadata:";
+
+/// The `calibre:` metadata of a book, see [EpubDoc::calibre_metadata].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CalibreMetadata {
+    /// `calibre:timestamp`, when the book was added to the library
+    pub timestamp: Option<PartialDate>,
+    /// `calibre:rating`, from `0` to `10` (twice the number of stars)
+    pub rating: Option<f32>,
+    /// `calibre:title_sort`, the title used for sorting, e.g. `Christmas Carol, A`
+    pub title_sort: Option<String>,
+    /// `calibre:author_link_map`, the JSON object mapping authors to links
+    pub author_link_map: Option<String>,
+    /// The JSON definitions (including the value) of the user defined columns by lookup name, e.g. `#genre`
+    pub user_metadata: BTreeMap<String, String>,
+}
+
+impl CalibreMetadata {
+    /// Decodes the JSON definition of the user defined column with the given lookup name, e.g. `#genre`.
+    ///
+    /// The value of the column is in its `#value#` field. Returns `None` if the book has no such column.
+    ///
+    /// Only available with the `calibre` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the definition isn't valid JSON.
+    #[cfg(feature = "calibre")]
+    pub fn user_metadata_json(&self, name: &str) -> Option<serde_json::Result<serde_json::Value>> {
+        self.user_metadata
+            .get(name)
+            .map(|json| serde_json::from_str(json))
+    }
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns the metadata calibre stored in the book, every field is `None` (or empty) for books which never
+    /// passed through calibre.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/Metamorphosis-jackson.epub").unwrap();
+    /// let calibre = doc.calibre_metadata();
+    /// assert!(calibre.user_metadata.is_empty());
+    /// ```
+    pub fn calibre_metadata(&self) -> CalibreMetadata {
+        let value = |name| {
+            self.mdata(name)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let user_metadata = self
+            .metadata()
+            .iter()
+            .filter_map(|(name, nodes)| {
+                let column = name.strip_prefix(USER_METADATA_PREFIX)?;
+                Some((column.to_string(), nodes.first()?.content.clone()))
+            })
+            .collect();
+
+        CalibreMetadata {
+            timestamp: value("calibre:timestamp").and_then(PartialDate::parse),
+            rating: value("calibre:rating")
+                .and_then(|rating| rating.parse().ok())
+                .filter(|rating: &f32| rating.is_finite()),
+            title_sort: value("calibre:title_sort").map(str::to_string),
+            author_link_map: value("calibre:author_link_map").map(str::to_string),
+            user_metadata,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Cancelling whole-book operations, and following their progress.
+//!
+//! Operations like [EpubDoc::search](crate::doc::EpubDoc::search) read every chapter, which can take seconds on big
+//! books. Their `_with_control` variants take a [ScanControl], which is checked between chapters.
+
+use crate::error::{ArchiveError, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag to cancel an operation from another thread.
+///
+/// Clones share the flag, so a clone can be handed to the operation while the original is kept to cancel it.
+///
+/// # Examples
+///
+/// ```
+/// # use epub::cancel::CancelToken;
+/// let token = CancelToken::new();
+/// let handle = token.clone();
+///
+/// std::thread::spawn(move || handle.cancel()).join().unwrap();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the operations using this token (or a clone of it), at their next check.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Cancellation and progress reporting for a long-running operation.
+///
+/// # Examples
+///
+/// ```
+/// # use epub::doc::EpubDoc;
+/// # use epub::cancel::{CancelToken, ScanControl};
+/// # let doc = EpubDoc::new("test.epub").unwrap();
+/// let token = CancelToken::new();
+/// let mut progress = Vec::new();
+///
+/// let mut control = ScanControl::new()
+///     .cancel_token(token.clone())
+///     .on_progress(|done, total| progress.push((done, total)));
+/// doc.search_with_control("capítulo", &mut control).unwrap();
+/// drop(control);
+///
+/// assert_eq!(Some(&(17, 17)), progress.last());
+/// ```
+#[derive(Default)]
+pub struct ScanControl<'a> {
+    token: Option<CancelToken>,
+    progress: Option<Box<dyn FnMut(usize, usize) + 'a>>,
+}
+
+impl<'a> ScanControl<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop the operation with [ArchiveError::Cancelled] once `token` is cancelled.
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Call `progress` with the number of chapters done and the total, after each chapter.
+    pub fn on_progress(mut self, progress: impl FnMut(usize, usize) + 'a) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Returns [ArchiveError::Cancelled] if the token was cancelled.
+    pub(crate) fn check(&self) -> Result<()> {
+        match &self.token {
+            Some(token) if token.is_cancelled() => Err(ArchiveError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reports that `done` out of `total` chapters are done.
+    pub(crate) fn progress(&mut self, done: usize, total: usize) {
+        if let Some(progress) = &mut self.progress {
+            progress(done, total);
+        }
+    }
+}
+
+impl std::fmt::Debug for ScanControl<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScanControl")
+            .field("token", &self.token)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,356 @@
+//! EPUB Canonical Fragment Identifiers.
+//!
+//! CFIs identify a location within an epub, and are used by most reading systems (epub.js, Readium, ...) for
+//! bookmarks and annotations. See the [specification](https://idpf.org/epub/linking/cfi/epub-cfi.html).
+//!
+//! A CFI starts with the steps to an `itemref` in the spine of the package document (e.g. `/6/4[chap01ref]`),
+//! followed by an indirection (`!`) into the referenced content document.
+
+use crate::doc::{EpubDoc, OpfSection};
+use crate::error::{ArchiveError, Result};
+use std::io::{Read, Seek};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A single step of a CFI path, e.g. `/10[para05]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CfiStep {
+    /// The raw step number, even numbers refer to elements and odd numbers to the content between them
+    pub index: usize,
+    /// The (unescaped) id assertion, if any
+    pub id: Option<String>,
+}
+
+impl CfiStep {
+    /// Returns the zero-based index among the element children if this step refers to an element.
+    pub fn element_index(&self) -> Option<usize> {
+        (self.index.is_multiple_of(2) && self.index > 0).then(|| self.index / 2 - 1)
+    }
+}
+
+/// The result of [EpubDoc::resolve_cfi].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CfiLocation {
+    /// The index of the chapter in the spine
+    pub spine_index: usize,
+    /// The id of the resource the chapter refers to
+    pub spine_id: String,
+    /// The steps into the content document, to be resolved by the caller against its DOM
+    pub steps: Vec<CfiStep>,
+    /// The character offset within the node the last step refers to
+    pub offset: Option<usize>,
+}
+
+/// Builder for the textual form of a CFI.
+///
+/// # Examples
+///
+/// ```
+/// use epub::cfi::CfiBuilder;
+///
+/// let cfi = CfiBuilder::new()
+///     .element(2, None)
+///     .element(1, Some("chap01ref"))
+///     .indirection()
+///     .element_path(&[1, 0])
+///     .element(4, Some("para05"))
+///     .step(3, None)
+///     .offset(10)
+///     .build();
+///
+/// assert_eq!("epubcfi(/6/4[chap01ref]!/4/2/10[para05]/3:10)", cfi);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CfiBuilder {
+    path: String,
+}
+
+impl CfiBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a raw step, `index` being the CFI step number.
+    ///
+    /// Even numbers refer to elements, odd numbers to the (text) content between elements.
+    pub fn step(mut self, index: usize, id: Option<&str>) -> Self {
+        self.path.push('/');
+        self.path.push_str(&index.to_string());
+
+        if let Some(id) = id {
+            self.path.push('[');
+            self.path.push_str(&escape(id));
+            self.path.push(']');
+        }
+
+        self
+    }
+
+    /// Append a step to the element child with the (zero-based) index `child_index`, optionally asserting its `id`.
+    pub fn element(self, child_index: usize, id: Option<&str>) -> Self {
+        self.step((child_index + 1) * 2, id)
+    }
+
+    /// Append a step for every element child index in `path`, from the outermost element inwards.
+    pub fn element_path(self, path: &[usize]) -> Self {
+        path.iter()
+            .fold(self, |cfi, &index| cfi.element(index, None))
+    }
+
+    /// Append an indirection (`!`), continuing in the document referenced by the previous step.
+    pub fn indirection(mut self) -> Self {
+        self.path.push('!');
+        self
+    }
+
+    /// Append a character offset (`:offset`), this should be the last step.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.path.push(':');
+        self.path.push_str(&offset.to_string());
+        self
+    }
+
+    /// Returns the path without the `epubcfi(...)` wrapper.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the full CFI, e.g. `epubcfi(/6/4[chap01ref]!)`.
+    pub fn build(&self) -> String {
+        format!("epubcfi({})", self.path)
+    }
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns a [CfiBuilder] pointing at the content document of the chapter at `spine_index`.
+    ///
+    /// The steps are generated the same way epub.js does, including the `id` assertion of the `itemref`
+    /// (not of the resource it references) when present. Element steps into the content document can be appended.
+    pub fn cfi_builder_for_chapter(&self, spine_index: usize) -> Option<CfiBuilder> {
+        let item = self.spine().get(spine_index)?;
+
+        Some(
+            CfiBuilder::new()
+                .element(self.ctx().spine_element_index, None)
+                .element(spine_index, item.id.as_deref())
+                .indirection(),
+        )
+    }
+
+    /// Returns the CFI of the start of the chapter at `spine_index`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(Some("epubcfi(/6/2!)".to_string()), doc.cfi_for_chapter(0));
+    /// assert_eq!(None, doc.cfi_for_chapter(100));
+    /// ```
+    pub fn cfi_for_chapter(&self, spine_index: usize) -> Option<String> {
+        self.cfi_builder_for_chapter(spine_index)
+            .map(|cfi| cfi.build())
+    }
+
+    /// Resolves the given CFI to the chapter it points into.
+    ///
+    /// The `itemref` is looked up by its `id` assertion when present, falling back to its position in the spine.
+    /// For range CFIs the start of the range is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let location = doc.resolve_cfi("epubcfi(/6/8!/4/2/1:12)").unwrap();
+    ///
+    /// assert_eq!(3, location.spine_index);
+    /// assert_eq!("002.xhtml", location.spine_id);
+    /// assert_eq!(Some(0), location.steps[1].element_index());
+    /// assert_eq!(Some(12), location.offset);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::InvalidCfi] if the CFI is malformed, uses unsupported features (spatial or temporal
+    /// offsets, nested indirections), or doesn't point at a chapter of this epub.
+    pub fn resolve_cfi(&self, cfi: &str) -> Result<CfiLocation> {
+        self.ensure_section(OpfSection::Spine)?;
+
+        let parsed = ParsedCfi::parse(cfi)?;
+        let [spine_step, item_step] = parsed.package.as_slice() else {
+            return Err(invalid("expected exactly two steps before the indirection"));
+        };
+
+        if spine_step.element_index() != Some(self.ctx().spine_element_index) {
+            return Err(invalid("the first step doesn't point at the spine"));
+        }
+
+        let by_id = item_step.id.as_deref().and_then(|id| {
+            self.spine()
+                .iter()
+                .position(|item| item.id.as_deref() == Some(id))
+        });
+        let spine_index = by_id
+            .or_else(|| {
+                item_step
+                    .element_index()
+                    .filter(|&i| i < self.spine().len())
+            })
+            .ok_or_else(|| invalid("the second step doesn't point at an itemref in the spine"))?;
+
+        Ok(CfiLocation {
+            spine_index,
+            spine_id: self.spine()[spine_index].idref.clone(),
+            steps: parsed.content,
+            offset: parsed.offset,
+        })
+    }
+}
+
+fn invalid(reason: &str) -> ArchiveError {
+    ArchiveError::InvalidCfi(reason.to_string())
+}
+
+/// A syntactically valid CFI, split at the indirection.
+#[derive(Debug, Default)]
+struct ParsedCfi {
+    package: Vec<CfiStep>,
+    content: Vec<CfiStep>,
+    offset: Option<usize>,
+}
+
+impl ParsedCfi {
+    fn parse(cfi: &str) -> Result<Self> {
+        let cfi = cfi.trim();
+        let path = match cfi.strip_prefix("epubcfi(") {
+            Some(inner) => inner
+                .strip_suffix(')')
+                .ok_or_else(|| invalid("missing closing parenthesis"))?,
+            None => cfi,
+        };
+
+        // Ranges are written as `parent,start,end`, only the start is of interest.
+        let path = match split_range(path).as_slice() {
+            [path] => path.to_string(),
+            [parent, start, _end] => format!("{parent}{start}"),
+            _ => return Err(invalid("a range needs exactly three parts")),
+        };
+
+        let mut output = ParsedCfi::default();
+        let mut chars = path.chars().peekable();
+        let mut indirected = false;
+
+        if chars.peek() != Some(&'/') {
+            return Err(invalid("a path needs to start with a step"));
+        }
+
+        while let Some(c) = chars.next() {
+            match c {
+                '/' => {
+                    let step = CfiStep {
+                        index: parse_number(&mut chars)?,
+                        id: parse_assertion(&mut chars)?,
+                    };
+                    if indirected {
+                        output.content.push(step);
+                    } else {
+                        output.package.push(step);
+                    }
+                }
+                '!' if indirected => return Err(invalid("nested indirections are not supported")),
+                '!' => indirected = true,
+                ':' => {
+                    output.offset = Some(parse_number(&mut chars)?);
+                    // Text location assertions are of no use without the content document
+                    parse_assertion(&mut chars)?;
+
+                    if chars.peek().is_some() {
+                        return Err(invalid("a character offset needs to be the last part"));
+                    }
+                }
+                '~' | '@' => return Err(invalid("temporal and spatial offsets are not supported")),
+                c => {
+                    return Err(ArchiveError::InvalidCfi(format!(
+                        "unexpected character {c:?}"
+                    )))
+                }
+            }
+        }
+
+        if output.offset.is_some() && !indirected {
+            return Err(invalid(
+                "a character offset needs to be within the content document",
+            ));
+        }
+
+        Ok(output)
+    }
+}
+
+fn parse_number(chars: &mut Peekable<Chars<'_>>) -> Result<usize> {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+
+    digits.parse().map_err(|_| invalid("expected a number"))
+}
+
+/// Parse an optional `[...]` assertion, returning its unescaped content.
+fn parse_assertion(chars: &mut Peekable<Chars<'_>>) -> Result<Option<String>> {
+    if chars.next_if_eq(&'[').is_none() {
+        return Ok(None);
+    }
+
+    let mut output = String::new();
+    loop {
+        match chars.next() {
+            Some('^') => output.push(chars.next().ok_or_else(|| invalid("dangling escape"))?),
+            Some(']') => return Ok(Some(output)),
+            Some(c) => output.push(c),
+            None => return Err(invalid("unterminated assertion")),
+        }
+    }
+}
+
+/// Split on the commas which aren't escaped or part of an assertion.
+fn split_range(path: &str) -> Vec<&str> {
+    let mut output = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    let mut in_assertion = false;
+
+    for (i, c) in path.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '^' => escaped = true,
+            '[' => in_assertion = true,
+            ']' => in_assertion = false,
+            ',' if !in_assertion => {
+                output.push(&path[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    output.push(&path[start..]);
+
+    output
+}
+
+/// Characters with a special meaning in CFIs need to be escaped with a `^`.
+pub(crate) const SPECIAL_CHARS: &[char] = &['^', '[', ']', '(', ')', ',', ';', '='];
+
+fn escape(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if SPECIAL_CHARS.contains(&c) {
+            output.push('^');
+        }
+        output.push(c);
+    }
+
+    output
+}
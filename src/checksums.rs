@@ -0,0 +1,107 @@
+//! Per-chapter change detection for incremental sync.
+//!
+//! [EpubDoc::chapter_checksums] reads the CRC-32 of every chapter from the zip directory, so a client can store
+//! them and later use [EpubDoc::diff_chapters] to find out which chapters need to be synced again, without
+//! decompressing the book.
+
+use crate::doc::EpubDoc;
+use std::io::{Read, Seek};
+
+/// The checksum reported for a chapter whose resource is missing from the archive.
+///
+/// A missing chapter therefore shows up as changed once its file is added, instead of being silently left out.
+pub const MISSING_CHECKSUM: u32 = u32::MAX;
+
+/// The difference between two sets of [chapter checksums](EpubDoc::chapter_checksums), by spine idref.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChapterDiff {
+    /// Chapters which are only in the new spine
+    pub added: Vec<String>,
+    /// Chapters which are only in the old spine
+    pub removed: Vec<String>,
+    /// Chapters in both spines whose checksum differs
+    pub changed: Vec<String>,
+}
+
+impl ChapterDiff {
+    /// Whether no chapter was added, removed, or changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns the idref and CRC-32 of every chapter in the spine, in reading order.
+    ///
+    /// The checksums come from the zip directory, nothing is decompressed. Chapters whose resource is missing
+    /// from the manifest or the archive get [MISSING_CHECKSUM].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let checksums = doc.chapter_checksums();
+    /// assert_eq!(doc.get_num_pages(), checksums.len());
+    /// assert_eq!("titlepage.xhtml", checksums[0].0);
+    /// ```
+    pub fn chapter_checksums(&self) -> Vec<(String, u32)> {
+        let mut archive = self.archive();
+
+        self.spine()
+            .iter()
+            .map(|item| {
+                let crc = self
+                    .ctx()
+                    .resources
+                    .get(&item.idref)
+                    .and_then(|res| archive.get_entry_crc32(&res.path).ok())
+                    .unwrap_or(MISSING_CHECKSUM);
+
+                (item.idref.clone(), crc)
+            })
+            .collect()
+    }
+
+    /// Compares previously stored [chapter checksums](EpubDoc::chapter_checksums) with the current ones.
+    ///
+    /// Chapters are matched by idref, the order of the returned idrefs follows the spine they come from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let stored = doc.chapter_checksums();
+    /// assert!(doc.diff_chapters(&stored).is_empty());
+    /// ```
+    pub fn diff_chapters(&self, old: &[(String, u32)]) -> ChapterDiff {
+        let new = self.chapter_checksums();
+        let find = |list: &[(String, u32)], id: &str| {
+            list.iter()
+                .find(|(idref, _)| idref == id)
+                .map(|(_, crc)| *crc)
+        };
+        let mut diff = ChapterDiff::default();
+
+        for (idref, crc) in &new {
+            match find(old, idref) {
+                None => diff.added.push(idref.clone()),
+                Some(old_crc) if old_crc != *crc => diff.changed.push(idref.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .iter()
+            .filter(|(idref, _)| find(&new, idref).is_none())
+            .map(|(idref, _)| idref.clone())
+            .collect();
+
+        diff.added.dedup();
+        diff.changed.dedup();
+        diff.removed.dedup();
+
+        diff
+    }
+}
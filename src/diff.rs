@@ -0,0 +1,400 @@
+//! Structured differences between two epubs, e.g. two editions of the same book.
+//!
+//! [diff] compares the metadata, the resources in the manifest, the spine, and the table of contents. Resources
+//! are compared by the CRC-32 and size in the zip directory, so unchanged files are never decompressed.
+
+use crate::archive::EpubArchive;
+use crate::doc::{EpubDoc, MetadataNode, NavPoint};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::{Read, Seek};
+use std::path::PathBuf;
+
+/// The differences between two epubs, see [diff].
+///
+/// The [Display](fmt::Display) implementation prints one change per line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EpubDiff {
+    /// Changed metadata, sorted by name
+    pub metadata: Vec<MetadataChange>,
+    /// Added, removed, and modified resources, sorted by path
+    pub resources: Vec<ResourceChange>,
+    /// Changes to the reading order
+    pub spine: Vec<SpineChange>,
+    /// Changes to the table of contents, in ToC order
+    pub toc: Vec<TocChange>,
+}
+
+impl EpubDiff {
+    /// Whether both epubs are the same, as far as the compared parts are concerned.
+    pub fn is_empty(&self) -> bool {
+        self.metadata.is_empty()
+            && self.resources.is_empty()
+            && self.spine.is_empty()
+            && self.toc.is_empty()
+    }
+}
+
+/// A metadata name whose values differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetadataChange {
+    /// The name of the metadata, as used in [EpubDoc::metadata]
+    pub name: String,
+    /// The contents in the old epub, empty if the metadata was added
+    pub old: Vec<String>,
+    /// The contents in the new epub, empty if the metadata was removed
+    pub new: Vec<String>,
+}
+
+/// A change to a resource in the manifest, resources are matched by path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResourceChange {
+    Added {
+        id: String,
+        path: PathBuf,
+    },
+    Removed {
+        id: String,
+        path: PathBuf,
+    },
+    /// The content (or the presence in the archive) of the resource changed
+    Modified {
+        id: String,
+        path: PathBuf,
+    },
+}
+
+/// A change to the spine, spine items are matched by idref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpineChange {
+    /// The item was added at `index` of the new spine
+    Added { idref: String, index: usize },
+    /// The item at `index` of the old spine was removed
+    Removed { idref: String, index: usize },
+    /// The item moved relative to the other items, from `from` in the old spine to `to` in the new one
+    Moved {
+        idref: String,
+        from: usize,
+        to: usize,
+    },
+}
+
+/// A change to the table of contents.
+///
+/// Entries are matched by their position: the index at every level of the tree, e.g. `[1, 0]` is the first child
+/// of the second top level entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TocChange {
+    Added {
+        position: Vec<usize>,
+        label: String,
+    },
+    Removed {
+        position: Vec<usize>,
+        label: String,
+    },
+    Relabeled {
+        position: Vec<usize>,
+        old: String,
+        new: String,
+    },
+    /// The entry points to a different resource or fragment, `None` for a heading without a link
+    Retargeted {
+        position: Vec<usize>,
+        old: Option<PathBuf>,
+        new: Option<PathBuf>,
+    },
+}
+
+/// Compares two epubs.
+///
+/// A resource counts as modified if its CRC-32 or size changed. When only the CRC-32 differs the contents are
+/// compared as well, to not be fooled by tools which write wrong checksums.
+///
+/// # Examples
+///
+/// ```
+/// # use epub::doc::EpubDoc;
+/// let old = EpubDoc::new("test.epub").unwrap();
+/// let new = EpubDoc::new("test.epub").unwrap();
+/// assert!(epub::diff::diff(&old, &new).is_empty());
+/// ```
+pub fn diff<A: Read + Seek, B: Read + Seek>(old: &EpubDoc<A>, new: &EpubDoc<B>) -> EpubDiff {
+    EpubDiff {
+        metadata: diff_metadata(old, new),
+        resources: diff_resources(old, new),
+        spine: diff_spine(old, new),
+        toc: diff_toc(old.get_toc(), new.get_toc()),
+    }
+}
+
+fn diff_metadata<A: Read + Seek, B: Read + Seek>(
+    old: &EpubDoc<A>,
+    new: &EpubDoc<B>,
+) -> Vec<MetadataChange> {
+    let names: BTreeSet<_> = old.metadata().keys().chain(new.metadata().keys()).collect();
+    let contents = |nodes: Option<&Vec<MetadataNode>>| -> Vec<String> {
+        nodes
+            .into_iter()
+            .flatten()
+            .map(|node| node.content.clone())
+            .collect()
+    };
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let old = contents(old.metadata().get(name));
+            let new = contents(new.metadata().get(name));
+
+            (old != new).then(|| MetadataChange {
+                name: name.clone(),
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+fn diff_resources<A: Read + Seek, B: Read + Seek>(
+    old: &EpubDoc<A>,
+    new: &EpubDoc<B>,
+) -> Vec<ResourceChange> {
+    let paths: BTreeSet<_> = old
+        .resources()
+        .values()
+        .chain(new.resources().values())
+        .map(|item| item.path.clone())
+        .collect();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            match (
+                old.resource_id_by_path(&path).map(String::from),
+                new.resource_id_by_path(&path).map(String::from),
+            ) {
+                (Some(_), Some(id)) if resource_modified(old, new, &path) => {
+                    Some(ResourceChange::Modified { id, path })
+                }
+                (Some(_), Some(_)) => None,
+                (Some(id), None) => Some(ResourceChange::Removed { id, path }),
+                (None, Some(id)) => Some(ResourceChange::Added { id, path }),
+                (None, None) => None,
+            }
+        })
+        .collect()
+}
+
+fn resource_modified<A: Read + Seek, B: Read + Seek>(
+    old: &EpubDoc<A>,
+    new: &EpubDoc<B>,
+    path: &PathBuf,
+) -> bool {
+    let old_entry = entry_checksum(&mut old.archive(), path);
+    let new_entry = entry_checksum(&mut new.archive(), path);
+
+    match (old_entry, new_entry) {
+        (Some((old_crc, old_size)), Some((new_crc, new_size))) => {
+            if old_size != new_size {
+                true
+            } else if old_crc != new_crc {
+                // Same size but a different checksum, make sure it isn't just a bad checksum
+                old.get_resource_by_path(path).ok() != new.get_resource_by_path(path).ok()
+            } else {
+                false
+            }
+        }
+        (None, None) => false,
+        _ => true,
+    }
+}
+
+/// Returns the CRC-32 and the uncompressed size of an entry, `None` if it's missing.
+fn entry_checksum<R: Read + Seek>(
+    archive: &mut EpubArchive<R>,
+    path: &PathBuf,
+) -> Option<(u32, u64)> {
+    Some((
+        archive.get_entry_crc32(path).ok()?,
+        archive.get_entry_size(path).ok()?,
+    ))
+}
+
+fn diff_spine<A: Read + Seek, B: Read + Seek>(
+    old: &EpubDoc<A>,
+    new: &EpubDoc<B>,
+) -> Vec<SpineChange> {
+    let old: Vec<_> = old.spine().iter().map(|item| item.idref.as_str()).collect();
+    let new: Vec<_> = new.spine().iter().map(|item| item.idref.as_str()).collect();
+    let (old_kept, new_kept) = longest_common_subsequence(&old, &new);
+    let mut changes = Vec::new();
+
+    for (index, idref) in old.iter().enumerate() {
+        if old_kept[index] {
+            continue;
+        }
+        match new.iter().position(|other| other == idref) {
+            Some(to) if !new_kept[to] => changes.push(SpineChange::Moved {
+                idref: idref.to_string(),
+                from: index,
+                to,
+            }),
+            _ if new.contains(idref) => {}
+            _ => changes.push(SpineChange::Removed {
+                idref: idref.to_string(),
+                index,
+            }),
+        }
+    }
+    for (index, idref) in new.iter().enumerate() {
+        if !new_kept[index] && !old.contains(idref) {
+            changes.push(SpineChange::Added {
+                idref: idref.to_string(),
+                index,
+            });
+        }
+    }
+
+    changes
+}
+
+/// Marks the items of `a` and `b` which are part of their longest common subsequence.
+fn longest_common_subsequence(a: &[&str], b: &[&str]) -> (Vec<bool>, Vec<bool>) {
+    // lengths[i][j] is the length of the LCS of a[i..] and b[j..]
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut a_kept = vec![false; a.len()];
+    let mut b_kept = vec![false; b.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            a_kept[i] = true;
+            b_kept[j] = true;
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    (a_kept, b_kept)
+}
+
+fn diff_toc(old: &[NavPoint], new: &[NavPoint]) -> Vec<TocChange> {
+    let mut changes = Vec::new();
+    diff_toc_level(old, new, &mut Vec::new(), &mut changes);
+    changes
+}
+
+fn diff_toc_level(
+    old: &[NavPoint],
+    new: &[NavPoint],
+    position: &mut Vec<usize>,
+    changes: &mut Vec<TocChange>,
+) {
+    for index in 0..old.len().max(new.len()) {
+        position.push(index);
+
+        match (old.get(index), new.get(index)) {
+            (Some(old), Some(new)) => {
+                if old.label != new.label {
+                    changes.push(TocChange::Relabeled {
+                        position: position.clone(),
+                        old: old.label.clone(),
+                        new: new.label.clone(),
+                    });
+                }
+                if old.content != new.content || old.fragment != new.fragment {
+                    changes.push(TocChange::Retargeted {
+                        position: position.clone(),
+                        old: old.href().map(Into::into),
+                        new: new.href().map(Into::into),
+                    });
+                }
+                diff_toc_level(&old.children, &new.children, position, changes);
+            }
+            (Some(old), None) => changes.push(TocChange::Removed {
+                position: position.clone(),
+                label: old.label.clone(),
+            }),
+            (None, Some(new)) => changes.push(TocChange::Added {
+                position: position.clone(),
+                label: new.label.clone(),
+            }),
+            (None, None) => {}
+        }
+
+        position.pop();
+    }
+}
+
+impl fmt::Display for EpubDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.metadata {
+            writeln!(
+                f,
+                "~ metadata {}: {:?} -> {:?}",
+                change.name, change.old, change.new
+            )?;
+        }
+        for change in &self.resources {
+            match change {
+                ResourceChange::Added { path, .. } => writeln!(f, "+ resource {}", path.display())?,
+                ResourceChange::Removed { path, .. } => {
+                    writeln!(f, "- resource {}", path.display())?
+                }
+                ResourceChange::Modified { path, .. } => {
+                    writeln!(f, "~ resource {}", path.display())?
+                }
+            }
+        }
+        for change in &self.spine {
+            match change {
+                SpineChange::Added { idref, index } => writeln!(f, "+ spine {idref} at {index}")?,
+                SpineChange::Removed { idref, index } => writeln!(f, "- spine {idref} at {index}")?,
+                SpineChange::Moved { idref, from, to } => {
+                    writeln!(f, "~ spine {idref} moved from {from} to {to}")?
+                }
+            }
+        }
+        for change in &self.toc {
+            match change {
+                TocChange::Added { position, label } => {
+                    writeln!(f, "+ toc {position:?} {label:?}")?
+                }
+                TocChange::Removed { position, label } => {
+                    writeln!(f, "- toc {position:?} {label:?}")?
+                }
+                TocChange::Relabeled { position, old, new } => {
+                    writeln!(f, "~ toc {position:?} {old:?} -> {new:?}")?
+                }
+                TocChange::Retargeted { position, old, new } => {
+                    let display = |path: &Option<PathBuf>| {
+                        path.as_deref()
+                            .map_or_else(|| "-".to_string(), |path| path.display().to_string())
+                    };
+                    writeln!(f, "~ toc {position:?} {} -> {}", display(old), display(new))?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
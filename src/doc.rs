@@ -3,40 +3,86 @@
 //! Provides easy methods to navigate through the epub content, cover,
 //! chapters, etc.
 
+#[cfg(feature = "rewrite")]
+use crate::anchors::ChapterAnchors;
 use crate::archive::EpubArchive;
 use crate::error::{ArchiveError, Result};
-use crate::parsers::{EpubMetadata, EpubParser};
-use roxmltree::StringStorage;
+use crate::metadata::TextDirection;
+use crate::options::{MediaTypeSniffing, ParseMode, ParseOptions};
+use crate::parsers::{self, EpubParser};
+use crate::sniff::{self, SniffResult};
+use crate::split_support::PackageEdits;
 use std::borrow::Cow;
-use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::BufReader;
 use std::io::{Read, Seek};
 use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
 
 use crate::parsers::v2::EpubV2Parser;
 use crate::parsers::v3::EpubV3Parser;
+use crate::utils;
 use crate::xmlutils;
-use crate::xmlutils::{OwnedAttribute, OwnedName, XMLError};
+
+pub use crate::parsers::EpubMetadata;
+pub use crate::xmlutils::{OwnedAttribute, OwnedName};
+/// The map of [EpubDoc::resources], re-exported so it can be named without depending on `indexmap`.
+pub use indexmap::IndexMap;
 
 /// Struct that represent a navigation point in a table of content
-#[derive(Debug, Eq, Clone)]
+///
+/// Two entries are equal if all their fields are, children included, and they come from the same position in the
+/// document. They are ordered by their `play_order`, then by their document order, so sorting keeps entries which
+/// share a play order in place.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NavPoint {
     /// the title of this navpoint
     pub label: String,
     /// the resource path
-    pub content: PathBuf,
+    ///
+    /// `None` for a `<span>` heading of the EPUB3 nav, which has no link of its own and only groups its nested
+    /// entries, e.g. the parts of a book. The navigation helpers skip such entries.
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_opt_path"))]
+    pub content: Option<PathBuf>,
+    /// The fragment of the href within the resource, if any, e.g. `sec-2` for `chapter3.xhtml#sec-2`
+    pub fragment: Option<String>,
+    /// The `id` of the NCX `navPoint`, or of the nav `li` (or else its link)
+    pub id: Option<String>,
+    /// The `class` of the NCX `navPoint`, e.g. `part` or `chapter`, or the `epub:type` of the nav `li` (or else its
+    /// link)
+    pub kind: Option<String>,
     /// nested navpoints
     pub children: Vec<NavPoint>,
+    /// Whether the entry, or the nav or list containing it, has the `hidden` attribute, i.e. it isn't meant to be
+    /// shown to readers. Always `false` for the NCX.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hidden: bool,
     /// the order in the toc
+    ///
+    /// The `playOrder` of the NCX `navPoint`, or its index among its siblings if any of them lacks one. The entries
+    /// of the nav are numbered in document order. Entries are kept in document order, unless all siblings have an
+    /// explicit `playOrder`, then they are (stably) sorted by it.
     pub play_order: usize,
+    /// The index of the entry in document order among its siblings, breaks ties between equal play orders
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) document_index: usize,
 }
 
 impl Ord for NavPoint {
     fn cmp(&self, other: &NavPoint) -> Ordering {
-        self.play_order.cmp(&other.play_order)
+        self.play_order
+            .cmp(&other.play_order)
+            .then_with(|| self.document_index.cmp(&other.document_index))
+            .then_with(|| self.label.cmp(&other.label))
+            .then_with(|| self.content.cmp(&other.content))
+            .then_with(|| self.fragment.cmp(&other.fragment))
+            .then_with(|| self.id.cmp(&other.id))
+            .then_with(|| self.kind.cmp(&other.kind))
+            .then_with(|| self.hidden.cmp(&other.hidden))
+            .then_with(|| self.children.cmp(&other.children))
     }
 }
 
@@ -46,12 +92,6 @@ impl PartialOrd for NavPoint {
     }
 }
 
-impl PartialEq for NavPoint {
-    fn eq(&self, other: &NavPoint) -> bool {
-        self.play_order == other.play_order
-    }
-}
-
 /// A resource item is any item that was listed in the `content.opf` as part of the manifest.
 /// It is guaranteed to have a path within the Epub, and a mime type.
 ///
@@ -59,20 +99,141 @@ impl PartialEq for NavPoint {
 /// Note that `cover-image` and `nav` properties are already handled in the Epub V3 parsing.
 /// See [EpubDoc::get_cover] and [EpubDoc::get_toc] for more information.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ResourceItem {
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_path"))]
     pub path: PathBuf,
     pub mime: String,
-    pub property: Option<String>,
+    /// The space separated `properties` attribute, e.g. `["nav", "scripted"]`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub properties: Vec<String>,
+    /// The `media-overlay` attribute, the manifest id of the SMIL document narrating this item, see
+    /// [EpubDoc::media_overlay_for]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub media_overlay: Option<String>,
+    /// The `fallback` attribute, the manifest id of the item to use if this one's mime type isn't supported, see
+    /// [EpubDoc::resolve_fallback_chain]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fallback: Option<String>,
+    /// The uncompressed size of the file as recorded in the zip directory, `None` if the file is missing
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub size: Option<u64>,
+    /// The compressed size of the file as recorded in the zip directory, `None` if the file is missing
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compressed_size: Option<u64>,
+    /// Any other attributes of the `item`, e.g. vendor specific ones, as written
+    pub extra_attrs: Vec<OwnedAttribute>,
+}
+
+impl ResourceItem {
+    /// Whether the `properties` attribute lists the given property, e.g. `nav` or `cover-image`.
+    pub fn has_property(&self, property: &str) -> bool {
+        self.properties.iter().any(|p| p == property)
+    }
+
+    /// Whether this is a content document: XHTML, HTML, or an SVG image, which may be in the spine too.
+    pub fn is_document(&self) -> bool {
+        mime_matches_any(
+            &["application/xhtml+xml", "text/html", "image/svg+xml"],
+            &self.mime,
+        )
+    }
+
+    /// Whether this is an image of one of the core media types: GIF, JPEG, PNG, SVG or WebP.
+    pub fn is_image(&self) -> bool {
+        mime_matches_any(
+            &[
+                "image/gif",
+                "image/jpeg",
+                "image/png",
+                "image/svg+xml",
+                "image/webp",
+            ],
+            &self.mime,
+        )
+    }
+
+    /// Whether this is a CSS stylesheet.
+    pub fn is_stylesheet(&self) -> bool {
+        mime_matches("text/css", &self.mime)
+    }
+
+    /// Whether this is a font, a `font/*` type or one of the legacy ones like `application/vnd.ms-opentype`.
+    pub fn is_font(&self) -> bool {
+        mime_matches_any(FONT_MIMES, &self.mime)
+    }
+
+    /// Whether this is audio of one of the core media types: MP3, MP4 (AAC) or Ogg (Opus).
+    pub fn is_audio(&self) -> bool {
+        mime_matches_any(&["audio/mpeg", "audio/mp4", "audio/ogg"], &self.mime)
+    }
+
+    /// Whether this is a video, any `video/*` type as the specification leaves them open.
+    pub fn is_video(&self) -> bool {
+        mime_matches("video/*", &self.mime)
+    }
+
+    /// Whether this is a script, JavaScript by any of its media types.
+    pub fn is_script(&self) -> bool {
+        mime_matches_any(
+            &[
+                "application/javascript",
+                "application/ecmascript",
+                "text/javascript",
+            ],
+            &self.mime,
+        )
+    }
+
+    /// Whether the item is a remote resource, with an absolute URL like `https://example.com/audio.mp3` as its
+    /// `path`, see [EpubDoc::remote_resources].
+    pub fn is_remote(&self) -> bool {
+        utils::is_remote(&self.path.to_string_lossy())
+    }
+
+    /// The media type going by the extension of the path, for the common types of an epub, e.g.
+    /// `application/xhtml+xml` for `.xhtml`.
+    ///
+    /// Meant to cross-check the declared `mime`, see [EpubDoc::sniff_resource_mime] to check the content instead.
+    pub fn inferred_mime(&self) -> Option<&'static str> {
+        sniff::mime_from_extension(&self.path)
+    }
+
+    /// The `properties` attribute, with the properties separated by a single space.
+    #[deprecated(note = "use `properties` or `has_property` instead")]
+    pub fn property(&self) -> Option<String> {
+        Some(self.properties.join(" ")).filter(|properties| !properties.is_empty())
+    }
+}
+
+/// An `itemref` in the spine of the package document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpineItem {
+    /// The id of the referenced resource in the manifest
+    pub idref: String,
+    /// The id of the `itemref` element itself, if any
+    pub id: Option<String>,
+    /// Whether this item is part of the primary reading order (`linear="no"` makes it auxiliary content)
+    pub linear: bool,
+    /// The raw `properties` attribute, e.g. `page-spread-left`
+    pub properties: Option<String>,
+    /// Any other attributes of the `itemref`, e.g. vendor specific ones, as written
+    pub extra_attrs: Vec<OwnedAttribute>,
 }
 
 /// A Metadata Node represents a piece of metadata that is in the `content.opf` file of the Epub.
 /// It contains its textual content, as well as any attributes that was on the XML node.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MetadataNode {
     /// The textual content that was within the XML open and close tags
     pub content: String,
     /// The attributes of the XML node
     pub attr: Vec<OwnedAttribute>,
+    /// The namespace of the metadata name, e.g. the Dublin Core namespace for both `<dc:title>` and
+    /// `<meta property="dc:title">`, if known
+    pub namespace: Option<String>,
 }
 
 impl MetadataNode {
@@ -80,26 +241,29 @@ impl MetadataNode {
         MetadataNode {
             content: content.into(),
             attr: Vec::new(),
+            namespace: None,
         }
     }
 
     pub fn from_attr(content: impl Into<String>, node: &roxmltree::Node) -> MetadataNode {
+        Self::from_attr_limited(content, node, usize::MAX)
+    }
+
+    /// Like [MetadataNode::from_attr], but only keeps the first `max_attributes` attributes.
+    pub(crate) fn from_attr_limited(
+        content: impl Into<String>,
+        node: &roxmltree::Node,
+        max_attributes: usize,
+    ) -> MetadataNode {
         let attrs = node
             .attributes()
-            .map(|attr| OwnedAttribute {
-                name: OwnedName {
-                    namespace: attr.namespace().map(|r| r.to_owned()),
-                    tag: attr.name().to_owned(),
-                },
-                value: match attr.value_storage() {
-                    StringStorage::Borrowed(val) => (*val).into(),
-                    StringStorage::Owned(val) => val.clone(),
-                },
-            })
+            .take(max_attributes)
+            .map(|attr| OwnedAttribute::from_attribute(&attr))
             .collect();
         MetadataNode {
             content: content.into(),
             attr: attrs,
+            namespace: node.tag_name().namespace().map(Into::into),
         }
     }
 
@@ -116,10 +280,150 @@ impl MetadataNode {
     }
 }
 
+/// A `reference` from the (EPUB2) `guide` section of the package document, or an EPUB3 landmark.
+///
+/// See [GuideReference::semantic] for a normalized version of the `kind`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GuideReference {
+    /// The `type` attribute, e.g. `cover` or `text`, or the `epub:type` of a landmark, e.g. `frontmatter titlepage`
+    pub kind: String,
+    /// The human readable title, if any
+    pub title: Option<String>,
+    /// The full path of the referenced resource within the epub
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_path"))]
+    pub path: PathBuf,
+    /// The fragment of the href, if any
+    pub fragment: Option<String>,
+    /// Whether the landmark, or the nav containing it, has the `hidden` attribute. Always `false` for the guide.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hidden: bool,
+}
+
+/// An entry of the EPUB3 `landmarks` nav, e.g. the `bodymatter` where reading starts, see [EpubDoc::landmarks].
+///
+/// Landmarks share their representation with the EPUB2 guide, which serves the same purpose.
+pub type Landmark = GuideReference;
+
+/// A manifest item outside the epub, see [EpubDoc::remote_resources].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RemoteResource {
+    /// The manifest id
+    pub id: String,
+    /// The absolute URL, as written in the manifest
+    pub url: String,
+    /// The media type
+    pub mime: String,
+}
+
+/// An EPUB3 `collection` of the package document, grouping resources for a specific purpose, see
+/// [EpubDoc::collections].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PackageCollection {
+    /// The `role` attribute, e.g. `dictionary`, `index` or `manifest`
+    pub role: String,
+    /// The `id` attribute, if any
+    pub id: Option<String>,
+    /// The full paths within the epub of the resources the collection links to, in document order
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_paths"))]
+    pub links: Vec<PathBuf>,
+    /// The collections within this one
+    pub nested: Vec<PackageCollection>,
+}
+
+/// A `link` element in the package metadata, e.g. to a bibliographic record, see [EpubDoc::meta_links].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetaLink {
+    /// The `rel` attribute, e.g. `record` or `dcterms:conformsTo`
+    pub rel: String,
+    /// The `href` attribute as written, relative to the package document unless it's remote
+    pub href: String,
+    /// The `media-type` attribute, e.g. `application/xml`
+    pub media_type: Option<String>,
+    /// The `properties` attribute, e.g. `onix` or `marc21xml-record` for the format of a record
+    pub properties: Option<String>,
+}
+
+impl MetaLink {
+    /// Whether the link points outside the epub, e.g. `https://example.com/record.xml`.
+    pub fn is_remote(&self) -> bool {
+        utils::is_remote(&self.href)
+    }
+}
+
+/// An entry of the NCX `pageList` or EPUB3 `page-list` nav, where a page of the print edition starts, see [EpubDoc::page_list].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageTarget {
+    /// The page number as displayed, e.g. `12` or `xiv`
+    pub label: String,
+    /// The full path of the referenced resource within the epub
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_path"))]
+    pub path: PathBuf,
+    /// The fragment of the href, if any
+    pub fragment: Option<String>,
+    /// Whether the page, or the nav containing it, has the `hidden` attribute, as the page-list usually has.
+    /// Always `false` for the NCX.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hidden: bool,
+}
+
+/// The `docTitle`, `docAuthor` and `dtb:` head metadata of an NCX, see [EpubDoc::ncx_metadata].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NcxMetadata {
+    /// The text of `docTitle`
+    pub title: Option<String>,
+    /// The text of the first `docAuthor`
+    pub author: Option<String>,
+    /// `dtb:depth`, the depth of the `navMap`
+    pub depth: Option<u32>,
+    /// `dtb:totalPageCount`, zero if the book has no page numbers
+    pub total_page_count: Option<u32>,
+    /// `dtb:maxPageNumber`, zero if the book has no page numbers
+    pub max_page_number: Option<u32>,
+    /// `dtb:uid`, which should match the unique identifier of the package
+    pub uid: Option<String>,
+}
+
+/// The sections of the package document which are parsed independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OpfSection {
+    /// The package document as a whole
+    Package,
+    Metadata,
+    Manifest,
+    Spine,
+    Toc,
+    Guide,
+}
+
+/// A section of the package document which could not be parsed, see [EpubDoc::partial_failures].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PartialFailure {
+    pub section: OpfSection,
+    /// Description of the error which occurred
+    pub message: String,
+}
+
+/// Something which was dropped or changed while parsing the package document, see [EpubDoc::parse_warnings].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseWarning {
+    pub section: OpfSection,
+    /// Description of what happened
+    pub message: String,
+}
+
 /// Struct to control the epub document
 pub struct EpubDoc<R: Read + Seek> {
     /// the zip archive
-    archive: RefCell<EpubArchive<R>>,
+    archive: Mutex<EpubArchive<R>>,
 
     /// root file base path
     pub root_base: PathBuf,
@@ -127,9 +431,58 @@ pub struct EpubDoc<R: Read + Seek> {
     /// root file full path
     pub root_file: PathBuf,
 
+    /// The spine index of the current chapter
+    current: usize,
+
+    /// How far into the current chapter the reader is, from `0.0` to `1.0`
+    pub(crate) current_fraction: f32,
+
+    /// Whether navigation helpers skip non-linear spine items
+    pub(crate) linear_navigation: bool,
+
+    /// Whether the media type lookups prefer the type detected from the content
+    pub(crate) prefer_sniffed_mime: bool,
+
+    /// Lazily computed uncompressed sizes of the spine items
+    pub(crate) spine_sizes: OnceLock<Vec<u64>>,
+
+    /// Lazily parsed `META-INF/metadata.xml`, see [EpubDoc::container_metadata]
+    container_metadata: OnceLock<Option<HashMap<String, Vec<MetadataNode>>>>,
+
+    /// Lazily scanned anchors of the chapters, by spine index
+    #[cfg(feature = "rewrite")]
+    pub(crate) anchor_cache: Mutex<HashMap<usize, std::sync::Arc<ChapterAnchors>>>,
+
+    /// Changes to the manifest, spine and ToC which the writer has to persist
+    pub(crate) edits: PackageEdits,
+
+    /// The parsed package document.
+    ///
+    /// Prefer the accessor methods such as [EpubDoc::metadata], [EpubDoc::resources] and [EpubDoc::spine],
+    /// this field will stop being public in a future release.
+    #[deprecated(
+        since = "2.1.0",
+        note = "use the accessor methods such as `EpubDoc::metadata` instead"
+    )]
     pub context: EpubMetadata,
 }
 
+impl<R: Read + Seek> std::fmt::Debug for EpubDoc<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut metadata_keys: Vec<_> = self.metadata().keys().collect();
+        metadata_keys.sort();
+
+        f.debug_struct("EpubDoc")
+            .field("root_base", &self.root_base)
+            .field("root_file", &self.root_file)
+            .field("spine_len", &self.spine().len())
+            .field("resources", &self.resources().len())
+            .field("metadata_keys", &metadata_keys)
+            .field("cover_id", &self.get_cover_id())
+            .finish_non_exhaustive()
+    }
+}
+
 impl EpubDoc<BufReader<File>> {
     /// Opens the epub file in `path`.
     ///
@@ -150,9 +503,19 @@ impl EpubDoc<BufReader<File>> {
     /// Returns an error if the epub is broken or if the file doesn't
     /// exists.
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        Self::new_with_options(path, ParseOptions::default())
+    }
+
+    /// Opens the epub file in `path` with the given [ParseOptions].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the epub is broken or if the file doesn't
+    /// exists.
+    pub fn new_with_options<P: AsRef<Path>>(path: P, options: ParseOptions) -> Result<Self> {
         let path = path.as_ref();
         let file = File::open(path)?;
-        let doc = EpubDoc::from_reader(BufReader::new(file))?;
+        let doc = EpubDoc::from_reader_with_options(BufReader::new(file), options)?;
 
         Ok(doc)
     }
@@ -185,34 +548,421 @@ impl<R: Read + Seek> EpubDoc<R> {
     ///
     /// Returns an error if the epub is broken.
     pub fn from_reader(reader: R) -> Result<Self> {
-        let mut archive = EpubArchive::from_reader(reader)?;
-        let resources = HashMap::new();
+        Self::from_reader_with_options(reader, ParseOptions::default())
+    }
 
+    /// Opens the epub contained in `reader` with the given [ParseOptions].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the epub is broken, see [ParseMode] for what is considered broken.
+    pub fn from_reader_with_options(reader: R, options: ParseOptions) -> Result<Self> {
+        let archive = EpubArchive::from_reader(reader)?;
+        let mut doc = Self::from_parts(EpubMetadata::default(), archive)?;
+
+        doc.fill_resources(&options)?;
+
+        Ok(doc)
+    }
+
+    /// Reassembles an epub from its parts, see [EpubDoc::into_parts].
+    ///
+    /// Only the container file is read to find the root file again, the package document isn't parsed.
+    /// `metadata` should come from the same epub as `archive`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// let doc = EpubDoc::new("test.epub").unwrap();
+    /// let (metadata, archive) = doc.into_parts();
+    ///
+    /// // The metadata can be kept around cheaply, and the epub reopened when needed
+    /// let doc = EpubDoc::from_parts(metadata, archive).unwrap();
+    /// assert!(doc.get_resource("portada.png").is_some());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the container file can't be read.
+    pub fn from_parts(mut metadata: EpubMetadata, mut archive: EpubArchive<R>) -> Result<Self> {
         let container = archive.get_container_file()?;
+        // The resources are public, they may have been changed since
+        metadata.index_resource_paths();
         let root_file = get_root_file(&container)?;
         let base_path = root_file.parent().expect("All files have a parent");
 
-        let mut doc = EpubDoc {
-            archive: RefCell::new(archive),
+        #[allow(deprecated)]
+        Ok(EpubDoc {
+            archive: Mutex::new(archive),
             root_base: base_path.to_path_buf(),
             root_file,
-            context: EpubMetadata {
-                spine: vec![],
-                resources,
-                toc: vec![],
-                metadata: Default::default(),
-                cover_id: None,
-                unique_identifier: None,
-            },
-        };
+            current: 0,
+            current_fraction: 0.0,
+            linear_navigation: false,
+            prefer_sniffed_mime: false,
+            spine_sizes: OnceLock::new(),
+            container_metadata: OnceLock::new(),
+            #[cfg(feature = "rewrite")]
+            anchor_cache: Mutex::default(),
+            edits: PackageEdits::default(),
+            context: metadata,
+        })
+    }
 
-        doc.fill_resources()?;
+    /// Splits the epub into the parsed package document and the archive.
+    ///
+    /// The current position and navigation settings are discarded.
+    #[allow(deprecated)]
+    pub fn into_parts(self) -> (EpubMetadata, EpubArchive<R>) {
+        let archive = self
+            .archive
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
 
-        Ok(doc)
+        (self.context, archive)
+    }
+
+    /// Unwraps the epub, returning the underlying reader.
+    ///
+    /// The position of the reader is undefined.
+    pub fn into_inner(self) -> R {
+        self.into_parts().1.into_inner()
+    }
+
+    pub(crate) fn archive(&self) -> MutexGuard<'_, EpubArchive<R>> {
+        // The archive is never left in an inconsistent state by a panic, so poisoning can be ignored
+        self.archive.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Internal access to the parsed package, used while the public `context` field is deprecated.
+    #[allow(deprecated)]
+    pub(crate) fn ctx(&self) -> &EpubMetadata {
+        &self.context
+    }
+
+    #[allow(deprecated)]
+    pub(crate) fn ctx_mut(&mut self) -> &mut EpubMetadata {
+        &mut self.context
+    }
+
+    /// Drops everything cached by spine index, after the spine changed.
+    pub(crate) fn invalidate_spine_caches(&mut self) {
+        self.spine_sizes = OnceLock::new();
+        #[cfg(feature = "rewrite")]
+        self.anchor_cache
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Returns all metadata found in the epub, indexed by the metadata name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::{EpubDoc, MetadataNode};
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let title = doc.metadata().get("title");
+    /// let expected = MetadataNode {
+    ///     namespace: Some("http://purl.org/dc/elements/1.1/".to_string()),
+    ///     ..MetadataNode::from_content("Todo es mío")
+    /// };
+    ///
+    /// assert_eq!(title.unwrap(), &vec![expected]);
+    /// ```
+    pub fn metadata(&self) -> &HashMap<String, Vec<MetadataNode>> {
+        &self.ctx().metadata
+    }
+
+    /// Returns all metadata as found in the epub, including the values discarded by the
+    /// [MetadataDedup](crate::options::MetadataDedup) policy.
+    ///
+    /// The same as [EpubDoc::metadata] with the default policy, which keeps everything.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(doc.metadata(), doc.raw_metadata());
+    /// ```
+    pub fn raw_metadata(&self) -> &HashMap<String, Vec<MetadataNode>> {
+        let ctx = self.ctx();
+        ctx.raw_metadata.as_ref().unwrap_or(&ctx.metadata)
+    }
+
+    /// Returns every metadata element with its name, in the order of the package document.
+    ///
+    /// Like [EpubDoc::raw_metadata] this includes the values discarded by the
+    /// [MetadataDedup](crate::options::MetadataDedup) policy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let (name, node) = doc.metadata_iter().next().unwrap();
+    /// assert_eq!(("title", "Todo es mío"), (name, node.content.as_str()));
+    /// ```
+    pub fn metadata_iter(&self) -> impl Iterator<Item = (&str, &MetadataNode)> {
+        let metadata = self.raw_metadata();
+
+        self.ctx()
+            .metadata_order
+            .iter()
+            .filter_map(|(name, index)| Some((name.as_str(), metadata.get(name)?.get(*index)?)))
+    }
+
+    /// Sets the content of the first metadata with this name, adding it if there is none, and returns the previous
+    /// content. The other metadata with this name, and the attributes of the changed one, are kept.
+    ///
+    /// Like with [EpubDoc::mdata] Dublin Core metadata can be named with or without its prefix. State derived from
+    /// the metadata is kept up to date, e.g. [EpubDoc::get_cover_id] after changing the `cover` and
+    /// [EpubDoc::unique_identifier] after changing the `identifier`.
+    ///
+    /// The change is in memory only, the epub itself isn't modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let mut doc = EpubDoc::new("test.epub").unwrap();
+    /// let previous = doc.set_mdata("dc:title", "Todo es tuyo");
+    /// assert_eq!(Some("Todo es mío".to_string()), previous);
+    /// assert_eq!(Some("Todo es tuyo"), doc.mdata("title"));
+    /// ```
+    pub fn set_mdata(&mut self, name: &str, value: &str) -> Option<String> {
+        self.ctx_mut().set_metadata(name, value)
+    }
+
+    /// Adds a metadata node with this name after the existing ones, see [EpubDoc::set_mdata].
+    ///
+    /// A node without a namespace gets the one of a `dc:` or `dcterms:` prefixed name. A node with a `refines`
+    /// attribute shows up in the refinements of the element it refers to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::{EpubDoc, MetadataNode};
+    /// # let mut doc = EpubDoc::new("test.epub").unwrap();
+    /// doc.add_mdata("dc:subject", MetadataNode::from_content("Novela"));
+    /// assert_eq!(Some("Novela"), doc.mdata("subject"));
+    /// ```
+    pub fn add_mdata(&mut self, name: &str, node: MetadataNode) {
+        self.ctx_mut().add_metadata(name, node);
+    }
+
+    /// Removes every metadata with this name, returning the removed nodes, see [EpubDoc::set_mdata].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let mut doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(1, doc.remove_mdata("cover").len());
+    /// assert_eq!(None, doc.get_cover_id());
+    /// ```
+    pub fn remove_mdata(&mut self, name: &str) -> Vec<MetadataNode> {
+        self.ctx_mut().remove_metadata(name)
+    }
+
+    /// Returns every resource listed in the manifest, indexed by its id.
+    ///
+    /// Iterating follows the order of the manifest. If an id is declared more than once, the last item wins, at the
+    /// position of the first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use std::path::Path;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let tpage = doc.resources().get("titlepage.xhtml").unwrap();
+    /// assert_eq!(tpage.path, Path::new("OEBPS/Text/titlepage.xhtml"));
+    ///
+    /// let (first, _) = doc.resources().first().unwrap();
+    /// assert_eq!("ncx", first);
+    /// ```
+    pub fn resources(&self) -> &IndexMap<String, ResourceItem> {
+        &self.ctx().resources
+    }
+
+    /// Returns the spine, in reading order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!("titlepage.xhtml", doc.spine()[0].idref);
+    /// ```
+    pub fn spine(&self) -> &[SpineItem] {
+        &self.ctx().spine
+    }
+
+    /// Returns the manifest id of the NCX declared by the `toc` attribute of the spine, if any.
+    ///
+    /// If the attribute is missing or refers to nothing and there's no EPUB3 navigation document, an NCX in the
+    /// manifest is used instead, which is recorded in [EpubDoc::parse_warnings].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(Some("ncx"), doc.toc_id());
+    /// ```
+    pub fn toc_id(&self) -> Option<&str> {
+        self.ctx().toc_id.as_deref()
+    }
+
+    /// Returns the `id` attribute of the spine, if any.
+    pub fn spine_id(&self) -> Option<&str> {
+        self.ctx().spine_id.as_deref()
+    }
+
+    /// Returns the `reference`s listed in the EPUB2 guide, if any.
+    pub fn guide(&self) -> &[GuideReference] {
+        &self.ctx().guide
+    }
+
+    /// Returns the entries of the EPUB3 landmarks navigation, if any.
+    pub fn landmarks(&self) -> &[Landmark] {
+        &self.ctx().landmarks
+    }
+
+    /// Returns the entries of the EPUB3 page-list navigation or else the NCX page list, mapping print pages to
+    /// locations in the epub, if any.
+    pub fn page_list(&self) -> &[PageTarget] {
+        &self.ctx().page_list
+    }
+
+    /// Returns the head metadata of the NCX, or `None` if the book has no NCX or it wasn't parsed.
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// let doc = EpubDoc::new("test.epub").unwrap();
+    /// let ncx = doc.ncx_metadata().unwrap();
+    /// assert_eq!(Some("Todo es mío"), ncx.title.as_deref());
+    /// assert_eq!(Some(3), ncx.depth);
+    /// ```
+    pub fn ncx_metadata(&self) -> Option<&NcxMetadata> {
+        self.ctx().ncx.as_ref()
+    }
+
+    /// Returns the metadata of `META-INF/metadata.xml`, the container-level metadata reserved by the OCF
+    /// specification, or `None` if the book has none or it isn't well-formed XML.
+    ///
+    /// The elements are converted the same way as the package metadata, see [EpubDoc::metadata]. The file is only
+    /// read on the first call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(None, doc.container_metadata());
+    /// ```
+    pub fn container_metadata(&self) -> Option<&HashMap<String, Vec<MetadataNode>>> {
+        self.container_metadata
+            .get_or_init(|| {
+                let content = self.archive().get_entry("META-INF/metadata.xml").ok()?;
+                let text = xmlutils::ensure_utf8(&content).ok()?;
+                let root = xmlutils::parse_xml(&text).ok()?;
+
+                Some(parsers::parse_metadata_element(&root.root_element()))
+            })
+            .as_ref()
+    }
+
+    /// Returns the EPUB3 `collection` elements of the package document, in document order.
+    pub fn collections(&self) -> &[PackageCollection] {
+        &self.ctx().collections
+    }
+
+    /// Returns the `link` elements of the package metadata, in document order.
+    pub fn meta_links(&self) -> &[MetaLink] {
+        &self.ctx().meta_links
+    }
+
+    /// Reads the resource a metadata [link](EpubDoc::meta_links) points at from the archive, e.g. an ONIX record.
+    ///
+    /// Relative hrefs are resolved against the package document, any fragment is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::RemoteResource] if the link points outside the epub, or an error if the resource is
+    /// missing from the archive.
+    pub fn get_meta_record(&self, link: &MetaLink) -> Result<Vec<u8>> {
+        if link.is_remote() {
+            return Err(ArchiveError::RemoteResource(link.href.clone()));
+        }
+        let (href, _) = utils::split_fragment(&link.href);
+        let path = build_epub_uri(&self.root_file, "", href);
+
+        self.get_resource_by_path(path.as_ref())
+    }
+
+    /// Returns the `version` attribute of the package document, e.g. `2.0` or `3.0`.
+    pub fn epub_version(&self) -> Option<&str> {
+        self.ctx().version.as_deref()
+    }
+
+    /// Returns the sections of the package document which could not be parsed.
+    ///
+    /// This can only be non-empty for the optional sections (ToC and guide), unless the epub was opened with
+    /// [ParseMode::Lenient].
+    pub fn partial_failures(&self) -> &[PartialFailure] {
+        &self.ctx().partial_failures
+    }
+
+    /// Returns the warnings raised while parsing, e.g. metadata truncated because of the
+    /// [MetadataLimits](crate::options::MetadataLimits).
+    ///
+    /// Unlike [EpubDoc::partial_failures], the sections these refer to are still available.
+    pub fn parse_warnings(&self) -> &[ParseWarning] {
+        &self.ctx().warnings
+    }
+
+    /// Returns [ArchiveError::SectionUnavailable] if the given section failed to parse.
+    pub(crate) fn ensure_section(&self, section: OpfSection) -> Result<()> {
+        if self
+            .ctx()
+            .partial_failures
+            .iter()
+            .any(|failure| failure.section == section)
+        {
+            Err(ArchiveError::SectionUnavailable(section))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the value of the identifier referenced by the package `unique-identifier` attribute.
+    pub fn unique_identifier(&self) -> Option<&str> {
+        self.ctx().unique_identifier.as_deref()
+    }
+
+    /// Returns the `dc:identifier` referenced by the package `unique-identifier` attribute, with its attributes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let node = doc.unique_identifier_node().unwrap();
+    /// assert_eq!(Some(node.content.as_str()), doc.unique_identifier());
+    /// ```
+    pub fn unique_identifier_node(&self) -> Option<&MetadataNode> {
+        let ctx = self.ctx();
+        ctx.identifiers.get(ctx.unique_identifier_index?)
     }
 
     /// Returns the content of the first metadata found with this name.
     ///
+    /// Dublin Core metadata can be looked up with or without its prefix, `title` and `dc:title` are the same.
+    ///
     /// #Examples
     ///
     /// ```
@@ -222,13 +972,10 @@ impl<R: Read + Seek> EpubDoc<R> {
     /// let title = doc.mdata("title");
     /// assert_eq!(title.unwrap(), "Todo es mío");
     pub fn mdata(&self, name: &str) -> Option<&str> {
-        match self.context.metadata.get(name) {
-            Some(v) => v.get(0).map(|m| m.content.as_str()),
-            None => None,
-        }
+        self.mdata_full(name).map(|m| m.content.as_str())
     }
 
-    /// Returns the first full metadata found with this name.
+    /// Returns the first full metadata found with this name, see [EpubDoc::mdata].
     ///
     /// #Examples
     ///
@@ -239,15 +986,219 @@ impl<R: Read + Seek> EpubDoc<R> {
     /// let title = doc.mdata_full("title");
     /// assert_eq!(title.unwrap().content, "Todo es mío");
     pub fn mdata_full(&self, name: &str) -> Option<&MetadataNode> {
-        match self.context.metadata.get(name) {
-            Some(v) => v.get(0),
-            None => None,
+        self.mdata_nodes(name).first()
+    }
+
+    /// Returns the content of the metadata with this name in the language `lang`, from its `xml:lang` attribute or
+    /// else the one of the package, see [EpubDoc::metadata_lang].
+    ///
+    /// Language tags are compared case-insensitively and match more specific tags, so `en` matches `en-US`. If
+    /// nothing matches, `lang` is truncated (`en-US` to `en`) and tried again. If there is still no match the first
+    /// node without a language is returned, as it applies to every language.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// // The title has no language, so it matches any
+    /// assert_eq!(Some("Todo es mío"), doc.mdata_lang("title", "en-GB"));
+    /// ```
+    pub fn mdata_lang(&self, name: &str, lang: &str) -> Option<&str> {
+        let nodes = self.mdata_nodes(name);
+        let mut range = lang;
+
+        loop {
+            let found = nodes.iter().find(|node| {
+                self.metadata_lang(node).is_some_and(|tag| {
+                    tag.get(..range.len())
+                        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(range))
+                        && matches!(tag.as_bytes().get(range.len()), None | Some(b'-'))
+                })
+            });
+            if let Some(node) = found {
+                return Some(node.content.as_str());
+            }
+
+            match range.rsplit_once('-') {
+                Some((shorter, _)) => range = shorter,
+                None => break,
+            }
+        }
+
+        nodes
+            .iter()
+            .find(|node| self.metadata_lang(node).is_none())
+            .map(|node| node.content.as_str())
+    }
+
+    /// Returns the distinct languages of the metadata with this name, in document order, see
+    /// [EpubDoc::metadata_lang].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert!(doc.mdata_languages("title").is_empty());
+    /// ```
+    pub fn mdata_languages(&self, name: &str) -> Vec<&str> {
+        let mut languages: Vec<&str> = Vec::new();
+
+        for lang in self
+            .mdata_nodes(name)
+            .iter()
+            .filter_map(|node| self.metadata_lang(node))
+        {
+            if !languages
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(lang))
+            {
+                languages.push(lang);
+            }
+        }
+
+        languages
+    }
+
+    /// Returns the content of the first metadata whose property resolves to `property_iri`, see
+    /// [EpubDoc::resolve_property].
+    ///
+    /// Prefixes declared by the book are tried first, then the reserved ones, so a book can call its
+    /// `http://apple.com/ibooks/html-extensions` vocabulary `ib:` and still be found. Dublin Core elements are found
+    /// by their namespace, e.g. `http://purl.org/dc/elements/1.1/title`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(Some("Todo es mío"), doc.mdata_by_iri("http://purl.org/dc/elements/1.1/title"));
+    /// ```
+    pub fn mdata_by_iri(&self, property_iri: &str) -> Option<&str> {
+        if let Some(local) = property_iri.strip_prefix(xmlutils::DC_NAMESPACE) {
+            return self
+                .mdata_nodes(local)
+                .iter()
+                .find(|node| node.namespace.as_deref() == Some(xmlutils::DC_NAMESPACE))
+                .map(|node| node.content.as_str());
+        }
+
+        let metadata = &self.ctx().metadata;
+        let declared = self
+            .ctx()
+            .prefixes
+            .iter()
+            .map(|(prefix, iri)| (prefix.as_str(), iri.as_str()));
+        let reserved = parsers::RESERVED_PREFIXES
+            .iter()
+            .copied()
+            // Declared prefixes take precedence over the reserved ones
+            .filter(|(prefix, _)| !self.ctx().prefixes.iter().any(|(p, _)| p == prefix));
+
+        let prefixed = declared.chain(reserved).find_map(|(prefix, iri)| {
+            let reference = property_iri.strip_prefix(iri)?;
+            metadata.get(&format!("{prefix}:{reference}"))?.first()
+        });
+        let unprefixed = || {
+            let reference = property_iri.strip_prefix(parsers::DEFAULT_VOCABULARY)?;
+            // Dublin Core elements are stored unprefixed as well
+            metadata
+                .get(reference)?
+                .iter()
+                .find(|node| node.namespace.is_none())
+        };
+
+        prefixed
+            .or_else(unprefixed)
+            .map(|node| node.content.as_str())
+    }
+
+    /// Returns the full IRI of a metadata property, e.g. `http://www.idpf.org/vocab/rendition/#layout` for
+    /// `rendition:layout`, or `None` if its prefix is unknown.
+    ///
+    /// Prefixes are resolved through the `prefix` attribute of the package, then the reserved prefixes of the EPUB
+    /// specification. Unprefixed properties belong to the default `meta` vocabulary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(
+    ///     Some("http://schema.org/accessMode".to_string()),
+    ///     doc.resolve_property("schema:accessMode")
+    /// );
+    /// assert_eq!(None, doc.resolve_property("unknown:property"));
+    /// ```
+    pub fn resolve_property(&self, property: &str) -> Option<String> {
+        let Some((prefix, reference)) = property.split_once(':') else {
+            return Some(format!("{}{property}", parsers::DEFAULT_VOCABULARY));
+        };
+
+        let iri = self
+            .ctx()
+            .prefixes
+            .iter()
+            .find(|(p, _)| p == prefix)
+            .map(|(_, iri)| iri.as_str())
+            .or_else(|| {
+                parsers::RESERVED_PREFIXES
+                    .iter()
+                    .find(|(p, _)| *p == prefix)
+                    .map(|(_, iri)| *iri)
+            })?;
+        Some(format!("{iri}{reference}"))
+    }
+
+    /// The prefixes bound to the `vocabulary` IRI, the reserved one first.
+    pub(crate) fn vocabulary_prefixes(&self, vocabulary: &str) -> Vec<&str> {
+        let reserved = parsers::RESERVED_PREFIXES
+            .iter()
+            .filter(|(_, iri)| *iri == vocabulary)
+            .map(|(prefix, _)| *prefix);
+        let declared = self
+            .ctx()
+            .prefixes
+            .iter()
+            .filter(|(_, iri)| iri == vocabulary)
+            .map(|(prefix, _)| prefix.as_str());
+
+        let mut prefixes: Vec<&str> = Vec::new();
+        for prefix in reserved.chain(declared) {
+            if !prefixes.contains(&prefix) {
+                prefixes.push(prefix);
+            }
         }
+
+        prefixes
+    }
+
+    /// All metadata nodes with this name, see [EpubDoc::mdata].
+    fn mdata_nodes(&self, name: &str) -> &[MetadataNode] {
+        let metadata = &self.ctx().metadata;
+        // Dublin Core metadata is stored by its local name
+        metadata
+            .get(name)
+            .or_else(|| metadata.get(name.strip_prefix("dc:")?))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the content of the first EPUB3 `meta` with the given `property` which refines `node`.
+    pub(crate) fn refinement(&self, node: &MetadataNode, property: &str) -> Option<&str> {
+        let id = node.find_attr("id")?;
+
+        self.refinements_for(id)
+            .into_iter()
+            .find(|refinement| refinement.property == property)
+            .map(|refinement| refinement.node.content.as_str())
     }
 
     /// Returns the id of the epub cover.
     ///
-    /// The cover is searched in the doc metadata, by the tag <meta name="cover" value"..">
+    /// The cover is searched in the doc metadata, by the tag `<meta name="cover" content="..">`. The malformed
+    /// variants found in the wild are accepted too: a `value` instead of a `content` attribute, and the href of the
+    /// image instead of its id. For EPUB3 the `cover-image` item is used otherwise.
     ///
     /// # Examples
     ///
@@ -265,7 +1216,7 @@ impl<R: Read + Seek> EpubDoc<R> {
     ///
     /// Returns `None` if the cover path can't be found.
     pub fn get_cover_id(&self) -> Option<&str> {
-        self.context.cover_id.as_deref()
+        self.ctx().cover_id.as_deref()
     }
 
     /// Returns the cover as Vec<u8>
@@ -299,18 +1250,74 @@ impl<R: Read + Seek> EpubDoc<R> {
         Some(cover_data)
     }
 
-    /// Returns the ToC as found in the Epub.
+    /// Returns the ToC as found in the Epub, the one of the EPUB3 navigation document if there is one, otherwise the
+    /// one of the NCX.
     ///
     /// Note that if no ToC was found this [Vec] will be empty
     pub fn get_toc(&self) -> &Vec<NavPoint> {
-        &self.context.toc
+        &self.ctx().toc
     }
 
-    /// Returns Release Identifier defined at
-    /// https://www.w3.org/publishing/epub3/epub-packages.html#sec-metadata-elem-identifiers-pid
+    /// Returns the ToC of the NCX, which can differ from the one of the navigation document in EPUB3 books with
+    /// both, see [EpubDoc::get_toc].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(doc.get_toc(), doc.get_toc_ncx());
+    /// assert!(doc.get_toc_nav().is_empty());
+    /// ```
+    pub fn get_toc_ncx(&self) -> &[NavPoint] {
+        &self.ctx().toc_ncx
+    }
+
+    /// Returns the ToC of the EPUB3 navigation document, see [EpubDoc::get_toc].
+    pub fn get_toc_nav(&self) -> &[NavPoint] {
+        &self.ctx().toc_nav
+    }
+
+    /// Returns an identifier of this release of the book, `<identifier>@<modified>`.
+    ///
+    /// Unlike [EpubDoc::release_identifier_strict] this falls back to what is available, so it also works for EPUB2
+    /// books: the unique identifier or else the first `dc:identifier`, and the [modification
+    /// date](EpubDoc::modified_date) including the EPUB2 `opf:event="modification"` date. Without a modification
+    /// date it's just the identifier, which stays the same across releases of the book, so it no longer tells
+    /// releases apart. Returns `None` if the book has no identifier at all.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(
+    ///     Some("urn:uuid:09132750-3601-4d19-b3a4-55fdf8639849@2015-08-10T18:12:03Z".to_string()),
+    ///     doc.get_release_identifier()
+    /// );
+    /// ```
     pub fn get_release_identifier(&self) -> Option<String> {
+        let identifier = self.unique_identifier().map(str::trim).or_else(|| {
+            self.mdata_nodes("identifier")
+                .iter()
+                .map(|node| node.content.trim())
+                .find(|identifier| !identifier.is_empty())
+        })?;
+
+        match self.modified_date() {
+            Some(modified) => Some(format!("{identifier}@{}", modified.raw)),
+            None => Some(identifier.to_string()),
+        }
+    }
+
+    /// Returns the Release Identifier defined at
+    /// https://www.w3.org/publishing/epub3/epub-packages.html#sec-metadata-elem-identifiers-pid
+    ///
+    /// Returns `None` unless the book has both a unique identifier and a `dcterms:modified`, as required by EPUB3,
+    /// see [EpubDoc::get_release_identifier] for a version which also works for EPUB2 books.
+    pub fn release_identifier_strict(&self) -> Option<String> {
         match (
-            self.context.unique_identifier.as_ref(),
+            self.ctx().unique_identifier.as_ref(),
             self.mdata("dcterms:modified"),
         ) {
             (Some(unique_identifier), Some(modified)) => {
@@ -320,13 +1327,34 @@ impl<R: Read + Seek> EpubDoc<R> {
         }
     }
 
+    /// Returns a hash of the content of the whole epub archive.
+    ///
+    /// The hash is computed from the name, CRC-32, and size of every entry as recorded in the zip directory,
+    /// so nothing is decompressed. It's stable across releases of this crate.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the zip directory can't be read.
+    pub fn content_hash(&self) -> Result<u64> {
+        let mut layout = self.archive().layout()?;
+        layout.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let hash = layout.iter().fold(utils::FNV_OFFSET, |hash, entry| {
+            let hash = utils::fnv1a(hash, entry.name.as_bytes());
+            let hash = utils::fnv1a(hash, &entry.crc32.to_le_bytes());
+            utils::fnv1a(hash, &entry.size.to_le_bytes())
+        });
+
+        Ok(hash)
+    }
+
     /// Returns the resource content by full path in the epub archive
     ///
     /// # Errors
     ///
     /// Returns an error if the path doesn't exists in the epub
     pub fn get_resource_by_path<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
-        let content = self.archive.borrow_mut().get_entry(path)?;
+        let content = self.archive().get_entry(path)?;
         Ok(content)
     }
 
@@ -334,20 +1362,81 @@ impl<R: Read + Seek> EpubDoc<R> {
     ///
     /// # Returns
     ///
-    /// Returns `None` if the `id` doesn't exists in the epub
+    /// Returns `None` if the `id` doesn't exists in the epub, or is a [remote resource](EpubDoc::remote_resources).
     pub fn get_resource(&self, id: &str) -> Option<Vec<u8>> {
-        let res_item = self.context.resources.get(id)?;
+        let res_item = self.ctx().resources.get(id)?;
 
         self.get_resource_by_path(&res_item.path).ok()
     }
 
+    /// Reads the resource content by the id defined in the manifest into `buf`, returning the number of
+    /// bytes read.
+    ///
+    /// `buf` is cleared first, so a single buffer can be reused to read many resources without allocating
+    /// for each of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let mut buf = Vec::new();
+    /// for item in doc.spine() {
+    ///     let read = doc.get_resource_into(&item.idref, &mut buf).unwrap();
+    ///     assert_eq!(read, buf.len());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::InvalidId] if the `id` doesn't exist in the manifest, [ArchiveError::RemoteResource]
+    /// if it's a [remote resource](EpubDoc::remote_resources), or an error if the resource doesn't exist in the epub.
+    pub fn get_resource_into(&self, id: &str, buf: &mut Vec<u8>) -> Result<usize> {
+        let item = self
+            .ctx()
+            .resources
+            .get(id)
+            .ok_or(ArchiveError::InvalidId)?;
+        if item.is_remote() {
+            return Err(ArchiveError::RemoteResource(
+                item.path.to_string_lossy().into_owned(),
+            ));
+        }
+        let path = item.path.clone();
+
+        self.archive().read_entry_into(path, buf)
+    }
+
+    /// Returns whether the resource with the given manifest `id` exists in the epub archive,
+    /// without reading its content.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert!(doc.has_resource("portada.png"));
+    /// assert!(!doc.has_resource("not-in-the-manifest"));
+    /// ```
+    pub fn has_resource(&self, id: &str) -> bool {
+        self.ctx()
+            .resources
+            .get(id)
+            .is_some_and(|item| self.has_resource_path(&item.path))
+    }
+
+    /// Returns whether the full `path` exists in the epub archive, without reading its content.
+    pub fn has_resource_path(&self, path: impl AsRef<Path>) -> bool {
+        self.archive().contains(path)
+    }
+
     /// Returns the resource content by full path in the epub archive, as String
     ///
     /// # Returns
     ///
     /// Returns `None` if the path doesn't exists in the epub
     pub fn get_resource_str_by_path(&self, path: impl AsRef<Path>) -> Result<String, ArchiveError> {
-        let content = self.archive.borrow_mut().get_entry_as_str(path)?;
+        let content = self.archive().get_entry_as_str(path)?;
 
         Ok(content)
     }
@@ -358,7 +1447,7 @@ impl<R: Read + Seek> EpubDoc<R> {
     ///
     /// Returns `None` if the id doesn't exists in the epub
     pub fn get_resource_str(&self, id: &str) -> Option<String> {
-        let res_item = self.context.resources.get(id)?;
+        let res_item = self.ctx().resources.get(id)?;
 
         self.get_resource_str_by_path(&res_item.path).ok()
     }
@@ -377,11 +1466,11 @@ impl<R: Read + Seek> EpubDoc<R> {
     /// # Returns
     ///
     /// Fails if the resource can't be found.
+    ///
+    /// See [EpubDoc::set_prefer_sniffed_mime] to correct mislabeled resources.
     pub fn get_resource_mime(&self, id: &str) -> Option<&str> {
-        self.context
-            .resources
-            .get(id)
-            .map(|item| item.mime.as_str())
+        let item = self.ctx().resources.get(id)?;
+        Some(self.resource_mime(item))
     }
 
     /// Returns the resource mime searching by source full path
@@ -399,15 +1488,150 @@ impl<R: Read + Seek> EpubDoc<R> {
     /// # Errors
     ///
     /// Fails if the resource can't be found.
+    ///
+    /// See [EpubDoc::set_prefer_sniffed_mime] to correct mislabeled resources.
     pub fn get_resource_mime_by_path(&self, path: impl AsRef<Path>) -> Option<&str> {
-        let path = path.as_ref();
+        let id = self.resource_id_by_path(path)?;
+        Some(self.resource_mime(&self.ctx().resources[id]))
+    }
+
+    /// Returns the manifest id of the resource with the full `path` within the epub, the first one if several
+    /// items have that path.
+    ///
+    /// The path may be percent-encoded or not, and use either separator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(Some("portada.png"), doc.resource_id_by_path("OEBPS/Images/portada.png"));
+    /// assert_eq!(Some("normal.xml"), doc.resource_id_by_path("OEBPS/a%20normal%20item.xml"));
+    /// assert_eq!(Some("normal.xml"), doc.resource_id_by_path("OEBPS/a normal item.xml"));
+    /// assert_eq!(None, doc.resource_id_by_path("OEBPS/missing.png"));
+    /// ```
+    pub fn resource_id_by_path(&self, path: impl AsRef<Path>) -> Option<&str> {
+        let key = PathBuf::from(utils::normalized_path(path.as_ref()));
+        self.ctx().resource_paths.get(&key).map(String::as_str)
+    }
+
+    /// Returns the resources with the given mime type, as `(id, resource)` pairs in the order of the manifest.
+    ///
+    /// The match ignores case and any parameters like `;charset=utf-8`, and a trailing `*` matches any mime type
+    /// starting with the part before it, e.g. `image/*`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(2, doc.resources_by_mime("image/*").count());
+    /// assert_eq!(1, doc.resources_by_mime("text/css").count());
+    /// ```
+    ///
+    /// See [EpubDoc::set_prefer_sniffed_mime] to correct mislabeled resources.
+    pub fn resources_by_mime<'a>(
+        &'a self,
+        mime: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a ResourceItem)> + 'a {
+        self.resources_matching(move |item_mime| mime_matches(mime, item_mime))
+    }
+
+    /// Returns the images, i.e. the `image/*` resources, see [EpubDoc::resources_by_mime].
+    pub fn images(&self) -> impl Iterator<Item = (&str, &ResourceItem)> + '_ {
+        self.resources_by_mime("image/*")
+    }
+
+    /// Returns the `text/css` stylesheets, see [EpubDoc::resources_by_mime].
+    pub fn stylesheets(&self) -> impl Iterator<Item = (&str, &ResourceItem)> + '_ {
+        self.resources_by_mime("text/css")
+    }
+
+    /// Returns the fonts, i.e. the `font/*` resources as well as those with one of the legacy font mime types such
+    /// as `application/vnd.ms-opentype`, see [EpubDoc::resources_by_mime].
+    pub fn fonts(&self) -> impl Iterator<Item = (&str, &ResourceItem)> + '_ {
+        self.resources_matching(|mime| mime_matches_any(FONT_MIMES, mime))
+    }
 
-        self.context
+    /// Returns the manifest items outside the epub, with an absolute URL as their href, in the order of the manifest.
+    ///
+    /// EPUB3 allows audio, video and fonts to be remote, the content documents using them have the
+    /// `remote-resources` property. Reading systems usually ask before fetching them over the network.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert!(doc.remote_resources().is_empty());
+    /// ```
+    pub fn remote_resources(&self) -> Vec<RemoteResource> {
+        self.ctx()
             .resources
-            .values()
-            .filter(|data| data.path == path)
-            .map(|data| data.mime.as_str())
-            .next()
+            .iter()
+            .filter(|(_, item)| item.is_remote())
+            .map(|(id, item)| RemoteResource {
+                id: id.clone(),
+                url: item.path.to_string_lossy().into_owned(),
+                mime: item.mime.clone(),
+            })
+            .collect()
+    }
+
+    /// Follows the `fallback` chain starting at the resource with the manifest `id`, returning the first resource
+    /// whose mime type is `supported`, which may be the resource itself.
+    ///
+    /// Returns `None` if no resource of the chain is supported, or the chain ends in a dangling reference or a
+    /// cycle, which the specification forbids.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let png = doc.resolve_fallback_chain("portada.png", |mime| mime.starts_with("image/"));
+    /// assert!(png.is_some());
+    /// assert!(doc.resolve_fallback_chain("portada.png", |mime| mime == "image/svg+xml").is_none());
+    /// ```
+    ///
+    /// See [EpubDoc::set_prefer_sniffed_mime] to correct mislabeled resources.
+    pub fn resolve_fallback_chain(
+        &self,
+        id: &str,
+        supported: impl Fn(&str) -> bool,
+    ) -> Option<&ResourceItem> {
+        let mut visited = HashSet::new();
+        let mut id = id;
+
+        while visited.insert(id) {
+            let item = self.ctx().resources.get(id)?;
+            if supported(self.resource_mime(item)) {
+                return Some(item);
+            }
+            id = item.fallback.as_deref()?;
+        }
+
+        None
+    }
+
+    fn resources_matching<'a>(
+        &'a self,
+        matches: impl Fn(&str) -> bool + 'a,
+    ) -> impl Iterator<Item = (&'a str, &'a ResourceItem)> + 'a {
+        self.ctx()
+            .resources
+            .iter()
+            .filter(move |(_, item)| matches(self.resource_mime(item)))
+            .map(|(id, item)| (id.as_str(), item))
+    }
+
+    fn resource_mime<'a>(&self, item: &'a ResourceItem) -> &'a str {
+        match self.prefer_sniffed_mime {
+            true => self
+                .sniffed_override(&item.path, &item.mime)
+                .unwrap_or(&item.mime),
+            false => &item.mime,
+        }
     }
 
     /// Returns the chapter data at the provided spine id, with resource uris renamed so they
@@ -418,31 +1642,37 @@ impl<R: Read + Seek> EpubDoc<R> {
     /// for the relative path in the filesystem and that file isn't there. You should provide files
     /// with `url_prepend` using the get_resource_by_path
     ///
+    /// Requires the `rewrite` feature.
+    ///
     /// # Examples
     ///
     /// ```
     /// # use epub::doc::EpubDoc;
     /// # let mut doc = EpubDoc::new("test.epub").unwrap();
-    /// let spine_id = doc.context.spine.get(1).unwrap();
-    /// let current = doc.get_page_with_epub_uris(&spine_id, "epub://").unwrap();
+    /// let spine_id = &doc.spine()[1].idref;
+    /// let current = doc.get_page_with_epub_uris(spine_id, "epub://").unwrap();
     /// let text = String::from_utf8(current).unwrap();
     /// assert!(text.contains("epub://OEBPS/Styles/stylesheet.css"));
     /// assert!(text.contains("http://creativecommons.org/licenses/by-sa/3.0/"));
     /// ```
+    #[cfg(feature = "rewrite")]
     pub fn get_page_with_epub_uris(&self, spine_id: &str, url_prepend: &str) -> Result<Vec<u8>> {
+        self.ensure_section(OpfSection::Manifest)?;
         let path = &self
-            .context
+            .ctx()
             .resources
             .get(spine_id)
             .ok_or(ArchiveError::InvalidId)?
             .path;
         let html = self.get_resource_by_path(path)?;
-        let content = xmlutils::ensure_utf8(&html);
+        let content = xmlutils::ensure_utf8(&html)?;
 
         let settings = lol_html::Settings {
             element_content_handlers: vec![
                 lol_html::element!("a[href], link[href], image[href]", |el| {
-                    let current_val = el.get_attribute("href").ok_or(XMLError::NoElements)?;
+                    let current_val = el
+                        .get_attribute("href")
+                        .ok_or(xmlutils::XMLError::NoElements)?;
                     let href = build_epub_uri(path, url_prepend, &current_val);
 
                     el.set_attribute("href", &href)?;
@@ -450,7 +1680,9 @@ impl<R: Read + Seek> EpubDoc<R> {
                     Ok(())
                 }),
                 lol_html::element!("img[src]", |el| {
-                    let current_val = el.get_attribute("src").ok_or(XMLError::NoElements)?;
+                    let current_val = el
+                        .get_attribute("src")
+                        .ok_or(xmlutils::XMLError::NoElements)?;
                     let href = build_epub_uri(path, url_prepend, &current_val);
 
                     el.set_attribute("src", &href)?;
@@ -477,7 +1709,7 @@ impl<R: Read + Seek> EpubDoc<R> {
     /// assert_eq!(17, doc.get_num_pages());
     /// ```
     pub fn get_num_pages(&self) -> usize {
-        self.context.spine.len()
+        self.ctx().spine.len()
     }
 
     /// Function to convert a resource path to a chapter number in the spine
@@ -486,41 +1718,201 @@ impl<R: Read + Seek> EpubDoc<R> {
     /// This method is useful to convert a toc NavPoint content to a chapter number
     /// to be able to navigate easily
     pub fn resource_uri_to_chapter(&self, uri: impl AsRef<Path>) -> Option<usize> {
-        for (key, item) in self.context.resources.iter() {
-            if item.path == uri.as_ref() {
-                return self.resource_id_to_chapter(key);
-            }
-        }
-
-        None
+        self.resource_id_to_chapter(self.resource_id_by_path(uri)?)
     }
 
     /// Function to convert a resource id to a chapter number in the spine
     /// If the resource isn't in the spine list, None will be returned
     pub fn resource_id_to_chapter(&self, uri: &str) -> Option<usize> {
-        self.context.spine.iter().position(|item| item == uri)
+        self.ctx().spine.iter().position(|item| item.idref == uri)
+    }
+
+    /// Returns the spine index of the current chapter
+    pub fn get_current_chapter(&self) -> usize {
+        self.current
+    }
+
+    /// Returns the resource id of the current chapter
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let mut doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(Some("titlepage.xhtml"), doc.get_current_id());
+    /// assert!(doc.set_current_chapter(1));
+    /// assert_eq!(Some("000.xhtml"), doc.get_current_id());
+    /// ```
+    pub fn get_current_id(&self) -> Option<&str> {
+        self.ctx()
+            .spine
+            .get(self.current)
+            .map(|item| item.idref.as_str())
+    }
+
+    /// Changes the current chapter to the given spine index, resetting the position within the chapter.
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` (and leaves the current chapter untouched) if `n` is not a valid spine index.
+    pub fn set_current_chapter(&mut self, n: usize) -> bool {
+        if n < self.ctx().spine.len() {
+            self.current = n;
+            self.current_fraction = 0.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves the current chapter to the chapter the given ToC entry points at.
+    ///
+    /// Returns the [fragment](NavPoint::fragment) of the entry (the part after `#`), if any, so the caller can scroll
+    /// to the right position within the chapter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let mut doc = EpubDoc::new("test.epub").unwrap();
+    /// let nav = doc.get_toc()[2].clone();
+    /// let fragment = doc.go_to_toc_entry(&nav).unwrap();
+    /// assert_eq!(None, fragment);
+    /// assert_eq!(Some("002.xhtml"), doc.get_current_id());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::NotInSpine] if the entry points at a resource outside the spine, or
+    /// [ArchiveError::NoTarget] if it's a heading without a link, in which case the current chapter is left untouched.
+    pub fn go_to_toc_entry(&mut self, nav: &NavPoint) -> Result<Option<String>> {
+        self.ensure_section(OpfSection::Spine)?;
+        let content = nav
+            .content
+            .as_deref()
+            .ok_or_else(|| ArchiveError::NoTarget(nav.label.clone()))?;
+        let chapter = self
+            .toc_path_to_chapter(content)
+            .ok_or_else(|| ArchiveError::NotInSpine(content.to_path_buf()))?;
+
+        self.current = chapter;
+        self.current_fraction = 0.0;
+
+        Ok(nav.fragment.clone())
     }
 
-    fn fill_resources(&mut self) -> Result<()> {
-        let mut archive = self.archive.borrow_mut();
+    /// Converts the path (without fragment) of a ToC entry to a chapter number in the spine.
+    pub(crate) fn toc_path_to_chapter(&self, path: &Path) -> Option<usize> {
+        self.resource_uri_to_chapter(path).or_else(|| {
+            // The ToC parsers percent-decode their hrefs, whilst manifest paths are kept as-is.
+            let id = self.ctx().resources.iter().find_map(|(id, item)| {
+                let raw = item.path.to_string_lossy();
+                let decoded = utils::percent_decode(&raw)?;
+                (Path::new(decoded.as_ref()) == path).then_some(id)
+            })?;
+            self.resource_id_to_chapter(id)
+        })
+    }
+
+    #[allow(deprecated)]
+    fn fill_resources(&mut self, options: &ParseOptions) -> Result<()> {
+        let archive = self
+            .archive
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
         let root_container = archive.get_entry(&self.root_file)?;
-        let txt = xmlutils::ensure_utf8(&root_container);
-        let root = crate::xmlutils::parse_xml(&txt)?;
-        let epub_version = root
+        let txt = xmlutils::ensure_utf8(&root_container)?;
+        let recovered;
+        let mut escaping = None;
+        let root = match crate::xmlutils::parse_xml(&txt) {
+            Ok(root) => root,
+            // A single attempt, if the unescaped document doesn't parse either the book is given up on
+            Err(e) => match xmlutils::unescape_document(&txt) {
+                Some((unescaped, kind)) if options.mode == ParseMode::Lenient => {
+                    recovered = unescaped;
+                    escaping = Some(kind);
+                    crate::xmlutils::parse_xml(&recovered)?
+                }
+                _ => return Err(e),
+            },
+        };
+        let epub_version = match root.root_element().attribute("version") {
+            Some(version) => version,
+            None if options.mode == ParseMode::Lenient => "3.0",
+            None => return Err(ArchiveError::ParsingFailure),
+        };
+        let ctx = &mut self.context;
+        ctx.version = root.root_element().attribute("version").map(str::to_string);
+        ctx.prefix = root.root_element().attribute("prefix").map(str::to_string);
+        ctx.package_lang = root
+            .root_element()
+            .attribute((xmlutils::XML_NAMESPACE, "lang"))
+            .map(str::to_string);
+        ctx.package_dir = root
             .root_element()
-            .attribute("version")
-            .ok_or(ArchiveError::ParsingFailure)?;
+            .attribute("dir")
+            .and_then(TextDirection::parse);
+        ctx.prefixes = ctx
+            .prefix
+            .as_deref()
+            .map(parsers::parse_prefixes)
+            .unwrap_or_default();
+        if let Some(escaping) = escaping {
+            ctx.warnings.push(ParseWarning {
+                section: OpfSection::Package,
+                message: format!(
+                    "the package document is {escaping}, it was decoded to be able to parse it"
+                ),
+            });
+        }
 
         match epub_version {
             "2.0" => {
                 // Parse with only the V2 parser
-                EpubV2Parser::parse(&mut self.context, &self.root_base, &root, &mut archive)?;
+                EpubV2Parser::parse(ctx, &self.root_base, &root, archive, options)?;
             }
             _ => {
                 // Always assume it's a V3 epub
                 // Parse with the V2 parser, followed by the V3 parser
-                EpubV2Parser::parse(&mut self.context, &self.root_base, &root, &mut archive)?;
-                EpubV3Parser::parse(&mut self.context, &self.root_base, &root, &mut archive)?;
+                EpubV2Parser::parse(ctx, &self.root_base, &root, archive, options)?;
+                EpubV3Parser::parse(ctx, &self.root_base, &root, archive, options)?;
+            }
+        }
+        // The nav is the richer of the two
+        ctx.toc = if ctx.toc_nav.is_empty() {
+            ctx.toc_ncx.clone()
+        } else {
+            ctx.toc_nav.clone()
+        };
+
+        for item in ctx.resources.values_mut() {
+            if let Some(record) = archive.entry_record(&item.path) {
+                item.size = Some(record.size);
+                item.compressed_size = Some(record.compressed_size);
+            }
+        }
+
+        if options.media_type_sniffing != MediaTypeSniffing::Off {
+            for (id, item) in ctx.resources.iter_mut() {
+                let Ok(Some(detected)) = sniff::sniff_entry(archive, &item.path) else {
+                    continue;
+                };
+                let sniffed = SniffResult::new(&item.mime, Some(detected));
+                if sniffed.agrees {
+                    continue;
+                }
+
+                let message = format!(
+                    "resource `{id}` is declared as `{}` but its content is `{detected}`",
+                    item.mime
+                );
+                if options.media_type_sniffing == MediaTypeSniffing::Correct {
+                    item.mime = detected.to_string();
+                }
+                ctx.warnings.push(ParseWarning {
+                    section: OpfSection::Manifest,
+                    message,
+                });
             }
         }
 
@@ -528,8 +1920,44 @@ impl<R: Read + Seek> EpubDoc<R> {
     }
 }
 
+/// The font types, `font/*` and the legacy ones from before the `font` top-level type existed.
+const FONT_MIMES: &[&str] = &[
+    "font/*",
+    "application/vnd.ms-opentype",
+    "application/font-sfnt",
+    "application/x-font-otf",
+    "application/x-font-opentype",
+    "application/x-font-ttf",
+    "application/x-font-truetype",
+    "application/font-woff",
+    "application/x-font-woff",
+    "application/font-woff2",
+];
+
+/// Whether the `mime` type matches any of the `patterns`, see [mime_matches].
+fn mime_matches_any(patterns: &[&str], mime: &str) -> bool {
+    patterns.iter().any(|pattern| mime_matches(pattern, mime))
+}
+
+/// Whether the `mime` type matches the `pattern`, see [EpubDoc::resources_by_mime].
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    let essence = |mime: &str| {
+        mime.split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    };
+    let (pattern, mime) = (essence(pattern), essence(mime));
+
+    match pattern.strip_suffix('*') {
+        Some(prefix) => mime.starts_with(prefix),
+        None => mime == pattern,
+    }
+}
+
 fn get_root_file(content: &[u8]) -> Result<PathBuf, ArchiveError> {
-    let txt = xmlutils::ensure_utf8(content);
+    let txt = xmlutils::ensure_utf8(content)?;
     let root = crate::xmlutils::parse_xml(&txt)?;
     let element = root
         .descendants()
@@ -542,7 +1970,11 @@ fn get_root_file(content: &[u8]) -> Result<PathBuf, ArchiveError> {
     Ok(PathBuf::from(attr))
 }
 
-fn build_epub_uri<'a>(path: impl AsRef<Path>, url_prepend: &str, append: &'a str) -> Cow<'a, str> {
+pub(crate) fn build_epub_uri<'a>(
+    path: impl AsRef<Path>,
+    url_prepend: &str,
+    append: &'a str,
+) -> Cow<'a, str> {
     // allowing external links
     if append.starts_with("http") {
         return append.into();
@@ -20,6 +20,24 @@ pub enum ArchiveError {
     InvalidId,
     #[error("Invalid UTF-8 Path")]
     PathUtf8,
+    #[error("The resource {0:?} is not part of the spine")]
+    NotInSpine(std::path::PathBuf),
+    #[error("The ToC entry {0:?} is a heading without a link")]
+    NoTarget(String),
+    #[error("The reading position doesn't match any chapter of this epub")]
+    InvalidPosition,
+    #[error("Unavailable as the {0:?} section of the package document failed to parse")]
+    SectionUnavailable(crate::doc::OpfSection),
+    #[error("Invalid CFI: {0}")]
+    InvalidCfi(String),
+    #[error("The id {0:?} is already used in the manifest")]
+    DuplicateId(String),
+    #[error("The operation was cancelled")]
+    Cancelled,
+    #[error("The resource {0:?} is outside of the epub")]
+    RemoteResource(String),
+    #[error("The zip archive failed to reopen after its reader was used directly")]
+    ZipUnavailable,
 }
 impl From<std::string::FromUtf8Error> for ArchiveError {
     fn from(e: std::string::FromUtf8Error) -> Self {
@@ -0,0 +1,284 @@
+//! A machine readable snapshot of everything this crate parsed from an epub.
+//!
+//! Only available with the `export` feature.
+//!
+//! [EpubDoc::export_structure] is meant for QA pipelines: store the JSON of a book, and compare it with a fresh
+//! export after upgrading this crate (or changing the book) to see what is understood differently. The output is
+//! deterministic, every map is sorted and every list is in document order (or sorted by id, for the manifest).
+
+use crate::doc::{EpubDoc, NavPoint};
+use crate::generator::GeneratorInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+/// The version of the [StructureExport] format.
+///
+/// Bumped on every change which could make an older reader misinterpret an export (removed or renamed fields, or
+/// a changed meaning). Adding fields doesn't bump it, readers should ignore fields they don't know.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The parsed structure of an epub, see [EpubDoc::export_structure].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructureExport {
+    /// The version of the export format, see [SCHEMA_VERSION]
+    pub schema_version: u32,
+    /// The `version` attribute of the package document
+    pub version: Option<String>,
+    /// Metadata by name, with the content of each node trimmed
+    pub metadata: BTreeMap<String, Vec<ExportedMetadata>>,
+    /// The manifest, sorted by id
+    pub manifest: Vec<ExportedResource>,
+    pub spine: Vec<ExportedSpineItem>,
+    pub toc: Vec<ExportedNavPoint>,
+    pub guide: Vec<ExportedReference>,
+    pub landmarks: Vec<ExportedReference>,
+    pub page_list: Vec<ExportedReference>,
+    /// The partial failures and warnings of parsing
+    pub warnings: Vec<ExportedWarning>,
+    pub provenance: Provenance,
+}
+
+/// A metadata node of a [StructureExport].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedMetadata {
+    pub content: String,
+    /// Attributes by name, namespaced attributes as `{namespace}name`
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// A manifest item of a [StructureExport].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedResource {
+    pub id: String,
+    pub path: String,
+    pub mime: String,
+    pub properties: Option<String>,
+    /// The uncompressed size, `None` if the file is missing
+    pub size: Option<u64>,
+    /// The CRC-32 recorded in the zip directory, `None` if the file is missing
+    pub crc32: Option<u32>,
+}
+
+/// A spine item of a [StructureExport].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedSpineItem {
+    pub idref: String,
+    pub id: Option<String>,
+    pub linear: bool,
+    pub properties: Option<String>,
+}
+
+/// A ToC entry of a [StructureExport].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedNavPoint {
+    pub label: String,
+    /// The full path within the epub, possibly with a fragment, `None` for a heading without a link
+    pub path: Option<String>,
+    pub children: Vec<ExportedNavPoint>,
+}
+
+/// A guide reference, landmark or page of a [StructureExport].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedReference {
+    /// The type of the reference, `None` for pages
+    pub kind: Option<String>,
+    /// The title, or the page number for pages
+    pub title: Option<String>,
+    pub path: String,
+    pub fragment: Option<String>,
+}
+
+/// A parse failure or warning of a [StructureExport].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedWarning {
+    /// The section of the package document, e.g. `Manifest`
+    pub section: String,
+    pub message: String,
+    /// Whether (part of) the section couldn't be parsed at all
+    pub failure: bool,
+}
+
+/// Where a [StructureExport] came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// The path of the package document
+    pub root_file: String,
+    pub unique_identifier: Option<String>,
+    /// See [EpubDoc::get_release_identifier]
+    pub release_identifier: Option<String>,
+    /// See [EpubDoc::generator]
+    pub generator: Option<GeneratorInfo>,
+}
+
+impl StructureExport {
+    /// Serializes the export as (pretty printed) JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Reads an export back from JSON, e.g. a stored snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON isn't a valid export, or was written with a newer [SCHEMA_VERSION].
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let export: StructureExport = serde_json::from_str(json)?;
+        if export.schema_version > SCHEMA_VERSION {
+            return Err(serde::de::Error::custom(format!(
+                "unsupported schema version {}, the latest known is {SCHEMA_VERSION}",
+                export.schema_version
+            )));
+        }
+
+        Ok(export)
+    }
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Exports everything which was parsed from the epub, in a serializable form.
+    ///
+    /// The sizes and checksums of the resources are read from the zip directory, nothing is decompressed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use epub::export::StructureExport;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let export = doc.export_structure();
+    /// assert_eq!(Some("2.0"), export.version.as_deref());
+    ///
+    /// let json = export.to_json().unwrap();
+    /// assert_eq!(export, StructureExport::from_json(&json).unwrap());
+    /// ```
+    pub fn export_structure(&self) -> StructureExport {
+        let metadata = self
+            .metadata()
+            .iter()
+            // Text between the metadata elements is recorded without a name
+            .filter(|(name, _)| !name.is_empty())
+            .map(|(name, nodes)| {
+                let nodes = nodes
+                    .iter()
+                    .map(|node| ExportedMetadata {
+                        content: node.content.trim().to_string(),
+                        attributes: node
+                            .attr
+                            .iter()
+                            .map(|attr| {
+                                let name = match &attr.name.namespace {
+                                    Some(namespace) => format!("{{{namespace}}}{}", attr.name.tag),
+                                    None => attr.name.tag.clone(),
+                                };
+                                (name, attr.value.to_string())
+                            })
+                            .collect(),
+                    })
+                    .collect();
+                (name.clone(), nodes)
+            })
+            .collect();
+
+        let mut manifest: Vec<_> = {
+            let mut archive = self.archive();
+            self.resources()
+                .iter()
+                .map(|(id, item)| ExportedResource {
+                    id: id.clone(),
+                    path: path_string(&item.path),
+                    mime: item.mime.clone(),
+                    properties: Some(item.properties.join(" "))
+                        .filter(|properties| !properties.is_empty()),
+                    size: archive.get_entry_size(&item.path).ok(),
+                    crc32: archive.get_entry_crc32(&item.path).ok(),
+                })
+                .collect()
+        };
+        manifest.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let spine = self
+            .spine()
+            .iter()
+            .map(|item| ExportedSpineItem {
+                idref: item.idref.clone(),
+                id: item.id.clone(),
+                linear: item.linear,
+                properties: item.properties.clone(),
+            })
+            .collect();
+
+        let references = |references: &[crate::doc::GuideReference]| {
+            references
+                .iter()
+                .map(|reference| ExportedReference {
+                    kind: Some(reference.kind.clone()),
+                    title: reference.title.clone(),
+                    path: path_string(&reference.path),
+                    fragment: reference.fragment.clone(),
+                })
+                .collect()
+        };
+        let page_list = self
+            .page_list()
+            .iter()
+            .map(|page| ExportedReference {
+                kind: None,
+                title: Some(page.label.clone()),
+                path: path_string(&page.path),
+                fragment: page.fragment.clone(),
+            })
+            .collect();
+
+        let failures = self
+            .partial_failures()
+            .iter()
+            .map(|failure| ExportedWarning {
+                section: format!("{:?}", failure.section),
+                message: failure.message.clone(),
+                failure: true,
+            });
+        let warnings = self.parse_warnings().iter().map(|warning| ExportedWarning {
+            section: format!("{:?}", warning.section),
+            message: warning.message.clone(),
+            failure: false,
+        });
+
+        StructureExport {
+            schema_version: SCHEMA_VERSION,
+            version: self.epub_version().map(str::to_string),
+            metadata,
+            manifest,
+            spine,
+            toc: self.get_toc().iter().map(export_nav_point).collect(),
+            guide: references(self.guide()),
+            landmarks: references(self.landmarks()),
+            page_list,
+            warnings: failures.chain(warnings).collect(),
+            provenance: Provenance {
+                root_file: path_string(&self.root_file),
+                unique_identifier: self.unique_identifier().map(str::to_string),
+                release_identifier: self.get_release_identifier(),
+                generator: self.generator(),
+            },
+        }
+    }
+}
+
+fn export_nav_point(nav: &NavPoint) -> ExportedNavPoint {
+    ExportedNavPoint {
+        label: nav.label.clone(),
+        path: nav.href(),
+        children: nav.children.iter().map(export_nav_point).collect(),
+    }
+}
+
+/// Paths always use `/`, whatever the platform.
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
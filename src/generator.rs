@@ -0,0 +1,210 @@
+//! Heuristics to find out which tool produced an epub.
+//!
+//! Authoring and conversion tools tend to leave fingerprints in the package metadata or the `META-INF` directory.
+//! [EpubDoc::generator] looks for the ones of well known tools, and reports what it found as evidence.
+
+use crate::doc::{EpubDoc, MetadataNode};
+use std::io::{Read, Seek};
+
+/// The tool which (most likely) produced an epub, see [EpubDoc::generator].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeneratorInfo {
+    /// The name of the tool, e.g. `calibre` or `Sigil`
+    pub name: String,
+    /// The version of the tool, if it was recorded
+    pub version: Option<String>,
+    /// Human readable descriptions of the fingerprints the guess is based on
+    pub evidence: Vec<String>,
+}
+
+impl GeneratorInfo {
+    fn new(name: &str) -> Self {
+        GeneratorInfo {
+            name: name.to_string(),
+            version: None,
+            evidence: Vec::new(),
+        }
+    }
+
+    /// Set the version if none was found yet.
+    fn version(&mut self, version: Option<&str>) {
+        if self.version.is_none() {
+            self.version = version.map(str::to_string);
+        }
+    }
+
+    /// Returns `self` if any evidence was found.
+    fn found(self) -> Option<Self> {
+        (!self.evidence.is_empty()).then_some(self)
+    }
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Guesses which tool produced the epub, based on fingerprints in the metadata and the `META-INF` directory.
+    ///
+    /// Detects calibre, Sigil, Adobe InDesign, the Standard Ebooks toolset, and pandoc. If the fingerprints of
+    /// several tools are found (e.g. a book converted with calibre and then edited in Sigil), the one with the
+    /// most evidence wins, preferring tools which recorded their version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let generator = doc.generator().unwrap();
+    /// assert_eq!("Sigil", generator.name);
+    /// assert_eq!(Some("0.4.0"), generator.version.as_deref());
+    /// ```
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if no known fingerprint was found.
+    pub fn generator(&self) -> Option<GeneratorInfo> {
+        let candidates = [
+            self.detect_sigil(),
+            self.detect_calibre(),
+            self.detect_indesign(),
+            self.detect_standard_ebooks(),
+            self.detect_pandoc(),
+        ];
+        let score = |info: &GeneratorInfo| (info.evidence.len(), info.version.is_some());
+
+        candidates.into_iter().flatten().reduce(|best, info| {
+            if score(&info) > score(&best) {
+                info
+            } else {
+                best
+            }
+        })
+    }
+
+    fn detect_sigil(&self) -> Option<GeneratorInfo> {
+        let mut info = GeneratorInfo::new("Sigil");
+
+        for node in self.metadata_nodes("Sigil version") {
+            info.evidence
+                .push(format!("meta \"Sigil version\" = \"{}\"", node.content));
+            info.version(Some(node.content.trim()));
+        }
+        for node in self.generator_metas("sigil") {
+            info.evidence
+                .push(format!("meta \"generator\" = \"{}\"", node.content));
+            info.version(version_after_name(&node.content));
+        }
+
+        info.found()
+    }
+
+    fn detect_calibre(&self) -> Option<GeneratorInfo> {
+        let mut info = GeneratorInfo::new("calibre");
+
+        let mut calibre_metas: Vec<_> = self
+            .metadata()
+            .keys()
+            .filter(|key| key.starts_with("calibre:"))
+            .collect();
+        calibre_metas.sort();
+        for key in calibre_metas {
+            info.evidence.push(format!("meta \"{key}\""));
+        }
+
+        for contributor in self.contributors() {
+            if contributor.role.as_deref() == Some("bkp")
+                && contributor.name.to_lowercase().starts_with("calibre")
+            {
+                info.evidence.push(format!(
+                    "contributor \"{}\" with role bkp",
+                    contributor.name
+                ));
+                // e.g. `calibre (5.3.0) [https://calibre-ebook.com]`
+                info.version(
+                    contributor
+                        .name
+                        .split_once('(')
+                        .and_then(|(_, rest)| rest.split_once(')'))
+                        .map(|(version, _)| version.trim()),
+                );
+            }
+        }
+
+        for node in self.metadata_nodes("identifier") {
+            if node.find_attr("scheme") == Some("calibre") {
+                info.evidence
+                    .push("identifier with scheme calibre".to_string());
+            }
+        }
+
+        // The bookmarks only prove calibre touched the file, not that it produced it
+        if !info.evidence.is_empty() && self.has_resource_path("META-INF/calibre_bookmarks.txt") {
+            info.evidence
+                .push("file META-INF/calibre_bookmarks.txt".to_string());
+        }
+
+        info.found()
+    }
+
+    fn detect_indesign(&self) -> Option<GeneratorInfo> {
+        let mut info = GeneratorInfo::new("Adobe InDesign");
+
+        for node in self.generator_metas("adobe indesign") {
+            info.evidence
+                .push(format!("meta \"generator\" = \"{}\"", node.content));
+            info.version(version_after_name(&node.content));
+        }
+
+        info.found()
+    }
+
+    fn detect_standard_ebooks(&self) -> Option<GeneratorInfo> {
+        let mut info = GeneratorInfo::new("Standard Ebooks");
+
+        for node in self.metadata_nodes("se:built-with") {
+            info.evidence
+                .push(format!("meta \"se:built-with\" = \"{}\"", node.content));
+            info.version(Some(node.content.trim()));
+        }
+
+        info.found()
+    }
+
+    fn detect_pandoc(&self) -> Option<GeneratorInfo> {
+        let mut info = GeneratorInfo::new("pandoc");
+
+        for node in self.generator_metas("pandoc") {
+            info.evidence
+                .push(format!("meta \"generator\" = \"{}\"", node.content));
+            info.version(version_after_name(&node.content));
+        }
+        // Any tool may record a format, so it only backs up the generator
+        if !info.evidence.is_empty() {
+            for node in self.metadata_nodes("format") {
+                info.evidence
+                    .push(format!("dc:format \"{}\"", node.content));
+            }
+        }
+
+        info.found()
+    }
+
+    fn metadata_nodes(&self, name: &str) -> &[MetadataNode] {
+        self.metadata()
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the `generator` metas whose content starts with the (lowercase) `tool` name.
+    fn generator_metas<'a>(&'a self, tool: &'a str) -> impl Iterator<Item = &'a MetadataNode> {
+        self.metadata_nodes("generator")
+            .iter()
+            .filter(move |node| node.content.trim().to_lowercase().starts_with(tool))
+    }
+}
+
+/// Returns the first word which starts with a digit, e.g. `17.0` in `Adobe InDesign 17.0 (Windows)`.
+fn version_after_name(generator: &str) -> Option<&str> {
+    generator
+        .split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+}
@@ -0,0 +1,160 @@
+//! A ToC generated from the headings of the chapters, for books which ship without a usable one.
+//!
+//! [EpubDoc::generate_toc_from_headings] only builds the ToC, use [EpubDoc::set_toc] to have the navigation helpers
+//! make use of it.
+
+use crate::doc::{EpubDoc, NavPoint};
+use crate::xmlutils::XMLError;
+use std::cell::RefCell;
+use std::io::{Read, Seek};
+use std::path::PathBuf;
+
+/// A heading as found in a chapter, before it's turned into a [NavPoint].
+struct Heading {
+    level: u8,
+    id: Option<String>,
+    text: String,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Builds a ToC from the `<h1>` up to `<h{max_level}>` headings of the XHTML chapters in the spine.
+    ///
+    /// Every heading becomes an entry pointing at its `id`, or at the start of the chapter if it has none. Lower
+    /// level headings are nested under the preceding higher level one, also across chapters, so a book with an
+    /// `<h1>` per part and an `<h2>` per chapter ends up with the chapters as children of the parts. Headings without
+    /// text are skipped, as are chapters which can't be read.
+    ///
+    /// The ToC of the book is left as is, see [EpubDoc::set_toc].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// let mut doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// let toc = doc.generate_toc_from_headings(2);
+    /// assert!(!toc.is_empty());
+    ///
+    /// if doc.get_toc().len() <= 1 {
+    ///     doc.set_toc(toc);
+    /// }
+    /// ```
+    pub fn generate_toc_from_headings(&self, max_level: u8) -> Vec<NavPoint> {
+        let max_level = max_level.min(6);
+        if max_level == 0 {
+            return Vec::new();
+        }
+
+        let mut headings = Vec::new();
+        for (i, item) in self.spine().iter().enumerate() {
+            let is_xhtml = self.resources().get(&item.idref).is_some_and(|resource| {
+                matches!(
+                    resource.mime.as_str(),
+                    "application/xhtml+xml" | "text/html"
+                )
+            });
+            if !is_xhtml {
+                continue;
+            }
+            let Ok((path, content)) = self.chapter_content(i) else {
+                continue;
+            };
+            let Ok(found) = scan_headings(&content, max_level) else {
+                continue;
+            };
+
+            headings.extend(found.into_iter().map(|heading| (path.clone(), heading)));
+        }
+
+        nest_headings(headings)
+    }
+}
+
+/// Collect the `<h1>` up to `<h{max_level}>` headings of a (X)HTML document, in document order.
+fn scan_headings(html: &str, max_level: u8) -> Result<Vec<Heading>, XMLError> {
+    let selector = (1..=max_level)
+        .map(|level| format!("h{level}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let headings = RefCell::new(Vec::new());
+
+    let settings = lol_html::Settings {
+        element_content_handlers: vec![
+            lol_html::element!(selector, |el| {
+                let level = el.tag_name()[1..].parse().unwrap_or(1);
+                headings.borrow_mut().push(Heading {
+                    level,
+                    id: el.get_attribute("id").filter(|id| !id.is_empty()),
+                    text: String::new(),
+                });
+                Ok(())
+            }),
+            lol_html::text!(selector, |text| {
+                if let Some(heading) = headings.borrow_mut().last_mut() {
+                    heading.text.push_str(text.as_str());
+                }
+                Ok(())
+            }),
+        ],
+        ..lol_html::Settings::default()
+    };
+
+    let mut rewriter = lol_html::HtmlRewriter::new(settings, |_: &[u8]| {});
+    rewriter.write(html.as_bytes())?;
+    rewriter.end()?;
+
+    Ok(headings.into_inner())
+}
+
+/// Turn the headings into entries, nested by their level.
+fn nest_headings(headings: Vec<(PathBuf, Heading)>) -> Vec<NavPoint> {
+    let mut toc = Vec::new();
+    // The entries which can still get children, from the top level down
+    let mut open: Vec<(u8, NavPoint)> = Vec::new();
+
+    let close = |open: &mut Vec<(u8, NavPoint)>, toc: &mut Vec<NavPoint>| {
+        let (_, nav) = open.pop().expect("only called with open entries");
+        match open.last_mut() {
+            Some((_, parent)) => parent.children.push(nav),
+            None => toc.push(nav),
+        }
+    };
+
+    let mut play_order = 0;
+    for (path, heading) in headings {
+        let label = crate::text::decode_entities(&heading.text)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if label.is_empty() {
+            continue;
+        }
+
+        while open
+            .last()
+            .is_some_and(|(level, _)| *level >= heading.level)
+        {
+            close(&mut open, &mut toc);
+        }
+
+        play_order += 1;
+        open.push((
+            heading.level,
+            NavPoint {
+                label,
+                content: Some(path),
+                fragment: heading.id,
+                id: None,
+                kind: None,
+                children: Vec::new(),
+                hidden: false,
+                play_order,
+                document_index: play_order,
+            },
+        ));
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut toc);
+    }
+
+    toc
+}
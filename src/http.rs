@@ -0,0 +1,121 @@
+//! Validators for serving the resources of a book over HTTP.
+//!
+//! [EpubDoc::resource_etag] and [EpubDoc::resource_last_modified] are built from the values the zip directory
+//! records for every entry, so a server can answer conditional requests (`If-None-Match`, `If-Modified-Since`)
+//! without decompressing, let alone hashing, the resources.
+
+use crate::doc::EpubDoc;
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use std::time::{Duration, SystemTime};
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns a strong ETag for the resource with the given `id`, or `None` if it isn't in the manifest or the
+    /// archive.
+    ///
+    /// The ETag is `"<crc32>-<size>"`, quotes included: the CRC-32 of the resource as 8 lowercase hexadecimal
+    /// digits, and its uncompressed size in lowercase hexadecimal. It only changes when the content of the
+    /// resource does, so it stays the same across reopens and across copies of the book which were saved without
+    /// touching that resource.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let etag = doc.resource_etag("portada.png").unwrap();
+    /// assert!(etag.starts_with('"') && etag.ends_with('"'));
+    /// assert_eq!(None, doc.resource_etag("missing"));
+    /// ```
+    pub fn resource_etag(&self, id: &str) -> Option<String> {
+        let path = &self.ctx().resources.get(id)?.path;
+        let record = *self.archive().entry_record(path)?;
+
+        Some(format_etag(record.crc32, record.size))
+    }
+
+    /// Returns the [ETag](EpubDoc::resource_etag) of every resource in the archive, by id.
+    ///
+    /// Resources which are missing from the archive are left out. This is meant to be called once, e.g. when a
+    /// server starts, instead of looking up every resource on its own.
+    pub fn resources_etag_map(&self) -> HashMap<String, String> {
+        let archive = self.archive();
+
+        self.ctx()
+            .resources
+            .iter()
+            .filter_map(|(id, resource)| {
+                let record = archive.entry_record(&resource.path)?;
+                Some((id.clone(), format_etag(record.crc32, record.size)))
+            })
+            .collect()
+    }
+
+    /// Returns the modification time the zip directory records for the resource with the given `id`, or `None`
+    /// if it isn't in the manifest or the archive, or the recorded time is invalid.
+    ///
+    /// Zip entries store their time in MS-DOS format, which has a resolution of 2 seconds and no time zone. The
+    /// time is read as UTC, so it's only suitable for `Last-Modified` if the book was packed by a tool which
+    /// writes UTC, but it is stable either way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use std::time::{Duration, SystemTime};
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let modified = doc.resource_last_modified("portada.png").unwrap();
+    /// assert!(modified > SystemTime::UNIX_EPOCH + Duration::from_secs(315_532_800));
+    /// ```
+    pub fn resource_last_modified(&self, id: &str) -> Option<SystemTime> {
+        let path = &self.ctx().resources.get(id)?.path;
+        let record = *self.archive().entry_record(path)?;
+
+        dos_to_system_time(&record.last_modified)
+    }
+}
+
+fn format_etag(crc: u32, size: u64) -> String {
+    format!("\"{crc:08x}-{size:x}\"")
+}
+
+/// Converts an MS-DOS date and time, taken as UTC, to a [SystemTime].
+fn dos_to_system_time(time: &zip::DateTime) -> Option<SystemTime> {
+    let (year, month, day) = (
+        i64::from(time.year()),
+        i64::from(time.month()),
+        i64::from(time.day()),
+    );
+    let (hour, minute, second) = (time.hour(), time.minute(), time.second());
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar, shifting the year to start in March so the leap
+    // day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds =
+        days * 86_400 + i64::from(hour) * 3600 + i64::from(minute) * 60 + i64::from(second);
+    // MS-DOS times start in 1980, so they are never before the epoch
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?))
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
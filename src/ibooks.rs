@@ -0,0 +1,132 @@
+//! Apple Books display options.
+//!
+//! Books made for Apple Books often ship `META-INF/com.apple.ibooks.display-options.xml`, which turns on the
+//! embedded fonts or fixed layout for that reading system, even though none of it is in the package document. See
+//! [EpubDoc::ibooks_display_options].
+
+use crate::doc::EpubDoc;
+use crate::xmlutils;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+
+/// The path of the display options within the epub.
+const DISPLAY_OPTIONS: &str = "META-INF/com.apple.ibooks.display-options.xml";
+
+/// The platform name of the options which apply everywhere.
+pub const ALL_PLATFORMS: &str = "*";
+
+/// The parsed `com.apple.ibooks.display-options.xml`, see [EpubDoc::ibooks_display_options].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IbooksDisplayOptions {
+    /// platform name, e.g. `*` ([ALL_PLATFORMS]), `ipad` or `iphone` -> option name -> value, as written
+    pub platforms: BTreeMap<String, BTreeMap<String, String>>,
+    /// What couldn't be read, e.g. malformed XML, in which case the options are empty
+    pub warnings: Vec<String>,
+}
+
+impl IbooksDisplayOptions {
+    /// Returns the value of an option for the `platform`, falling back to the options for [ALL_PLATFORMS].
+    pub fn get(&self, platform: &str, option: &str) -> Option<&str> {
+        [platform, ALL_PLATFORMS]
+            .into_iter()
+            .find_map(|platform| self.platforms.get(platform)?.get(option))
+            .map(String::as_str)
+    }
+
+    /// Returns a `true`/`false` option for the `platform`, see [IbooksDisplayOptions::get].
+    pub fn flag(&self, platform: &str, option: &str) -> Option<bool> {
+        match self.get(platform, option)? {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// `specified-fonts`, whether the fonts embedded in the book are used instead of the reader's choice.
+    pub fn specified_fonts(&self) -> Option<bool> {
+        self.flag(ALL_PLATFORMS, "specified-fonts")
+    }
+
+    /// `fixed-layout`, the pre-EPUB3 way to mark a book as fixed layout for Apple Books.
+    pub fn fixed_layout(&self) -> Option<bool> {
+        self.flag(ALL_PLATFORMS, "fixed-layout")
+    }
+
+    /// `open-to-spread`, whether a fixed layout book opens showing two pages side by side.
+    pub fn open_to_spread(&self) -> Option<bool> {
+        self.flag(ALL_PLATFORMS, "open-to-spread")
+    }
+
+    fn parse(content: &[u8]) -> Self {
+        let mut options = IbooksDisplayOptions::default();
+        let text = match xmlutils::ensure_utf8(content) {
+            Ok(text) => text,
+            Err(e) => {
+                options.warnings.push(format!("{DISPLAY_OPTIONS}: {e}"));
+                return options;
+            }
+        };
+        let root = match xmlutils::parse_xml(&text) {
+            Ok(root) => root,
+            Err(e) => {
+                options.warnings.push(format!("{DISPLAY_OPTIONS}: {e}"));
+                return options;
+            }
+        };
+
+        for platform in root
+            .root_element()
+            .children()
+            .filter(|node| node.has_tag_name("platform"))
+        {
+            let Some(name) = platform.attribute("name") else {
+                options
+                    .warnings
+                    .push("a `platform` without a name was skipped".to_string());
+                continue;
+            };
+            let entries = options
+                .platforms
+                .entry(name.trim().to_string())
+                .or_default();
+
+            for option in platform
+                .children()
+                .filter(|node| node.has_tag_name("option"))
+            {
+                match option.attribute("name") {
+                    Some(option_name) => {
+                        let value = option.text().unwrap_or_default().trim();
+                        entries.insert(option_name.trim().to_string(), value.to_string());
+                    }
+                    None => options.warnings.push(format!(
+                        "an `option` without a name was skipped for platform `{name}`"
+                    )),
+                }
+            }
+        }
+
+        options
+    }
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Reads and parses `META-INF/com.apple.ibooks.display-options.xml`, or returns `None` if the book has none.
+    ///
+    /// The file is read on every call. Malformed options are reported in [IbooksDisplayOptions::warnings] instead
+    /// of failing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(None, doc.ibooks_display_options());
+    /// ```
+    pub fn ibooks_display_options(&self) -> Option<IbooksDisplayOptions> {
+        let content = self.archive().get_entry(DISPLAY_OPTIONS).ok()?;
+
+        Some(IbooksDisplayOptions::parse(&content))
+    }
+}
@@ -0,0 +1,192 @@
+//! Checking the declared language against the text of the book.
+//!
+//! [EpubDoc::detect_content_language] classifies the letters of a sample of the chapters by script. That can't
+//! tell English from German, but it does catch a Russian book declared as `en`, which is what matters for picking
+//! fonts, hyphenation or a text-to-speech voice.
+
+use crate::doc::EpubDoc;
+use crate::text::{html_to_text, TextOptions};
+use crate::xmlutils;
+use std::io::{Read, Seek};
+
+/// The number of bytes read from the start of each sampled chapter.
+const SAMPLE_BYTES: usize = 64 * 1024;
+
+/// A writing system, see [EpubDoc::detect_content_language].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Script {
+    Latin,
+    Cyrillic,
+    /// Chinese characters, also used in Japanese (kanji)
+    Han,
+    /// Japanese hiragana and katakana
+    Kana,
+    Arabic,
+    Hebrew,
+    Devanagari,
+}
+
+impl Script {
+    /// Returns the script of a letter, `None` for anything else (digits, punctuation, other scripts).
+    pub fn of(c: char) -> Option<Script> {
+        let script = match c {
+            'A'..='Z' | 'a'..='z' => Script::Latin,
+            // Latin-1 Supplement letters up to Latin Extended Additional, skipping × and ÷
+            '\u{c0}'..='\u{24f}' if c != '\u{d7}' && c != '\u{f7}' => Script::Latin,
+            '\u{1e00}'..='\u{1eff}' => Script::Latin,
+            '\u{400}'..='\u{52f}' => Script::Cyrillic,
+            '\u{590}'..='\u{5ff}' | '\u{fb1d}'..='\u{fb4f}' => Script::Hebrew,
+            '\u{600}'..='\u{6ff}' | '\u{750}'..='\u{77f}' | '\u{fb50}'..='\u{fdff}' => {
+                Script::Arabic
+            }
+            '\u{fe70}'..='\u{feff}' => Script::Arabic,
+            '\u{900}'..='\u{97f}' => Script::Devanagari,
+            '\u{3040}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}' | '\u{ff66}'..='\u{ff9d}' => {
+                Script::Kana
+            }
+            '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}' => {
+                Script::Han
+            }
+            '\u{20000}'..='\u{2ebef}' => Script::Han,
+            _ => return None,
+        };
+
+        Some(script)
+    }
+}
+
+/// The result of [EpubDoc::detect_content_language].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LanguageGuess {
+    /// The script most letters of the sample are written in, `None` if the sample has no classified letters
+    pub dominant_script: Option<Script>,
+    /// The primary declared language, see [EpubDoc::primary_language]
+    pub declared: Option<String>,
+    /// Whether the declared language is usually written in the dominant script.
+    ///
+    /// `true` when either is unknown, or the declared language isn't one this crate knows the script of, as there
+    /// is nothing to contradict it.
+    pub agreement: bool,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Guesses the script of the text from up to `sample_chapters` evenly spaced linear chapters, and checks it
+    /// against the declared language.
+    ///
+    /// Only the start of each sampled chapter is read, so this runs in bounded time regardless of the size of the
+    /// book. Chapters which can't be read are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use epub::language::Script;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let guess = doc.detect_content_language(5);
+    /// assert_eq!(Some(Script::Latin), guess.dominant_script);
+    /// assert_eq!(Some("es"), guess.declared.as_deref());
+    /// assert!(guess.agreement);
+    /// ```
+    pub fn detect_content_language(&self, sample_chapters: usize) -> LanguageGuess {
+        let linear: Vec<_> = self.spine().iter().filter(|item| item.linear).collect();
+        let samples = sample_chapters.min(linear.len());
+        let mut counts = [0usize; 7];
+
+        for i in 0..samples {
+            // The middle of each of `samples` equal parts of the spine
+            let item = linear[(2 * i + 1) * linear.len() / (2 * samples)];
+            let Some(resource) = self.ctx().resources.get(&item.idref) else {
+                continue;
+            };
+            let Ok(prefix) = self
+                .archive()
+                .get_entry_prefix(&resource.path, SAMPLE_BYTES)
+            else {
+                continue;
+            };
+            let Ok(html) = xmlutils::ensure_utf8(&prefix) else {
+                continue;
+            };
+
+            for script in html_to_text(&html, &TextOptions::default())
+                .chars()
+                .filter_map(Script::of)
+            {
+                counts[script as usize] += 1;
+            }
+        }
+
+        let dominant_script = SCRIPTS
+            .iter()
+            .zip(counts)
+            .filter(|(_, count)| *count > 0)
+            // The first script on a tie
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(script, _)| *script);
+        let declared = self.primary_language();
+        let agreement = match (
+            dominant_script,
+            declared.as_deref().and_then(language_scripts),
+        ) {
+            (Some(dominant), Some(expected)) => expected.contains(&dominant),
+            _ => true,
+        };
+
+        LanguageGuess {
+            dominant_script,
+            declared,
+            agreement,
+        }
+    }
+}
+
+/// Every [Script], in declaration order.
+const SCRIPTS: [Script; 7] = [
+    Script::Latin,
+    Script::Cyrillic,
+    Script::Han,
+    Script::Kana,
+    Script::Arabic,
+    Script::Hebrew,
+    Script::Devanagari,
+];
+
+/// The scripts the language `tag` is usually written in, `None` if unknown.
+fn language_scripts(tag: &str) -> Option<&'static [Script]> {
+    let mut subtags = tag.split('-');
+    let language = subtags.next()?.to_ascii_lowercase();
+
+    // An explicit script subtag, e.g. `sr-Latn`
+    for subtag in subtags.filter(|subtag| subtag.len() == 4) {
+        let scripts: &[Script] = match subtag.to_ascii_lowercase().as_str() {
+            "latn" => &[Script::Latin],
+            "cyrl" => &[Script::Cyrillic],
+            "hans" | "hant" | "hani" => &[Script::Han],
+            "jpan" => &[Script::Han, Script::Kana],
+            "arab" => &[Script::Arabic],
+            "hebr" => &[Script::Hebrew],
+            "deva" => &[Script::Devanagari],
+            _ => continue,
+        };
+        return Some(scripts);
+    }
+
+    let scripts: &[Script] = match language.as_str() {
+        "en" | "de" | "fr" | "es" | "it" | "pt" | "nl" | "sv" | "da" | "no" | "nb" | "nn"
+        | "fi" | "is" | "pl" | "cs" | "sk" | "sl" | "hr" | "bs" | "hu" | "ro" | "tr" | "id"
+        | "ms" | "vi" | "ca" | "eu" | "gl" | "la" | "et" | "lv" | "lt" | "ga" | "cy" | "sq"
+        | "af" | "sw" | "tl" | "eo" | "mt" => &[Script::Latin],
+        "ru" | "uk" | "be" | "bg" | "mk" | "sr" | "kk" | "ky" | "mn" | "tg" => &[Script::Cyrillic],
+        "zh" => &[Script::Han],
+        "ja" => &[Script::Han, Script::Kana],
+        "ar" | "fa" | "ur" | "ps" => &[Script::Arabic],
+        "he" | "iw" | "yi" => &[Script::Hebrew],
+        "hi" | "mr" | "ne" | "sa" => &[Script::Devanagari],
+        _ => return None,
+    };
+
+    Some(scripts)
+}
@@ -13,9 +13,17 @@
 //!
 //! ```
 //!
+//! ## Importing
+//!
+//! The most commonly used types are available through the [prelude]
+//!
+//! ```
+//! use epub::prelude::*;
+//! ```
+//!
 //! ## Getting doc metatada
 //!
-//! Metadata is a HashMap storing all metadata defined in the epub
+//! Metadata is a HashMap storing all metadata defined in the epub, see [doc::EpubDoc::metadata]
 //!
 //! ```
 //! # use epub::doc::EpubDoc;
@@ -27,33 +35,32 @@
 //!
 //! ## Accessing resources
 //!
-//! In the resources var is stored each resource defined
-//! in the epub indexed by the id and with the full internal
-//! path and mimetype. It's a HashMap<a: String, (b: String, c: String)>
-//! where 'a' is the resource id, 'b' is the resource full path and
-//! 'c' is the resource mimetype
+//! The resources are each resource defined in the epub indexed
+//! by the id and with the full internal path and mimetype.
+//! It's a HashMap<a: String, b: ResourceItem> where 'a' is the resource id
+//! and 'b' contains the resource full path and mimetype
 //!
 //! ```
 //! # use epub::doc::EpubDoc;
 //! # use std::path::Path;
 //! # let doc = EpubDoc::new("test.epub");
 //! # let doc = doc.unwrap();
-//! assert_eq!(23, doc.context.resources.len());
-//! let tpage = doc.context.resources.get("titlepage.xhtml");
+//! assert_eq!(23, doc.resources().len());
+//! let tpage = doc.resources().get("titlepage.xhtml");
 //! assert_eq!(tpage.unwrap().path, Path::new("OEBPS/Text/titlepage.xhtml"));
 //! assert_eq!(tpage.unwrap().mime, "application/xhtml+xml");
 //! ```
 //!
 //! ## Navigating using the spine
 //!
-//! Spine is a Vec<String> storing the epub spine as resources ids
+//! Spine is a list of SpineItems storing the epub spine as resources ids
 //!
 //! ```
 //! # use epub::doc::EpubDoc;
 //! # let doc = EpubDoc::new("test.epub");
 //! # let doc = doc.unwrap();
-//! assert_eq!(17, doc.context.spine.len());
-//! assert_eq!("titlepage.xhtml", doc.context.spine[0]);
+//! assert_eq!(17, doc.spine().len());
+//! assert_eq!("titlepage.xhtml", doc.spine()[0].idref);
 //! ```
 //!
 //! ## Getting the cover
@@ -77,8 +84,42 @@
 
 mod xmlutils;
 
+pub mod accessibility;
+#[cfg(feature = "rewrite")]
+pub mod anchors;
+pub mod anomalies;
 pub mod archive;
+pub mod calibre;
+pub mod cancel;
+pub mod cfi;
+pub mod checksums;
+pub mod diff;
 pub mod doc;
 pub mod error;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod generator;
+#[cfg(feature = "rewrite")]
+pub mod headings;
+pub mod http;
+pub mod ibooks;
+pub mod language;
+pub mod media_overlay;
+pub mod metadata;
+pub mod navigation;
+pub mod options;
 pub(crate) mod parsers;
+pub mod position;
+pub mod prelude;
+#[cfg(feature = "readium")]
+pub mod readium;
+pub mod rendering;
+pub mod rendition;
+pub mod search;
+pub mod semantics;
+pub mod sniff;
+pub mod split_support;
+pub mod text;
+pub mod usage;
 mod utils;
+pub mod writer;
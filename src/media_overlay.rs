@@ -0,0 +1,184 @@
+//! The metadata of EPUB3 media overlays, the synchronized narration of read-aloud books.
+//!
+//! The package declares the length of the narration with `media:duration`, globally for the whole book and refining
+//! each SMIL document of the manifest, see [EpubDoc::media_overlay_info].
+
+use crate::doc::{EpubDoc, ResourceItem};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+use std::time::Duration;
+
+/// The IRI of the media overlays vocabulary, bound to the reserved `media:` prefix.
+const OVERLAYS_VOCABULARY: &str = "http://www.idpf.org/epub/vocab/overlays/#";
+
+/// The media overlay metadata of a book, see [EpubDoc::media_overlay_info].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaOverlayInfo {
+    /// The global `media:duration`, the length of all the narration together
+    pub duration: Option<MediaDuration>,
+    /// The `media:duration` of each media overlay document, by manifest id
+    pub item_durations: BTreeMap<String, MediaDuration>,
+    /// The `media:narrator` metas, in document order
+    pub narrators: Vec<String>,
+    /// `media:active-class`, the CSS class a reading system adds to the element being read
+    pub active_class: Option<String>,
+    /// `media:playback-active-class`, the CSS class a reading system adds to the document while playing
+    pub playback_active_class: Option<String>,
+}
+
+/// A `media:duration`, see [MediaOverlayInfo].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaDuration {
+    /// The SMIL clock value as written, with surrounding whitespace trimmed, e.g. `0:32:19.500`
+    pub raw: String,
+    /// The parsed duration, `None` if it isn't a valid clock value
+    pub parsed: Option<Duration>,
+}
+
+impl MediaDuration {
+    fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+        MediaDuration {
+            raw: raw.to_string(),
+            parsed: parse_clock_value(raw),
+        }
+    }
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns the media overlay metadata of the book, or `None` if it has none.
+    ///
+    /// Durations refining anything but a manifest item are ignored. Besides the reserved `media:` prefix, any
+    /// prefix the `prefix` attribute of the `package` binds to the media overlays vocabulary is recognized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(None, doc.media_overlay_info());
+    /// ```
+    pub fn media_overlay_info(&self) -> Option<MediaOverlayInfo> {
+        let prefixes = self.vocabulary_prefixes(OVERLAYS_VOCABULARY);
+        let mut info = MediaOverlayInfo::default();
+        let mut found = false;
+
+        for (name, node) in self.metadata_iter() {
+            let Some(property) = prefixes
+                .iter()
+                .find_map(|prefix| name.strip_prefix(prefix)?.strip_prefix(':'))
+            else {
+                continue;
+            };
+            let value = node.content.trim();
+            let refines = node
+                .find_attr("refines")
+                .map(|refines| refines.trim().trim_start_matches('#'));
+
+            match (property, refines) {
+                ("duration", None) if info.duration.is_none() => {
+                    info.duration = Some(MediaDuration::parse(value));
+                }
+                ("duration", Some(id)) if self.ctx().resources.contains_key(id) => {
+                    info.item_durations
+                        .entry(id.to_string())
+                        .or_insert_with(|| MediaDuration::parse(value));
+                }
+                ("narrator", _) if !value.is_empty() => info.narrators.push(value.to_string()),
+                ("active-class", None) if info.active_class.is_none() => {
+                    info.active_class = Some(value.to_string());
+                }
+                ("playback-active-class", None) if info.playback_active_class.is_none() => {
+                    info.playback_active_class = Some(value.to_string());
+                }
+                _ => continue,
+            }
+            found = true;
+        }
+
+        found.then_some(info)
+    }
+
+    /// Returns the media overlay narrating the resource with the manifest id `spine_id`, as referenced by its
+    /// `media-overlay` attribute, or `None` if it has none or the reference is dangling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(None, doc.media_overlay_for("000.xhtml"));
+    /// ```
+    pub fn media_overlay_for(&self, spine_id: &str) -> Option<&ResourceItem> {
+        let overlay = self
+            .ctx()
+            .resources
+            .get(spine_id)?
+            .media_overlay
+            .as_deref()?;
+        self.ctx().resources.get(overlay)
+    }
+}
+
+/// Parses a SMIL clock value: `H:MM:SS.fff`, `MM:SS.fff`, or a count with an optional `h`, `min`, `s` or `ms`
+/// metric, e.g. `12.5s`. A count without a metric is in seconds.
+fn parse_clock_value(value: &str) -> Option<Duration> {
+    if value.contains(':') {
+        let parts: Vec<&str> = value.split(':').collect();
+        let (hours, minutes, seconds) = match parts[..] {
+            [hours, minutes, seconds] => (parse_digits(hours)?, minutes, seconds),
+            [minutes, seconds] => (0, minutes, seconds),
+            _ => return None,
+        };
+        let minutes = Some(minutes)
+            .filter(|minutes| minutes.len() == 2)
+            .and_then(parse_digits)
+            .filter(|minutes| *minutes < 60)?;
+        let (whole, _) = seconds.split_once('.').unwrap_or((seconds, ""));
+        let seconds = Some(seconds)
+            .filter(|_| whole.len() == 2)
+            .and_then(parse_seconds)
+            .filter(|seconds| seconds.as_secs() < 60)?;
+
+        return Duration::from_secs(hours.checked_mul(3600)?.checked_add(minutes * 60)?)
+            .checked_add(seconds);
+    }
+
+    let (count, factor) = [("ms", 0.001), ("min", 60.0), ("h", 3600.0), ("s", 1.0)]
+        .into_iter()
+        .find_map(|(metric, factor)| Some((value.strip_suffix(metric)?, factor)))
+        .unwrap_or((value, 1.0));
+    if factor == 1.0 {
+        parse_seconds(count)
+    } else {
+        // Validated the same way, the float only scales it
+        parse_seconds(count)?;
+        Duration::try_from_secs_f64(count.parse::<f64>().ok()? * factor).ok()
+    }
+}
+
+/// Parses `SS.fff` exactly, without going through a float.
+fn parse_seconds(value: &str) -> Option<Duration> {
+    let (whole, fraction) = match value.split_once('.') {
+        Some((whole, fraction)) if !fraction.is_empty() => (whole, fraction),
+        Some(_) => return None,
+        None => (value, ""),
+    };
+    let seconds = parse_digits(whole)?;
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // Nanoseconds, anything beyond them is dropped
+    let nanos = format!("{:0<9}", &fraction[..fraction.len().min(9)]);
+
+    Some(Duration::new(seconds, nanos.parse().ok()?))
+}
+
+fn parse_digits(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
@@ -0,0 +1,1010 @@
+//! Typed access to common metadata.
+//!
+//! [EpubDoc::metadata] gives the raw metadata nodes. Some of them carry more meaning than their content, spread
+//! over attributes (EPUB2) or separate `meta` elements refining them (EPUB3). The accessors in this module merge
+//! both encodings.
+
+use crate::doc::{EpubDoc, MetadataNode};
+use std::io::{Read, Seek};
+
+/// A `dc:creator` or `dc:contributor`, see [EpubDoc::creators] and [EpubDoc::contributors].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Creator<'a> {
+    /// The name as displayed, e.g. `A. A. Milne`
+    pub name: String,
+    /// The name to sort by, e.g. `Milne, A. A.`, from `opf:file-as` or a `file-as` refinement
+    pub sort_name: Option<String>,
+    /// The MARC relator code, e.g. `aut` or `trl`, from `opf:role` or a `role` refinement
+    pub role: Option<String>,
+    /// The name in other scripts, as (language, name) from `alternate-script` refinements, see
+    /// [Title::alternate_scripts]
+    pub alternate_scripts: Vec<(String, String)>,
+    /// The metadata node this creator was read from
+    pub raw: &'a MetadataNode,
+}
+
+/// A `dc:title`, see [EpubDoc::titles].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Title {
+    /// The title, with surrounding whitespace trimmed
+    pub text: String,
+    /// What the title is used for
+    pub kind: TitleKind,
+    /// The position in which the title should be displayed, from a `display-seq` refinement
+    pub display_seq: Option<u32>,
+    /// The language of the title, from its `xml:lang` attribute or the one of the package
+    pub lang: Option<String>,
+    /// The base direction of the title, from its `dir` attribute or the one of the package
+    pub dir: Option<TextDirection>,
+    /// The title in other scripts, e.g. the Japanese original of a romanized title, as (language, title) from
+    /// `alternate-script` refinements in document order. The language is empty if unknown.
+    pub alternate_scripts: Vec<(String, String)>,
+}
+
+/// The base direction of text, from a `dir` attribute, see [EpubDoc::metadata_dir].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextDirection {
+    /// Left to right
+    Ltr,
+    /// Right to left, e.g. Hebrew or Arabic
+    Rtl,
+    /// Determined from the text itself, by the Unicode bidirectional algorithm
+    Auto,
+}
+
+impl TextDirection {
+    pub(crate) fn parse(dir: &str) -> Option<Self> {
+        match dir.trim() {
+            "ltr" => Some(TextDirection::Ltr),
+            "rtl" => Some(TextDirection::Rtl),
+            "auto" => Some(TextDirection::Auto),
+            _ => None,
+        }
+    }
+}
+
+/// The role of a [Title], from its EPUB3 `title-type` refinement.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TitleKind {
+    Main,
+    Subtitle,
+    /// An abbreviated title, e.g. for narrow displays
+    Short,
+    /// The title of the series or collection the book is part of
+    Collection,
+    /// The edition, e.g. `Second Edition`
+    Edition,
+    /// The full title, including any subtitles
+    Expanded,
+    /// A `title-type` not defined by the specification
+    Other(String),
+}
+
+impl TitleKind {
+    fn from_title_type(title_type: &str) -> Self {
+        match title_type {
+            "main" => TitleKind::Main,
+            "subtitle" => TitleKind::Subtitle,
+            "short" => TitleKind::Short,
+            "collection" => TitleKind::Collection,
+            "edition" => TitleKind::Edition,
+            "expanded" => TitleKind::Expanded,
+            other => TitleKind::Other(other.to_string()),
+        }
+    }
+}
+
+/// A `dc:identifier`, see [EpubDoc::identifiers].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Identifier {
+    /// The identifier without a recognized scheme prefix (e.g. `urn:isbn:`), ISBNs also without hyphens and spaces
+    pub value: String,
+    /// The kind of identifier, if it could be determined
+    pub scheme: Option<IdScheme>,
+    /// The `id` attribute of the `dc:identifier`
+    pub id_attr: Option<String>,
+    /// Whether this is the identifier referenced by the package `unique-identifier`, see
+    /// [EpubDoc::unique_identifier]
+    pub is_unique_identifier: bool,
+}
+
+/// The kind of an [Identifier].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IdScheme {
+    Isbn,
+    Uuid,
+    Doi,
+    /// A URN in a namespace not listed above, with the namespace, e.g. `Urn("oclc")` for `urn:oclc:...`
+    Urn(String),
+    /// A scheme declared by the epub which isn't listed above, e.g. `calibre` or `MOBI-ASIN`
+    Other(String),
+}
+
+impl IdScheme {
+    /// Interpret an EPUB2 `opf:scheme` attribute.
+    fn from_scheme_attr(scheme: &str) -> Self {
+        match scheme.to_ascii_lowercase().as_str() {
+            "isbn" => IdScheme::Isbn,
+            "uuid" => IdScheme::Uuid,
+            "doi" => IdScheme::Doi,
+            _ => IdScheme::Other(scheme.to_string()),
+        }
+    }
+
+    /// Interpret an EPUB3 `identifier-type` refinement, usually an ONIX code list 5 code.
+    fn from_identifier_type(identifier_type: &str, value: &str) -> Option<Self> {
+        match identifier_type.to_ascii_lowercase().as_str() {
+            "02" | "15" | "isbn" => Some(IdScheme::Isbn),
+            "06" | "doi" => Some(IdScheme::Doi),
+            "uuid" => Some(IdScheme::Uuid),
+            // The URN itself says what it is
+            "22" | "urn" => IdScheme::from_value(value),
+            _ => Some(IdScheme::Other(identifier_type.to_string())),
+        }
+    }
+
+    /// Detect the scheme from the URN prefix, or the shape of the value.
+    fn from_value(value: &str) -> Option<Self> {
+        let lower = value.to_ascii_lowercase();
+
+        if lower.starts_with("urn:isbn:") || lower.starts_with("isbn:") {
+            Some(IdScheme::Isbn)
+        } else if lower.starts_with("urn:uuid:") {
+            Some(IdScheme::Uuid)
+        } else if DOI_PREFIXES.iter().any(|prefix| lower.starts_with(prefix)) {
+            Some(IdScheme::Doi)
+        } else if let Some(rest) = lower.strip_prefix("urn:") {
+            let namespace = rest.split(':').next().filter(|ns| !ns.is_empty())?;
+            Some(IdScheme::Urn(namespace.to_string()))
+        } else if is_isbn(&strip_isbn(value)) {
+            Some(IdScheme::Isbn)
+        } else if is_uuid(value) {
+            Some(IdScheme::Uuid)
+        } else if lower.starts_with("10.") && lower.contains('/') {
+            Some(IdScheme::Doi)
+        } else {
+            None
+        }
+    }
+
+    /// Strip the prefix this scheme is recognized by.
+    fn normalize(&self, value: &str) -> String {
+        let lower = value.to_ascii_lowercase();
+        let strip = |prefixes: &[&str]| {
+            prefixes
+                .iter()
+                .find(|prefix| lower.starts_with(*prefix))
+                .map_or(value, |prefix| &value[prefix.len()..])
+        };
+
+        match self {
+            IdScheme::Isbn => strip_isbn(strip(&["urn:isbn:", "isbn:"])),
+            IdScheme::Uuid => strip(&["urn:uuid:"]).to_string(),
+            IdScheme::Doi => strip(DOI_PREFIXES).to_string(),
+            IdScheme::Urn(_) | IdScheme::Other(_) => value.to_string(),
+        }
+    }
+}
+
+const DOI_PREFIXES: &[&str] = &[
+    "urn:doi:",
+    "doi:",
+    "https://doi.org/",
+    "http://doi.org/",
+    "https://dx.doi.org/",
+    "http://dx.doi.org/",
+];
+
+/// Remove the separators from an ISBN.
+fn strip_isbn(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Whether `isbn` (without separators) is a valid ISBN-10 or ISBN-13, including its check digit.
+fn is_isbn(isbn: &str) -> bool {
+    let digits: Vec<u32> = isbn
+        .chars()
+        .enumerate()
+        .map_while(|(i, c)| match c {
+            'X' if i == 9 => Some(10),
+            c => c.to_digit(10),
+        })
+        .collect();
+
+    match (isbn.len(), digits.len()) {
+        (10, 10) => {
+            let sum: u32 = digits
+                .iter()
+                .zip((1..=10).rev())
+                .map(|(digit, weight)| digit * weight)
+                .sum();
+            sum.is_multiple_of(11)
+        }
+        (13, 13) if isbn.starts_with("978") || isbn.starts_with("979") => {
+            let sum: u32 = digits
+                .iter()
+                .zip([1, 3].into_iter().cycle())
+                .map(|(digit, weight)| digit * weight)
+                .sum();
+            sum.is_multiple_of(10)
+        }
+        _ => false,
+    }
+}
+
+/// Whether `value` has the `8-4-4-4-12` hex digit shape of a UUID.
+fn is_uuid(value: &str) -> bool {
+    let groups: Vec<_> = value.split('-').collect();
+
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// A `dc:subject`, see [EpubDoc::subjects].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Subject {
+    /// The subject as displayed, e.g. `FICTION / Classics`
+    pub label: String,
+    /// The subject scheme, e.g. `BISAC`, from an `authority` refinement
+    pub authority: Option<String>,
+    /// The code of the subject within the scheme, e.g. `FIC004000`, from a `term` refinement
+    pub term: Option<String>,
+}
+
+/// The series a book belongs to, see [EpubDoc::series].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SeriesInfo {
+    pub name: String,
+    /// The position of the book within the series, which can be fractional (`1.5` for a side story)
+    pub position: Option<f32>,
+    pub source: SeriesSource,
+}
+
+/// Where a [SeriesInfo] was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SeriesSource {
+    /// An EPUB3 `belongs-to-collection`, with a `group-position` refinement
+    Collection,
+    /// The `calibre:series` and `calibre:series_index` metadata written by calibre
+    Calibre,
+}
+
+/// A `dc:date` or `dcterms:modified`, see [EpubDoc::dates].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EpubDate {
+    /// What happened at this date
+    pub kind: DateKind,
+    /// The date as written, with surrounding whitespace trimmed
+    pub raw: String,
+    /// The parsed date, `None` if it isn't (a prefix of) an ISO 8601 date
+    pub parsed: Option<PartialDate>,
+}
+
+/// The event of an [EpubDate].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DateKind {
+    /// `opf:event="publication"`
+    Publication,
+    /// `opf:event="creation"`
+    Creation,
+    /// `opf:event="modification"`, or the EPUB3 `dcterms:modified`
+    Modification,
+    /// A `dc:date` without an `opf:event`, which is the publication date in EPUB3
+    Unspecified,
+    /// An `opf:event` not listed above, e.g. `original-publication`
+    Other(String),
+}
+
+impl DateKind {
+    fn from_event(event: &str) -> Self {
+        match event.to_ascii_lowercase().as_str() {
+            "publication" => DateKind::Publication,
+            "creation" => DateKind::Creation,
+            "modification" => DateKind::Modification,
+            _ => DateKind::Other(event.to_string()),
+        }
+    }
+}
+
+/// An ISO 8601 date, which may be just a year (`1998`) or a year and month (`2011-03`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PartialDate {
+    pub year: i32,
+    /// 1 to 12
+    pub month: Option<u8>,
+    /// 1 to 31, only present with a month
+    pub day: Option<u8>,
+    /// The time of day, only present with a day
+    pub time: Option<TimeOfDay>,
+}
+
+/// The time part of a [PartialDate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeOfDay {
+    pub hour: u8,
+    pub minute: u8,
+    /// Fractions of a second are dropped
+    pub second: u8,
+    /// The offset from UTC in minutes, `Some(0)` for `Z`, `None` for a local time
+    pub utc_offset: Option<i16>,
+}
+
+impl PartialDate {
+    /// Parses the ISO 8601 forms used in epubs: `YYYY`, `YYYY-MM`, `YYYY-MM-DD` and `YYYYMMDD`, optionally followed
+    /// by a time `Thh:mm[:ss[.sss]]` with a `Z` or `±hh[:mm]` offset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::metadata::PartialDate;
+    /// let date = PartialDate::parse("2011-03").unwrap();
+    /// assert_eq!((2011, Some(3), None), (date.year, date.month, date.day));
+    ///
+    /// let modified = PartialDate::parse("2012-01-01T12:00:00Z").unwrap();
+    /// assert_eq!(Some(0), modified.time.unwrap().utc_offset);
+    ///
+    /// assert_eq!(None, PartialDate::parse("March 2011"));
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (date, time) = match value.split_once(['T', 't']) {
+            Some((date, time)) => (date, Some(time)),
+            None => (value, None),
+        };
+        if !date.is_ascii() {
+            return None;
+        }
+
+        let bytes = date.as_bytes();
+        let (year, month, day) = match (date.len(), bytes.get(4), bytes.get(7)) {
+            (4, _, _) => (date, None, None),
+            (7, Some(b'-'), _) => (&date[..4], Some(&date[5..]), None),
+            (10, Some(b'-'), Some(b'-')) => (&date[..4], Some(&date[5..7]), Some(&date[8..])),
+            (8, _, _) => (&date[..4], Some(&date[4..6]), Some(&date[6..])),
+            _ => return None,
+        };
+        if year.len() != 4 || !year.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        Some(PartialDate {
+            year: year.parse().ok()?,
+            month: match month {
+                Some(month) => Some(two_digits(month, 1..=12)?),
+                None => None,
+            },
+            day: match day {
+                Some(day) => Some(two_digits(day, 1..=31)?),
+                None => None,
+            },
+            time: match time {
+                Some(time) if day.is_some() => Some(TimeOfDay::parse(time)?),
+                Some(_) => return None,
+                None => None,
+            },
+        })
+    }
+}
+
+impl TimeOfDay {
+    /// Parses `hh:mm[:ss[.sss]]` with an optional `Z` or `±hh[:mm]` offset.
+    fn parse(time: &str) -> Option<Self> {
+        // The offset is split at byte indices
+        if !time.is_ascii() {
+            return None;
+        }
+
+        let (clock, utc_offset) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+            (clock, Some(0))
+        } else if let Some(sign) = time.rfind(['+', '-']) {
+            let (clock, offset) = time.split_at(sign);
+            let (hours, minutes) = match offset[1..].split_once(':') {
+                Some((hours, minutes)) => (hours, minutes),
+                None if offset.len() == 5 => offset[1..].split_at(2),
+                None => (&offset[1..], "00"),
+            };
+            let minutes = i16::from(two_digits(hours, 0..=23)?) * 60
+                + i16::from(two_digits(minutes, 0..=59)?);
+            (
+                clock,
+                Some(if offset.starts_with('-') {
+                    -minutes
+                } else {
+                    minutes
+                }),
+            )
+        } else {
+            (time, None)
+        };
+
+        let mut parts = clock.split(':');
+        let hour = two_digits(parts.next()?, 0..=24)?;
+        let minute = two_digits(parts.next()?, 0..=59)?;
+        let second = match parts.next() {
+            // Leap seconds are allowed
+            Some(second) => two_digits(second.split(['.', ',']).next()?, 0..=60)?,
+            None => 0,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(TimeOfDay {
+            hour,
+            minute,
+            second,
+            utc_offset,
+        })
+    }
+}
+
+/// Parses exactly two ASCII digits within `range`.
+fn two_digits(value: &str, range: std::ops::RangeInclusive<u8>) -> Option<u8> {
+    if value.len() != 2 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    value.parse().ok().filter(|number| range.contains(number))
+}
+
+/// An EPUB3 `meta` refining another element, see [EpubDoc::refinements_for].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Refinement<'a> {
+    /// The `property` of the refining `meta`, e.g. `role` or `title-type`
+    pub property: &'a str,
+    pub node: &'a MetadataNode,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns the metadata which refines the element with the given `id`, in document order.
+    ///
+    /// Refinements can be chained, a refining `meta` with an `id` can be refined in turn. Ids which aren't
+    /// refined (or don't exist) simply have no refinements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// let refinements = doc.refinements_for("author");
+    /// assert!(refinements
+    ///     .iter()
+    ///     .any(|r| r.property == "file-as" && r.node.content == "Milne, A. A."));
+    /// ```
+    pub fn refinements_for(&self, id: &str) -> Vec<Refinement<'_>> {
+        let Some(refinements) = self.ctx().refinements.get(id) else {
+            return Vec::new();
+        };
+
+        refinements
+            .iter()
+            .filter_map(|(property, index)| {
+                Some(Refinement {
+                    property,
+                    node: self.raw_metadata().get(property)?.get(*index)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the metadata which refines `node`, see [EpubDoc::refinements_for].
+    ///
+    /// Returns nothing if the node has no `id`.
+    pub fn refinements(&self, node: &MetadataNode) -> Vec<Refinement<'_>> {
+        node.find_attr("id")
+            .map(|id| self.refinements_for(id))
+            .unwrap_or_default()
+    }
+
+    /// Returns every `dc:creator`, in document order.
+    ///
+    /// EPUB3 refinements take precedence over the EPUB2 attributes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// let creators = doc.creators();
+    /// assert_eq!("A. A. Milne", creators[0].name);
+    /// assert_eq!(Some("Milne, A. A."), creators[0].sort_name.as_deref());
+    /// assert_eq!(Some("aut"), creators[0].role.as_deref());
+    /// ```
+    pub fn creators(&self) -> Vec<Creator<'_>> {
+        self.people("creator")
+    }
+
+    /// Returns every `dc:contributor`, in document order, see [EpubDoc::creators].
+    pub fn contributors(&self) -> Vec<Creator<'_>> {
+        self.people("contributor")
+    }
+
+    /// Returns every `dc:title`, in document order.
+    ///
+    /// Titles without a `title-type` refinement (e.g. in every EPUB2 book) are assumed to be the main title if they
+    /// come first and no title is explicitly typed `main`, and a subtitle otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use epub::metadata::TitleKind;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let titles = doc.titles();
+    /// assert_eq!("Todo es mío", titles[0].text);
+    /// assert_eq!(TitleKind::Main, titles[0].kind);
+    /// ```
+    pub fn titles(&self) -> Vec<Title> {
+        let nodes = self
+            .metadata()
+            .get("title")
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let title_type = |node| self.refinement(node, "title-type").map(str::trim);
+        let has_main = nodes.iter().any(|node| title_type(node) == Some("main"));
+
+        nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| Title {
+                text: node.content.trim().to_string(),
+                kind: match title_type(node) {
+                    Some(title_type) => TitleKind::from_title_type(title_type),
+                    None if i == 0 && !has_main => TitleKind::Main,
+                    None => TitleKind::Subtitle,
+                },
+                display_seq: self
+                    .refinement(node, "display-seq")
+                    .and_then(|seq| seq.trim().parse().ok()),
+                lang: self.metadata_lang(node).map(str::to_string),
+                dir: self.metadata_dir(node),
+                alternate_scripts: self.alternate_scripts(node),
+            })
+            .collect()
+    }
+
+    /// Returns the title typed `main`, or the first `dc:title` if none is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(Some("Todo es mío"), doc.main_title());
+    /// ```
+    pub fn main_title(&self) -> Option<&str> {
+        let nodes = self.metadata().get("title")?;
+
+        nodes
+            .iter()
+            .find(|node| self.refinement(node, "title-type").map(str::trim) == Some("main"))
+            .or_else(|| nodes.first())
+            .map(|node| node.content.trim())
+    }
+
+    /// Returns every `dc:identifier`, in document order.
+    ///
+    /// The scheme is taken from an EPUB3 `identifier-type` refinement, the EPUB2 `opf:scheme` attribute, or
+    /// detected from a URN prefix or the shape of the value (valid ISBNs, UUIDs and DOIs), in that order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use epub::metadata::IdScheme;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let identifiers = doc.identifiers();
+    /// assert_eq!(Some(IdScheme::Uuid), identifiers[0].scheme);
+    /// assert!(identifiers[0].is_unique_identifier);
+    /// ```
+    pub fn identifiers(&self) -> Vec<Identifier> {
+        let unique_id = self.ctx().unique_identifier_id.as_deref();
+        let mut unique_found = false;
+
+        self.metadata()
+            .get("identifier")
+            .into_iter()
+            .flatten()
+            .map(|node| {
+                let value = node.content.trim();
+                let scheme = match self.refinement(node, "identifier-type") {
+                    Some(identifier_type) => {
+                        IdScheme::from_identifier_type(identifier_type.trim(), value)
+                    }
+                    None => match node.find_attr("scheme") {
+                        Some(scheme) => Some(IdScheme::from_scheme_attr(scheme.trim())),
+                        None => IdScheme::from_value(value),
+                    },
+                };
+                let id_attr = node.find_attr("id");
+                // Like the parser, only the first identifier with the referenced id counts
+                let is_unique_identifier =
+                    !unique_found && id_attr.is_some() && id_attr == unique_id;
+                unique_found |= is_unique_identifier;
+
+                Identifier {
+                    value: scheme
+                        .as_ref()
+                        .map_or_else(|| value.to_string(), |scheme| scheme.normalize(value)),
+                    scheme,
+                    id_attr: id_attr.map(str::to_string),
+                    is_unique_identifier,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the ISBN of the book, without hyphens or spaces.
+    ///
+    /// Considers the [identifiers](EpubDoc::identifiers) with the ISBN scheme (`urn:isbn:`, `opf:scheme="ISBN"`, or
+    /// a bare ISBN), skipping those with an invalid check digit. An ISBN-13 is preferred over an ISBN-10.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// // Only has a UUID
+    /// assert_eq!(None, doc.isbn());
+    /// ```
+    pub fn isbn(&self) -> Option<String> {
+        let mut isbn_10 = None;
+
+        for identifier in self.identifiers() {
+            if identifier.scheme != Some(IdScheme::Isbn) || !is_isbn(&identifier.value) {
+                continue;
+            }
+            if identifier.value.len() == 13 {
+                return Some(identifier.value);
+            }
+            isbn_10.get_or_insert(identifier.value);
+        }
+
+        isbn_10
+    }
+
+    /// Returns every `dc:subject`, in document order and including duplicates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/herman-melville_moby-dick.epub").unwrap();
+    /// let subjects = doc.subjects();
+    /// assert!(subjects.iter().any(|subject| subject.authority.as_deref() == Some("LCSH")));
+    /// ```
+    pub fn subjects(&self) -> Vec<Subject> {
+        let property = |node, property| {
+            self.refinement(node, property)
+                .or_else(|| node.find_attr(property))
+                .map(|value| value.trim().to_string())
+        };
+
+        self.metadata()
+            .get("subject")
+            .into_iter()
+            .flatten()
+            .map(|node| Subject {
+                label: node.content.trim().to_string(),
+                authority: property(node, "authority"),
+                term: property(node, "term"),
+            })
+            .collect()
+    }
+
+    /// Returns every `dc:date`, followed by the EPUB3 `dcterms:modified`, in document order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use epub::metadata::DateKind;
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// let modified = doc
+    ///     .dates()
+    ///     .into_iter()
+    ///     .find(|date| date.kind == DateKind::Modification)
+    ///     .unwrap();
+    /// assert!(modified.parsed.unwrap().time.is_some());
+    /// ```
+    pub fn dates(&self) -> Vec<EpubDate> {
+        let date = |node: &MetadataNode, kind| EpubDate {
+            kind,
+            raw: node.content.trim().to_string(),
+            parsed: PartialDate::parse(&node.content),
+        };
+        let metadata = self.metadata();
+
+        let dates = metadata.get("date").into_iter().flatten().map(|node| {
+            let kind = match node.find_attr("event") {
+                Some(event) => DateKind::from_event(event.trim()),
+                None => DateKind::Unspecified,
+            };
+            date(node, kind)
+        });
+        let modified = metadata
+            .get("dcterms:modified")
+            .into_iter()
+            .flatten()
+            .map(|node| date(node, DateKind::Modification));
+
+        dates.chain(modified).collect()
+    }
+
+    /// Returns the date the book was last modified, from `dcterms:modified` or else the EPUB2 `dc:date` with the
+    /// modification event.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let modified = doc.modified_date().unwrap();
+    /// assert_eq!("2015-08-10T18:12:03Z", modified.raw);
+    /// assert_eq!(2015, modified.parsed.unwrap().year);
+    /// ```
+    pub fn modified_date(&self) -> Option<EpubDate> {
+        let metadata = self.metadata();
+        let modified = metadata
+            .get("dcterms:modified")
+            .into_iter()
+            .flatten()
+            // A refining `dcterms:modified` is the modification date of something else
+            .find(|node| node.find_attr("refines").is_none())
+            .or_else(|| {
+                metadata.get("date").into_iter().flatten().find(|node| {
+                    node.find_attr("event").is_some_and(|event| {
+                        DateKind::from_event(event.trim()) == DateKind::Modification
+                    })
+                })
+            })?;
+
+        Some(EpubDate {
+            kind: DateKind::Modification,
+            raw: modified.content.trim().to_string(),
+            parsed: PartialDate::parse(&modified.content),
+        })
+    }
+
+    /// Returns the date with the publication event, or else the first `dc:date` without an event.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// let published = doc.publication_date().unwrap();
+    /// assert!(published.parsed.is_some());
+    /// ```
+    pub fn publication_date(&self) -> Option<EpubDate> {
+        let dates = self.dates();
+
+        dates
+            .iter()
+            .find(|date| date.kind == DateKind::Publication)
+            .or_else(|| dates.iter().find(|date| date.kind == DateKind::Unspecified))
+            .cloned()
+    }
+
+    /// Returns the series of the book.
+    ///
+    /// An EPUB3 `belongs-to-collection` is preferred over the calibre metadata, unless its `collection-type`
+    /// is neither `series` nor `set`. A position which isn't a number is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(None, doc.series());
+    /// ```
+    pub fn series(&self) -> Option<SeriesInfo> {
+        let metadata = self.metadata();
+
+        let collection = metadata
+            .get("belongs-to-collection")
+            .into_iter()
+            .flatten()
+            .filter(|node| !node.content.trim().is_empty())
+            .find(|node| {
+                self.refinement(node, "collection-type")
+                    .is_none_or(|kind| matches!(kind.trim(), "series" | "set"))
+            })
+            .map(|node| SeriesInfo {
+                name: node.content.trim().to_string(),
+                position: self
+                    .refinement(node, "group-position")
+                    .and_then(parse_position),
+                source: SeriesSource::Collection,
+            });
+
+        collection.or_else(|| {
+            let name = self.mdata("calibre:series")?.trim();
+            (!name.is_empty()).then(|| SeriesInfo {
+                name: name.to_string(),
+                position: self.mdata("calibre:series_index").and_then(parse_position),
+                source: SeriesSource::Calibre,
+            })
+        })
+    }
+
+    /// Returns every `dc:language` in document order, normalized to the usual BCP 47 casing and without duplicates.
+    ///
+    /// Underscores become hyphens, and the subtags are cased as recommended: `en_us` becomes `en-US` and
+    /// `ZH-HANT-tw` becomes `zh-Hant-TW`. Values which don't look like a language tag are returned as-is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(vec!["es"], doc.languages());
+    /// ```
+    pub fn languages(&self) -> Vec<String> {
+        let mut languages: Vec<String> = Vec::new();
+
+        for node in self.metadata().get("language").into_iter().flatten() {
+            let language = normalize_language(node.content.trim());
+            if !language.is_empty() && !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+
+        languages
+    }
+
+    /// Returns the first language of [EpubDoc::languages].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// assert_eq!(Some("en-GB".to_string()), doc.primary_language());
+    /// ```
+    pub fn primary_language(&self) -> Option<String> {
+        self.languages().into_iter().next()
+    }
+
+    /// Returns the `xml:lang` attribute of the `package`, the default language of the metadata.
+    pub fn package_lang(&self) -> Option<&str> {
+        self.ctx().package_lang.as_deref()
+    }
+
+    /// Returns the `dir` attribute of the `package`, the default base direction of the metadata.
+    pub fn package_dir(&self) -> Option<TextDirection> {
+        self.ctx().package_dir
+    }
+
+    /// Returns the language of a metadata `node`, from its `xml:lang` attribute or else the [package
+    /// language](EpubDoc::package_lang).
+    ///
+    /// An empty `xml:lang` explicitly makes the language unknown, so it returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let title = doc.mdata_full("title").unwrap();
+    /// assert_eq!(None, doc.metadata_lang(title));
+    /// ```
+    pub fn metadata_lang<'a>(&'a self, node: &'a MetadataNode) -> Option<&'a str> {
+        match node.find_attr("lang") {
+            Some(lang) => Some(lang).filter(|lang| !lang.is_empty()),
+            None => self.package_lang(),
+        }
+    }
+
+    /// Returns the base direction of a metadata `node`, from its `dir` attribute or else the [package
+    /// direction](EpubDoc::package_dir).
+    pub fn metadata_dir(&self, node: &MetadataNode) -> Option<TextDirection> {
+        node.find_attr("dir")
+            .and_then(TextDirection::parse)
+            .or_else(|| self.package_dir())
+    }
+
+    /// The `alternate-script` refinements of `node`, as (language, text).
+    fn alternate_scripts(&self, node: &MetadataNode) -> Vec<(String, String)> {
+        self.refinements(node)
+            .into_iter()
+            .filter(|refinement| refinement.property == "alternate-script")
+            .map(|refinement| {
+                (
+                    self.metadata_lang(refinement.node)
+                        .unwrap_or_default()
+                        .to_string(),
+                    refinement.node.content.trim().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn people(&self, name: &str) -> Vec<Creator<'_>> {
+        let property = |node, property| {
+            self.refinement(node, property)
+                .or_else(|| node.find_attr(property))
+                .map(|value| value.trim().to_string())
+        };
+
+        self.metadata()
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|node| Creator {
+                name: node.content.trim().to_string(),
+                sort_name: property(node, "file-as"),
+                role: property(node, "role"),
+                alternate_scripts: self.alternate_scripts(node),
+                raw: node,
+            })
+            .collect()
+    }
+}
+
+fn parse_position(position: &str) -> Option<f32> {
+    position
+        .trim()
+        .parse()
+        .ok()
+        .filter(|position: &f32| position.is_finite())
+}
+
+/// Normalize the casing and separators of a BCP 47 language tag, leaving anything else untouched.
+fn normalize_language(tag: &str) -> String {
+    let subtags: Vec<&str> = tag.split(['-', '_']).collect();
+    let language = subtags[0];
+    let well_formed = subtags.iter().all(|subtag| {
+        (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+    });
+    if !well_formed
+        || !(2..=3).contains(&language.len())
+        || !language.bytes().all(|b| b.is_ascii_alphabetic())
+    {
+        return tag.to_string();
+    }
+
+    let mut normalized = language.to_ascii_lowercase();
+    let mut private_use = false;
+    for subtag in &subtags[1..] {
+        normalized.push('-');
+        let alphabetic = subtag.bytes().all(|b| b.is_ascii_alphabetic());
+        match subtag.len() {
+            // Extensions and private use subtags keep the default lowercase
+            _ if private_use => normalized.push_str(&subtag.to_ascii_lowercase()),
+            1 => {
+                private_use = true;
+                normalized.push_str(&subtag.to_ascii_lowercase());
+            }
+            // Script, e.g. `Hant`
+            4 if alphabetic => {
+                normalized.push_str(&subtag[..1].to_ascii_uppercase());
+                normalized.push_str(&subtag[1..].to_ascii_lowercase());
+            }
+            // Region, e.g. `US` or `419`
+            2 if alphabetic => normalized.push_str(&subtag.to_ascii_uppercase()),
+            _ => normalized.push_str(&subtag.to_ascii_lowercase()),
+        }
+    }
+
+    normalized
+}
@@ -0,0 +1,350 @@
+//! Reading order navigation which takes the ToC into account.
+//!
+//! The reading order of an epub is more fine-grained than its spine: a single chapter file can contain several
+//! sections which all have their own ToC entry. A [Locator] points at either the start of a chapter, or one of
+//! those sections.
+
+use crate::doc::{EpubDoc, NavPoint};
+use crate::utils;
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+/// A stop in the reading order, the start of a chapter or a fragment within it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Locator {
+    /// The index of the chapter in the spine
+    pub spine_index: usize,
+    /// The fragment (the part after `#`) within the chapter, `None` for the start of the chapter
+    pub fragment: Option<String>,
+}
+
+impl Locator {
+    /// A locator pointing at the start of the chapter at `spine_index`.
+    pub fn chapter(spine_index: usize) -> Self {
+        Locator {
+            spine_index,
+            fragment: None,
+        }
+    }
+}
+
+/// The result of [EpubDoc::reading_order_neighbors].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Neighbors {
+    /// The previous stop in the reading order, `None` at the start of the book
+    pub prev: Option<Locator>,
+    /// The next stop in the reading order, `None` at the end of the book
+    pub next: Option<Locator>,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Sets whether [EpubDoc::reading_order_neighbors] skips spine items marked `linear="no"`, defaults to `false`.
+    pub fn set_linear_navigation(&mut self, linear_only: bool) {
+        self.linear_navigation = linear_only;
+    }
+
+    /// Returns whether navigation skips non-linear spine items, see [EpubDoc::set_linear_navigation].
+    pub fn linear_navigation(&self) -> bool {
+        self.linear_navigation
+    }
+
+    /// Returns the previous and next stops in the reading order relative to `position`.
+    ///
+    /// Every chapter has a stop at its start, followed by one stop for every distinct fragment the ToC points at
+    /// within that chapter, in ToC order. Non-linear chapters are skipped if [EpubDoc::linear_navigation] is
+    /// enabled, unless `position` is inside one.
+    ///
+    /// A `position` with a fragment that isn't in the ToC is treated as the start of its chapter,
+    /// and a `position` outside the spine has no neighbors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use epub::navigation::Locator;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let neighbors = doc.reading_order_neighbors(&Locator::chapter(0));
+    ///
+    /// assert_eq!(None, neighbors.prev);
+    /// assert_eq!(Some(Locator::chapter(1)), neighbors.next);
+    /// ```
+    pub fn reading_order_neighbors(&self, position: &Locator) -> Neighbors {
+        if position.spine_index >= self.spine().len() {
+            return Neighbors::default();
+        }
+
+        let stops = self.reading_order_stops(position.spine_index);
+        let current = stops
+            .iter()
+            .position(|stop| stop == position)
+            .or_else(|| {
+                let start = Locator::chapter(position.spine_index);
+                stops.iter().position(|stop| *stop == start)
+            })
+            .expect("The chapter of the position is always a stop");
+
+        Neighbors {
+            prev: current.checked_sub(1).and_then(|i| stops.get(i)).cloned(),
+            next: stops.get(current + 1).cloned(),
+        }
+    }
+
+    /// Returns the deepest ToC entry pointing at `fragment` in the chapter at `spine_index`.
+    ///
+    /// Without a `fragment`, entries pointing at the chapter itself match. When nothing points at the `fragment`,
+    /// those entries are used as a fallback. If several entries match, the deepest one wins, and the first in ToC
+    /// order among those.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let entry = doc.toc_entry_for(2, None).unwrap();
+    /// assert_eq!("Despertar", entry.label);
+    /// ```
+    pub fn toc_entry_for(&self, spine_index: usize, fragment: Option<&str>) -> Option<&NavPoint> {
+        self.toc_breadcrumbs(spine_index, fragment).pop()
+    }
+
+    /// Returns the chain of ToC entries from the top level down to [EpubDoc::toc_entry_for], e.g. for "Part II ›
+    /// Chapter 5 › Section 3" breadcrumbs.
+    ///
+    /// Returns nothing if no entry points at the chapter at `spine_index`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::{EpubDoc, NavPoint};
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// let breadcrumbs = doc.toc_breadcrumbs(3, None);
+    /// assert_eq!(vec!["Introduction"], NavPoint::path_labels(&breadcrumbs));
+    /// ```
+    pub fn toc_breadcrumbs(&self, spine_index: usize, fragment: Option<&str>) -> Vec<&NavPoint> {
+        let points_at = |nav: &NavPoint, wanted: Option<&str>| {
+            nav.fragment.as_deref() == wanted && self.toc_entry_chapter(nav) == Some(spine_index)
+        };
+
+        let mut best = Vec::new();
+        deepest_match(
+            self.get_toc(),
+            &|nav| points_at(nav, fragment),
+            &mut Vec::new(),
+            &mut best,
+        );
+        if best.is_empty() && fragment.is_some() {
+            deepest_match(
+                self.get_toc(),
+                &|nav| points_at(nav, None),
+                &mut Vec::new(),
+                &mut best,
+            );
+        }
+
+        best
+    }
+
+    /// Returns the ToC entry pointing at the resource at `path`, e.g. to highlight it in a ToC sidebar.
+    ///
+    /// Paths are compared after normalizing their separators and percent-encoding. Entries without a fragment are
+    /// preferred over those with one, and among those the deepest entry wins, and the first in ToC order on a tie.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// let entry = doc.toc_entry_for_path("epub/text/introduction.xhtml").unwrap();
+    /// assert_eq!("Introduction", entry.label);
+    /// ```
+    pub fn toc_entry_for_path(&self, path: impl AsRef<Path>) -> Option<&NavPoint> {
+        let wanted = utils::normalized_path(path.as_ref());
+        self.deepest_entry(|nav| {
+            nav.content
+                .as_deref()
+                .is_some_and(|content| utils::normalized_path(content) == wanted)
+        })
+    }
+
+    /// Returns the ToC entry pointing at the chapter at `spine_index`, preferring entries without a fragment like
+    /// [EpubDoc::toc_entry_for_path].
+    ///
+    /// Unlike [EpubDoc::toc_entry_for] with no fragment, entries pointing into the chapter are used when no entry
+    /// points at the chapter as a whole.
+    pub fn toc_entry_for_chapter(&self, spine_index: usize) -> Option<&NavPoint> {
+        self.deepest_entry(|nav| self.toc_entry_chapter(nav) == Some(spine_index))
+    }
+
+    /// Replaces the ToC used by [EpubDoc::get_toc] and the navigation helpers, returning the previous one, e.g. with
+    /// one from [EpubDoc::generate_toc_from_headings](crate::doc::EpubDoc::generate_toc_from_headings).
+    ///
+    /// This only changes the ToC in memory, the NCX and navigation document are left as is when saving.
+    pub fn set_toc(&mut self, toc: Vec<NavPoint>) -> Vec<NavPoint> {
+        std::mem::replace(&mut self.ctx_mut().toc, toc)
+    }
+
+    /// Returns every ToC entry depth-first with its depth, `0` for the top level, e.g. to render the ToC as an
+    /// indented list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// for (depth, nav) in doc.toc_flat() {
+    ///     println!("{}{}", "  ".repeat(depth), nav.label);
+    /// }
+    /// assert_eq!(Some((0, "Vestidor")), doc.toc_flat().nth(2).map(|(d, nav)| (d, nav.label.as_str())));
+    /// ```
+    pub fn toc_flat(&self) -> impl Iterator<Item = (usize, &NavPoint)> {
+        let mut levels = vec![self.get_toc().iter()];
+
+        std::iter::from_fn(move || loop {
+            let depth = levels.len().checked_sub(1)?;
+            match levels[depth].next() {
+                Some(nav) => {
+                    levels.push(nav.children.iter());
+                    return Some((depth, nav));
+                }
+                None => {
+                    levels.pop();
+                }
+            }
+        })
+    }
+
+    /// Returns the spine index every ToC entry lands on, as `(index in` [EpubDoc::toc_flat]`, spine index)`, in ToC
+    /// order. Entries pointing outside the spine have `None`, headings without a link of their own are left out.
+    ///
+    /// The whole ToC is mapped in a single pass, which is much cheaper than looking up every entry with
+    /// [EpubDoc::resource_uri_to_chapter] for large ToCs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let map = doc.toc_spine_map();
+    /// assert_eq!(doc.toc_flat().count(), map.len());
+    /// assert_eq!((2, Some(3)), map[2]);
+    /// ```
+    pub fn toc_spine_map(&self) -> Vec<(usize, Option<usize>)> {
+        let index = self.spine_path_index();
+
+        self.toc_flat()
+            .enumerate()
+            .filter_map(|(i, (_, nav))| {
+                let content = nav.content.as_deref()?;
+                Some((i, index.get(&utils::normalized_path(content)).copied()))
+            })
+            .collect()
+    }
+
+    /// The chapter the ToC entry points at, `None` for headings without a link.
+    fn toc_entry_chapter(&self, nav: &NavPoint) -> Option<usize> {
+        self.toc_path_to_chapter(nav.content.as_deref()?)
+    }
+
+    /// The normalized path of every spine item -> its (first) spine index.
+    fn spine_path_index(&self) -> HashMap<String, usize> {
+        let mut index = HashMap::new();
+        for (i, item) in self.spine().iter().enumerate() {
+            if let Some(resource) = self.resources().get(&item.idref) {
+                index
+                    .entry(utils::normalized_path(&resource.path))
+                    .or_insert(i);
+            }
+        }
+
+        index
+    }
+
+    /// The deepest entry for which `matches` holds, preferring entries without a fragment.
+    fn deepest_entry(&self, matches: impl Fn(&NavPoint) -> bool) -> Option<&NavPoint> {
+        let mut best = Vec::new();
+        deepest_match(
+            self.get_toc(),
+            &|nav| nav.fragment.is_none() && matches(nav),
+            &mut Vec::new(),
+            &mut best,
+        );
+        if best.is_empty() {
+            deepest_match(self.get_toc(), &matches, &mut Vec::new(), &mut best);
+        }
+
+        best.pop()
+    }
+
+    /// All stops in the reading order, always including the chapter at `include`.
+    fn reading_order_stops(&self, include: usize) -> Vec<Locator> {
+        let index = self.spine_path_index();
+        let mut fragments: Vec<Vec<&str>> = vec![Vec::new(); self.spine().len()];
+        for (_, nav) in self.toc_flat() {
+            let Some(fragment) = nav.fragment.as_deref() else {
+                continue;
+            };
+            let Some(content) = nav.content.as_deref() else {
+                continue;
+            };
+            let Some(&chapter) = index.get(&utils::normalized_path(content)) else {
+                continue;
+            };
+
+            // Several entries pointing at the same target would otherwise result in zero-length steps.
+            if !fragments[chapter].contains(&fragment) {
+                fragments[chapter].push(fragment);
+            }
+        }
+
+        self.spine()
+            .iter()
+            .zip(fragments)
+            .enumerate()
+            .filter(|(i, (item, _))| item.linear || !self.linear_navigation || *i == include)
+            .flat_map(|(i, (_, fragments))| {
+                let fragments = fragments.into_iter().map(move |fragment| Locator {
+                    spine_index: i,
+                    fragment: Some(fragment.to_string()),
+                });
+
+                std::iter::once(Locator::chapter(i)).chain(fragments)
+            })
+            .collect()
+    }
+}
+
+impl NavPoint {
+    /// Returns the labels of a chain of entries, like the one returned by [EpubDoc::toc_breadcrumbs].
+    pub fn path_labels<'a>(path: &[&'a NavPoint]) -> Vec<&'a str> {
+        path.iter().map(|nav| nav.label.as_str()).collect()
+    }
+
+    /// The full path of the entry with its fragment, if any, e.g. `OEBPS/chapter3.xhtml#sec-2`. `None` for a
+    /// heading without a link.
+    pub(crate) fn href(&self) -> Option<String> {
+        let path = utils::relative_href("", self.content.as_ref()?);
+        match &self.fragment {
+            Some(fragment) => Some(format!("{path}#{fragment}")),
+            None => Some(path),
+        }
+    }
+}
+
+/// Depth-first search for the deepest entry matching `matches`, keeping the first one found on a tie.
+fn deepest_match<'a>(
+    toc: &'a [NavPoint],
+    matches: &impl Fn(&NavPoint) -> bool,
+    path: &mut Vec<&'a NavPoint>,
+    best: &mut Vec<&'a NavPoint>,
+) {
+    for nav in toc {
+        path.push(nav);
+        if matches(nav) && path.len() > best.len() {
+            best.clone_from(path);
+        }
+        deepest_match(&nav.children, matches, path, best);
+        path.pop();
+    }
+}
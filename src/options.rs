@@ -0,0 +1,182 @@
+//! Options to control how an epub is opened.
+
+/// How strictly the package document of an epub is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Fail to open the epub when any required section (metadata, manifest, spine) of the package can't be parsed.
+    #[default]
+    Strict,
+    /// Open the epub with whatever could be parsed.
+    ///
+    /// The sections which failed are listed in [EpubDoc::partial_failures](crate::doc::EpubDoc::partial_failures).
+    /// A package document which was HTML entity escaped or percent-encoded as a whole is decoded (once), with a
+    /// warning in [EpubDoc::parse_warnings](crate::doc::EpubDoc::parse_warnings).
+    Lenient,
+}
+
+/// Options used when opening an epub.
+///
+/// # Examples
+///
+/// ```
+/// use epub::doc::EpubDoc;
+/// use epub::options::{ParseMode, ParseOptions};
+///
+/// let options = ParseOptions {
+///     mode: ParseMode::Lenient,
+///     ..Default::default()
+/// };
+/// let doc = EpubDoc::new_with_options("test.epub", options).unwrap();
+/// assert!(doc.partial_failures().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// How to deal with sections of the package document which fail to parse
+    pub mode: ParseMode,
+    /// Caps on the size of the parsed metadata, unlimited by default
+    pub metadata_limits: MetadataLimits,
+    /// Whether to check the declared media types of the resources against their content, off by default
+    pub media_type_sniffing: MediaTypeSniffing,
+    /// Which value to keep when the same metadata occurs more than once, all of them by default
+    pub metadata_dedup: MetadataDedup,
+    /// The maximum number of levels of the ToC, 64 by default.
+    ///
+    /// The entries of the last level get every entry nested deeper as (childless) children, in document order,
+    /// which is reported in [EpubDoc::parse_warnings](crate::doc::EpubDoc::parse_warnings). This bounds the
+    /// recursion on maliciously deep ToCs.
+    pub max_toc_depth: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            mode: ParseMode::default(),
+            metadata_limits: MetadataLimits::default(),
+            media_type_sniffing: MediaTypeSniffing::default(),
+            metadata_dedup: MetadataDedup::default(),
+            max_toc_depth: 64,
+        }
+    }
+}
+
+/// What to do with resources whose content doesn't match their declared media type, see
+/// [EpubDoc::sniff_resource_mime](crate::doc::EpubDoc::sniff_resource_mime).
+///
+/// Checking reads the start of every resource in the manifest, which makes opening the epub slower.
+///
+/// # Examples
+///
+/// ```
+/// use epub::doc::EpubDoc;
+/// use epub::options::{MediaTypeSniffing, ParseOptions};
+///
+/// let options = ParseOptions {
+///     media_type_sniffing: MediaTypeSniffing::Warn,
+///     ..Default::default()
+/// };
+/// let doc = EpubDoc::new_with_options("test.epub", options).unwrap();
+/// assert!(doc.parse_warnings().is_empty());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MediaTypeSniffing {
+    /// Trust the declared media types
+    #[default]
+    Off,
+    /// Report mismatches in [EpubDoc::parse_warnings](crate::doc::EpubDoc::parse_warnings)
+    Warn,
+    /// Report mismatches, and replace the declared media type with the detected one
+    Correct,
+}
+
+/// Which metadata to keep when a name (e.g. `title`) occurs more than once, to mirror a specific reading system.
+///
+/// The policy is applied per name. `meta`s refining another element are always kept, as they describe different
+/// elements. The discarded values are still available from
+/// [EpubDoc::raw_metadata](crate::doc::EpubDoc::raw_metadata).
+///
+/// # Examples
+///
+/// ```
+/// use epub::doc::EpubDoc;
+/// use epub::options::{MetadataDedup, ParseOptions};
+///
+/// let options = ParseOptions {
+///     metadata_dedup: MetadataDedup::Longest,
+///     ..Default::default()
+/// };
+/// let doc = EpubDoc::new_with_options("test.epub", options).unwrap();
+/// assert_eq!(Some("Todo es mío"), doc.mdata("title"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetadataDedup {
+    /// Keep every value
+    #[default]
+    KeepAll,
+    /// Keep the first value in document order
+    First,
+    /// Keep the last value in document order
+    Last,
+    /// Keep the longest value (ignoring surrounding whitespace), the first one on a tie
+    Longest,
+}
+
+/// Caps on the metadata kept while parsing, to bound the memory used by pathological package documents.
+///
+/// Anything over a limit is truncated or dropped, and reported in
+/// [EpubDoc::parse_warnings](crate::doc::EpubDoc::parse_warnings). Every limit is off by default, use
+/// [MetadataLimits::recommended] when opening untrusted epubs, e.g. on a server.
+///
+/// # Examples
+///
+/// ```
+/// use epub::doc::EpubDoc;
+/// use epub::options::{MetadataLimits, ParseOptions};
+///
+/// let options = ParseOptions {
+///     metadata_limits: MetadataLimits {
+///         max_value_len: Some(8),
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// let doc = EpubDoc::new_with_options("test.epub", options).unwrap();
+/// assert_eq!(Some("Todo es "), doc.mdata("title"));
+/// assert!(!doc.parse_warnings().is_empty());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MetadataLimits {
+    /// The maximum length in bytes of a metadata value or attribute value, longer values are truncated
+    pub max_value_len: Option<usize>,
+    /// The maximum number of attributes kept per metadata node
+    pub max_attributes: Option<usize>,
+    /// The maximum number of metadata nodes kept with the same name
+    pub max_entries_per_key: Option<usize>,
+}
+
+impl MetadataLimits {
+    /// Limits which no reasonable epub comes close to: 64 KiB per value, 32 attributes per node, and 256 nodes
+    /// with the same name.
+    pub fn recommended() -> Self {
+        MetadataLimits {
+            max_value_len: Some(64 * 1024),
+            max_attributes: Some(32),
+            max_entries_per_key: Some(256),
+        }
+    }
+
+    /// Truncates `value` to at most [MetadataLimits::max_value_len] bytes (at a character boundary),
+    /// returning whether it was truncated.
+    pub(crate) fn truncate(&self, value: &mut String) -> bool {
+        match self.max_value_len {
+            Some(max) if value.len() > max => {
+                let mut end = max;
+                while !value.is_char_boundary(end) {
+                    end -= 1;
+                }
+                value.truncate(end);
+                true
+            }
+            _ => false,
+        }
+    }
+}
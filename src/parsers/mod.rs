@@ -5,12 +5,21 @@
 //! without compatibility crud.
 
 use crate::archive::EpubArchive;
-use crate::doc::{MetadataNode, NavPoint, ResourceItem};
+use crate::doc::{
+    GuideReference, Landmark, MetaLink, MetadataNode, NavPoint, NcxMetadata, OpfSection,
+    PackageCollection, PageTarget, ParseWarning, PartialFailure, ResourceItem, SpineItem,
+};
 use crate::error::Result;
+use crate::metadata::TextDirection;
+use crate::options::{MetadataDedup, ParseMode, ParseOptions};
+use crate::sniff;
 use crate::utils;
+use crate::xmlutils::{self, OwnedAttribute};
+use indexmap::IndexMap;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::{Read, Seek};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub(crate) mod v2;
 pub(crate) mod v3;
@@ -26,20 +35,48 @@ pub trait EpubParser {
         root_base: PATH,
         xml: &roxmltree::Document<'_>,
         archive: &mut EpubArchive<R>,
+        options: &ParseOptions,
     ) -> Result<()>;
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EpubMetadata {
-    /// epub spine ids
-    pub spine: Vec<String>,
+    /// epub spine, in reading order
+    pub spine: Vec<SpineItem>,
 
-    /// resource id -> (path, mime)
-    pub resources: HashMap<String, ResourceItem>,
+    /// The manifest id of the NCX, as declared by the `toc` attribute of the `spine`, or found by its media type if
+    /// that attribute is missing or refers to nothing
+    pub toc_id: Option<String>,
 
-    /// table of content, list of `NavPoint` in the toc.ncx
+    /// The manifest id of the EPUB3 navigation document, the item with the `nav` property or else the one which
+    /// looked like a nav
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) nav_id: Option<String>,
+
+    /// The `id` attribute of the `spine` element
+    pub spine_id: Option<String>,
+
+    /// The index of the `spine` element among the element children of the `package`, used for CFIs
+    pub spine_element_index: usize,
+
+    /// resource id -> (path, mime), in the order of the manifest
+    pub resources: IndexMap<String, ResourceItem>,
+
+    /// The [normalized](crate::doc::EpubDoc::resource_id_by_path) path -> the id of the first resource with that
+    /// path, rebuilt by [EpubDoc::from_parts](crate::doc::EpubDoc::from_parts)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) resource_paths: HashMap<PathBuf, String>,
+
+    /// table of content, the one of the EPUB3 nav if there is one, otherwise the one of the toc.ncx
     pub toc: Vec<NavPoint>,
 
+    /// The table of contents of the toc.ncx
+    pub toc_ncx: Vec<NavPoint>,
+
+    /// The table of contents of the EPUB3 nav
+    pub toc_nav: Vec<NavPoint>,
+
     /// The epub metadata stored as key -> value
     ///
     /// #Examples
@@ -48,9 +85,13 @@ pub struct EpubMetadata {
     /// # use epub::doc::{EpubDoc, MetadataNode};
     /// # let doc = EpubDoc::new("test.epub");
     /// # let doc = doc.unwrap();
-    /// let title = doc.context.metadata.get("title");
+    /// let title = doc.metadata().get("title");
+    /// let expected = MetadataNode {
+    ///     namespace: Some("http://purl.org/dc/elements/1.1/".to_string()),
+    ///     ..MetadataNode::from_content("Todo es mío".to_string())
+    /// };
     ///
-    /// assert_eq!(title.unwrap(), &vec![MetadataNode::from_content("Todo es mío".to_string())]);
+    /// assert_eq!(title.unwrap(), &vec![expected]);
     /// ```
     pub metadata: HashMap<String, Vec<MetadataNode>>,
 
@@ -59,9 +100,379 @@ pub struct EpubMetadata {
 
     /// unique identifier
     pub unique_identifier: Option<String>,
+
+    /// The `id` of the `dc:identifier` the unique identifier was read from, as written
+    pub unique_identifier_id: Option<String>,
+
+    /// Every `dc:identifier` in document order, regardless of the [MetadataDedup] policy. The
+    /// [MetadataLimits](crate::options::MetadataLimits) apply, but never drop the unique identifier.
+    pub identifiers: Vec<MetadataNode>,
+
+    /// The index in `identifiers` of the one referenced by the package `unique-identifier` attribute
+    pub(crate) unique_identifier_index: Option<usize>,
+
+    /// The references in the EPUB2 `guide`
+    pub guide: Vec<GuideReference>,
+
+    /// The head metadata of the NCX, set whenever the NCX is parsed
+    pub ncx: Option<NcxMetadata>,
+
+    /// The entries of the EPUB3 `landmarks` nav
+    pub landmarks: Vec<Landmark>,
+
+    /// The `link` elements of the package metadata, in document order
+    pub meta_links: Vec<MetaLink>,
+
+    /// The EPUB3 `collection` elements of the package, in document order
+    pub collections: Vec<PackageCollection>,
+
+    /// The entries of the EPUB3 `page-list` nav, or else the NCX `pageList`, in reading order
+    pub page_list: Vec<PageTarget>,
+
+    /// The `version` attribute of the package document
+    pub version: Option<String>,
+
+    /// The `prefix` attribute of the package document, declaring the vocabularies of the metadata properties
+    pub prefix: Option<String>,
+
+    /// The `xml:lang` attribute of the package document, the default language of the metadata
+    pub package_lang: Option<String>,
+
+    /// The `dir` attribute of the package document, the default base direction of the metadata
+    pub package_dir: Option<TextDirection>,
+
+    /// The (prefix, IRI) pairs declared by the `prefix` attribute, in declaration order, see
+    /// [EpubDoc::resolve_property](crate::doc::EpubDoc::resolve_property)
+    pub prefixes: Vec<(String, String)>,
+
+    /// Sections of the package document which failed to parse
+    pub partial_failures: Vec<PartialFailure>,
+
+    /// Non-fatal issues found while parsing
+    pub warnings: Vec<ParseWarning>,
+
+    /// id -> the metadata which refines the element with that id, as (name, index in `raw_metadata[name]`),
+    /// in document order
+    pub(crate) refinements: HashMap<String, Vec<(String, usize)>>,
+
+    /// The metadata elements in document order, as (name, index in `raw_metadata[name]`)
+    pub(crate) metadata_order: Vec<(String, usize)>,
+
+    /// Every metadata node as parsed, only set when the [MetadataDedup] policy discarded some of them
+    pub(crate) raw_metadata: Option<HashMap<String, Vec<MetadataNode>>>,
 }
 
 impl EpubMetadata {
+    /// Check the `result` of parsing an individual `section` of the package document.
+    ///
+    /// Failures are recorded in [EpubMetadata::partial_failures] and swallowed for the optional sections (ToC and guide),
+    /// as well as for every section in [ParseMode::Lenient]. Otherwise the error is returned.
+    pub(crate) fn check_section<T>(
+        &mut self,
+        section: OpfSection,
+        result: Result<T>,
+        options: &ParseOptions,
+    ) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e)
+                if options.mode == ParseMode::Lenient
+                    || matches!(section, OpfSection::Toc | OpfSection::Guide) =>
+            {
+                self.partial_failures.push(PartialFailure {
+                    section,
+                    message: e.to_string(),
+                });
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Add a metadata node, applying the [MetadataLimits](crate::options::MetadataLimits) of the `options`.
+    pub(crate) fn insert_metadata(
+        &mut self,
+        name: &str,
+        content: &str,
+        node: &roxmltree::Node<'_, '_>,
+        options: &ParseOptions,
+    ) {
+        let (name, namespace) = metadata_name(name, node);
+        let limits = &options.metadata_limits;
+        let entries = self.metadata.get(name).map_or(0, Vec::len);
+        if limits.max_entries_per_key.is_some_and(|max| entries >= max) {
+            let message = format!("only the first {entries} `{name}` metadata entries are kept");
+            if !self
+                .warnings
+                .iter()
+                .any(|warning| warning.message == message)
+            {
+                self.warn(message);
+            }
+            return;
+        }
+
+        let mut item = self.limited_node(name, content, node, options);
+        item.namespace = namespace.map(Into::into);
+
+        let entries = self.metadata.entry(name.to_string()).or_default();
+        if let Some(refines) = item.find_attr("refines") {
+            // Usually `#id`, but any IRI with a fragment refers to an element in the package document
+            let target = refines.rsplit_once('#').map_or(refines, |(_, id)| id);
+            self.refinements
+                .entry(target.to_string())
+                .or_default()
+                .push((name.to_string(), entries.len()));
+        }
+        self.metadata_order.push((name.to_string(), entries.len()));
+        entries.push(item);
+    }
+
+    /// Build the metadata node of the `name` element, truncating its value and attributes according to the
+    /// [MetadataLimits](crate::options::MetadataLimits) of the `options`.
+    fn limited_node(
+        &mut self,
+        name: &str,
+        content: &str,
+        node: &roxmltree::Node<'_, '_>,
+        options: &ParseOptions,
+    ) -> MetadataNode {
+        let limits = &options.metadata_limits;
+        let max_attributes = limits.max_attributes.unwrap_or(usize::MAX);
+        let mut content = content.to_string();
+        if limits.truncate(&mut content) {
+            self.warn(format!(
+                "`{name}` metadata value truncated to {} bytes",
+                content.len()
+            ));
+        }
+        let mut item = MetadataNode::from_attr_limited(content, node, max_attributes);
+
+        let attributes = node.attributes().len();
+        if attributes > max_attributes {
+            self.warn(format!(
+                "`{name}` metadata kept {max_attributes} of its {attributes} attributes"
+            ));
+        }
+        for attr in &mut item.attr {
+            if limits
+                .max_value_len
+                .is_some_and(|max| attr.value.len() > max)
+            {
+                let mut value = attr.value.to_string();
+                limits.truncate(&mut value);
+                self.warn(format!(
+                    "`{name}` metadata attribute `{}` truncated to {} bytes",
+                    attr.name.tag,
+                    value.len()
+                ));
+                attr.value = value.into();
+            }
+        }
+
+        item
+    }
+
+    /// Set the content of the first `name` metadata which doesn't refine another element, or add one if there is
+    /// none, returning the previous content.
+    pub(crate) fn set_metadata(&mut self, name: &str, value: &str) -> Option<String> {
+        let (key, namespace) = metadata_key(name);
+        let Some(node) = self
+            .metadata
+            .get_mut(key)
+            .and_then(|nodes| nodes.iter_mut().find(|n| n.find_attr("refines").is_none()))
+        else {
+            self.add_metadata(
+                key,
+                MetadataNode {
+                    namespace: namespace.map(Into::into),
+                    ..MetadataNode::from_content(value)
+                },
+            );
+            return None;
+        };
+
+        let previous = node.clone();
+        node.content = value.to_string();
+        // The same node is also kept aside with the discarded ones
+        if let Some(raw) = self
+            .raw_metadata
+            .as_mut()
+            .and_then(|raw| raw.get_mut(key))
+            .and_then(|nodes| nodes.iter_mut().find(|n| **n == previous))
+        {
+            raw.content = value.to_string();
+        }
+        self.sync_metadata(key);
+
+        Some(previous.content)
+    }
+
+    /// Add a metadata node after the existing ones, as the last element of the metadata.
+    pub(crate) fn add_metadata(&mut self, name: &str, mut node: MetadataNode) {
+        let (key, namespace) = metadata_key(name);
+        if node.namespace.is_none() {
+            node.namespace = namespace.map(Into::into);
+        }
+
+        let index = match &mut self.raw_metadata {
+            Some(raw) => {
+                let nodes = raw.entry(key.to_string()).or_default();
+                nodes.push(node.clone());
+                nodes.len() - 1
+            }
+            None => self.metadata.get(key).map_or(0, Vec::len),
+        };
+        if let Some(refines) = node.find_attr("refines") {
+            let target = refines.rsplit_once('#').map_or(refines, |(_, id)| id);
+            self.refinements
+                .entry(target.to_string())
+                .or_default()
+                .push((key.to_string(), index));
+        }
+        self.metadata_order.push((key.to_string(), index));
+        self.metadata.entry(key.to_string()).or_default().push(node);
+        self.sync_metadata(key);
+    }
+
+    /// Remove every `name` metadata, returning the removed nodes.
+    pub(crate) fn remove_metadata(&mut self, name: &str) -> Vec<MetadataNode> {
+        let (key, _) = metadata_key(name);
+        if let Some(raw) = &mut self.raw_metadata {
+            raw.remove(key);
+        }
+        self.metadata_order.retain(|(name, _)| name != key);
+        self.refinements.retain(|_, refinements| {
+            refinements.retain(|(name, _)| name != key);
+            !refinements.is_empty()
+        });
+        let removed = self.metadata.remove(key).unwrap_or_default();
+        self.sync_metadata(key);
+
+        removed
+    }
+
+    /// The manifest id of the cover from the first `cover` meta, which should hold the id but sometimes holds the
+    /// href of the image instead.
+    pub(crate) fn cover_id_from_meta(&self) -> Option<String> {
+        let content = self.metadata.get("cover")?.first()?.content.trim();
+        if self.resources.contains_key(content) {
+            return Some(content.to_string());
+        }
+
+        // The href is usually relative to the package document, but as the `..` and `.` are dropped it matches
+        // hrefs relative to the root of the epub as well. The shortest path is the closest match.
+        let (href, _) = utils::split_fragment(content);
+        let href: PathBuf = href
+            .split('/')
+            .filter(|segment| !matches!(*segment, "" | "." | ".."))
+            .collect();
+        if href.as_os_str().is_empty() {
+            return None;
+        }
+        self.resources
+            .iter()
+            .filter(|(_, item)| item.path.ends_with(&href))
+            .min_by_key(|(id, item)| (item.path.components().count(), *id))
+            .map(|(id, _)| id.clone())
+    }
+
+    /// Update the state derived from the `key` metadata after it changed.
+    fn sync_metadata(&mut self, key: &str) {
+        match key {
+            "cover" => {
+                // Otherwise the EPUB3 `cover-image` item, like the parser does
+                self.cover_id = self.cover_id_from_meta().or_else(|| {
+                    self.resources
+                        .iter()
+                        .find(|(_, item)| item.has_property("cover-image"))
+                        .map(|(id, _)| id.clone())
+                });
+            }
+            "identifier" => {
+                let nodes = self.raw_metadata.as_ref().unwrap_or(&self.metadata);
+                self.identifiers = nodes
+                    .get("identifier")
+                    .into_iter()
+                    .flatten()
+                    .filter(|node| node.find_attr("refines").is_none())
+                    .cloned()
+                    .collect();
+                // The id the package refers to stays the same
+                let unique_id = self.unique_identifier_id.as_deref().map(str::trim);
+                self.unique_identifier_index = unique_id.and_then(|unique_id| {
+                    self.identifiers
+                        .iter()
+                        .position(|node| node.find_attr("id").map(str::trim) == Some(unique_id))
+                });
+                self.unique_identifier = self
+                    .unique_identifier_index
+                    .map(|i| self.identifiers[i].content.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// Apply the [MetadataDedup] policy to the parsed metadata, keeping the full metadata aside if anything is
+    /// discarded.
+    pub(crate) fn dedup_metadata(&mut self, policy: MetadataDedup) {
+        if policy == MetadataDedup::KeepAll {
+            return;
+        }
+
+        let mut deduped = self.metadata.clone();
+        let mut discarded = false;
+        for nodes in deduped.values_mut() {
+            let candidates = nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, node)| node.find_attr("refines").is_none());
+            let keep = match policy {
+                MetadataDedup::KeepAll => None,
+                MetadataDedup::First => candidates.map(|(i, _)| i).next(),
+                MetadataDedup::Last => candidates.map(|(i, _)| i).next_back(),
+                // `max_by_key` returns the last maximum, reverse to get the first
+                MetadataDedup::Longest => candidates
+                    .rev()
+                    .max_by_key(|(_, node)| node.content.trim().chars().count())
+                    .map(|(i, _)| i),
+            };
+            let Some(keep) = keep else {
+                continue;
+            };
+
+            let len = nodes.len();
+            let mut index = 0;
+            nodes.retain(|node| {
+                let kept = index == keep || node.find_attr("refines").is_some();
+                index += 1;
+                kept
+            });
+            discarded |= nodes.len() != len;
+        }
+
+        if discarded {
+            self.raw_metadata = Some(std::mem::replace(&mut self.metadata, deduped));
+        }
+    }
+
+    fn warn(&mut self, message: String) {
+        self.warnings.push(ParseWarning {
+            section: OpfSection::Metadata,
+            message,
+        });
+    }
+
+    /// Rebuild the `resource_paths` index from the `resources`.
+    pub(crate) fn index_resource_paths(&mut self) {
+        self.resource_paths.clear();
+        for (id, item) in &self.resources {
+            self.resource_paths
+                .entry(PathBuf::from(utils::normalized_path(&item.path)))
+                .or_insert_with(|| id.clone());
+        }
+    }
+
     pub(crate) fn insert_resource(
         &mut self,
         root_base: impl AsRef<Path>,
@@ -69,18 +480,214 @@ impl EpubMetadata {
     ) -> Option<()> {
         let id = item.attribute("id")?;
         let href = item.attribute("href")?;
-        let mtype = item.attribute("media-type")?;
-        let path = utils::convert_path_separators(root_base, href);
+        let (path, escaped) = if utils::is_remote(href) {
+            // A remote resource, which isn't a path within the epub, see [EpubDoc::remote_resources]
+            (PathBuf::from(href), false)
+        } else {
+            // An item is a whole file, some tools add a fragment anyway
+            let (path, fragment) = utils::split_fragment(href);
+            if fragment.is_some() || href.ends_with('#') {
+                self.warnings.push(ParseWarning {
+                    section: OpfSection::Manifest,
+                    message: format!(
+                        "the href `{href}` of the manifest item `{id}` has a fragment, it was ignored"
+                    ),
+                });
+            }
+            utils::resolve_href(root_base, path)
+        };
+        let mtype = match item.attribute("media-type") {
+            Some(mtype) => mtype,
+            // Dropping the item would break the spine, a guess is better
+            None => {
+                let inferred =
+                    sniff::mime_from_extension(&path).unwrap_or("application/octet-stream");
+                self.warnings.push(ParseWarning {
+                    section: OpfSection::Manifest,
+                    message: format!(
+                        "the manifest item `{id}` has no media type, using `{inferred}`"
+                    ),
+                });
+                inferred
+            }
+        };
+        if escaped {
+            self.warnings.push(ParseWarning {
+                section: OpfSection::Manifest,
+                message: format!(
+                    "the href `{href}` of the manifest item `{id}` points outside the epub, `{}` is used instead",
+                    path.display()
+                ),
+            });
+        }
 
         self.resources.insert(
             id.to_string(),
             ResourceItem {
                 path,
                 mime: mtype.to_string(),
-                property: item.attribute("properties").map(Into::into),
+                properties: item
+                    .attribute("properties")
+                    .map(|p| p.split_ascii_whitespace().map(String::from).collect())
+                    .unwrap_or_default(),
+                media_overlay: item.attribute("media-overlay").map(Into::into),
+                fallback: item.attribute("fallback").map(Into::into),
+                // Filled in once the whole manifest is known
+                size: None,
+                compressed_size: None,
+                extra_attrs: OwnedAttribute::unknown(
+                    item,
+                    &[
+                        "id",
+                        "href",
+                        "media-type",
+                        "properties",
+                        "media-overlay",
+                        "fallback",
+                    ],
+                ),
             },
         );
 
         Some(())
     }
 }
+
+/// Returns the name and content of a child of a `metadata` element: an element like `dc:title` by its local name,
+/// or a `meta` by its `name` (EPUB2) or `property` (EPUB3).
+///
+/// Returns `None` for anything else, like the text between the elements or a `meta` with neither.
+pub(crate) fn metadata_entry<'a>(item: &roxmltree::Node<'a, '_>) -> Option<(&'a str, &'a str)> {
+    if !item.is_element() {
+        return None;
+    }
+
+    if item.has_tag_name("meta") {
+        // Some EPUB2 books write `value` instead of `content`
+        let content = item.attribute("content").or(item.attribute("value"));
+        if let (Some(name), Some(content)) = (item.attribute("name"), content) {
+            Some((name, content))
+        } else {
+            Some((item.attribute("property")?, item.text().unwrap_or_default()))
+        }
+    } else {
+        Some((item.tag_name().name(), item.text().unwrap_or_default()))
+    }
+}
+
+/// Parses the children of a `metadata` element on their own, the way the package metadata is parsed but without
+/// any limits.
+pub(crate) fn parse_metadata_element(
+    metadata: &roxmltree::Node<'_, '_>,
+) -> HashMap<String, Vec<MetadataNode>> {
+    let mut epub = EpubMetadata::default();
+    let options = ParseOptions::default();
+
+    for item in metadata.children() {
+        if let Some((name, content)) = metadata_entry(&item) {
+            epub.insert_metadata(name, content, &item, &options);
+        }
+    }
+
+    epub.metadata
+}
+
+/// Percent-decodes the fragment of a ToC href, keeping it as written if it isn't valid UTF-8 once decoded.
+pub(crate) fn decode_fragment(fragment: &str) -> String {
+    utils::percent_decode(fragment).map_or_else(|| fragment.to_string(), Cow::into_owned)
+}
+
+/// The warning for a ToC nested deeper than [ParseOptions::max_toc_depth].
+pub(crate) fn toc_depth_warning(max_depth: usize) -> ParseWarning {
+    ParseWarning {
+        section: OpfSection::Toc,
+        message: format!(
+            "the ToC is nested deeper than {max_depth} levels, the deeper entries were flattened"
+        ),
+    }
+}
+
+/// The text of a ToC label and everything nested in it, e.g. `<span>`s, with runs of whitespace collapsed to single
+/// spaces and trimmed.
+pub(crate) fn label_text(label: &roxmltree::Node<'_, '_>) -> String {
+    let text: String = label
+        .descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect();
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Normalizes the name of a metadata node, returning the key it's stored under and the namespace of the name.
+///
+/// Dublin Core properties are stored by their local name whether they're elements or `meta` properties, so
+/// `<meta property="dc:title">` ends up next to `<dc:title>`. Other prefixed names, like `dcterms:modified`, are
+/// kept as written.
+fn metadata_name<'a>(name: &'a str, node: &roxmltree::Node<'a, '_>) -> (&'a str, Option<&'a str>) {
+    match metadata_key(name) {
+        // An element, e.g. `<dc:title>`, which roxmltree already reports by its local name
+        (key, None) if !name.contains(':') && node.tag_name().name() == name => {
+            (key, node.tag_name().namespace())
+        }
+        key => key,
+    }
+}
+
+/// Like [metadata_name] for a name on its own, e.g. one given by the user: only the prefix determines the namespace.
+pub(crate) fn metadata_key(name: &str) -> (&str, Option<&'static str>) {
+    match name.split_once(':') {
+        Some(("dc", local)) => (local, Some(xmlutils::DC_NAMESPACE)),
+        Some(("dcterms", _)) => (name, Some(xmlutils::DCTERMS_NAMESPACE)),
+        _ => (name, None),
+    }
+}
+
+/// The vocabulary of unprefixed metadata properties, e.g. `belongs-to-collection`.
+pub(crate) const DEFAULT_VOCABULARY: &str = "http://idpf.org/epub/vocab/package/meta/#";
+
+/// The prefixes which can be used without declaring them.
+pub(crate) const RESERVED_PREFIXES: &[(&str, &str)] = &[
+    ("a11y", "http://www.idpf.org/epub/vocab/package/a11y/#"),
+    ("dcterms", xmlutils::DCTERMS_NAMESPACE),
+    ("marc", "http://id.loc.gov/vocabulary/"),
+    ("media", "http://www.idpf.org/epub/vocab/overlays/#"),
+    ("msv", "http://www.idpf.org/epub/vocab/structure/magazine/#"),
+    (
+        "onix",
+        "http://www.editeur.org/ONIX/book/codelists/current.html#",
+    ),
+    (
+        "prism",
+        "http://www.prismstandard.org/specifications/3.0/PRISM_CV_Spec_3.0.htm#",
+    ),
+    ("rendition", "http://www.idpf.org/vocab/rendition/#"),
+    ("schema", "http://schema.org/"),
+    ("xsd", "http://www.w3.org/2001/XMLSchema#"),
+];
+
+/// Parses the `prefix` attribute of the package, whitespace separated `prefix: iri` pairs.
+///
+/// Malformed pairs are skipped, as are attempts to rebind the default vocabulary with an empty prefix.
+pub(crate) fn parse_prefixes(declaration: &str) -> Vec<(String, String)> {
+    let mut prefixes: Vec<(String, String)> = Vec::new();
+    let mut tokens = declaration.split_ascii_whitespace().peekable();
+
+    while let Some(token) = tokens.next() {
+        let Some(prefix) = token.strip_suffix(':') else {
+            continue;
+        };
+        let Some(iri) = tokens.next_if(|iri| !iri.ends_with(':')) else {
+            continue;
+        };
+        if prefix.is_empty() {
+            continue;
+        }
+
+        // A prefix declared twice keeps its last IRI
+        prefixes.retain(|(known, _)| known != prefix);
+        prefixes.push((prefix.to_string(), iri.to_string()));
+    }
+
+    prefixes
+}
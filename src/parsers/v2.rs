@@ -1,8 +1,13 @@
 use crate::archive::EpubArchive;
-use crate::doc::{MetadataNode, NavPoint};
+use crate::doc::{
+    build_epub_uri, GuideReference, MetaLink, NavPoint, NcxMetadata, OpfSection, PageTarget,
+    ParseWarning, ResourceItem, SpineItem,
+};
 use crate::error::{ArchiveError, Result};
-use crate::parsers::{EpubMetadata, EpubParser};
+use crate::options::ParseOptions;
+use crate::parsers::{self, EpubMetadata, EpubParser};
 use crate::utils;
+use crate::xmlutils::OwnedAttribute;
 use std::io::{Read, Seek};
 use std::path::{Path, PathBuf};
 
@@ -14,12 +19,62 @@ impl EpubParser for EpubV2Parser {
         root_base: PATH,
         xml: &roxmltree::Document<'_>,
         archive: &mut EpubArchive<R>,
+        options: &ParseOptions,
     ) -> Result<()> {
         let root = xml;
         let root_base = root_base.as_ref();
-        let unique_identifier_id = root.root_element().attribute("unique-identifier");
 
         // resources from manifest
+        let manifest = Self::parse_manifest(epub, root_base, root);
+        epub.check_section(OpfSection::Manifest, manifest, options)?;
+
+        // items from spine
+        let spine = Self::parse_spine(epub, root);
+        epub.check_section(OpfSection::Spine, spine, options)?;
+
+        // toc.ncx, found by its media type if the spine doesn't refer to it and there's no EPUB3 nav to use instead
+        let declared = epub
+            .toc_id
+            .clone()
+            .filter(|id| epub.resources.contains_key(id));
+        let has_nav = epub.resources.values().any(|item| item.has_property("nav"));
+        if declared.is_none() && !has_nav && epub.toc_ncx.is_empty() {
+            if let Some(found) = Self::find_ncx(epub) {
+                epub.warnings.push(ParseWarning {
+                    section: OpfSection::Toc,
+                    message: format!(
+                        "the spine doesn't refer to an NCX, using `{found}` from the manifest instead"
+                    ),
+                });
+                epub.toc_id = Some(found);
+            }
+        }
+        if let Some(toc) = epub.toc_id.clone() {
+            let toc = Self::fill_toc(epub, archive, &toc, options.max_toc_depth);
+            epub.check_section(OpfSection::Toc, toc, options)?;
+        }
+
+        // metadata
+        let metadata = Self::parse_metadata(epub, root, options);
+        epub.check_section(OpfSection::Metadata, metadata, options)?;
+
+        // guide
+        let guide = Self::parse_guide(epub, root_base, root);
+        epub.check_section(OpfSection::Guide, guide, options)?;
+
+        // Cover
+        epub.cover_id = epub.cover_id_from_meta();
+
+        Ok(())
+    }
+}
+
+impl EpubV2Parser {
+    fn parse_manifest(
+        epub: &mut EpubMetadata,
+        root_base: &Path,
+        root: &roxmltree::Document<'_>,
+    ) -> Result<()> {
         let manifest = root
             .descendants()
             .find(|r| r.has_tag_name("manifest"))
@@ -27,8 +82,12 @@ impl EpubParser for EpubV2Parser {
         for item in manifest.children() {
             let _ = epub.insert_resource(root_base, &item);
         }
+        epub.index_resource_paths();
 
-        // items from spine
+        Ok(())
+    }
+
+    fn parse_spine(epub: &mut EpubMetadata, root: &roxmltree::Document<'_>) -> Result<()> {
         let spine = root
             .descendants()
             .find(|r| r.has_tag_name("spine"))
@@ -36,151 +95,347 @@ impl EpubParser for EpubV2Parser {
         for item in spine.children() {
             let _ = Self::insert_spine(epub, &item);
         }
+        // The spine itself is the first of its previous siblings
+        epub.spine_element_index = spine
+            .prev_siblings()
+            .skip(1)
+            .filter(|n| n.is_element())
+            .count();
 
-        // toc.ncx
-        if let Some(toc) = spine.attribute("toc") {
-            let _ = Self::fill_toc(epub, root_base, archive, toc);
-        }
+        epub.toc_id = spine.attribute("toc").map(Into::into);
+        epub.spine_id = spine.attribute("id").map(Into::into);
 
-        // metadata
+        Ok(())
+    }
+
+    fn parse_metadata(
+        epub: &mut EpubMetadata,
+        root: &roxmltree::Document<'_>,
+        options: &ParseOptions,
+    ) -> Result<()> {
+        let unique_identifier_id = root.root_element().attribute("unique-identifier");
         let metadata = root
             .descendants()
             .find(|r| r.has_tag_name("metadata"))
             .ok_or(ArchiveError::ParsingFailure)?;
         for item in metadata.children() {
-            if item.has_tag_name("meta") {
-                if let (Some(k), Some(v)) = (item.attribute("name"), item.attribute("content")) {
-                    epub.metadata
-                        .entry(k.to_string())
-                        .or_insert(vec![])
-                        .push(MetadataNode::from_attr(v, &item));
-                } else if let Some(k) = item.attribute("property") {
-                    let v = item.text().unwrap_or_default().to_owned();
-
-                    let node = MetadataNode::from_attr(v, &item);
-
-                    epub.metadata
-                        .entry(k.to_string())
-                        .or_insert(vec![])
-                        .push(node);
-                }
-            } else {
-                let v = item.text().unwrap_or_default().to_owned();
-                if item.has_tag_name("identifier")
-                    && epub.unique_identifier.is_none()
-                    && unique_identifier_id.is_some()
-                {
-                    if let Some(id) = item.attribute("id") {
-                        if &id == unique_identifier_id.as_ref().unwrap() {
-                            epub.unique_identifier = Some(v.clone());
-                        }
-                    }
-                }
-
-                let node = MetadataNode::from_attr(v, &item);
+            if item.has_tag_name("link") {
+                Self::insert_meta_link(epub, &item);
+            }
+            let is_unique = || match (item.attribute("id"), unique_identifier_id) {
+                (Some(id), Some(unique_id)) => id.trim() == unique_id.trim(),
+                _ => false,
+            };
+            if item.has_tag_name("identifier")
+                && (options
+                    .metadata_limits
+                    .max_entries_per_key
+                    .is_none_or(|max| epub.identifiers.len() < max)
+                    || is_unique())
+            {
+                // Any truncation is reported once, when the element is inserted as metadata below
+                let warnings = epub.warnings.len();
+                let v = item.text().unwrap_or_default();
+                let node = epub.limited_node("identifier", v, &item, options);
+                epub.warnings.truncate(warnings);
+                epub.identifiers.push(node);
+            }
 
-                epub.metadata
-                    .entry(item.tag_name().name().to_string())
-                    .or_insert(vec![])
-                    .push(node);
+            if let Some((k, v)) = parsers::metadata_entry(&item) {
+                epub.insert_metadata(k, v, &item, options);
             }
         }
+        epub.dedup_metadata(options.metadata_dedup);
 
-        // Cover
-        if epub.metadata.contains_key("cover") {
-            epub.cover_id = epub
-                .metadata
-                .get("cover")
-                .and_then(|i| i.get(0))
-                .filter(|i| epub.resources.contains_key(&i.content))
-                .map(|i| i.content.to_string());
+        // Resolved once every identifier is known, ids are case-sensitive but surrounding whitespace is ignored
+        if let Some(unique_id) = unique_identifier_id.map(str::trim) {
+            epub.unique_identifier_index = epub
+                .identifiers
+                .iter()
+                .position(|node| node.find_attr("id").map(str::trim) == Some(unique_id));
+        }
+        if let Some(node) = epub.unique_identifier_index.map(|i| &epub.identifiers[i]) {
+            epub.unique_identifier = Some(node.content.clone());
+            epub.unique_identifier_id = node.find_attr("id").map(str::to_string);
         }
 
         Ok(())
     }
-}
 
-impl EpubV2Parser {
+    /// Parse the (optional) guide, any malformed references are skipped and reported as a failure.
+    fn parse_guide(
+        epub: &mut EpubMetadata,
+        root_base: &Path,
+        root: &roxmltree::Document<'_>,
+    ) -> Result<()> {
+        let Some(guide) = root.descendants().find(|r| r.has_tag_name("guide")) else {
+            return Ok(());
+        };
+        let mut malformed = false;
+
+        for item in guide.children().filter(|r| r.has_tag_name("reference")) {
+            let (Some(kind), Some(href)) = (item.attribute("type"), item.attribute("href")) else {
+                malformed = true;
+                continue;
+            };
+            let (href, fragment) = utils::split_fragment(href);
+
+            epub.guide.push(GuideReference {
+                kind: kind.to_string(),
+                title: item.attribute("title").map(Into::into),
+                path: utils::convert_path_separators(root_base, href),
+                fragment: fragment.map(Into::into),
+                hidden: false,
+            });
+        }
+
+        if malformed {
+            Err(ArchiveError::ParsingFailure)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Add a metadata `link`, those without `rel` or `href` are skipped.
+    fn insert_meta_link(epub: &mut EpubMetadata, item: &roxmltree::Node<'_, '_>) -> Option<()> {
+        epub.meta_links.push(MetaLink {
+            rel: item.attribute("rel")?.to_string(),
+            href: item.attribute("href")?.to_string(),
+            media_type: item.attribute("media-type").map(Into::into),
+            properties: item.attribute("properties").map(Into::into),
+        });
+
+        Some(())
+    }
+
     fn insert_spine(epub: &mut EpubMetadata, item: &roxmltree::Node<'_, '_>) -> Option<()> {
         let id = item.attribute("idref")?;
 
-        epub.spine.push(id.to_string());
+        epub.spine.push(SpineItem {
+            idref: id.to_string(),
+            id: item.attribute("id").map(Into::into),
+            linear: item.attribute("linear") != Some("no"),
+            properties: item.attribute("properties").map(Into::into),
+            extra_attrs: OwnedAttribute::unknown(item, &["idref", "id", "linear", "properties"]),
+        });
 
         Some(())
     }
 
-    fn fill_toc<R: Read + Seek, PATH: AsRef<Path>>(
+    fn fill_toc<R: Read + Seek>(
         epub: &mut EpubMetadata,
-        root_base: PATH,
         archive: &mut EpubArchive<R>,
         id: &str,
-    ) -> Option<()> {
-        let toc_res = epub.resources.get(id)?;
+        max_depth: usize,
+    ) -> Result<()> {
+        let toc_path = epub
+            .resources
+            .get(id)
+            .ok_or(ArchiveError::InvalidId)?
+            .path
+            .clone();
+
+        let toc_xml = archive.get_entry(&toc_path)?;
+        let txt = crate::xmlutils::ensure_utf8(&toc_xml)?;
+        let root = crate::xmlutils::parse_xml(&txt)?;
 
-        let toc_xml = archive.get_entry(&toc_res.path).ok()?;
-        let txt = crate::xmlutils::ensure_utf8(&toc_xml);
-        let root = crate::xmlutils::parse_xml(&txt).ok()?;
+        epub.ncx = Some(Self::parse_ncx_metadata(&root));
+        epub.page_list = Self::parse_page_list(&toc_path, &root);
 
-        let map_node = root.descendants().find(|r| r.has_tag_name("navMap"))?;
+        let map_node = root
+            .descendants()
+            .find(|r| r.has_tag_name("navMap"))
+            .ok_or(ArchiveError::ParsingFailure)?;
 
-        epub.toc
-            .append(&mut Self::get_navpoints(root_base, &map_node));
-        epub.toc.sort();
+        let mut flattened = false;
+        epub.toc_ncx.append(&mut Self::get_navpoints(
+            &toc_path,
+            &map_node,
+            max_depth.max(1),
+            &mut flattened,
+        ));
+        if flattened {
+            epub.warnings.push(parsers::toc_depth_warning(max_depth));
+        }
 
-        Some(())
+        Ok(())
     }
 
-    /// Recursively extract all nav-points from a node.
-    fn get_navpoints(
-        root_base: impl AsRef<Path>,
-        parent: &roxmltree::Node<'_, '_>,
-    ) -> Vec<NavPoint> {
-        let root_base = root_base.as_ref();
+    /// The manifest id of an NCX by its media type, or else its `.ncx` extension, the first in manifest order if there are several.
+    fn find_ncx(epub: &EpubMetadata) -> Option<String> {
+        let find = |matches: &dyn Fn(&ResourceItem) -> bool| {
+            epub.resources
+                .iter()
+                .filter(|(_, item)| matches(item))
+                .map(|(id, _)| id.clone())
+                .next()
+        };
 
-        // TODO: get docTitle
-        // TODO: parse metadata (dtb:totalPageCount, dtb:depth, dtb:maxPageNumber)
+        find(&|item| item.mime == "application/x-dtbncx+xml").or_else(|| {
+            find(&|item| {
+                item.path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("ncx"))
+            })
+        })
+    }
+
+    /// Extract the `docTitle`, `docAuthor` and the `dtb:` metadata of the `head` of an NCX.
+    fn parse_ncx_metadata(root: &roxmltree::Document<'_>) -> NcxMetadata {
+        let text = |tag: &str| {
+            root.root_element()
+                .children()
+                .find(|r| r.has_tag_name(tag))
+                .map(|r| parsers::label_text(&r))
+                .filter(|text| !text.is_empty())
+        };
+        let meta = |name: &str| {
+            root.root_element()
+                .children()
+                .filter(|r| r.has_tag_name("head"))
+                .flat_map(|head| head.children())
+                .find(|r| r.has_tag_name("meta") && r.attribute("name") == Some(name))
+                .and_then(|r| r.attribute("content"))
+                .map(str::trim)
+        };
+        let number = |name: &str| meta(name).and_then(|value| value.parse().ok());
+
+        NcxMetadata {
+            title: text("docTitle"),
+            author: text("docAuthor"),
+            depth: number("dtb:depth"),
+            total_page_count: number("dtb:totalPageCount"),
+            max_page_number: number("dtb:maxPageNumber"),
+            uid: meta("dtb:uid").map(Into::into),
+        }
+    }
 
-        let mut output: Vec<_> = parent
+    /// Extract the `pageTarget`s of the (optional) `pageList` of an NCX, ordered by their `playOrder`, or their
+    /// `value` if any `playOrder` is missing. Entries without a `content` are skipped.
+    fn parse_page_list(toc_path: &Path, root: &roxmltree::Document<'_>) -> Vec<PageTarget> {
+        let Some(page_list) = root.descendants().find(|r| r.has_tag_name("pageList")) else {
+            return Vec::new();
+        };
+        let mut targets: Vec<_> = page_list
             .children()
-            .flat_map(|item| Self::parse_nav_point(&item, root_base))
+            .filter(|r| r.has_tag_name("pageTarget"))
             .collect();
 
-        output.sort();
-        output
+        if targets.iter().all(|item| Self::play_order(item).is_some()) {
+            targets.sort_by_key(|item| Self::play_order(item));
+        } else if targets.iter().all(|item| Self::page_value(item).is_some()) {
+            targets.sort_by_key(|item| Self::page_value(item));
+        }
+
+        targets
+            .iter()
+            .filter_map(|item| {
+                let (href, fragment) = item
+                    .children()
+                    .find(|r| r.has_tag_name("content"))
+                    .and_then(|c| c.attribute("src"))
+                    .map(utils::split_fragment)?;
+                let content = build_epub_uri(toc_path, "", href);
+                let href = utils::percent_decode(&content)?;
+                let label = item
+                    .children()
+                    .find(|r| r.has_tag_name("navLabel"))
+                    .map(|l| parsers::label_text(&l))
+                    .filter(|label| !label.is_empty())
+                    .or_else(|| item.attribute("value").map(Into::into))
+                    .unwrap_or_default();
+
+                Some(PageTarget {
+                    label,
+                    path: PathBuf::from(href.as_ref()),
+                    fragment: fragment.map(parsers::decode_fragment),
+                    hidden: false,
+                })
+            })
+            .collect()
+    }
+
+    /// The numeric `value` attribute of a `pageTarget`, which is optional and only numeric for `normal` pages.
+    fn page_value(item: &roxmltree::Node<'_, '_>) -> Option<u32> {
+        item.attribute("value")?.trim().parse().ok()
     }
 
-    fn parse_nav_point(item: &roxmltree::Node<'_, '_>, root_base: &Path) -> Option<NavPoint> {
-        if !item.has_tag_name("navPoint") {
-            return None;
+    /// Recursively extract all nav-points from a node, the hrefs are relative to the NCX at `toc_path`.
+    ///
+    /// At most `levels` levels are kept, on the last level every nested nav-point is included, without children.
+    /// `flattened` is set if that happened.
+    fn get_navpoints(
+        toc_path: &Path,
+        parent: &roxmltree::Node<'_, '_>,
+        levels: usize,
+        flattened: &mut bool,
+    ) -> Vec<NavPoint> {
+        let is_nav_point = |r: &roxmltree::Node<'_, '_>| r.has_tag_name("navPoint");
+        let items: Vec<_> = if levels > 1 {
+            parent.children().filter(is_nav_point).collect()
+        } else {
+            let items: Vec<_> = parent.descendants().skip(1).filter(is_nav_point).collect();
+            *flattened |= items.len() > parent.children().filter(is_nav_point).count();
+            items
+        };
+        let mut output: Vec<_> = items
+            .iter()
+            .enumerate()
+            .flat_map(|(index, item)| {
+                let mut nav = Self::parse_nav_point(item, index, toc_path)?;
+                if levels > 1 {
+                    nav.children = Self::get_navpoints(toc_path, item, levels - 1, flattened);
+                }
+                Some(nav)
+            })
+            .collect();
+
+        // The order of the document is kept if any `playOrder` is missing, mixing them with the indices makes no
+        // sense. The sort is stable, so duplicate play orders keep their order too.
+        if items.iter().all(|item| Self::play_order(item).is_some()) {
+            output.sort_by_key(|nav| nav.play_order);
         }
+        output
+    }
+
+    /// The `playOrder` attribute, which is optional.
+    fn play_order(item: &roxmltree::Node<'_, '_>) -> Option<usize> {
+        item.attribute("playOrder")?.trim().parse().ok()
+    }
 
-        let play_order = item
-            .attribute("playOrder")
-            .and_then(|n| n.parse::<usize>().ok())?;
-        let content = item
+    /// Parse a `navPoint` without its children, the play order falls back to its `index` within its parent.
+    fn parse_nav_point(
+        item: &roxmltree::Node<'_, '_>,
+        index: usize,
+        toc_path: &Path,
+    ) -> Option<NavPoint> {
+        let play_order = Self::play_order(item).unwrap_or(index);
+        let (href, fragment) = item
             .descendants()
             .find(|r| r.has_tag_name("content"))
-            .and_then(|c| c.attribute("src").map(|p| root_base.join(p)))?;
+            .and_then(|c| c.attribute("src"))
+            .map(utils::split_fragment)?;
+        let content = build_epub_uri(toc_path, "", href);
         let label = item
             .descendants()
             .find(|r| r.has_tag_name("navLabel"))
-            .and_then(|l| {
-                l.first_element_child()
-                    .and_then(|t| t.text())
-                    .map(|t| t.to_owned())
-            })?;
+            .map(|l| parsers::label_text(&l))?;
 
-        if let Some(href) = utils::percent_decode(&content.to_string_lossy()) {
+        if let Some(href) = utils::percent_decode(&content) {
             let navpoint = NavPoint {
                 label,
-                content: PathBuf::from(href.as_ref()),
-                children: Self::get_navpoints(root_base, item),
+                content: Some(PathBuf::from(href.as_ref())),
+                fragment: fragment.map(parsers::decode_fragment),
+                id: item.attribute("id").map(Into::into),
+                kind: item.attribute("class").map(Into::into),
+                children: Vec::new(),
+                hidden: false,
                 play_order,
+                document_index: index,
             };
 
             Some(navpoint)
         } else {
-            // println!("Failure in v2 parser, invalid ToC href entry: {:?}", c);
             None
         }
     }
@@ -1,9 +1,13 @@
 //! Parser for Epub Spec version 3.0/3.2
 
 use crate::archive::EpubArchive;
-use crate::doc::NavPoint;
-use crate::error::Result;
-use crate::parsers::{EpubMetadata, EpubParser};
+use crate::doc::{
+    build_epub_uri, GuideReference, NavPoint, OpfSection, PackageCollection, PageTarget,
+    ParseWarning,
+};
+use crate::error::{ArchiveError, Result};
+use crate::options::ParseOptions;
+use crate::parsers::{self, EpubMetadata, EpubParser};
 use crate::utils;
 use crate::xmlutils::RoxmlNodeExt;
 use std::io::{Read, Seek};
@@ -15,94 +19,366 @@ impl EpubParser for EpubV3Parser {
     fn parse<R: Read + Seek, PATH: AsRef<Path>>(
         epub: &mut EpubMetadata,
         root_base: PATH,
-        _xml: &roxmltree::Document<'_>,
+        xml: &roxmltree::Document<'_>,
         archive: &mut EpubArchive<R>,
+        options: &ParseOptions,
     ) -> Result<()> {
         // Cover
         if epub.cover_id.is_none() {
             // In the Epub 3.2 specification an `item` element in the `manifest` can have the `cover-image` property.
             for (key, item) in epub.resources.iter() {
-                if matches!(&item.property, Some(property) if property == "cover-image") {
+                if item.has_property("cover-image") {
                     epub.cover_id = Some(key.clone());
                     break;
                 }
             }
         }
 
-        // ToC, only done if the book didn't contain a V2 fallback
-        if epub.toc.is_empty() {
-            // toc.ncx is not in spine, thus we need to find it in manifest
-            let mut nav = None;
-            // Find nav item, see: https://www.w3.org/publishing/epub3/epub-packages.html#sec-nav
-            for (k, item) in epub.resources.iter() {
-                if matches!(&item.property, Some(property) if property == "nav") {
-                    nav = Some(k.clone());
-                    break;
-                }
+        // toc.ncx is not in spine, thus we need to find it in manifest
+        let mut nav = None;
+        // Find nav item, see: https://www.w3.org/publishing/epub3/epub-packages.html#sec-nav
+        for (k, item) in epub.resources.iter() {
+            if item.has_property("nav") {
+                nav = Some(k.clone());
+                break;
             }
+        }
 
-            if let Some(nav) = nav {
-                // We ignore the error here as failing to parse the ToC is not fatal.
-                let _ = fill_toc(epub, root_base, archive, &nav);
+        // Some tools forget the property, a document which looks like a nav is the best we can do then
+        if nav.is_none() && epub.toc_ncx.is_empty() {
+            nav = guess_nav(epub, archive);
+            if let Some(found) = &nav {
+                epub.warnings.push(ParseWarning {
+                    section: OpfSection::Toc,
+                    message: format!(
+                        "no manifest item has the `nav` property, using `{found}` which contains a toc nav"
+                    ),
+                });
             }
         }
 
+        epub.nav_id.clone_from(&nav);
+        if let Some(nav) = nav {
+            // ToC, kept apart from the one of a V2 fallback. Failing to parse it is not fatal, it is merely recorded.
+            let toc = fill_toc(epub, archive, &nav, options.max_toc_depth);
+            epub.check_section(OpfSection::Toc, toc, options)?;
+
+            // Landmarks are the EPUB3 replacement of the guide
+            let landmarks = fill_landmarks(epub, archive, &nav);
+            epub.check_section(OpfSection::Guide, landmarks, options)?;
+
+            // Takes precedence over the page list of the NCX, which is kept if the nav has none
+            let page_list = fill_page_list(epub, archive, &nav);
+            epub.check_section(OpfSection::Toc, page_list, options)?;
+        }
+
+        epub.collections = xml
+            .root_element()
+            .children()
+            .filter(|node| node.has_tag_name("collection"))
+            .map(|node| parse_collection(root_base.as_ref(), &node))
+            .collect();
+
         Ok(())
     }
 }
 
-fn fill_toc<R: Read + Seek, PATH: AsRef<Path>>(
+/// Parse a `collection` and the ones nested in it, remote links are skipped.
+fn parse_collection(root_base: &Path, node: &roxmltree::Node<'_, '_>) -> PackageCollection {
+    let links = node
+        .children()
+        .filter(|child| child.has_tag_name("link"))
+        .filter_map(|link| link.attribute("href"))
+        .filter(|href| !utils::is_remote(href))
+        .map(|href| utils::convert_path_separators(root_base, utils::split_fragment(href).0))
+        .collect();
+
+    PackageCollection {
+        role: node
+            .attribute("role")
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        id: node.attribute("id").map(Into::into),
+        links,
+        nested: node
+            .children()
+            .filter(|child| child.has_tag_name("collection"))
+            .map(|child| parse_collection(root_base, &child))
+            .collect(),
+    }
+}
+
+fn fill_toc<R: Read + Seek>(
     epub: &mut EpubMetadata,
-    root_base: PATH,
     archive: &mut EpubArchive<R>,
     id: &str,
-) -> Option<()> {
-    let toc_res = epub.resources.get(id)?;
+    max_depth: usize,
+) -> Result<()> {
+    let toc_path = epub
+        .resources
+        .get(id)
+        .ok_or(ArchiveError::InvalidId)?
+        .path
+        .clone();
 
-    let toc_xml = archive.get_entry(&toc_res.path).ok()?;
-    let txt = crate::xmlutils::ensure_utf8(&toc_xml);
-    let root = crate::xmlutils::parse_xml(&txt).ok()?;
+    let toc_xml = archive.get_entry(&toc_path)?;
+    let txt = crate::xmlutils::ensure_utf8(&toc_xml)?;
+    let root = crate::xmlutils::parse_xml(&txt)?;
 
     let mut navs = root.descendants().filter(|r| r.has_tag_name("nav"));
 
-    let toc = navs.find(|nav| {
-        nav.attr_no_namespace("type")
-            .map(|name| name == "toc")
-            .unwrap_or_default()
-    })?;
+    let toc = navs
+        .find(|nav| {
+            nav.attr_no_namespace("type")
+                .map(|name| name == "toc")
+                .unwrap_or_default()
+        })
+        .ok_or(ArchiveError::ParsingFailure)?;
 
-    epub.toc.append(&mut get_navpoints(root_base, &toc));
-    epub.toc.sort();
+    if let Some(list) = toc.descendants().find(|r| r.has_tag_name("ol")) {
+        let mut flattened = false;
+        epub.toc_nav.append(&mut get_navpoints(
+            &toc_path,
+            &list,
+            &mut 0,
+            max_depth.max(1),
+            &mut flattened,
+            &mut epub.warnings,
+        ));
+        if flattened {
+            epub.warnings.push(parsers::toc_depth_warning(max_depth));
+        }
+    }
+    // The play orders follow the document order, which is the order of the nav, so there is nothing to sort
+
+    Ok(())
+}
+
+/// Parse the (optional) `landmarks` nav, any entries without an `epub:type` or `href` are skipped.
+fn fill_landmarks<R: Read + Seek>(
+    epub: &mut EpubMetadata,
+    archive: &mut EpubArchive<R>,
+    id: &str,
+) -> Result<()> {
+    let (nav_path, txt) = read_nav(epub, archive, id)?;
+    let root = crate::xmlutils::parse_xml(&txt)?;
 
-    Some(())
+    let Some(landmarks) = find_nav(&root, "landmarks") else {
+        return Ok(());
+    };
+
+    for item in landmarks.descendants().filter(|r| r.has_tag_name("a")) {
+        let (Some(kind), Some(href)) = (
+            item.attr_no_namespace("type"),
+            item.attr_no_namespace("href"),
+        ) else {
+            continue;
+        };
+        let (href, fragment) = utils::split_fragment(href);
+        let title = parsers::label_text(&item);
+
+        epub.landmarks.push(GuideReference {
+            kind: kind.to_string(),
+            title: Some(title).filter(|t| !t.is_empty()),
+            path: PathBuf::from(build_epub_uri(&nav_path, "", href).as_ref()),
+            fragment: fragment.map(Into::into),
+            hidden: is_hidden(&item),
+        });
+    }
+
+    Ok(())
 }
 
-/// Recursively extract all navpoints from a node.
-fn get_navpoints(root_base: impl AsRef<Path>, parent: &roxmltree::Node<'_, '_>) -> Vec<NavPoint> {
+/// Parse the (optional) `page-list` nav, replacing the page list of the NCX if it has any entries. Entries without
+/// an `href` are skipped.
+fn fill_page_list<R: Read + Seek>(
+    epub: &mut EpubMetadata,
+    archive: &mut EpubArchive<R>,
+    id: &str,
+) -> Result<()> {
+    let (nav_path, txt) = read_nav(epub, archive, id)?;
+    let root = crate::xmlutils::parse_xml(&txt)?;
+
+    let Some(page_list) = find_nav(&root, "page-list") else {
+        return Ok(());
+    };
+
+    let mut pages = Vec::new();
+    for item in page_list.descendants().filter(|r| r.has_tag_name("a")) {
+        let Some(href) = item.attr_no_namespace("href") else {
+            continue;
+        };
+        let (href, fragment) = utils::split_fragment(href);
+
+        pages.push(PageTarget {
+            label: parsers::label_text(&item),
+            path: PathBuf::from(build_epub_uri(&nav_path, "", href).as_ref()),
+            fragment: fragment.map(Into::into),
+            hidden: is_hidden(&item),
+        });
+    }
+    if !pages.is_empty() {
+        epub.page_list = pages;
+    }
+
+    Ok(())
+}
+
+/// Read the navigation document with the manifest `id`, returning its path, which its hrefs are relative to.
+fn read_nav<R: Read + Seek>(
+    epub: &EpubMetadata,
+    archive: &mut EpubArchive<R>,
+    id: &str,
+) -> Result<(PathBuf, String)> {
+    let nav_res = epub.resources.get(id).ok_or(ArchiveError::InvalidId)?;
+    let nav_xml = archive.get_entry(&nav_res.path)?;
+
+    Ok((
+        nav_res.path.clone(),
+        crate::xmlutils::ensure_utf8(&nav_xml)?.into_owned(),
+    ))
+}
+
+/// Find a navigation document without the `nav` property: an XHTML item named like one (e.g. `nav.xhtml` or
+/// `toc.xhtml`) which contains a `toc` nav, the first by path if there are several.
+fn guess_nav<R: Read + Seek>(epub: &EpubMetadata, archive: &mut EpubArchive<R>) -> Option<String> {
+    let mut candidates: Vec<_> = epub
+        .resources
+        .iter()
+        .filter(|(_, item)| item.mime == "application/xhtml+xml")
+        .filter(|(_, item)| {
+            item.path.file_stem().is_some_and(|stem| {
+                let stem = stem.to_string_lossy().to_ascii_lowercase();
+                stem.contains("nav") || stem.contains("toc")
+            })
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.1.path.cmp(&b.1.path));
+
+    candidates.into_iter().find_map(|(id, item)| {
+        // Best-effort, anything which can't be read or parsed just isn't the nav
+        let content = archive.get_entry(&item.path).ok()?;
+        let txt = crate::xmlutils::ensure_utf8(&content).ok()?;
+        let root = crate::xmlutils::parse_xml(&txt).ok()?;
+        find_nav(&root, "toc").map(|_| id.clone())
+    })
+}
+
+/// Find the `nav` with the given `epub:type`.
+fn find_nav<'a, 'input>(
+    root: &'a roxmltree::Document<'input>,
+    kind: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    root.descendants().find(|nav| {
+        nav.has_tag_name("nav")
+            && nav
+                .attr_no_namespace("type")
+                .is_some_and(|types| types.split_ascii_whitespace().any(|k| k == kind))
+    })
+}
+
+/// Whether the node, or any of its ancestors up to and including the `nav`, has the `hidden` attribute.
+fn is_hidden(node: &roxmltree::Node<'_, '_>) -> bool {
+    for ancestor in node.ancestors() {
+        if ancestor.attr_no_namespace("hidden").is_some() {
+            return true;
+        }
+        if ancestor.has_tag_name("nav") {
+            break;
+        }
+    }
+    false
+}
+
+/// Recursively extract the navpoints of the `ol` of a nav, every `li` is one navpoint, with the entries of its own
+/// nested `ol` as children.
+///
+/// The play order is the position in the whole nav, in document order. The hrefs are relative to the nav document
+/// at `toc_path`.
+///
+/// At most `levels` levels are kept, on the last level every nested entry is included, without children.
+/// `flattened` is set if that happened.
+///
+/// Entries whose href can't be decoded are skipped, with a warning pushed to `warnings`.
+fn get_navpoints(
+    toc_path: &Path,
+    list: &roxmltree::Node<'_, '_>,
+    play_order: &mut usize,
+    levels: usize,
+    flattened: &mut bool,
+    warnings: &mut Vec<ParseWarning>,
+) -> Vec<NavPoint> {
     let mut navpoints = Vec::new();
-    let root_base = root_base.as_ref();
-    let link_elements = parent
-        .descendants()
-        .filter(|r| r != parent)
-        .filter(|r| r.has_tag_name("a"));
-
-    for (i, item) in link_elements.enumerate() {
-        let content = item.attr_no_namespace("href").map(|i| root_base.join(i));
-
-        if let (Some(label), Some(content)) = (item.text(), content) {
-            if let Some(href) = utils::percent_decode(&content.to_string_lossy()) {
-                let navpoint = NavPoint {
-                    label: label.to_owned(),
-                    content: PathBuf::from(href.as_ref()),
-                    children: get_navpoints(root_base, &item),
-                    play_order: i,
-                };
+    let is_entry = |r: &roxmltree::Node<'_, '_>| r.has_tag_name("li");
+    let entries: Vec<_> = if levels > 1 {
+        list.children().filter(is_entry).collect()
+    } else {
+        let entries: Vec<_> = list.descendants().skip(1).filter(is_entry).collect();
+        *flattened |= entries.len() > list.children().filter(is_entry).count();
+        entries
+    };
 
-                navpoints.push(navpoint);
-            } else {
-                println!("Failure in v3 parser, invalid ToC href entry: {content:?}",);
+    for entry in entries {
+        let Some(heading) = entry
+            .children()
+            .find(|r| r.has_tag_name("a") || r.has_tag_name("span"))
+        else {
+            continue;
+        };
+        // A `span` heading has no link of its own, it only groups the entries of its nested list
+        let (content, fragment) = match heading.attr_no_namespace("href") {
+            Some(href) => {
+                let (href, fragment) = utils::split_fragment(href);
+                let content = build_epub_uri(toc_path, "", href).into_owned();
+                let Some(path) = utils::percent_decode(&content) else {
+                    warnings.push(ParseWarning {
+                        section: OpfSection::Toc,
+                        message: format!("skipped the ToC entry with the invalid href {content:?}"),
+                    });
+                    continue;
+                };
+                (
+                    Some(PathBuf::from(path.as_ref())),
+                    fragment.map(parsers::decode_fragment),
+                )
             }
-        }
+            None => (None, None),
+        };
+
+        let order = *play_order;
+        *play_order += 1;
+        let nested = entry
+            .children()
+            .find(|r| r.has_tag_name("ol"))
+            .filter(|_| levels > 1);
+        let attribute = |name: &str| {
+            entry
+                .attr_no_namespace(name)
+                .or_else(|| heading.attr_no_namespace(name))
+                .map(String::from)
+        };
+
+        navpoints.push(NavPoint {
+            label: parsers::label_text(&heading),
+            content,
+            fragment,
+            id: attribute("id"),
+            kind: attribute("type"),
+            children: nested.map_or_else(Vec::new, |nested| {
+                get_navpoints(
+                    toc_path,
+                    &nested,
+                    play_order,
+                    levels - 1,
+                    flattened,
+                    warnings,
+                )
+            }),
+            hidden: is_hidden(&heading),
+            play_order: order,
+            document_index: navpoints.len(),
+        });
     }
 
     navpoints
@@ -0,0 +1,147 @@
+//! Persistable reading positions.
+//!
+//! A [ReadingPosition] records where a reader stopped in a way that survives re-opening the epub, and ideally
+//! minor changes to the file as well. [EpubDoc::progress] turns a position into a fraction of the whole book.
+
+use crate::doc::{EpubDoc, OpfSection};
+use crate::error::{ArchiveError, Result};
+use std::io::{Read, Seek};
+
+/// A position in the reading order of an epub.
+///
+/// With the `serde` feature enabled this type can be (de)serialized, e.g. to store it as JSON.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadingPosition {
+    /// The resource id of the chapter in the spine
+    pub spine_id: String,
+    /// The index of the chapter in the spine, used when the `spine_id` no longer exists
+    pub spine_index: usize,
+    /// How far into the chapter the reader is, from `0.0` to `1.0`
+    pub fraction: f32,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns the current reading position, or `None` if the epub has an empty spine.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let mut doc = EpubDoc::new("test.epub").unwrap();
+    /// doc.set_current_chapter(2);
+    /// doc.set_current_fraction(0.5);
+    ///
+    /// let position = doc.current_position().unwrap();
+    /// assert_eq!("001.xhtml", position.spine_id);
+    /// assert_eq!(2, position.spine_index);
+    /// assert_eq!(0.5, position.fraction);
+    /// ```
+    pub fn current_position(&self) -> Option<ReadingPosition> {
+        Some(ReadingPosition {
+            spine_id: self.get_current_id()?.to_string(),
+            spine_index: self.get_current_chapter(),
+            fraction: self.current_fraction,
+        })
+    }
+
+    /// Sets how far into the current chapter the reader is, clamped to `0.0..=1.0`. NaN and the infinities count
+    /// as `0.0`.
+    pub fn set_current_fraction(&mut self, fraction: f32) {
+        self.current_fraction = clamp_fraction(fraction);
+    }
+
+    /// Moves to a previously saved [ReadingPosition].
+    ///
+    /// The chapter is looked up by its `spine_id` first, falling back to the `spine_index` if the id no
+    /// longer exists in this epub.
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::InvalidPosition] if neither the id nor the index match a chapter, in which case
+    /// the current position is left untouched.
+    /// Returns [ArchiveError::SectionUnavailable] if the spine failed to parse.
+    pub fn restore_position(&mut self, position: &ReadingPosition) -> Result<()> {
+        self.ensure_section(OpfSection::Spine)?;
+        let chapter = self
+            .resource_id_to_chapter(&position.spine_id)
+            .or_else(|| {
+                (position.spine_index < self.get_num_pages()).then_some(position.spine_index)
+            })
+            .ok_or(ArchiveError::InvalidPosition)?;
+
+        self.set_current_chapter(chapter);
+        self.set_current_fraction(position.fraction);
+
+        Ok(())
+    }
+
+    /// Returns the uncompressed size in bytes of every chapter in the spine, `0` for missing resources.
+    ///
+    /// The sizes are read from the zip directory once and cached, nothing is decompressed.
+    pub fn spine_sizes(&self) -> Vec<u64> {
+        self.cached_spine_sizes().to_vec()
+    }
+
+    fn cached_spine_sizes(&self) -> &[u64] {
+        self.spine_sizes.get_or_init(|| {
+            let mut archive = self.archive();
+
+            self.spine()
+                .iter()
+                .map(|item| {
+                    self.ctx()
+                        .resources
+                        .get(&item.idref)
+                        .and_then(|res| archive.get_entry_size(&res.path).ok())
+                        .unwrap_or_default()
+                })
+                .collect()
+        })
+    }
+
+    /// Returns how far into the book a position is, from `0.0` to `1.0`, weighing chapters by their size.
+    ///
+    /// `within_chapter` is clamped to `0.0..=1.0`, with NaN and the infinities counting as `0.0`, and a `spine_index`
+    /// past the end of the spine counts as the end of the book. Chapters are weighed equally if none of them have a
+    /// size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert_eq!(0.0, doc.progress(0, 0.0));
+    /// assert!(doc.progress(3, 0.5) < doc.progress(4, 0.0));
+    /// assert_eq!(1.0, doc.progress(16, 1.0));
+    /// ```
+    pub fn progress(&self, spine_index: usize, within_chapter: f32) -> f32 {
+        let sizes = self.cached_spine_sizes();
+        if sizes.is_empty() {
+            return 0.0;
+        }
+        if spine_index >= sizes.len() {
+            return 1.0;
+        }
+
+        let within_chapter = f64::from(clamp_fraction(within_chapter));
+        let total: u64 = sizes.iter().sum();
+        let progress = if total == 0 {
+            (spine_index as f64 + within_chapter) / sizes.len() as f64
+        } else {
+            let before: u64 = sizes[..spine_index].iter().sum();
+            (before as f64 + sizes[spine_index] as f64 * within_chapter) / total as f64
+        };
+
+        progress as f32
+    }
+}
+
+/// Clamps `fraction` to `0.0..=1.0`, `f32::clamp` keeps NaN so non-finite values count as `0.0`.
+fn clamp_fraction(fraction: f32) -> f32 {
+    if fraction.is_finite() {
+        fraction.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,50 @@
+//! Re-exports of the types most users of this crate will need.
+//!
+//! ```
+//! use epub::prelude::*;
+//!
+//! let doc: EpubDoc<_> = EpubDoc::new("test.epub").unwrap();
+//! let resource: &ResourceItem = doc.resources().get("portada.png").unwrap();
+//! assert_eq!(resource.mime, "image/png");
+//! ```
+
+pub use crate::accessibility::{AccessMode, AccessibilityMetadata, Hazard};
+#[cfg(feature = "rewrite")]
+pub use crate::anchors::AnchorInfo;
+pub use crate::archive::{EntryLayout, EpubArchive};
+pub use crate::calibre::CalibreMetadata;
+pub use crate::cancel::{CancelToken, ScanControl};
+pub use crate::cfi::{CfiBuilder, CfiLocation, CfiStep};
+pub use crate::checksums::ChapterDiff;
+pub use crate::diff::EpubDiff;
+pub use crate::doc::{
+    EpubDoc, EpubMetadata, GuideReference, Landmark, MetaLink, MetadataNode, NavPoint, NcxMetadata,
+    OpfSection, OwnedAttribute, OwnedName, PackageCollection, PageTarget, ParseWarning,
+    PartialFailure, RemoteResource, ResourceItem, SpineItem,
+};
+pub use crate::error::ArchiveError;
+#[cfg(feature = "export")]
+pub use crate::export::StructureExport;
+pub use crate::generator::GeneratorInfo;
+pub use crate::ibooks::IbooksDisplayOptions;
+pub use crate::language::{LanguageGuess, Script};
+pub use crate::media_overlay::{MediaDuration, MediaOverlayInfo};
+pub use crate::metadata::{
+    Creator, DateKind, EpubDate, IdScheme, Identifier, PartialDate, Refinement, SeriesInfo,
+    SeriesSource, Subject, TextDirection, TimeOfDay, Title, TitleKind,
+};
+pub use crate::navigation::{Locator, Neighbors};
+pub use crate::options::{
+    MediaTypeSniffing, MetadataDedup, MetadataLimits, ParseMode, ParseOptions,
+};
+pub use crate::position::ReadingPosition;
+#[cfg(feature = "readium")]
+pub use crate::readium::RwpmManifest;
+pub use crate::rendering::ChapterFeatures;
+pub use crate::rendition::{Flow, Layout, Orientation, Rendition, Spread};
+pub use crate::search::{SearchHit, TextIndex};
+pub use crate::semantics::SemanticLocation;
+pub use crate::sniff::SniffResult;
+pub use crate::split_support::SplitRange;
+pub use crate::text::{RubyMode, TextOptions};
+pub use crate::writer::{EpubWriter, SaveReport};
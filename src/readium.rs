@@ -0,0 +1,368 @@
+//! Conversion to a [Readium Web Publication Manifest](https://readium.org/webpub-manifest/) (RWPM).
+//!
+//! Only available with the `readium` feature.
+//!
+//! All hrefs in the manifest are full paths within the epub, percent-encoded as URLs. A server can therefore serve
+//! the publication by passing a requested href straight to [EpubDoc::get_resource_by_href].
+
+use crate::doc::{EpubDoc, NavPoint};
+use crate::error::Result;
+use crate::utils;
+use percent_encoding::{AsciiSet, CONTROLS};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek};
+use std::path::{Component, Path};
+
+/// The `@context` of a Readium Web Publication Manifest.
+pub const RWPM_CONTEXT: &str = "https://readium.org/webpub-manifest/context.jsonld";
+
+/// Characters which are percent-encoded in hrefs, on top of controls and non-ASCII characters.
+const HREF_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// A Readium Web Publication Manifest, see [EpubDoc::to_rwpm].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RwpmManifest {
+    #[serde(rename = "@context")]
+    pub context: String,
+    pub metadata: RwpmMetadata,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<RwpmLink>,
+    /// The linear chapters of the spine
+    pub reading_order: Vec<RwpmLink>,
+    /// Every other resource in the manifest, sorted by href
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<RwpmLink>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub toc: Vec<RwpmLink>,
+}
+
+impl RwpmManifest {
+    /// Serializes the manifest as (pretty printed) JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// The metadata of a [RwpmManifest].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RwpmMetadata {
+    #[serde(rename = "@type")]
+    pub kind: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub language: Vec<String>,
+    /// The `dcterms:modified` date
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub author: Vec<RwpmContributor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub translator: Vec<RwpmContributor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub editor: Vec<RwpmContributor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artist: Vec<RwpmContributor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub illustrator: Vec<RwpmContributor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub colorist: Vec<RwpmContributor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub narrator: Vec<RwpmContributor>,
+    /// Contributors with any other (or no) role
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contributor: Vec<RwpmContributor>,
+}
+
+/// A person or organization in the [RwpmMetadata].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RwpmContributor {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_as: Option<String>,
+    /// The MARC relator code, only set for roles without a dedicated [RwpmMetadata] field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+}
+
+/// A link to a resource of the publication.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RwpmLink {
+    /// The percent-encoded full path within the epub, possibly with a fragment
+    pub href: String,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rel: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties: Option<RwpmProperties>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<RwpmLink>,
+}
+
+/// The properties of a [RwpmLink].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RwpmProperties {
+    /// `left`, `right`, or `center`, from the `page-spread-*` spine properties
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<String>,
+    /// `mathml`, `js`, `svg`, and/or `remote-resources`, from the manifest properties
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contains: Vec<String>,
+}
+
+impl RwpmProperties {
+    fn into_option(self) -> Option<Self> {
+        (self != Self::default()).then_some(self)
+    }
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Converts the epub to a Readium Web Publication Manifest.
+    ///
+    /// Non-linear spine items end up in the `resources` instead of the `readingOrder`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let manifest = doc.to_rwpm();
+    /// assert_eq!("Todo es mío", manifest.metadata.title);
+    /// assert_eq!("OEBPS/Text/titlepage.xhtml", manifest.reading_order[0].href);
+    ///
+    /// let json = manifest.to_json().unwrap();
+    /// assert!(json.contains("\"readingOrder\""));
+    /// ```
+    pub fn to_rwpm(&self) -> RwpmManifest {
+        let mut reading_order = Vec::new();
+        let mut in_reading_order = Vec::new();
+
+        for item in self.spine().iter().filter(|item| item.linear) {
+            let Some(resource) = self.resources().get(&item.idref) else {
+                continue;
+            };
+            let page = item.properties.as_deref().and_then(|properties| {
+                properties.split_whitespace().find_map(|property| {
+                    match property.trim_start_matches("rendition:") {
+                        "page-spread-left" => Some("left"),
+                        "page-spread-right" => Some("right"),
+                        "page-spread-center" => Some("center"),
+                        _ => None,
+                    }
+                })
+            });
+            let properties = RwpmProperties {
+                page: page.map(str::to_string),
+                contains: contains(&resource.properties),
+            };
+
+            in_reading_order.push(item.idref.as_str());
+            reading_order.push(RwpmLink {
+                href: href_for_path(&resource.path),
+                media_type: Some(resource.mime.clone()),
+                title: None,
+                rel: Vec::new(),
+                properties: properties.into_option(),
+                children: Vec::new(),
+            });
+        }
+
+        let mut resources: Vec<_> = self
+            .resources()
+            .iter()
+            .filter(|(id, _)| !in_reading_order.contains(&id.as_str()))
+            .map(|(id, resource)| {
+                let mut rel = Vec::new();
+                if self.get_cover_id() == Some(id.as_str()) || resource.has_property("cover-image")
+                {
+                    rel.push("cover".to_string());
+                }
+                if resource.has_property("nav") {
+                    rel.push("contents".to_string());
+                }
+                let properties = RwpmProperties {
+                    page: None,
+                    contains: contains(&resource.properties),
+                };
+
+                RwpmLink {
+                    href: href_for_path(&resource.path),
+                    media_type: Some(resource.mime.clone()),
+                    title: None,
+                    rel,
+                    properties: properties.into_option(),
+                    children: Vec::new(),
+                }
+            })
+            .collect();
+        resources.sort_by(|a, b| a.href.cmp(&b.href));
+
+        RwpmManifest {
+            context: RWPM_CONTEXT.to_string(),
+            metadata: self.rwpm_metadata(),
+            links: Vec::new(),
+            reading_order,
+            resources,
+            toc: self.get_toc().iter().map(toc_link).collect(),
+        }
+    }
+
+    /// Returns the resource content by a (percent-encoded) href, as used in the [RwpmManifest].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the href doesn't point to a file in the epub.
+    pub fn get_resource_by_href(&self, href: &str) -> Result<Vec<u8>> {
+        let (path, _) = utils::split_fragment(href);
+        let path = utils::percent_decode(path).unwrap_or(path.into());
+
+        self.get_resource_by_path(path.as_ref())
+    }
+
+    fn rwpm_metadata(&self) -> RwpmMetadata {
+        let mut metadata = RwpmMetadata {
+            kind: "http://schema.org/Book".to_string(),
+            title: self.main_title().unwrap_or_default().to_string(),
+            identifier: self.unique_identifier().map(str::to_string),
+            language: self.languages(),
+            modified: self.mdata("dcterms:modified").map(str::to_string),
+            author: Vec::new(),
+            translator: Vec::new(),
+            editor: Vec::new(),
+            artist: Vec::new(),
+            illustrator: Vec::new(),
+            colorist: Vec::new(),
+            narrator: Vec::new(),
+            contributor: Vec::new(),
+        };
+
+        let people = self
+            .creators()
+            .into_iter()
+            .map(|creator| (creator, "aut"))
+            .chain(self.contributors().into_iter().map(|creator| (creator, "")));
+
+        for (creator, default_role) in people {
+            let role = creator.role.as_deref().unwrap_or(default_role);
+            let mut person = RwpmContributor {
+                name: creator.name.clone(),
+                sort_as: creator.sort_name.clone(),
+                role: None,
+            };
+
+            let list = match role {
+                "aut" => &mut metadata.author,
+                "trl" => &mut metadata.translator,
+                "edt" => &mut metadata.editor,
+                "art" => &mut metadata.artist,
+                "ill" => &mut metadata.illustrator,
+                "clr" => &mut metadata.colorist,
+                "nrt" => &mut metadata.narrator,
+                "" => &mut metadata.contributor,
+                role => {
+                    person.role = Some(role.to_string());
+                    &mut metadata.contributor
+                }
+            };
+            list.push(person);
+        }
+
+        metadata
+    }
+}
+
+/// Converts a full path within the epub to the percent-encoded href used in a [RwpmManifest].
+///
+/// # Examples
+///
+/// ```
+/// # use epub::readium::href_for_path;
+/// assert_eq!("OEBPS/Text/a%20b.xhtml", href_for_path("OEBPS/Text/a b.xhtml"));
+/// assert_eq!("OEBPS/Text/a%20b.xhtml", href_for_path("OEBPS/Text/a%20b.xhtml"));
+/// ```
+pub fn href_for_path(path: impl AsRef<Path>) -> String {
+    let segments: Vec<String> = path
+        .as_ref()
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(segment) => Some(segment.to_string_lossy()),
+            _ => None,
+        })
+        .map(|segment| {
+            // Manifest paths may or may not be percent-encoded already
+            let segment = utils::percent_decode(&segment)
+                .map(|decoded| decoded.into_owned())
+                .unwrap_or_else(|| segment.into_owned());
+            percent_encoding::utf8_percent_encode(&segment, HREF_ENCODE_SET).to_string()
+        })
+        .collect();
+
+    segments.join("/")
+}
+
+fn toc_link(nav: &NavPoint) -> RwpmLink {
+    // Links always have an href, headings without a link of their own get `#` like in Readium's parsers
+    let mut href = nav
+        .content
+        .as_ref()
+        .map_or_else(|| "#".to_string(), href_for_path);
+    if let Some(fragment) = &nav.fragment {
+        href.push('#');
+        href.extend(percent_encoding::utf8_percent_encode(
+            fragment,
+            HREF_ENCODE_SET,
+        ));
+    }
+
+    RwpmLink {
+        href,
+        media_type: None,
+        title: Some(nav.label.clone()),
+        rel: Vec::new(),
+        properties: None,
+        children: nav.children.iter().map(toc_link).collect(),
+    }
+}
+
+/// Maps the manifest properties to the RWPM `contains` values.
+fn contains(properties: &[String]) -> Vec<String> {
+    properties
+        .iter()
+        .filter_map(|property| match property.as_str() {
+            "mathml" => Some("mathml"),
+            "scripted" => Some("js"),
+            "svg" => Some("svg"),
+            "remote-resources" => Some("remote-resources"),
+            _ => None,
+        })
+        .map(str::to_string)
+        .collect()
+}
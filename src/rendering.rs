@@ -0,0 +1,104 @@
+//! Hints for renderers about what a chapter needs.
+//!
+//! Some content, like ruby annotations or vertical writing in Japanese books, requires support a simple renderer
+//! may not have. [EpubDoc::chapter_features] finds out upfront, without rendering the chapter.
+
+use crate::doc::{build_epub_uri, EpubDoc};
+use crate::error::Result;
+use crate::text::{Token, Tokens};
+use std::io::{Read, Seek};
+
+/// Rendering related features used by a chapter, see [EpubDoc::chapter_features].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChapterFeatures {
+    /// Contains `<ruby>` annotations
+    pub has_ruby: bool,
+    /// Uses a vertical `writing-mode`, in a `style` attribute, a `<style>` element, or a linked stylesheet
+    pub has_vertical_css: bool,
+    /// Contains `<bdo>` elements or explicit `dir` attributes
+    pub has_bdo_or_dir_overrides: bool,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Scans the chapter at `spine_index` (and its linked stylesheets) for features which need renderer support.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let features = doc.chapter_features(1).unwrap();
+    /// assert!(!features.has_ruby);
+    /// assert!(!features.has_vertical_css);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::InvalidPosition](crate::error::ArchiveError::InvalidPosition) if `spine_index` is out
+    /// of range, or an error if the chapter can't be read. Stylesheets which can't be read are ignored.
+    pub fn chapter_features(&self, spine_index: usize) -> Result<ChapterFeatures> {
+        let (path, content) = self.chapter_content(spine_index)?;
+        let mut features = ChapterFeatures::default();
+        let mut stylesheets = Vec::new();
+        let mut in_style = false;
+
+        for token in Tokens::new(&content) {
+            match token {
+                Token::Text(text) | Token::Cdata(text) if in_style => {
+                    features.has_vertical_css |= has_vertical_writing(text);
+                }
+                Token::Text(_) | Token::Cdata(_) => {}
+                Token::Tag(tag) if tag.closing => in_style &= tag.name != "style",
+                Token::Tag(tag) => {
+                    match tag.name.as_str() {
+                        "ruby" => features.has_ruby = true,
+                        "bdo" => features.has_bdo_or_dir_overrides = true,
+                        "style" => in_style = !tag.self_closing,
+                        "link" => {
+                            let is_stylesheet = tag.attribute("rel").is_some_and(|rel| {
+                                rel.split_whitespace()
+                                    .any(|rel| rel.eq_ignore_ascii_case("stylesheet"))
+                            });
+                            if let Some(href) = tag.attribute("href").filter(|_| is_stylesheet) {
+                                stylesheets.push(build_epub_uri(&path, "", &href).into_owned());
+                            }
+                        }
+                        _ => {}
+                    }
+
+                    features.has_bdo_or_dir_overrides |= tag
+                        .attribute("dir")
+                        .is_some_and(|dir| matches!(dir.trim(), "ltr" | "rtl"));
+                    features.has_vertical_css |= tag
+                        .attribute("style")
+                        .is_some_and(|style| has_vertical_writing(&style));
+                }
+            }
+        }
+
+        if !features.has_vertical_css {
+            features.has_vertical_css = stylesheets.iter().any(|stylesheet| {
+                self.get_resource_str_by_path(stylesheet)
+                    .is_ok_and(|css| has_vertical_writing(&css))
+            });
+        }
+
+        Ok(features)
+    }
+}
+
+/// Whether the `css` sets a vertical `writing-mode` (including the prefixed and deprecated SVG values).
+fn has_vertical_writing(css: &str) -> bool {
+    let css = css.to_ascii_lowercase();
+
+    css.match_indices("writing-mode").any(|(start, _)| {
+        let value = css[start + "writing-mode".len()..].trim_start();
+        let Some(value) = value.strip_prefix(':') else {
+            return false;
+        };
+        let value = value.trim_start();
+
+        value.starts_with("vertical-") || value.starts_with("tb")
+    })
+}
@@ -0,0 +1,169 @@
+//! The EPUB3 rendition properties, which say how a book should be laid out.
+//!
+//! Fixed-layout books (comics, picture books) declare `rendition:layout` as `pre-paginated` in the package metadata,
+//! see [EpubDoc::rendition]. Single spine items can override the global properties, see
+//! [EpubDoc::spine_rendition_overrides].
+
+use crate::doc::EpubDoc;
+use std::io::{Read, Seek};
+
+/// The IRI of the rendition vocabulary, bound to the reserved `rendition:` prefix.
+const RENDITION_VOCABULARY: &str = "http://www.idpf.org/vocab/rendition/#";
+
+/// The global rendition properties of a book, see [EpubDoc::rendition].
+///
+/// Missing or unrecognized values are the default (`auto` or reflowable), as the specification requires.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rendition {
+    /// `rendition:layout`
+    pub layout: Layout,
+    /// `rendition:orientation`
+    pub orientation: Orientation,
+    /// `rendition:spread`
+    pub spread: Spread,
+    /// `rendition:flow`
+    pub flow: Flow,
+}
+
+/// See [Rendition::layout].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Layout {
+    /// The content can be reflowed to fit the screen
+    #[default]
+    Reflowable,
+    /// Every content document is a fixed size page
+    PrePaginated,
+}
+
+/// See [Rendition::orientation].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Orientation {
+    #[default]
+    Auto,
+    Landscape,
+    Portrait,
+}
+
+/// See [Rendition::spread].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Spread {
+    #[default]
+    Auto,
+    /// Never show two pages side by side
+    None,
+    /// Show two pages side by side in landscape orientation
+    Landscape,
+    /// Deprecated, treated like [Spread::Both] by most reading systems
+    Portrait,
+    /// Show two pages side by side in both orientations
+    Both,
+}
+
+/// See [Rendition::flow].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Flow {
+    #[default]
+    Auto,
+    Paginated,
+    /// Scroll through the whole book, without breaks between the content documents
+    ScrolledContinuous,
+    /// Scroll through each content document separately
+    ScrolledDoc,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns the global rendition properties from the package metadata.
+    ///
+    /// Besides the reserved `rendition:` prefix, any prefix the `prefix` attribute of the `package` binds to the
+    /// rendition vocabulary is recognized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use epub::rendition::Layout;
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// assert_eq!(Layout::Reflowable, doc.rendition().layout);
+    /// ```
+    pub fn rendition(&self) -> Rendition {
+        let prefixes = self.vocabulary_prefixes(RENDITION_VOCABULARY);
+        let mut rendition = Rendition::default();
+
+        for (name, nodes) in self.metadata() {
+            let Some(property) = prefixes
+                .iter()
+                .find_map(|prefix| name.strip_prefix(prefix)?.strip_prefix(':'))
+            else {
+                continue;
+            };
+            // The properties only apply globally when they don't refine anything
+            let Some(value) = nodes
+                .iter()
+                .find(|node| node.find_attr("refines").is_none())
+                .map(|node| node.content.trim())
+            else {
+                continue;
+            };
+
+            match (property, value) {
+                ("layout", "pre-paginated") => rendition.layout = Layout::PrePaginated,
+                ("orientation", "landscape") => rendition.orientation = Orientation::Landscape,
+                ("orientation", "portrait") => rendition.orientation = Orientation::Portrait,
+                ("spread", "none") => rendition.spread = Spread::None,
+                ("spread", "landscape") => rendition.spread = Spread::Landscape,
+                ("spread", "portrait") => rendition.spread = Spread::Portrait,
+                ("spread", "both") => rendition.spread = Spread::Both,
+                ("flow", "paginated") => rendition.flow = Flow::Paginated,
+                ("flow", "scrolled-continuous") => rendition.flow = Flow::ScrolledContinuous,
+                ("flow", "scrolled-doc") => rendition.flow = Flow::ScrolledDoc,
+                _ => {}
+            }
+        }
+
+        rendition
+    }
+
+    /// Returns whether the book is pre-paginated, i.e. fixed-layout.
+    ///
+    /// Only the global [Rendition::layout] is considered, single spine items may still override it.
+    pub fn is_fixed_layout(&self) -> bool {
+        self.rendition().layout == Layout::PrePaginated
+    }
+
+    /// Returns the rendition properties of the spine item at `spine_index`, without prefix, e.g.
+    /// `layout-pre-paginated` or `page-spread-left`.
+    ///
+    /// These override the global [Rendition] for that item. `page-spread-left` and `page-spread-right` are also
+    /// recognized without prefix, as EPUB 3.0 defined them. Returns nothing if `spine_index` is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert!(doc.spine_rendition_overrides(0).is_empty());
+    /// ```
+    pub fn spine_rendition_overrides(&self, spine_index: usize) -> Vec<&str> {
+        let Some(properties) = self
+            .spine()
+            .get(spine_index)
+            .and_then(|item| item.properties.as_deref())
+        else {
+            return Vec::new();
+        };
+        let prefixes = self.vocabulary_prefixes(RENDITION_VOCABULARY);
+
+        properties
+            .split_ascii_whitespace()
+            .filter_map(|property| match property.split_once(':') {
+                Some((prefix, name)) => prefixes.contains(&prefix).then_some(name),
+                None => property.starts_with("page-spread-").then_some(property),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,275 @@
+//! Case-insensitive full text search.
+//!
+//! [EpubDoc::search] scans every chapter on each call. For repeated searches over large books a [TextIndex] can be
+//! built once (incrementally, if need be), persisted with the `serde` feature, and queried instead. Both return the
+//! same [SearchHit]s.
+
+use crate::cancel::ScanControl;
+use crate::doc::EpubDoc;
+use crate::error::Result;
+use std::io::{Read, Seek};
+
+/// A single match of a search query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchHit {
+    /// The index of the chapter in the spine
+    pub spine_index: usize,
+    /// Byte offset of the match in the lowercased [chapter text](EpubDoc::chapter_text)
+    pub offset: usize,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Searches every chapter for (non-overlapping) case-insensitive occurrences of `query`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// let hits = doc.search("Heffalump").unwrap();
+    /// assert!(!hits.is_empty());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a chapter can't be read.
+    pub fn search(&self, query: &str) -> Result<Vec<SearchHit>> {
+        self.search_with_control(query, &mut ScanControl::new())
+    }
+
+    /// [EpubDoc::search], which can be cancelled and reports its progress, see [ScanControl].
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::Cancelled](crate::error::ArchiveError::Cancelled) if cancelled, or an error if a
+    /// chapter can't be read.
+    pub fn search_with_control(
+        &self,
+        query: &str,
+        control: &mut ScanControl<'_>,
+    ) -> Result<Vec<SearchHit>> {
+        let query = normalize(query);
+        let mut hits = Vec::new();
+        let total = self.spine().len();
+
+        for spine_index in 0..total {
+            control.check()?;
+            let text = normalize(&self.chapter_text(spine_index)?);
+            search_chapter(&text, &query, spine_index, &mut hits);
+            control.progress(spine_index + 1, total);
+        }
+
+        Ok(hits)
+    }
+}
+
+/// A precomputed index of the text of an epub.
+///
+/// The index keeps the normalized text of every indexed chapter in memory, so searching it doesn't touch the epub at
+/// all, at the cost of holding (roughly) the whole text of the book. It can be built in steps with
+/// [TextIndex::build_chapters], e.g. while an application is idle.
+///
+/// A deserialized index is checked to be consistent, but it may still come from another epub, see
+/// [TextIndex::is_valid_for].
+///
+/// # Examples
+///
+/// ```
+/// # use epub::doc::EpubDoc;
+/// # use epub::search::TextIndex;
+/// let doc = EpubDoc::new("test.epub").unwrap();
+/// let mut index = TextIndex::new(&doc).unwrap();
+///
+/// while !index.is_complete() {
+///     index.build_chapters(&doc, 4).unwrap();
+/// }
+///
+/// assert!(index.is_valid_for(&doc));
+/// assert_eq!(doc.search("capítulo").unwrap(), index.search("capítulo"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "TextIndexData"))]
+pub struct TextIndex {
+    /// [EpubDoc::content_hash] of the indexed epub
+    content_hash: u64,
+    /// [EpubDoc::get_release_identifier] of the indexed epub
+    release_identifier: Option<String>,
+    /// The number of chapters in the spine of the indexed epub
+    chapters: usize,
+    /// The normalized text of all indexed chapters, back to back
+    text: String,
+    /// The offset in `text` where each indexed chapter starts
+    chapter_offsets: Vec<usize>,
+}
+
+/// A [TextIndex] as persisted, which is only accepted once its offsets are checked.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TextIndexData {
+    content_hash: u64,
+    release_identifier: Option<String>,
+    chapters: usize,
+    text: String,
+    chapter_offsets: Vec<usize>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<TextIndexData> for TextIndex {
+    type Error = String;
+
+    fn try_from(data: TextIndexData) -> std::result::Result<Self, Self::Error> {
+        if data.chapter_offsets.len() > data.chapters {
+            return Err(format!(
+                "{} chapters indexed out of {}",
+                data.chapter_offsets.len(),
+                data.chapters
+            ));
+        }
+        let mut previous = 0;
+        for &offset in &data.chapter_offsets {
+            if offset < previous || !data.text.is_char_boundary(offset) {
+                return Err(format!("invalid chapter offset {offset}"));
+            }
+            previous = offset;
+        }
+
+        Ok(TextIndex {
+            content_hash: data.content_hash,
+            release_identifier: data.release_identifier,
+            chapters: data.chapters,
+            text: data.text,
+            chapter_offsets: data.chapter_offsets,
+        })
+    }
+}
+
+impl TextIndex {
+    /// Creates an empty index for `doc`, nothing is indexed until [TextIndex::build_chapters] is called.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the zip directory can't be read.
+    pub fn new<R: Read + Seek>(doc: &EpubDoc<R>) -> Result<Self> {
+        Ok(TextIndex {
+            content_hash: doc.content_hash()?,
+            release_identifier: doc.get_release_identifier(),
+            chapters: doc.spine().len(),
+            text: String::new(),
+            chapter_offsets: Vec::new(),
+        })
+    }
+
+    /// Creates a complete index for `doc`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a chapter can't be read.
+    pub fn build<R: Read + Seek>(doc: &EpubDoc<R>) -> Result<Self> {
+        let mut index = Self::new(doc)?;
+        index.build_chapters(doc, usize::MAX)?;
+
+        Ok(index)
+    }
+
+    /// Indexes (up to) the next `count` chapters of `doc`, returning how many were indexed.
+    ///
+    /// `doc` must be the epub this index was created for, see [TextIndex::is_valid_for].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a chapter can't be read, the chapters indexed before it are kept.
+    pub fn build_chapters<R: Read + Seek>(
+        &mut self,
+        doc: &EpubDoc<R>,
+        count: usize,
+    ) -> Result<usize> {
+        self.build_chapters_with_control(doc, count, &mut ScanControl::new())
+    }
+
+    /// [TextIndex::build_chapters], which can be cancelled and reports its progress, see [ScanControl].
+    ///
+    /// The progress counts all indexed chapters, including those indexed by earlier calls.
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::Cancelled](crate::error::ArchiveError::Cancelled) if cancelled, or an error if a
+    /// chapter can't be read. Either way the chapters indexed before are kept.
+    pub fn build_chapters_with_control<R: Read + Seek>(
+        &mut self,
+        doc: &EpubDoc<R>,
+        count: usize,
+        control: &mut ScanControl<'_>,
+    ) -> Result<usize> {
+        let start = self.indexed_chapters();
+        let end = start.saturating_add(count).min(self.chapters);
+
+        for spine_index in start..end {
+            control.check()?;
+            let text = normalize(&doc.chapter_text(spine_index)?);
+            self.chapter_offsets.push(self.text.len());
+            self.text.push_str(&text);
+            control.progress(spine_index + 1, self.chapters);
+        }
+
+        Ok(end - start)
+    }
+
+    /// The number of chapters which have been indexed so far.
+    pub fn indexed_chapters(&self) -> usize {
+        self.chapter_offsets.len()
+    }
+
+    /// Whether every chapter has been indexed.
+    pub fn is_complete(&self) -> bool {
+        self.indexed_chapters() == self.chapters
+    }
+
+    /// Whether this index was built from `doc`, i.e. the content of the epub hasn't changed since.
+    ///
+    /// A (persisted) index which isn't valid for an epub should be discarded and built again.
+    pub fn is_valid_for<R: Read + Seek>(&self, doc: &EpubDoc<R>) -> bool {
+        doc.content_hash()
+            .is_ok_and(|hash| hash == self.content_hash)
+    }
+
+    /// Returns the release identifier of the indexed epub, which can be used to find the matching index.
+    pub fn release_identifier(&self) -> Option<&str> {
+        self.release_identifier.as_deref()
+    }
+
+    /// Searches the indexed chapters for (non-overlapping) case-insensitive occurrences of `query`.
+    ///
+    /// Returns the same hits as [EpubDoc::search] once the index [is complete](TextIndex::is_complete).
+    pub fn search(&self, query: &str) -> Vec<SearchHit> {
+        let query = normalize(query);
+        let mut hits = Vec::new();
+
+        for (spine_index, &start) in self.chapter_offsets.iter().enumerate() {
+            let end = self
+                .chapter_offsets
+                .get(spine_index + 1)
+                .copied()
+                .unwrap_or(self.text.len());
+            search_chapter(&self.text[start..end], &query, spine_index, &mut hits);
+        }
+
+        hits
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+}
+
+fn search_chapter(text: &str, query: &str, spine_index: usize, hits: &mut Vec<SearchHit>) {
+    if query.is_empty() {
+        return;
+    }
+
+    hits.extend(text.match_indices(query).map(|(offset, _)| SearchHit {
+        spine_index,
+        offset,
+    }));
+}
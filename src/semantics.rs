@@ -0,0 +1,192 @@
+//! Normalization of the EPUB2 guide and EPUB3 landmark vocabularies.
+//!
+//! Both describe the same kinds of locations (cover, title page, start of the main content, ...), but with
+//! slightly different spellings: `title-page` versus `titlepage`, or `text` versus `bodymatter`.
+//! [SemanticLocation] maps either to a single value.
+
+use crate::doc::{EpubDoc, GuideReference};
+use std::io::{Read, Seek};
+
+/// A semantic location within an epub, as declared by the guide or the landmarks.
+///
+/// Covers the [EPUB2 guide](https://idpf.org/epub/20/spec/OPF_2.0.1_draft.htm#Section2.6) types and the
+/// [EPUB3 structural semantics](https://www.w3.org/TR/epub-ssv-11/) commonly used for landmarks.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SemanticLocation {
+    Cover,
+    /// `title-page` (guide) or `titlepage` (landmarks)
+    TitlePage,
+    HalfTitlePage,
+    Toc,
+    Index,
+    Glossary,
+    /// `acknowledgements` (guide) or `acknowledgments` (landmarks)
+    Acknowledgements,
+    Bibliography,
+    Colophon,
+    CopyrightPage,
+    Dedication,
+    Epigraph,
+    Foreword,
+    Preface,
+    Introduction,
+    Prologue,
+    Epilogue,
+    Afterword,
+    Conclusion,
+    Appendix,
+    Imprint,
+    Errata,
+    /// `notes` (guide), or `endnotes` and the deprecated `rearnotes` and `footnotes` (landmarks)
+    Notes,
+    /// `loi`
+    ListOfIllustrations,
+    /// `lot`
+    ListOfTables,
+    /// `loa`
+    ListOfAudio,
+    /// `lov`
+    ListOfVideos,
+    FrontMatter,
+    /// `text` (guide) or `bodymatter` (landmarks), the start of the main content
+    BodyMatter,
+    BackMatter,
+    /// Any other (e.g. `other.` prefixed or vendor specific) type, as written in the epub
+    Other(String),
+}
+
+impl SemanticLocation {
+    /// Normalizes a single guide `type` or landmark `epub:type` value, ignoring case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::semantics::SemanticLocation;
+    /// assert_eq!(SemanticLocation::TitlePage, SemanticLocation::from_type("title-page"));
+    /// assert_eq!(SemanticLocation::TitlePage, SemanticLocation::from_type("titlepage"));
+    /// assert_eq!(
+    ///     SemanticLocation::Other("other.map".to_string()),
+    ///     SemanticLocation::from_type("other.map")
+    /// );
+    /// ```
+    pub fn from_type(kind: &str) -> SemanticLocation {
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "cover" => Self::Cover,
+            "title-page" | "titlepage" => Self::TitlePage,
+            "halftitlepage" => Self::HalfTitlePage,
+            "toc" => Self::Toc,
+            "index" => Self::Index,
+            "glossary" => Self::Glossary,
+            "acknowledgements" | "acknowledgments" => Self::Acknowledgements,
+            "bibliography" => Self::Bibliography,
+            "colophon" => Self::Colophon,
+            "copyright-page" => Self::CopyrightPage,
+            "dedication" => Self::Dedication,
+            "epigraph" => Self::Epigraph,
+            "foreword" => Self::Foreword,
+            "preface" => Self::Preface,
+            "introduction" => Self::Introduction,
+            "prologue" => Self::Prologue,
+            "epilogue" => Self::Epilogue,
+            "afterword" => Self::Afterword,
+            "conclusion" => Self::Conclusion,
+            "appendix" => Self::Appendix,
+            "imprint" => Self::Imprint,
+            "errata" => Self::Errata,
+            "notes" | "endnotes" | "rearnotes" | "footnotes" => Self::Notes,
+            "loi" => Self::ListOfIllustrations,
+            "lot" => Self::ListOfTables,
+            "loa" => Self::ListOfAudio,
+            "lov" => Self::ListOfVideos,
+            "frontmatter" => Self::FrontMatter,
+            "text" | "bodymatter" => Self::BodyMatter,
+            "backmatter" => Self::BackMatter,
+            _ => Self::Other(kind.trim().to_string()),
+        }
+    }
+
+    /// Whether this is one of the broad divisions of a book (front, body, or back matter).
+    pub fn is_division(&self) -> bool {
+        matches!(
+            self,
+            Self::FrontMatter | Self::BodyMatter | Self::BackMatter
+        )
+    }
+}
+
+impl GuideReference {
+    /// Returns the normalized value of every (whitespace separated) type in [GuideReference::kind].
+    pub fn semantics(&self) -> Vec<SemanticLocation> {
+        self.kind
+            .split_ascii_whitespace()
+            .map(SemanticLocation::from_type)
+            .collect()
+    }
+
+    /// Returns the most specific normalized type of this reference.
+    ///
+    /// Landmarks often combine a division with a more specific type, e.g. `frontmatter titlepage`,
+    /// in which case the latter is returned.
+    pub fn semantic(&self) -> SemanticLocation {
+        let semantics = self.semantics();
+        let is_known = |s: &&SemanticLocation| !matches!(s, SemanticLocation::Other(_));
+
+        semantics
+            .iter()
+            .filter(is_known)
+            .find(|s| !s.is_division())
+            .or_else(|| semantics.iter().find(is_known))
+            .or_else(|| semantics.first())
+            .cloned()
+            .unwrap_or_else(|| SemanticLocation::Other(String::new()))
+    }
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns the first landmark or guide reference of the given type.
+    ///
+    /// EPUB3 landmarks take precedence over the EPUB2 guide.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use epub::semantics::SemanticLocation;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let cover = doc.semantic_page(&SemanticLocation::Cover).unwrap();
+    /// assert_eq!("OEBPS/Text/titlepage.xhtml", cover.path.to_str().unwrap());
+    /// ```
+    pub fn semantic_page(&self, location: &SemanticLocation) -> Option<&GuideReference> {
+        self.landmarks()
+            .iter()
+            .chain(self.guide())
+            .find(|reference| reference.semantics().contains(location))
+    }
+
+    /// Returns the spine index where reading should start, as declared by the `bodymatter` landmark or
+    /// `text` guide reference.
+    ///
+    /// Returns `None` if neither was declared, or if it points outside the spine.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let mut doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// if let Some(start) = doc.start_reading_position() {
+    ///     doc.set_current_chapter(start);
+    /// }
+    /// assert_eq!(Some("chapter-1.xhtml"), doc.get_current_id());
+    /// ```
+    pub fn start_reading_position(&self) -> Option<usize> {
+        let start = self.semantic_page(&SemanticLocation::BodyMatter)?;
+
+        self.toc_path_to_chapter(&start.path)
+    }
+
+    /// Returns the spine index where reading should start.
+    #[deprecated(since = "2.1.0", note = "use `start_reading_position` instead")]
+    pub fn reading_start(&self) -> Option<usize> {
+        self.start_reading_position()
+    }
+}
@@ -0,0 +1,281 @@
+//! Detection of the actual type of a resource from its content.
+//!
+//! Manifests regularly declare the wrong media type, e.g. a JPEG cover declared as `image/png`, or fonts declared with
+//! one of the many legacy font types. [EpubDoc::sniff_resource_mime] compares the declared type with what the first
+//! bytes of the resource say.
+
+use crate::archive::EpubArchive;
+use crate::doc::EpubDoc;
+use crate::error::{ArchiveError, Result};
+use std::io::{Read, Seek};
+use std::path::Path;
+
+/// How many bytes are read from the start of a resource for detection.
+const SNIFF_LEN: usize = 4096;
+
+/// The declared and detected media type of a resource, see [EpubDoc::sniff_resource_mime].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SniffResult {
+    /// The media type declared in the manifest
+    pub declared: String,
+    /// The media type detected from the content, `None` if the format isn't recognized
+    pub detected: Option<String>,
+    /// Whether the declared type matches the detected one, taking aliases like `image/jpg` into account.
+    ///
+    /// Always `true` if nothing was detected.
+    pub agrees: bool,
+}
+
+impl SniffResult {
+    pub(crate) fn new(declared: &str, detected: Option<&str>) -> Self {
+        SniffResult {
+            declared: declared.to_string(),
+            detected: detected.map(str::to_string),
+            agrees: detected.is_none_or(|detected| same_type(declared, detected)),
+        }
+    }
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Detects the media type of the resource with the manifest `id` from its first bytes, and compares it with
+    /// the declared one.
+    ///
+    /// Recognizes PNG, JPEG, GIF, WebP, SVG, XHTML/HTML, OpenType/TrueType, WOFF, WOFF2, MP3 and MP4.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// let sniffed = doc.sniff_resource_mime("portada.png").unwrap();
+    /// assert_eq!(Some("image/png"), sniffed.detected.as_deref());
+    /// assert!(sniffed.agrees);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::InvalidId] if the `id` doesn't exist in the manifest, or an error if the resource
+    /// can't be read.
+    pub fn sniff_resource_mime(&self, id: &str) -> Result<SniffResult> {
+        let item = self
+            .ctx()
+            .resources
+            .get(id)
+            .ok_or(ArchiveError::InvalidId)?;
+        let detected = sniff_entry(&mut self.archive(), &item.path)?;
+
+        Ok(SniffResult::new(&item.mime, detected))
+    }
+
+    /// Sniffs every resource in the manifest, returning the ones whose content doesn't match their declared media
+    /// type, sorted by id.
+    ///
+    /// Resources which can't be read are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("test.epub").unwrap();
+    /// assert!(doc.mislabeled_resources().is_empty());
+    /// ```
+    pub fn mislabeled_resources(&self) -> Vec<(String, SniffResult)> {
+        let mut mislabeled: Vec<_> = self
+            .ctx()
+            .resources
+            .keys()
+            .filter_map(|id| Some((id.clone(), self.sniff_resource_mime(id).ok()?)))
+            .filter(|(_, sniffed)| !sniffed.agrees)
+            .collect();
+        mislabeled.sort_by(|a, b| a.0.cmp(&b.0));
+
+        mislabeled
+    }
+
+    /// Make [EpubDoc::get_resource_mime] and [EpubDoc::get_resource_mime_by_path] return the detected media type
+    /// when it disagrees with the declared one.
+    ///
+    /// Each lookup then reads the start of the resource. Off by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let mut doc = EpubDoc::new("test.epub").unwrap();
+    /// doc.set_prefer_sniffed_mime(true);
+    /// assert_eq!(Some("image/png"), doc.get_resource_mime("portada.png"));
+    /// ```
+    pub fn set_prefer_sniffed_mime(&mut self, prefer: bool) {
+        self.prefer_sniffed_mime = prefer;
+    }
+
+    /// Returns whether the detected media types are preferred, see [EpubDoc::set_prefer_sniffed_mime].
+    pub fn prefer_sniffed_mime(&self) -> bool {
+        self.prefer_sniffed_mime
+    }
+
+    /// Returns the detected media type of the resource at `path` if it disagrees with `declared`.
+    pub(crate) fn sniffed_override(&self, path: &Path, declared: &str) -> Option<&'static str> {
+        sniff_entry(&mut self.archive(), path)
+            .ok()
+            .flatten()
+            .filter(|detected| !same_type(declared, detected))
+    }
+}
+
+/// Detects the media type of the file at `path` in the `archive`.
+pub(crate) fn sniff_entry<R: Read + Seek>(
+    archive: &mut EpubArchive<R>,
+    path: impl AsRef<Path>,
+) -> Result<Option<&'static str>> {
+    Ok(sniff_mime(&archive.get_entry_prefix(path, SNIFF_LEN)?))
+}
+
+/// Detects the media type of a file from its first bytes.
+///
+/// A few KiB are enough for every recognized format. Returns `None` for anything else, including formats without
+/// a signature like CSS.
+///
+/// # Examples
+///
+/// ```
+/// use epub::sniff::sniff_mime;
+///
+/// assert_eq!(Some("image/gif"), sniff_mime(b"GIF89a\x01\x00\x01\x00"));
+/// assert_eq!(Some("image/svg+xml"), sniff_mime(br#"<?xml version="1.0"?><svg/>"#));
+/// assert_eq!(None, sniff_mime(b"body { margin: 0 }"));
+/// ```
+pub fn sniff_mime(bytes: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"OTTO", "font/otf"),
+        (b"\x00\x01\x00\x00", "font/ttf"),
+        (b"true", "font/ttf"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+        (b"ID3", "audio/mpeg"),
+    ];
+
+    if let Some((_, mime)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+    {
+        return Some(mime);
+    }
+
+    match bytes {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [_, _, _, _, b'f', b't', b'y', b'p', brand @ ..] => Some(match brand {
+            [b'M', b'4', b'A', ..] => "audio/mp4",
+            _ => "video/mp4",
+        }),
+        // An MPEG audio frame without an ID3 tag
+        [0xff, second, ..] if second & 0xe0 == 0xe0 => Some("audio/mpeg"),
+        _ => sniff_markup(bytes),
+    }
+}
+
+/// Detects the markup formats by their root element, skipping the prolog.
+fn sniff_markup(bytes: &[u8]) -> Option<&'static str> {
+    let text = String::from_utf8_lossy(bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes));
+    let mut rest = text.trim_start();
+    let mut xml_declaration = false;
+
+    let root = loop {
+        if let Some(after) = rest.strip_prefix("<?") {
+            xml_declaration |= after.starts_with("xml");
+            rest = after.split_once("?>")?.1;
+        } else if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.split_once("-->")?.1;
+        } else if let Some(after) = rest.strip_prefix("<!") {
+            // DOCTYPE, an internal subset is rare enough to be ignored
+            rest = after.split_once('>')?.1;
+        } else if let Some(after) = rest.strip_prefix('<') {
+            break after;
+        } else {
+            return None;
+        }
+        rest = rest.trim_start();
+    };
+
+    let name_end = root
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(root.len());
+    let name = root[..name_end].rsplit(':').next()?.to_ascii_lowercase();
+    let start_tag = root.split('>').next().unwrap_or(root);
+
+    match name.as_str() {
+        "svg" => Some("image/svg+xml"),
+        "html" if xml_declaration || start_tag.contains("http://www.w3.org/1999/xhtml") => {
+            Some("application/xhtml+xml")
+        }
+        "html" => Some("text/html"),
+        "ncx" => Some("application/x-dtbncx+xml"),
+        "smil" => Some("application/smil+xml"),
+        _ => None,
+    }
+}
+
+/// The media type of a file going by its extension, for the common types of an epub.
+pub(crate) fn mime_from_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+    Some(match extension.as_str() {
+        "xhtml" | "xht" => "application/xhtml+xml",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" => "application/javascript",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ncx" => "application/x-dtbncx+xml",
+        "smil" => "application/smil+xml",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        _ => return None,
+    })
+}
+
+/// Whether the `declared` media type is the `detected` one, or one of its common aliases.
+fn same_type(declared: &str, detected: &str) -> bool {
+    let declared = declared
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    canonical(&declared) == canonical(detected)
+}
+
+fn canonical(mime: &str) -> &str {
+    match mime {
+        "image/jpg" | "image/pjpeg" => "image/jpeg",
+        // The epub specifications themselves used `application/vnd.ms-opentype` for both flavours
+        "font/otf"
+        | "font/ttf"
+        | "font/sfnt"
+        | "application/font-sfnt"
+        | "application/vnd.ms-opentype"
+        | "application/x-font-otf"
+        | "application/x-font-opentype"
+        | "application/x-font-ttf"
+        | "application/x-font-truetype" => "font/sfnt",
+        "application/font-woff" | "application/x-font-woff" => "font/woff",
+        "application/font-woff2" => "font/woff2",
+        "audio/mp3" | "audio/mpeg3" | "audio/x-mpeg" => "audio/mpeg",
+        "audio/mp4" | "audio/x-m4a" | "audio/m4a" | "video/mp4" | "video/x-m4v" => "video/mp4",
+        mime => mime,
+    }
+}
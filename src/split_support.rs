@@ -0,0 +1,485 @@
+//! Changing the manifest, spine and ToC, e.g. to split oversized chapters.
+//!
+//! Splitting a chapter takes four steps: add the new part to the manifest with [EpubDoc::insert_resource], add it
+//! to the spine with [EpubDoc::insert_spine_item], move the ToC entries which now point into the new part with
+//! [EpubDoc::retarget_toc], and save with the [writer](crate::writer), replacing the content of the parts. The
+//! writer updates the package document, the NCX and the navigation document to match.
+
+use crate::doc::{build_epub_uri, EpubDoc, NavPoint, OpfSection, ResourceItem, SpineItem};
+use crate::error::{ArchiveError, Result};
+use crate::text::{decode_entities, Token, Tokens};
+use crate::utils;
+use crate::xmlutils::{self, escape_xml, OwnedAttribute};
+use indexmap::map::Entry;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+/// A part of a split chapter, see [EpubDoc::retarget_toc].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitRange {
+    /// The full path of the part within the epub
+    pub path: PathBuf,
+    /// The `id` of the element the part starts at, `None` for the part the chapter starts with
+    pub start_id: Option<String>,
+}
+
+/// The changes made through this module, which the writer has to persist.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PackageEdits {
+    /// The ids of the added manifest items
+    resources: Vec<String>,
+    spine: bool,
+    /// The ToC links which moved to another file, as (old path, fragment, new path)
+    moved: Vec<(PathBuf, Option<String>, PathBuf)>,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Adds an item to the manifest.
+    ///
+    /// `path` is the full path within the epub, e.g. `OEBPS/chapter-1b.xhtml`. A new file has to be added when
+    /// saving, with [EpubWriter::replace](crate::writer::EpubWriter::replace).
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::DuplicateId] if the `id` is already used, or
+    /// [ArchiveError::SectionUnavailable] if the manifest couldn't be parsed.
+    pub fn insert_resource(&mut self, id: &str, path: impl AsRef<Path>, mime: &str) -> Result<()> {
+        self.ensure_section(OpfSection::Manifest)?;
+
+        let ctx = self.ctx_mut();
+        match ctx.resources.entry(id.to_string()) {
+            Entry::Occupied(_) => return Err(ArchiveError::DuplicateId(id.to_string())),
+            Entry::Vacant(entry) => entry.insert(ResourceItem {
+                path: path.as_ref().to_path_buf(),
+                mime: mime.to_string(),
+                properties: Vec::new(),
+                media_overlay: None,
+                fallback: None,
+                size: None,
+                compressed_size: None,
+                extra_attrs: Vec::new(),
+            }),
+        };
+        ctx.resource_paths
+            .entry(PathBuf::from(utils::normalized_path(path.as_ref())))
+            .or_insert_with(|| id.to_string());
+        self.edits.resources.push(id.to_string());
+
+        Ok(())
+    }
+
+    /// Inserts the manifest item `idref` into the spine, directly after the spine index `after`.
+    ///
+    /// The current chapter keeps pointing at the same resource, and everything cached by spine index (e.g. the
+    /// chapter sizes of [EpubDoc::current_fraction]) is recomputed on its next use.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let mut doc = EpubDoc::new("tests/docs/charles-dickens_a-christmas-carol.epub").unwrap();
+    /// doc.insert_resource("chapter-1b", "epub/text/chapter-1b.xhtml", "application/xhtml+xml")
+    ///     .unwrap();
+    /// doc.insert_spine_item(4, "chapter-1b", true).unwrap();
+    /// assert_eq!(Some(5), doc.resource_id_to_chapter("chapter-1b"));
+    /// assert_eq!(Some(6), doc.resource_id_to_chapter("chapter-2.xhtml"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::InvalidId] if `idref` isn't in the manifest, [ArchiveError::InvalidPosition] if
+    /// `after` is out of range, or [ArchiveError::SectionUnavailable] if the spine couldn't be parsed.
+    pub fn insert_spine_item(&mut self, after: usize, idref: &str, linear: bool) -> Result<()> {
+        self.ensure_section(OpfSection::Spine)?;
+        if !self.ctx().resources.contains_key(idref) {
+            return Err(ArchiveError::InvalidId);
+        }
+        if after >= self.ctx().spine.len() {
+            return Err(ArchiveError::InvalidPosition);
+        }
+
+        self.ctx_mut().spine.insert(
+            after + 1,
+            SpineItem {
+                idref: idref.to_string(),
+                id: None,
+                linear,
+                properties: None,
+                extra_attrs: Vec::new(),
+            },
+        );
+        if self.get_current_chapter() > after {
+            self.set_current_chapter(self.get_current_chapter() + 1);
+        }
+        self.edits.spine = true;
+        self.invalidate_spine_caches();
+
+        Ok(())
+    }
+
+    /// Points the ToC and page list entries into the chapter at `old_path` at the part of the split chapter they
+    /// ended up in, returning the number of entries which changed.
+    ///
+    /// The `ranges` are the parts in reading order. An entry goes to the last part which starts at or before its
+    /// fragment in the original chapter, entries without (or with an unknown) fragment go to the first part. The
+    /// original chapter is read from the archive, so this has to be called before saving.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use epub::split_support::SplitRange;
+    /// # let mut doc = EpubDoc::new("tests/docs/charles-dickens_a-christmas-carol.epub").unwrap();
+    /// let ranges = [
+    ///     SplitRange {
+    ///         path: "epub/text/chapter-1.xhtml".into(),
+    ///         start_id: None,
+    ///     },
+    ///     SplitRange {
+    ///         path: "epub/text/chapter-1b.xhtml".into(),
+    ///         start_id: Some("chapter-1-part-2".into()),
+    ///     },
+    /// ];
+    /// // The ToC only points at the start of the chapter
+    /// assert_eq!(0, doc.retarget_toc("epub/text/chapter-1.xhtml", &ranges).unwrap());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the chapter at `old_path` can't be read.
+    pub fn retarget_toc(
+        &mut self,
+        old_path: impl AsRef<Path>,
+        ranges: &[SplitRange],
+    ) -> Result<usize> {
+        let old_path = old_path.as_ref();
+        let Some(first) = ranges.first() else {
+            return Ok(0);
+        };
+
+        let content = self.get_resource_by_path(old_path)?;
+        let content = xmlutils::ensure_utf8(&content)?;
+        let ids: Vec<String> = Tokens::new(&content)
+            .filter_map(|token| match token {
+                Token::Tag(tag) if !tag.closing => tag.attribute("id").map(|id| id.into_owned()),
+                _ => None,
+            })
+            .collect();
+        let position = |id: &str| ids.iter().position(|i| i == id);
+        let target = |fragment: Option<&str>| {
+            let at = fragment.and_then(position);
+            let range = ranges.iter().rev().find(|range| match &range.start_id {
+                None => true,
+                Some(start) => position(start)
+                    .zip(at)
+                    .is_some_and(|(start, at)| start <= at),
+            });
+            range.unwrap_or(first).path.as_path()
+        };
+
+        let mut moved = Vec::new();
+        let mut toc = std::mem::take(&mut self.ctx_mut().toc);
+        retarget_nav_points(&mut toc, old_path, &target, &mut moved);
+        self.ctx_mut().toc = toc;
+        // The preferred ToC is a copy of one of these, so their moves are already recorded
+        let ctx = self.ctx_mut();
+        for toc in [&mut ctx.toc_ncx, &mut ctx.toc_nav] {
+            retarget_nav_points(toc, old_path, &target, &mut Vec::new());
+        }
+
+        for page in &mut self.ctx_mut().page_list {
+            if same_path(&page.path, old_path) {
+                let new_path = target(page.fragment.as_deref());
+                if new_path != page.path.as_path() {
+                    moved.push((
+                        page.path.clone(),
+                        page.fragment.clone(),
+                        new_path.to_path_buf(),
+                    ));
+                    page.path = new_path.to_path_buf();
+                }
+            }
+        }
+
+        let count = moved.len();
+        for edit in moved {
+            if !self.edits.moved.contains(&edit) {
+                self.edits.moved.push(edit);
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Returns the files which have to be rewritten to persist the edits, as (path, content).
+    pub(crate) fn edited_files(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut files = Vec::new();
+
+        if !self.edits.resources.is_empty() || self.edits.spine {
+            let package = self.get_resource_by_path(&self.root_file)?;
+            let package = self.edited_package(&xmlutils::ensure_utf8(&package)?)?;
+            files.push((path_name(&self.root_file), package.into_bytes()));
+        }
+
+        if !self.edits.moved.is_empty() {
+            for id in [self.toc_id(), self.nav_id()].into_iter().flatten() {
+                let Some(item) = self.ctx().resources.get(id) else {
+                    continue;
+                };
+                let markup = self.get_resource_by_path(&item.path)?;
+                if let Some(markup) =
+                    self.retargeted_links(&item.path, &xmlutils::ensure_utf8(&markup)?)
+                {
+                    files.push((path_name(&item.path), markup.into_bytes()));
+                }
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Adds the new manifest items to, and regenerates the spine of, the package document.
+    fn edited_package(&self, opf: &str) -> Result<String> {
+        let document = xmlutils::parse_xml(opf)?;
+        let section = |name| {
+            document
+                .root_element()
+                .children()
+                .find(|node| node.is_element() && node.tag_name().name() == name)
+        };
+        let mut edited = opf.to_string();
+
+        // The spine comes after the manifest, so it's edited first to keep the manifest's range valid
+        if let Some(spine) = section("spine").filter(|_| self.edits.spine) {
+            let range = spine.range();
+            let element = &opf[range.clone()];
+            let name = qualified_name(element);
+            let start_tag = &element[..element.find('>').map_or(element.len(), |end| end + 1)];
+            let start_tag = match start_tag.strip_suffix("/>") {
+                Some(start) => format!("{}>", start.trim_end()),
+                None => start_tag.to_string(),
+            };
+            let indent = line_indent(opf, range.start);
+            let child_indent = child_indent(opf, spine, indent);
+            let item_name = sibling_name(name, "itemref");
+
+            let mut replacement = start_tag;
+            for item in self.spine() {
+                replacement.push_str(&format!(
+                    "\n{child_indent}<{item_name} idref=\"{}\"",
+                    escape_xml(&item.idref)
+                ));
+                if let Some(id) = &item.id {
+                    replacement.push_str(&format!(" id=\"{}\"", escape_xml(id)));
+                }
+                if !item.linear {
+                    replacement.push_str(" linear=\"no\"");
+                }
+                if let Some(properties) = &item.properties {
+                    replacement.push_str(&format!(" properties=\"{}\"", escape_xml(properties)));
+                }
+                for attr in &item.extra_attrs {
+                    replacement.push(' ');
+                    replacement.push_str(&attribute_markup(attr, &spine));
+                }
+                replacement.push_str("/>");
+            }
+            replacement.push_str(&format!("\n{indent}</{name}>"));
+
+            edited.replace_range(range, &replacement);
+        }
+
+        if let Some(manifest) = section("manifest").filter(|_| !self.edits.resources.is_empty()) {
+            let range = manifest.range();
+            let element = &opf[range.clone()];
+            let name = qualified_name(element);
+            let indent = line_indent(opf, range.start);
+            let child_indent = child_indent(opf, manifest, indent);
+            let item_name = sibling_name(name, "item");
+
+            let mut items = String::new();
+            for id in &self.edits.resources {
+                let Some(item) = self.ctx().resources.get(id) else {
+                    continue;
+                };
+                items.push_str(&format!(
+                    "\n{child_indent}<{item_name} id=\"{}\" href=\"{}\" media-type=\"{}\"/>",
+                    escape_xml(id),
+                    escape_xml(&utils::relative_href(&self.root_base, &item.path)),
+                    escape_xml(&item.mime),
+                ));
+            }
+
+            match manifest.children().rev().find(|node| node.is_element()) {
+                Some(last) => edited.insert_str(last.range().end, &items),
+                None if element.ends_with("/>") => {
+                    let start = element.trim_end_matches("/>").trim_end();
+                    let replacement = format!("{start}>{items}\n{indent}</{name}>");
+                    edited.replace_range(range, &replacement);
+                }
+                None => {
+                    let end_tag = range.start + element.rfind("</").unwrap_or(element.len());
+                    edited.insert_str(end_tag, &format!("{items}\n{indent}"));
+                }
+            }
+        }
+
+        Ok(edited)
+    }
+
+    /// Rewrites the `src` and `href` attributes of `markup` (the file at `path`) which point at a moved ToC
+    /// target, returning `None` if nothing moved.
+    fn retargeted_links(&self, path: &Path, markup: &str) -> Option<String> {
+        let mut edited = String::with_capacity(markup.len());
+        let mut copied = 0;
+
+        for token in Tokens::new(markup) {
+            let Token::Tag(tag) = token else {
+                continue;
+            };
+            let Some(range) = tag
+                .attribute_range("src")
+                .or_else(|| tag.attribute_range("href"))
+            else {
+                continue;
+            };
+            let Some(href) = self.moved_href(path, &decode_entities(&tag.raw[range.clone()]))
+            else {
+                continue;
+            };
+
+            // `raw` is a slice of `markup`
+            let tag_start = tag.raw.as_ptr() as usize - markup.as_ptr() as usize;
+            edited.push_str(&markup[copied..tag_start + range.start]);
+            edited.push_str(&escape_xml(&href));
+            copied = tag_start + range.end;
+        }
+
+        if copied == 0 {
+            return None;
+        }
+        edited.push_str(&markup[copied..]);
+
+        Some(edited)
+    }
+
+    /// Returns the new href (relative to the file at `path`) of `href`, if it points at a moved ToC target.
+    fn moved_href(&self, path: &Path, href: &str) -> Option<String> {
+        if href.contains(':') {
+            return None;
+        }
+        let decoded = utils::percent_decode(href).unwrap_or(href.into());
+        let (target, fragment) = utils::split_fragment(&decoded);
+        let target = build_epub_uri(path, "", target);
+
+        let (_, _, new_path) = self.edits.moved.iter().find(|(old, old_fragment, _)| {
+            same_path(Path::new(target.as_ref()), old) && old_fragment.as_deref() == fragment
+        })?;
+
+        let mut href = utils::relative_href(path.parent().unwrap_or(Path::new("")), new_path);
+        if let Some(fragment) = fragment {
+            href.push('#');
+            href.push_str(fragment);
+        }
+
+        Some(href)
+    }
+}
+
+fn retarget_nav_points<'a>(
+    points: &mut [NavPoint],
+    old_path: &Path,
+    target: &impl Fn(Option<&str>) -> &'a Path,
+    moved: &mut Vec<(PathBuf, Option<String>, PathBuf)>,
+) {
+    for point in points {
+        if let Some(content) = point
+            .content
+            .as_mut()
+            .filter(|content| same_path(content, old_path))
+        {
+            let new_path = target(point.fragment.as_deref());
+            if new_path != content {
+                moved.push((
+                    content.clone(),
+                    point.fragment.clone(),
+                    new_path.to_path_buf(),
+                ));
+                *content = new_path.to_path_buf();
+            }
+        }
+
+        retarget_nav_points(&mut point.children, old_path, target, moved);
+    }
+}
+
+/// Whether two paths within the epub are the same, the ToC parsers percent-decode their paths whilst manifest
+/// paths are kept as-is.
+fn same_path(a: &Path, b: &Path) -> bool {
+    let decode = |path: &Path| {
+        let path = path_name(path);
+        utils::percent_decode(&path).map_or(path.clone(), |decoded| decoded.into_owned())
+    };
+
+    a == b || decode(a) == decode(b)
+}
+
+/// Paths within the archive always use `/`, whatever the platform.
+fn path_name(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Writes `attr` as it would appear on a child of `parent`.
+///
+/// Namespaced attributes use a prefix declared in scope, or declare their own if there is none.
+fn attribute_markup(attr: &OwnedAttribute, parent: &roxmltree::Node<'_, '_>) -> String {
+    let value = escape_xml(&attr.value);
+    let Some(namespace) = &attr.name.namespace else {
+        return format!("{}=\"{value}\"", attr.name.tag);
+    };
+
+    match parent.lookup_prefix(namespace) {
+        Some(prefix) if !prefix.is_empty() => format!("{prefix}:{}=\"{value}\"", attr.name.tag),
+        _ => format!(
+            "xmlns:ns0=\"{}\" ns0:{}=\"{value}\"",
+            escape_xml(namespace),
+            attr.name.tag
+        ),
+    }
+}
+
+/// The (possibly prefixed) name of the element, e.g. `opf:spine`.
+fn qualified_name(element: &str) -> &str {
+    let name = element.trim_start_matches('<');
+    let end = name
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(name.len());
+    &name[..end]
+}
+
+/// The name of a child element, with the same prefix as its `parent`.
+fn sibling_name(parent: &str, name: &str) -> String {
+    match parent.split_once(':') {
+        Some((prefix, _)) => format!("{prefix}:{name}"),
+        None => name.to_string(),
+    }
+}
+
+/// The whitespace before `pos` on its line, if there is nothing else.
+fn line_indent(text: &str, pos: usize) -> &str {
+    let line = &text[text[..pos].rfind('\n').map_or(0, |i| i + 1)..pos];
+    if line.trim().is_empty() {
+        line
+    } else {
+        ""
+    }
+}
+
+/// The indentation of the children of `node`, following the existing children if there are any.
+fn child_indent(text: &str, node: roxmltree::Node<'_, '_>, indent: &str) -> String {
+    match node.children().find(|child| child.is_element()) {
+        Some(child) => line_indent(text, child.range().start).to_string(),
+        None if indent.contains('\t') => format!("{indent}\t"),
+        None => format!("{indent}  "),
+    }
+}
@@ -0,0 +1,536 @@
+//! Plain text extraction from (X)HTML content documents.
+//!
+//! The extractor is deliberately tolerant: it doesn't require well-formed XML, so it works equally well on
+//! content documents and on HTML snippets found in metadata.
+
+use crate::doc::{EpubDoc, OpfSection};
+use crate::error::{ArchiveError, Result};
+use std::io::{Read, Seek};
+use std::path::PathBuf;
+
+/// Elements which start on a new line.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "br",
+    "caption",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "li",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+/// Elements whose content is never part of the text.
+const SKIPPED_ELEMENTS: &[&str] = &["head", "script", "style", "template"];
+
+/// How ruby annotations (`<ruby>` with `<rt>` readings) end up in the extracted text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RubyMode {
+    /// Only the annotated base text, e.g. `漢字`
+    #[default]
+    BaseOnly,
+    /// Only the readings, e.g. `かんじ`
+    ReadingOnly,
+    /// The base text followed by the readings in parentheses, e.g. `漢字(かんじ)`
+    Bracketed,
+}
+
+/// Options for [EpubDoc::chapter_text_with_options].
+#[derive(Debug, Clone, Default)]
+pub struct TextOptions {
+    pub ruby: RubyMode,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns the plain text of the chapter at `spine_index`.
+    ///
+    /// Markup is removed and entities decoded, every block element (paragraphs, headings, ...) ends up on its own
+    /// line, and other whitespace is collapsed to single spaces. Ruby readings are left out, see
+    /// [EpubDoc::chapter_text_with_options] to include them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// # let chapter = doc.resource_uri_to_chapter("epub/text/chapter-1.xhtml").unwrap();
+    /// let text = doc.chapter_text(chapter).unwrap();
+    /// assert!(text.starts_with("I\nIn Which We Are Introduced to Winnie-the-Pooh"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::InvalidPosition] if `spine_index` is out of range, or an error if the chapter can't be
+    /// read.
+    pub fn chapter_text(&self, spine_index: usize) -> Result<String> {
+        self.chapter_text_with_options(spine_index, &TextOptions::default())
+    }
+
+    /// Returns the plain text of the chapter at `spine_index`, see [EpubDoc::chapter_text].
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::InvalidPosition] if `spine_index` is out of range, or an error if the chapter can't be
+    /// read.
+    pub fn chapter_text_with_options(
+        &self,
+        spine_index: usize,
+        options: &TextOptions,
+    ) -> Result<String> {
+        let (_, content) = self.chapter_content(spine_index)?;
+
+        Ok(html_to_text(&content, options))
+    }
+
+    /// Returns the `dc:description` as plain text, the way [EpubDoc::chapter_text] extracts it from a chapter.
+    ///
+    /// Descriptions often hold HTML, either as literal tags or escaped once more (`&lt;p&gt;`) in the package
+    /// document. Both are stripped, see [EpubDoc::mdata] for the description as written. Returns `None` if there is
+    /// no description or it has no text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/Metamorphosis-jackson.epub").unwrap();
+    /// let description = doc.description_text().unwrap();
+    /// assert!(!description.contains('<'));
+    /// ```
+    pub fn description_text(&self) -> Option<String> {
+        let description = self.mdata("description")?;
+        // Escaped markup without any actual tags, decode it to get to the tags
+        let html = if !description.contains('<') && description.contains("&lt;") {
+            decode_entities(description)
+        } else {
+            description.into()
+        };
+
+        let text = html_to_text(&html, &TextOptions::default());
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Returns the path and the (UTF-8) content of the chapter at `spine_index`.
+    pub(crate) fn chapter_content(&self, spine_index: usize) -> Result<(PathBuf, String)> {
+        self.ensure_section(OpfSection::Spine)?;
+        let item = self
+            .spine()
+            .get(spine_index)
+            .ok_or(ArchiveError::InvalidPosition)?;
+        let path = self
+            .ctx()
+            .resources
+            .get(&item.idref)
+            .ok_or(ArchiveError::InvalidId)?
+            .path
+            .clone();
+        let content = self.get_resource_by_path(&path)?;
+
+        Ok((path, crate::xmlutils::ensure_utf8(&content)?.into_owned()))
+    }
+}
+
+/// Convert (X)HTML to plain text, see [EpubDoc::chapter_text_with_options].
+pub(crate) fn html_to_text(html: &str, options: &TextOptions) -> String {
+    let mut output = String::with_capacity(html.len() / 2);
+    let mut skipping: Option<String> = None;
+    let mut ruby = RubyState::default();
+
+    for token in Tokens::new(html) {
+        let visible = skipping.is_none() && ruby.is_visible(options.ruby);
+
+        match token {
+            Token::Text(text) if visible => push_text(&mut output, text),
+            Token::Cdata(text) if visible => {
+                output.push_str(&text.replace(char::is_whitespace, " "))
+            }
+            Token::Text(_) | Token::Cdata(_) => {}
+            Token::Tag(tag) => match &skipping {
+                Some(name) if tag.closing && tag.name == *name => skipping = None,
+                Some(_) => {}
+                None if !tag.closing
+                    && !tag.self_closing
+                    && SKIPPED_ELEMENTS.contains(&tag.name.as_str()) =>
+                {
+                    skipping = Some(tag.name)
+                }
+                None if matches!(tag.name.as_str(), "ruby" | "rt" | "rp") => {
+                    ruby.handle(&tag, options.ruby, &mut output)
+                }
+                None if BLOCK_ELEMENTS.contains(&tag.name.as_str()) => output.push('\n'),
+                None => {}
+            },
+        }
+    }
+
+    collapse_whitespace(&output)
+}
+
+/// Tracks where in a `<ruby>` element the extractor is.
+#[derive(Default)]
+struct RubyState {
+    in_ruby: bool,
+    in_rt: bool,
+    /// Inside a `<rp>` fallback parenthesis, which is never part of the text
+    in_rp: bool,
+}
+
+impl RubyState {
+    fn is_visible(&self, mode: RubyMode) -> bool {
+        match (self.in_ruby, self.in_rt, self.in_rp) {
+            (false, _, _) => true,
+            (true, _, true) => false,
+            (true, true, _) => mode != RubyMode::BaseOnly,
+            (true, false, _) => mode != RubyMode::ReadingOnly,
+        }
+    }
+
+    fn handle(&mut self, tag: &Tag<'_>, mode: RubyMode, output: &mut String) {
+        if tag.name != "ruby" && !self.in_ruby {
+            return;
+        }
+
+        // The end tags of `rt` and `rp` are optional
+        if self.in_rt && (tag.name != "rt" || !tag.closing || tag.self_closing) {
+            self.close_rt(mode, output);
+        }
+
+        match (tag.name.as_str(), tag.closing) {
+            ("ruby", false) => self.in_ruby = true,
+            ("ruby", true) => *self = RubyState::default(),
+            ("rt", false) if !tag.self_closing => {
+                self.in_rp = false;
+                self.in_rt = true;
+                if mode == RubyMode::Bracketed {
+                    output.push('(');
+                }
+            }
+            ("rt", true) if self.in_rt => self.close_rt(mode, output),
+            ("rp", false) => self.in_rp = !tag.self_closing,
+            ("rp", true) => self.in_rp = false,
+            _ => {}
+        }
+    }
+
+    fn close_rt(&mut self, mode: RubyMode, output: &mut String) {
+        self.in_rt = false;
+        if mode == RubyMode::Bracketed {
+            output.push(')');
+        }
+    }
+}
+
+/// A piece of (X)HTML, as produced by [Tokens].
+pub(crate) enum Token<'a> {
+    /// Text, with entities still encoded
+    Text(&'a str),
+    /// The content of a CDATA section
+    Cdata(&'a str),
+    Tag(Tag<'a>),
+}
+
+/// A tolerant (X)HTML tokenizer, comments, doctypes, and processing instructions are skipped.
+pub(crate) struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    pub(crate) fn new(html: &'a str) -> Self {
+        Tokens { rest: html }
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        loop {
+            let rest = self.rest;
+            if rest.is_empty() {
+                return None;
+            }
+
+            match rest.find('<') {
+                Some(0) => {}
+                Some(start) => {
+                    self.rest = &rest[start..];
+                    return Some(Token::Text(&rest[..start]));
+                }
+                None => {
+                    self.rest = "";
+                    return Some(Token::Text(rest));
+                }
+            }
+
+            if let Some(comment) = rest.strip_prefix("<!--") {
+                self.rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata.find("]]>").unwrap_or(cdata.len());
+                self.rest = cdata.get(end + 3..).unwrap_or("");
+                return Some(Token::Cdata(&cdata[..end]));
+            } else if rest.starts_with("<!") || rest.starts_with("<?") {
+                self.rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            } else if let Some(tag) = Tag::parse(rest) {
+                self.rest = &rest[tag.raw.len()..];
+                return Some(Token::Tag(tag));
+            } else {
+                // Not a tag, e.g. `a < b`
+                self.rest = &rest[1..];
+                return Some(Token::Text("<"));
+            }
+        }
+    }
+}
+
+/// A start or end tag, e.g. `<p class="a">` or `</p>`.
+pub(crate) struct Tag<'a> {
+    /// The lowercase local name, without namespace prefix
+    pub(crate) name: String,
+    pub(crate) closing: bool,
+    pub(crate) self_closing: bool,
+    /// The whole tag, including the angle brackets
+    pub(crate) raw: &'a str,
+}
+
+impl<'a> Tag<'a> {
+    fn parse(input: &'a str) -> Option<Tag<'a>> {
+        let body = input.strip_prefix('<')?;
+        let (closing, body) = match body.strip_prefix('/') {
+            Some(body) => (true, body),
+            None => (false, body),
+        };
+
+        let name_len = body
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, ':' | '-' | '_' | '.')))
+            .unwrap_or(body.len());
+        let name = &body[..name_len];
+        if !name.starts_with(|c: char| c.is_alphabetic()) {
+            return None;
+        }
+
+        // Find the end of the tag, skipping over quoted attribute values
+        let mut quote = None;
+        let end = body[name_len..].char_indices().find_map(|(i, c)| {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '>') => return Some(i),
+                (None, _) => {}
+            }
+            None
+        });
+        let end = name_len + end.unwrap_or(body.len() - name_len);
+        let local_name = name.rsplit(':').next().unwrap_or(name);
+        let len = input.len() - body.len() + (end + 1).min(body.len());
+
+        Some(Tag {
+            name: local_name.to_ascii_lowercase(),
+            closing,
+            self_closing: body[..end].ends_with('/'),
+            raw: &input[..len],
+        })
+    }
+
+    /// Returns the (entity decoded) value of the attribute with the given lowercase local `name`.
+    pub(crate) fn attribute(&self, name: &str) -> Option<std::borrow::Cow<'a, str>> {
+        let range = self.attribute_range(name)?;
+        Some(decode_entities(&self.raw[range]))
+    }
+
+    /// Returns the position of the raw value (without quotes) of the attribute with the given lowercase local
+    /// `name` within [Tag::raw].
+    pub(crate) fn attribute_range(&self, name: &str) -> Option<std::ops::Range<usize>> {
+        let mut rest = self.raw.get(1..)?.trim_start_matches('/');
+        // Skip the tag name
+        rest = rest.trim_start_matches(|c: char| !c.is_whitespace() && c != '>' && c != '/');
+
+        loop {
+            rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+            let name_len =
+                rest.find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))?;
+            if name_len == 0 {
+                return None;
+            }
+            let attr_name = &rest[..name_len];
+            rest = rest[name_len..].trim_start();
+
+            let value = match rest.strip_prefix('=') {
+                Some(value) => {
+                    let value = value.trim_start();
+                    match value.chars().next() {
+                        Some(quote @ ('"' | '\'')) => {
+                            let end = value[1..].find(quote).map_or(value.len(), |end| end + 1);
+                            rest = value.get(end + 1..).unwrap_or("");
+                            &value[1..end]
+                        }
+                        _ => {
+                            let end = value
+                                .find(|c: char| c.is_whitespace() || c == '>')
+                                .unwrap_or(value.len());
+                            rest = &value[end..];
+                            &value[..end]
+                        }
+                    }
+                }
+                None => &rest[..0],
+            };
+
+            let local_name = attr_name.rsplit(':').next().unwrap_or(attr_name);
+            if local_name.eq_ignore_ascii_case(name) {
+                // `value` is a slice of `raw`
+                let start = value.as_ptr() as usize - self.raw.as_ptr() as usize;
+                return Some(start..start + value.len());
+            }
+        }
+    }
+}
+
+/// Append decoded text, turning all whitespace into plain spaces.
+fn push_text(output: &mut String, text: &str) {
+    let decoded = decode_entities(text);
+    output.extend(
+        decoded
+            .chars()
+            .map(|c| if c.is_ascii_whitespace() { ' ' } else { c }),
+    );
+}
+
+/// Trim every line, collapse runs of spaces, and drop empty lines.
+fn collapse_whitespace(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+
+    for line in text.lines() {
+        let mut words = line.split(' ').filter(|w| !w.is_empty()).peekable();
+        if words.peek().is_none() {
+            continue;
+        }
+
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        for (i, word) in words.enumerate() {
+            if i > 0 {
+                output.push(' ');
+            }
+            output.push_str(word);
+        }
+    }
+
+    output
+}
+
+/// Decode character references and the most common named entities, unknown entities are kept as-is.
+pub(crate) fn decode_entities(text: &str) -> std::borrow::Cow<'_, str> {
+    if !text.contains('&') {
+        return text.into();
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 32)
+            .and_then(|end| Some((decode_entity(&rest[1..end + 1])?, end + 2)));
+
+        match decoded {
+            Some((c, len)) => {
+                output.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+
+    output.into()
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+
+    let c = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "shy" => '\u{ad}',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "sbquo" => '‚',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bdquo" => '„',
+        "laquo" => '«',
+        "raquo" => '»',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "deg" => '°',
+        "middot" => '·',
+        "bull" => '•',
+        "times" => '×',
+        "iexcl" => '¡',
+        "iquest" => '¿',
+        "eacute" => 'é',
+        "egrave" => 'è',
+        "aacute" => 'á',
+        "agrave" => 'à',
+        "iacute" => 'í',
+        "oacute" => 'ó',
+        "uacute" => 'ú',
+        "ntilde" => 'ñ',
+        "ccedil" => 'ç',
+        "uuml" => 'ü',
+        "ouml" => 'ö',
+        "auml" => 'ä',
+        "szlig" => 'ß',
+        _ => return None,
+    };
+
+    Some(c)
+}
@@ -0,0 +1,256 @@
+//! Finding the manifest items nothing refers to.
+//!
+//! Books tend to accumulate dead weight: images from an earlier edition, fonts which no stylesheet uses anymore.
+//! [EpubDoc::unused_resources] follows every reference from the spine, the cover, the ToC documents and the metadata
+//! links, and reports the manifest items which were never reached.
+
+use crate::cancel::ScanControl;
+use crate::doc::{build_epub_uri, EpubDoc, OpfSection, ResourceItem};
+use crate::error::Result;
+use crate::text::{decode_entities, Token, Tokens};
+use crate::utils;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek};
+use std::path::{Component, Path};
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns the manifest items which aren't referenced by anything, sorted by id.
+    ///
+    /// Starting from the spine items, the cover, the NCX and navigation document, the guide and landmarks, and the
+    /// `link`s in the metadata, every (X)HTML, SVG and CSS resource which is reached is scanned for the resources it
+    /// refers to in turn. This reads every reachable content document (one at a time), so it's intended for
+    /// diagnostics rather than hot paths. Resources which can't be read are treated as referring to nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    /// let unused = doc.unused_resources().unwrap();
+    /// assert!(unused.iter().all(|(_, item)| item.mime != "application/xhtml+xml"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::SectionUnavailable](crate::error::ArchiveError::SectionUnavailable) if the manifest
+    /// or spine couldn't be parsed, as every resource would look unused.
+    pub fn unused_resources(&self) -> Result<Vec<(String, ResourceItem)>> {
+        self.unused_resources_with_control(&mut ScanControl::new())
+    }
+
+    /// [EpubDoc::unused_resources], which can be cancelled and reports its progress, see [ScanControl].
+    ///
+    /// The progress counts resources rather than chapters. As the references are only known while scanning, the
+    /// total is the number of resources reached so far, and grows along the way.
+    ///
+    /// # Errors
+    ///
+    /// Returns [ArchiveError::Cancelled](crate::error::ArchiveError::Cancelled) if cancelled, see
+    /// [EpubDoc::unused_resources] for the other errors.
+    pub fn unused_resources_with_control(
+        &self,
+        control: &mut ScanControl<'_>,
+    ) -> Result<Vec<(String, ResourceItem)>> {
+        self.ensure_section(OpfSection::Manifest)?;
+        self.ensure_section(OpfSection::Spine)?;
+
+        let resources = &self.ctx().resources;
+        // Several items can have the same path, e.g. if their hrefs only differ by a fragment
+        let mut by_path: HashMap<String, Vec<&str>> = HashMap::new();
+        for (id, item) in resources {
+            by_path
+                .entry(normalize_path(&item.path))
+                .or_default()
+                .push(id.as_str());
+        }
+
+        let mut roots: Vec<&str> = self
+            .spine()
+            .iter()
+            .map(|item| item.idref.as_str())
+            .collect();
+        roots.extend(self.get_cover_id());
+        roots.extend(self.toc_id());
+        roots.extend(self.nav_id());
+
+        let mut reached: HashSet<&str> = HashSet::new();
+        let mut queue: Vec<&str> = Vec::new();
+        let mut reach = |id: &str, queue: &mut Vec<_>| {
+            if let Some((id, _)) = resources.get_key_value(id) {
+                if reached.insert(id.as_str()) {
+                    queue.push(id.as_str());
+                }
+            }
+        };
+        let path_to_ids = |path: &Path| {
+            by_path
+                .get(&normalize_path(path))
+                .map_or(&[][..], Vec::as_slice)
+        };
+
+        for id in roots {
+            reach(id, &mut queue);
+        }
+        for reference in self.guide().iter().chain(self.landmarks()) {
+            for id in path_to_ids(&reference.path) {
+                reach(id, &mut queue);
+            }
+        }
+        for link in self.metadata().get("link").into_iter().flatten() {
+            let Some(href) = link.find_attr("href").filter(|href| is_local(href)) else {
+                continue;
+            };
+            let (href, _) = utils::split_fragment(href);
+            for id in path_to_ids(&utils::convert_path_separators(&self.root_base, href)) {
+                reach(id, &mut queue);
+            }
+        }
+
+        let mut scanned = 0;
+        while let Some(id) = queue.pop() {
+            control.check()?;
+            let item = &resources[id];
+            let references = match item.mime.as_str() {
+                "application/xhtml+xml"
+                | "text/html"
+                | "image/svg+xml"
+                | "application/x-dtbncx+xml" => self
+                    .get_resource_str_by_path(&item.path)
+                    .ok()
+                    .map(|markup| markup_references(&markup)),
+                "text/css" => self
+                    .get_resource_str_by_path(&item.path)
+                    .ok()
+                    .map(|css| css_references(&css)),
+                _ => None,
+            };
+
+            for href in references.into_iter().flatten() {
+                let path = build_epub_uri(&item.path, "", &href);
+                for id in path_to_ids(Path::new(path.as_ref())) {
+                    reach(id, &mut queue);
+                }
+            }
+            scanned += 1;
+            control.progress(scanned, scanned + queue.len());
+        }
+
+        let mut unused: Vec<_> = resources
+            .iter()
+            .filter(|(id, _)| !reached.contains(id.as_str()))
+            .map(|(id, item)| (id.clone(), item.clone()))
+            .collect();
+        unused.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(unused)
+    }
+
+    /// The manifest id of the EPUB3 navigation document.
+    pub(crate) fn nav_id(&self) -> Option<&str> {
+        self.ctx().nav_id.as_deref()
+    }
+}
+
+/// Returns the local hrefs (without fragment or query) in the attributes, `<style>` elements and `style`
+/// attributes of an (X)HTML, SVG or NCX document.
+fn markup_references(markup: &str) -> Vec<String> {
+    let mut references = Vec::new();
+    let mut in_style = false;
+
+    for token in Tokens::new(markup) {
+        match token {
+            Token::Text(css) | Token::Cdata(css) if in_style => {
+                references.extend(css_references(&decode_entities(css)));
+            }
+            Token::Text(_) | Token::Cdata(_) => {}
+            Token::Tag(tag) if tag.closing => in_style &= tag.name != "style",
+            Token::Tag(tag) => {
+                in_style = tag.name == "style" && !tag.self_closing;
+
+                // `href` also matches `xlink:href`
+                for name in ["href", "src", "poster", "data"] {
+                    references.extend(tag.attribute(name).map(|href| href.into_owned()));
+                }
+                if let Some(srcset) = tag.attribute("srcset") {
+                    references.extend(
+                        srcset
+                            .split(',')
+                            .filter_map(|candidate| candidate.split_whitespace().next())
+                            .map(str::to_string),
+                    );
+                }
+                if let Some(style) = tag.attribute("style") {
+                    references.extend(css_references(&style));
+                }
+            }
+        }
+    }
+
+    references.retain(|href| is_local(href));
+    references.iter_mut().for_each(strip_fragment);
+    references
+}
+
+/// Returns the local hrefs (without fragment or query) in the `url()`s and `@import`s of a stylesheet.
+fn css_references(css: &str) -> Vec<String> {
+    let mut references = Vec::new();
+
+    for (start, _) in css.match_indices("url(") {
+        let rest = css[start + "url(".len()..].trim_start();
+        let Some(end) = rest.find(')') else {
+            continue;
+        };
+        references.push(unquote(rest[..end].trim()).to_string());
+    }
+    for (start, _) in css.match_indices("@import") {
+        let rest = css[start + "@import".len()..].trim_start();
+        // `@import url(...)` is already covered above
+        if let Some(quote @ ('"' | '\'')) = rest.chars().next() {
+            if let Some(end) = rest[1..].find(quote) {
+                references.push(rest[1..=end].to_string());
+            }
+        }
+    }
+
+    references.retain(|href| is_local(href));
+    references.iter_mut().for_each(strip_fragment);
+    references
+}
+
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(value)
+}
+
+/// Whether the href points at a file within the epub, rather than a remote URL or into the same document.
+fn is_local(href: &str) -> bool {
+    !href.is_empty() && !href.starts_with('#') && !href.contains(':')
+}
+
+fn strip_fragment(href: &mut String) {
+    if let Some(end) = href.find(['#', '?']) {
+        href.truncate(end);
+    }
+}
+
+/// A comparable form of a path within the epub: `..` resolved, and percent-decoded.
+fn normalize_path(path: &Path) -> String {
+    let mut segments: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(segment) => segments.push(segment.to_string_lossy().into_owned()),
+            Component::ParentDir => {
+                segments.pop();
+            }
+            _ => {}
+        }
+    }
+    let path = segments.join("/");
+
+    match utils::percent_decode(&path) {
+        Some(decoded) => decoded.into_owned(),
+        None => path,
+    }
+}
@@ -1,26 +1,157 @@
-use std::borrow::{Cow};
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
 
 // Forcibly converts separators in a filepath to unix separators to
 // to ensure that ZipArchive's by_name method will retrieve the proper
 // file. Failing to convert to unix-style on Windows causes the
 // ZipArchive not to find the file.
+//
+// The `.` and `..` segments are resolved as well, see [resolve_href].
 pub fn convert_path_separators(root_base: impl AsRef<Path>, href: &str) -> PathBuf {
-    let path = root_base
-        .as_ref()
-        .join(href.split('/').collect::<PathBuf>());
+    resolve_href(root_base, href).0
+}
+
+/// Joins the `href` to `root_base` like [convert_path_separators], and lexically resolves the `.` and `..`
+/// segments, as the zip entries are stored with normalized paths.
+///
+/// An href with a leading `/` is relative to the root of the epub rather than to `root_base`. A `..` which would go
+/// above the root of the epub is dropped, the returned flag is set if that happened.
+pub fn resolve_href(root_base: impl AsRef<Path>, href: &str) -> (PathBuf, bool) {
+    let mut path = PathBuf::new();
+    let mut escaped = false;
+    let relative = href.split('/').collect::<PathBuf>();
+    let joined = match href.starts_with('/') {
+        true => relative,
+        false => root_base.as_ref().join(relative),
+    };
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => escaped |= !path.pop(),
+            Component::Normal(segment) => path.push(segment),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
 
-    if cfg!(windows) {
+    let path = if cfg!(windows) {
         let path = path.as_path().display().to_string().replace('\\', "/");
         PathBuf::from(path)
     } else {
         path
-    }
+    };
+    (path, escaped)
 }
 
 /// Decode the provided input if it contains percent encoded values (e.g, URLs).
-pub fn percent_decode(input: &str) -> Option<Cow<str>> {
+pub fn percent_decode(input: &str) -> Option<Cow<'_, str>> {
     percent_encoding::percent_decode(input.as_bytes())
         .decode_utf8()
         .ok()
 }
+
+/// The path with unix separators and without percent-encoding.
+pub fn normalized_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    match percent_decode(&path) {
+        Some(decoded) => decoded.into_owned(),
+        None => path,
+    }
+}
+
+/// Whether the href points outside the epub, e.g. `https://example.com`.
+pub fn is_remote(href: &str) -> bool {
+    href.contains("://")
+}
+
+/// Split a `path#fragment` href into the path and the (non-empty) fragment.
+pub fn split_fragment(href: &str) -> (&str, Option<&str>) {
+    match href.split_once('#') {
+        Some((path, fragment)) if !fragment.is_empty() => (path, Some(fragment)),
+        Some((path, _)) => (path, None),
+        None => (href, None),
+    }
+}
+
+/// Returns the href of `target` relative to the directory `base`, both paths within the epub.
+pub fn relative_href(base: impl AsRef<Path>, target: impl AsRef<Path>) -> String {
+    let base: Vec<_> = base.as_ref().components().collect();
+    let target: Vec<_> = target.as_ref().components().collect();
+    let common = base.iter().zip(&target).take_while(|(a, b)| a == b).count();
+
+    let parents = std::iter::repeat_n(Cow::Borrowed(".."), base.len() - common);
+    let rest = target[common..]
+        .iter()
+        .map(|component| component.as_os_str().to_string_lossy());
+    parents.chain(rest).collect::<Vec<_>>().join("/")
+}
+
+pub const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// Feed `bytes` into a 64-bit FNV-1a `hash`, a simple hash which (unlike std's `DefaultHasher`) is stable.
+pub fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// (De)serializes a path within the epub as a string with forward slashes, regardless of the OS.
+#[cfg(feature = "serde")]
+pub mod serde_path {
+    use std::path::{Path, PathBuf};
+
+    pub fn serialize<S: serde::Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::relative_href("", path))
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<PathBuf, D::Error> {
+        let path: String = serde::Deserialize::deserialize(deserializer)?;
+        Ok(super::convert_path_separators("", &path))
+    }
+}
+
+/// Like [serde_path], for an optional path.
+#[cfg(feature = "serde")]
+pub mod serde_opt_path {
+    use std::path::PathBuf;
+
+    pub fn serialize<S: serde::Serializer>(
+        path: &Option<PathBuf>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match path {
+            Some(path) => serializer.serialize_some(&super::relative_href("", path)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<PathBuf>, D::Error> {
+        let path: Option<String> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(path.map(|path| super::convert_path_separators("", &path)))
+    }
+}
+
+/// Like [serde_path], for a list of paths.
+#[cfg(feature = "serde")]
+pub mod serde_paths {
+    use std::path::PathBuf;
+
+    pub fn serialize<S: serde::Serializer>(
+        paths: &[PathBuf],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(paths.iter().map(|path| super::relative_href("", path)))
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<PathBuf>, D::Error> {
+        let paths: Vec<String> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(paths
+            .iter()
+            .map(|path| super::convert_path_separators("", path))
+            .collect())
+    }
+}
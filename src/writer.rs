@@ -0,0 +1,396 @@
+//! Saving epubs, optionally with some of their files replaced.
+//!
+//! By default every entry which isn't replaced is copied byte-for-byte: same local header (including extra fields),
+//! same compressed data, and same order. This keeps signatures over unchanged files (e.g. `META-INF/signatures.xml`)
+//! valid. The only other change made is moving a misplaced or compressed `mimetype` entry to the front, as required
+//! by the OCF specification.
+//!
+//! Changes made to the manifest, spine and ToC through the [split_support](crate::split_support) module are saved
+//! by rewriting the package document and the ToC documents.
+
+use crate::archive::{
+    read_u16, read_u32, EntryLayout, EpubArchive, CENTRAL_HEADER_LEN,
+    END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+};
+use crate::doc::EpubDoc;
+use crate::error::Result;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::CompressionMethod;
+
+const MIMETYPE: &str = "mimetype";
+const EPUB_MIMETYPE: &[u8] = b"application/epub+zip";
+
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
+
+/// Builder for saving an epub, see [EpubDoc::writer].
+pub struct EpubWriter<'a, R: Read + Seek> {
+    doc: &'a EpubDoc<R>,
+    replacements: Vec<(String, Vec<u8>)>,
+    passthrough: bool,
+}
+
+/// Summary of a save.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveReport {
+    /// The entries which were not copied byte-for-byte, in the order they were written
+    pub rewritten: Vec<String>,
+}
+
+impl<R: Read + Seek> EpubDoc<R> {
+    /// Returns a writer to save this epub, see the [module documentation](crate::writer).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use epub::doc::EpubDoc;
+    /// # use std::io::Cursor;
+    /// let doc = EpubDoc::new("test.epub").unwrap();
+    /// let opf = doc.get_resource_str_by_path(&doc.root_file).unwrap();
+    ///
+    /// let mut output = Cursor::new(Vec::new());
+    /// let report = doc
+    ///     .writer()
+    ///     .replace(&doc.root_file, opf.replace("Todo es mío", "Todo es tuyo"))
+    ///     .save(&mut output)
+    ///     .unwrap();
+    /// assert_eq!(vec!["mimetype", "OEBPS/content.opf"], report.rewritten);
+    ///
+    /// output.set_position(0);
+    /// let saved = EpubDoc::from_reader(output).unwrap();
+    /// assert_eq!(Some("Todo es tuyo"), saved.mdata("title"));
+    /// ```
+    pub fn writer(&self) -> EpubWriter<'_, R> {
+        EpubWriter {
+            doc: self,
+            replacements: Vec::new(),
+            passthrough: true,
+        }
+    }
+
+    /// Returns the layout of every entry of the underlying zip archive, see [EpubArchive::layout].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the local header of an entry can't be read.
+    pub fn archive_layout(&self) -> Result<Vec<EntryLayout>> {
+        self.archive().layout()
+    }
+}
+
+impl<'a, R: Read + Seek> EpubWriter<'a, R> {
+    /// Replaces the content of the entry at `path` (relative to the root of the archive),
+    /// or adds it to the end of the archive if there is no such entry.
+    pub fn replace(mut self, path: impl AsRef<Path>, content: impl Into<Vec<u8>>) -> Self {
+        let name = path.as_ref().to_string_lossy().replace('\\', "/");
+
+        self.replacements.retain(|(n, _)| *n != name);
+        self.replacements.push((name, content.into()));
+        self
+    }
+
+    /// Whether entries which aren't replaced are copied byte-for-byte, defaults to `true`.
+    ///
+    /// When disabled every entry is decompressed and compressed again with its original compression method,
+    /// which normalizes the archive at the cost of changing every entry.
+    pub fn passthrough(mut self, passthrough: bool) -> Self {
+        self.passthrough = passthrough;
+        self
+    }
+
+    /// Writes the epub to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading the original archive or writing the new one fails.
+    /// Archives which need ZIP64 extensions are only supported with [passthrough](Self::passthrough) disabled.
+    pub fn save<W: Write + Seek>(mut self, writer: W) -> Result<SaveReport> {
+        // Persist the changes to the manifest, spine and ToC, unless the file was replaced explicitly
+        for (name, content) in self.doc.edited_files()? {
+            if self.replacement(&name).is_none() {
+                self.replacements.push((name, content));
+            }
+        }
+        let mut archive = self.doc.archive();
+
+        if self.passthrough {
+            self.save_passthrough(&mut archive, writer)
+        } else {
+            self.save_recompressed(&mut archive, writer)
+        }
+    }
+
+    fn replacement(&self, name: &str) -> Option<&[u8]> {
+        self.replacements
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, content)| content.as_slice())
+    }
+
+    fn save_passthrough<W: Write + Seek>(
+        &self,
+        archive: &mut EpubArchive<R>,
+        mut writer: W,
+    ) -> Result<SaveReport> {
+        let mut layout = archive.layout()?;
+        layout.sort_by_key(|entry| entry.header_offset);
+        let comment = archive.comment();
+
+        let mut report = SaveReport::default();
+        let mut central_directory = Vec::new();
+        let start = writer.stream_position()?;
+        let mut write_new = |writer: &mut W, name: &str, method, content: &[u8]| {
+            report.rewritten.push(name.to_string());
+            write_new_entry(writer, start, name, method, content)
+        };
+
+        archive.with_reader(|reader| {
+            let mimetype = layout.iter().position(|e| e.name == MIMETYPE);
+            let mimetype_ok = mimetype == Some(0)
+                && self.replacement(MIMETYPE).is_none()
+                && layout[0].compression == CompressionMethod::Stored
+                && local_extra_len(reader, &layout[0])? == 0;
+
+            if mimetype_ok {
+                copy_raw(
+                    reader,
+                    &layout[0],
+                    &mut writer,
+                    start,
+                    &mut central_directory,
+                )?;
+            } else {
+                let content = self.replacement(MIMETYPE).unwrap_or(EPUB_MIMETYPE);
+                let method = CompressionMethod::Stored;
+                central_directory.extend(write_new(&mut writer, MIMETYPE, method, content)?);
+            }
+
+            for entry in layout.iter().filter(|e| e.name != MIMETYPE) {
+                match self.replacement(&entry.name) {
+                    Some(content) => {
+                        let method = writable_method(entry.compression);
+                        let record = write_new(&mut writer, &entry.name, method, content)?;
+                        central_directory.extend(record);
+                    }
+                    None => copy_raw(reader, entry, &mut writer, start, &mut central_directory)?,
+                }
+            }
+
+            for (name, content) in &self.replacements {
+                if name != MIMETYPE && !layout.iter().any(|e| e.name == *name) {
+                    let method = DEFAULT_METHOD;
+                    central_directory.extend(write_new(&mut writer, name, method, content)?);
+                }
+            }
+
+            Ok(())
+        })?;
+
+        let central_offset = writer.stream_position()? - start;
+        let entry_count = count_central_records(&central_directory);
+        writer.write_all(&central_directory)?;
+        write_end_of_central_directory(
+            &mut writer,
+            entry_count,
+            central_directory.len() as u64,
+            central_offset,
+            &comment,
+        )?;
+
+        Ok(report)
+    }
+
+    fn save_recompressed<W: Write + Seek>(
+        &self,
+        archive: &mut EpubArchive<R>,
+        writer: W,
+    ) -> Result<SaveReport> {
+        let mut report = SaveReport::default();
+        let mut zip = zip::ZipWriter::new(writer);
+        zip.set_raw_comment(archive.comment());
+
+        let mimetype = self.replacement(MIMETYPE).unwrap_or(EPUB_MIMETYPE);
+        zip.start_file(MIMETYPE, stored())?;
+        zip.write_all(mimetype)?;
+        report.rewritten.push(MIMETYPE.to_string());
+
+        for index in 0..archive.layout()?.len() {
+            let (name, method, content) = archive.entry_by_index(index)?;
+            if name == MIMETYPE {
+                continue;
+            }
+
+            if name.ends_with('/') {
+                zip.add_directory(name.as_str(), FileOptions::default())?;
+            } else {
+                let options = FileOptions::default().compression_method(writable_method(method));
+                zip.start_file(name.as_str(), options)?;
+                zip.write_all(self.replacement(&name).unwrap_or(&content))?;
+            }
+            report.rewritten.push(name);
+        }
+
+        for (name, content) in &self.replacements {
+            if !report.rewritten.contains(name) {
+                zip.start_file(name.as_str(), FileOptions::default())?;
+                zip.write_all(content)?;
+                report.rewritten.push(name.clone());
+            }
+        }
+
+        zip.finish()?;
+
+        Ok(report)
+    }
+}
+
+fn stored() -> FileOptions {
+    FileOptions::default().compression_method(CompressionMethod::Stored)
+}
+
+/// The compression of new files, and files whose compression method can't be written.
+#[cfg(feature = "deflate")]
+const DEFAULT_METHOD: CompressionMethod = CompressionMethod::Deflated;
+#[cfg(not(feature = "deflate"))]
+const DEFAULT_METHOD: CompressionMethod = CompressionMethod::Stored;
+
+/// Compression methods we can't write (e.g. deprecated ones, or ones whose feature is disabled) fall back to
+/// [DEFAULT_METHOD].
+fn writable_method(method: CompressionMethod) -> CompressionMethod {
+    match method {
+        CompressionMethod::Stored => method,
+        #[cfg(feature = "deflate")]
+        CompressionMethod::Deflated => method,
+        #[cfg(feature = "bzip2")]
+        CompressionMethod::Bzip2 => method,
+        #[cfg(feature = "zstd")]
+        CompressionMethod::Zstd => method,
+        _ => DEFAULT_METHOD,
+    }
+}
+
+fn unsupported(reason: &'static str) -> ZipError {
+    ZipError::UnsupportedArchive(reason)
+}
+
+fn local_extra_len(reader: &mut (impl Read + Seek), entry: &EntryLayout) -> Result<u16> {
+    let mut header = [0; 30];
+    reader.seek(SeekFrom::Start(entry.header_offset))?;
+    reader.read_exact(&mut header)?;
+
+    Ok(read_u16(&header, 28))
+}
+
+/// Copies the local header, data, and data descriptor of `entry` as-is,
+/// appending its central directory record (pointing at the new location) to `central_directory`.
+fn copy_raw(
+    reader: &mut (impl Read + Seek),
+    entry: &EntryLayout,
+    writer: &mut (impl Write + Seek),
+    start: u64,
+    central_directory: &mut Vec<u8>,
+) -> Result<()> {
+    let offset = writer.stream_position()? - start;
+    let offset = u32::try_from(offset).map_err(|_| unsupported("ZIP64 offsets"))?;
+
+    let mut local_header = vec![0; (entry.data_offset - entry.header_offset) as usize];
+    reader.seek(SeekFrom::Start(entry.header_offset))?;
+    reader.read_exact(&mut local_header)?;
+    writer.write_all(&local_header)?;
+    let copied = std::io::copy(&mut reader.by_ref().take(entry.compressed_size), writer)?;
+    if copied != entry.compressed_size {
+        return Err(unsupported("truncated entry").into());
+    }
+
+    let uses_data_descriptor = read_u16(&local_header, 6) & 0x08 != 0;
+    if uses_data_descriptor {
+        let mut descriptor = vec![0; 12];
+        reader.read_exact(&mut descriptor)?;
+        // The signature is optional
+        if read_u32(&descriptor, 0) == DATA_DESCRIPTOR_SIGNATURE {
+            descriptor.resize(16, 0);
+            reader.read_exact(&mut descriptor[12..])?;
+        }
+        writer.write_all(&descriptor)?;
+    }
+
+    let mut record = vec![0; CENTRAL_HEADER_LEN];
+    reader.seek(SeekFrom::Start(entry.central_header_offset))?;
+    reader.read_exact(&mut record)?;
+    let variable_len = read_u16(&record, 28) as usize
+        + read_u16(&record, 30) as usize
+        + read_u16(&record, 32) as usize;
+    record.resize(CENTRAL_HEADER_LEN + variable_len, 0);
+    reader.read_exact(&mut record[CENTRAL_HEADER_LEN..])?;
+
+    if read_u32(&record, 42) == u32::MAX {
+        return Err(unsupported("ZIP64 offsets").into());
+    }
+    record[42..46].copy_from_slice(&offset.to_le_bytes());
+    central_directory.extend(record);
+
+    Ok(())
+}
+
+/// Writes a freshly compressed entry, returning its central directory record.
+///
+/// The entry is first written to a scratch archive by the zip crate, and then copied over as-is.
+fn write_new_entry(
+    writer: &mut (impl Write + Seek),
+    start: u64,
+    name: &str,
+    method: CompressionMethod,
+    content: &[u8],
+) -> Result<Vec<u8>> {
+    let mut scratch = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    scratch.start_file(name, FileOptions::default().compression_method(method))?;
+    scratch.write_all(content)?;
+    let mut scratch = EpubArchive::from_reader(scratch.finish()?)?;
+
+    let layout = scratch.layout()?;
+    let mut record = Vec::new();
+    scratch.with_reader(|reader| copy_raw(reader, &layout[0], writer, start, &mut record))?;
+
+    Ok(record)
+}
+
+fn count_central_records(central_directory: &[u8]) -> u64 {
+    let mut count = 0;
+    let mut offset = 0;
+
+    while offset + CENTRAL_HEADER_LEN <= central_directory.len() {
+        let variable_len = read_u16(central_directory, offset + 28) as usize
+            + read_u16(central_directory, offset + 30) as usize
+            + read_u16(central_directory, offset + 32) as usize;
+        offset += CENTRAL_HEADER_LEN + variable_len;
+        count += 1;
+    }
+
+    count
+}
+
+fn write_end_of_central_directory(
+    writer: &mut impl Write,
+    entries: u64,
+    size: u64,
+    offset: u64,
+    comment: &[u8],
+) -> Result<()> {
+    let entries = u16::try_from(entries).map_err(|_| unsupported("ZIP64 entry counts"))?;
+    let size = u32::try_from(size).map_err(|_| unsupported("ZIP64 offsets"))?;
+    let offset = u32::try_from(offset).map_err(|_| unsupported("ZIP64 offsets"))?;
+
+    writer.write_all(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes())?;
+    // Number of this disk, and of the disk with the central directory
+    writer.write_all(&[0; 4])?;
+    writer.write_all(&entries.to_le_bytes())?;
+    writer.write_all(&entries.to_le_bytes())?;
+    writer.write_all(&size.to_le_bytes())?;
+    writer.write_all(&offset.to_le_bytes())?;
+    writer.write_all(&(comment.len() as u16).to_le_bytes())?;
+    writer.write_all(comment)?;
+
+    Ok(())
+}
@@ -5,10 +5,26 @@ use std::sync::Arc;
 pub enum XMLError {
     #[error("No XML Elements Found")]
     NoElements,
+    #[cfg(feature = "rewrite")]
     #[error("Error in HTML writer")]
     LolHtmlError(#[from] lol_html::errors::RewritingError),
+    #[error("Unsupported encoding {0}, enable the `encodings` feature")]
+    UnsupportedEncoding(&'static str),
+    #[error("Elements are nested deeper than {0} levels")]
+    TooDeep(usize),
 }
 
+/// The maximum nesting depth of the elements of an XML document, roxmltree parses nested elements recursively so
+/// deeper documents could overflow the stack.
+pub(crate) const MAX_XML_DEPTH: usize = 256;
+
+/// The namespace of the `xml:` prefix, e.g. `xml:lang`.
+pub(crate) const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+/// The Dublin Core elements namespace, usually bound to `dc:`.
+pub(crate) const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+/// The DCMI terms namespace, usually bound to `dcterms:`.
+pub(crate) const DCTERMS_NAMESPACE: &str = "http://purl.org/dc/terms/";
+
 pub trait RoxmlNodeExt {
     /// Find an attribute with the given `name`, ignoring any namespaces in the process.
     fn attr_no_namespace(&self, name: impl AsRef<str>) -> Option<&str>;
@@ -28,25 +44,145 @@ impl<'a, 'b> RoxmlNodeExt for roxmltree::Node<'a, 'b> {
 /// In order to work around that we must therefore ensure that we get a UTF-8 representation, which is done here.
 ///
 /// So long as the XML document was originally UTF-8 no new allocation is performed here, merely validation.
-pub fn ensure_utf8(content: &[u8]) -> Cow<'_, str> {
+#[cfg(feature = "encodings")]
+pub fn ensure_utf8(content: &[u8]) -> Result<Cow<'_, str>, XMLError> {
     let (encoding, skip) =
         encoding_rs::Encoding::for_bom(content).unwrap_or((encoding_rs::UTF_8, 0));
     let (out, _) = encoding.decode_without_bom_handling(&content[skip..]);
 
-    out
+    Ok(out)
+}
+
+/// Without the `encodings` feature only UTF-8 (with or without BOM) is supported, UTF-16 is rejected.
+#[cfg(not(feature = "encodings"))]
+pub fn ensure_utf8(content: &[u8]) -> Result<Cow<'_, str>, XMLError> {
+    match content {
+        [0xef, 0xbb, 0xbf, rest @ ..] => Ok(String::from_utf8_lossy(rest)),
+        [0xfe, 0xff, ..] => Err(XMLError::UnsupportedEncoding("UTF-16BE")),
+        [0xff, 0xfe, ..] => Err(XMLError::UnsupportedEncoding("UTF-16LE")),
+        _ => Ok(String::from_utf8_lossy(content)),
+    }
+}
+
+/// Undo one round of accidental escaping of a whole document, `&lt;package&gt;` or `%3Cpackage%3E`.
+///
+/// Returns the decoded document and a description of the escaping, or `None` if the content doesn't start like an
+/// escaped document.
+pub(crate) fn unescape_document(content: &str) -> Option<(String, &'static str)> {
+    let start = content.trim_start();
+    let starts_with = |prefix: &str| {
+        start
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    };
+
+    if starts_with("&lt;") {
+        let unescaped = crate::text::decode_entities(content).into_owned();
+        Some((unescaped, "HTML entity escaped"))
+    } else if starts_with("%3C") {
+        let decoded = percent_encoding::percent_decode(content.as_bytes())
+            .decode_utf8()
+            .ok()?;
+        Some((decoded.into_owned(), "percent-encoded"))
+    } else {
+        None
+    }
+}
+
+/// Escape a value for use in XML text or a (double quoted) attribute.
+pub fn escape_xml(value: &str) -> Cow<'_, str> {
+    if !value.contains(['&', '<', '>', '"']) {
+        return value.into();
+    }
+
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .into()
 }
 
-/// Parse the given XML content with permissive options
-pub fn parse_xml(content: &str) -> Result<roxmltree::Document, roxmltree::Error> {
-    roxmltree::Document::parse_with_options(
+/// Parse the given XML content with permissive options, documents nested deeper than [MAX_XML_DEPTH] are refused.
+pub fn parse_xml(content: &str) -> crate::error::Result<roxmltree::Document<'_>> {
+    if exceeds_depth(content, MAX_XML_DEPTH) {
+        return Err(XMLError::TooDeep(MAX_XML_DEPTH).into());
+    }
+
+    Ok(roxmltree::Document::parse_with_options(
         content,
         roxmltree::ParsingOptions {
             allow_dtd: true,
             nodes_limit: u32::MAX,
         },
-    )
+    )?)
 }
 
+/// Whether the elements of the XML `content` are nested deeper than `max_depth`, without parsing it.
+///
+/// Comments, CDATA sections, processing instructions and the doctype are skipped, malformed markup is left to the
+/// actual parser.
+fn exceeds_depth(content: &str, max_depth: usize) -> bool {
+    let bytes = content.as_bytes();
+    let find = |from: usize, needle: &[u8]| {
+        bytes[from..]
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .map_or(bytes.len(), |i| from + i + needle.len())
+    };
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while let Some(offset) = bytes[i..].iter().position(|&b| b == b'<') {
+        i += offset;
+        let rest = &bytes[i..];
+        i = if rest.starts_with(b"<!--") {
+            find(i, b"-->")
+        } else if rest.starts_with(b"<![CDATA[") {
+            find(i, b"]]>")
+        } else if rest.starts_with(b"<?") {
+            find(i, b"?>")
+        } else if rest.starts_with(b"<!") {
+            // A doctype with an internal subset ends at `]>`
+            let end = find(i, b">");
+            if bytes[i..end].contains(&b'[') {
+                find(i, b"]>")
+            } else {
+                end
+            }
+        } else if rest.starts_with(b"</") {
+            depth = depth.saturating_sub(1);
+            find(i, b">")
+        } else {
+            // A start tag, attribute values can contain a `>`
+            let mut quote = None;
+            let mut end = i + 1;
+            while end < bytes.len() {
+                match (quote, bytes[end]) {
+                    (Some(q), c) if c == q => quote = None,
+                    (None, c @ (b'"' | b'\'')) => quote = Some(c),
+                    (None, b'>') => break,
+                    _ => {}
+                }
+                end += 1;
+            }
+            if bytes[end - 1] != b'/' {
+                depth += 1;
+                if depth > max_depth {
+                    return true;
+                }
+            }
+            end + 1
+        };
+        if i >= bytes.len() {
+            break;
+        }
+    }
+
+    false
+}
+
+#[cfg(feature = "rewrite")]
 pub fn replace_attributes(html: &str, settings: lol_html::Settings) -> Result<Vec<u8>, XMLError> {
     let mut output = Vec::with_capacity(html.len());
     let mut rewriter =
@@ -58,14 +194,39 @@ pub fn replace_attributes(html: &str, settings: lol_html::Settings) -> Result<Ve
     Ok(output)
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OwnedAttribute {
     pub name: OwnedName,
     pub value: Arc<str>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OwnedName {
     pub namespace: Option<String>,
     pub tag: String,
 }
+
+impl OwnedAttribute {
+    pub(crate) fn from_attribute(attr: &roxmltree::Attribute<'_, '_>) -> Self {
+        OwnedAttribute {
+            name: OwnedName {
+                namespace: attr.namespace().map(|r| r.to_owned()),
+                tag: attr.name().to_owned(),
+            },
+            value: match attr.value_storage() {
+                roxmltree::StringStorage::Borrowed(val) => (*val).into(),
+                roxmltree::StringStorage::Owned(val) => val.clone(),
+            },
+        }
+    }
+
+    /// The attributes of `node`, except the ones without namespace named in `known`.
+    pub(crate) fn unknown(node: &roxmltree::Node<'_, '_>, known: &[&str]) -> Vec<Self> {
+        node.attributes()
+            .filter(|attr| attr.namespace().is_some() || !known.contains(&attr.name()))
+            .map(|attr| Self::from_attribute(&attr))
+            .collect()
+    }
+}
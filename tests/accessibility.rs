@@ -0,0 +1,117 @@
+mod common;
+
+use common::{opf, Fixture};
+use epub::accessibility::{AccessMode, AccessibilityMetadata, Hazard};
+
+/// The accessibility metadata of the DAISY "Accessible EPUB 3" sample.
+const DAISY_SAMPLE: &str = r##"
+    <meta property="schema:accessMode">textual</meta>
+    <meta property="schema:accessMode">visual</meta>
+    <meta property="schema:accessModeSufficient">textual,visual</meta>
+    <meta property="schema:accessModeSufficient">textual</meta>
+    <meta property="schema:accessibilityFeature">structuralNavigation</meta>
+    <meta property="schema:accessibilityFeature">MathML</meta>
+    <meta property="schema:accessibilityFeature">alternativeText</meta>
+    <meta property="schema:accessibilityFeature">longDescription</meta>
+    <meta property="schema:accessibilityFeature">readingOrder</meta>
+    <meta property="schema:accessibilityFeature">tableOfContents</meta>
+    <meta property="schema:accessibilityHazard">noFlashingHazard</meta>
+    <meta property="schema:accessibilityHazard">noSoundHazard</meta>
+    <meta property="schema:accessibilityHazard">noMotionSimulationHazard</meta>
+    <meta property="schema:accessibilitySummary">This publication conforms to the EPUB Accessibility specification at WCAG 2.0 Level AA.</meta>
+    <meta property="dcterms:conformsTo">EPUB Accessibility 1.1 - WCAG 2.0 Level AA</meta>
+    <meta property="a11y:certifiedBy" id="certifier">DAISY Consortium</meta>
+    <meta property="a11y:certifierCredential" refines="#certifier">DAISY OK</meta>
+    <meta property="a11y:certifierReport">https://example.com/report.html</meta>"##;
+
+#[test]
+fn daisy_sample() {
+    let doc = Fixture::new(opf("3.0", DAISY_SAMPLE, "", "<spine/>")).open();
+
+    assert_eq!(
+        AccessibilityMetadata {
+            access_modes: vec![AccessMode::Textual, AccessMode::Visual],
+            access_modes_sufficient: vec![
+                vec![AccessMode::Textual, AccessMode::Visual],
+                vec![AccessMode::Textual],
+            ],
+            features: vec![
+                "structuralNavigation".to_string(),
+                "MathML".to_string(),
+                "alternativeText".to_string(),
+                "longDescription".to_string(),
+                "readingOrder".to_string(),
+                "tableOfContents".to_string(),
+            ],
+            hazards: vec![
+                Hazard::NoFlashingHazard,
+                Hazard::NoSoundHazard,
+                Hazard::NoMotionSimulationHazard,
+            ],
+            summary: Some(
+                "This publication conforms to the EPUB Accessibility specification at WCAG 2.0 Level AA."
+                    .to_string()
+            ),
+            certified_by: vec!["DAISY Consortium".to_string()],
+            conforms_to: vec!["EPUB Accessibility 1.1 - WCAG 2.0 Level AA".to_string()],
+            other: vec![
+                ("a11y:certifierCredential".to_string(), "DAISY OK".to_string()),
+                (
+                    "a11y:certifierReport".to_string(),
+                    "https://example.com/report.html".to_string()
+                ),
+            ],
+        },
+        doc.accessibility()
+    );
+}
+
+#[test]
+fn epub2_combined_values() {
+    let metadata = r#"
+    <meta name="schema:accessMode" content="textual, visual auditory"/>
+    <meta name="schema:accessModeSufficient" content=" textual , chartOnVisual "/>
+    <meta name="schema:accessibilityFeature" content="alternativeText, tableOfContents"/>
+    <meta name="schema:accessibilityHazard" content="none"/>
+    <meta name="schema:accessibilityHazard" content="flashingHazardUnknown"/>
+    <meta name="schema:accessibilityAPI" content="ARIA"/>
+    <link rel="dcterms:conformsTo" href="http://www.idpf.org/epub/a11y/accessibility-20170105.html#wcag-aa"/>"#;
+    let doc = Fixture::new(opf("2.0", metadata, "", "<spine/>")).open();
+    let accessibility = doc.accessibility();
+
+    assert_eq!(
+        vec![
+            AccessMode::Textual,
+            AccessMode::Visual,
+            AccessMode::Auditory
+        ],
+        accessibility.access_modes
+    );
+    assert_eq!(
+        vec![vec![
+            AccessMode::Textual,
+            AccessMode::Other("chartOnVisual".to_string())
+        ]],
+        accessibility.access_modes_sufficient
+    );
+    assert_eq!(
+        vec!["alternativeText", "tableOfContents"],
+        accessibility.features
+    );
+    assert_eq!(
+        vec![
+            Hazard::None,
+            Hazard::Other("flashingHazardUnknown".to_string())
+        ],
+        accessibility.hazards
+    );
+    assert_eq!(
+        vec!["http://www.idpf.org/epub/a11y/accessibility-20170105.html#wcag-aa"],
+        accessibility.conforms_to
+    );
+    assert_eq!(
+        vec![("schema:accessibilityAPI".to_string(), "ARIA".to_string())],
+        accessibility.other
+    );
+    assert_eq!(None, accessibility.summary);
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "rewrite")]
+
+mod common;
+
+use common::{opf, xhtml, Fixture};
+use epub::error::ArchiveError;
+
+fn fixture() -> Fixture {
+    let manifest = r#"
+        <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+        <item id="c2" href="c2.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine><itemref idref="c1"/><itemref idref="c2"/></spine>"#;
+
+    Fixture::new(opf("3.0", "", manifest, spine))
+        .file(
+            "OEBPS/c1.xhtml",
+            r#"<html xmlns="http://www.w3.org/1999/xhtml"><body><h1 id="top">Title</h1><p>Some &amp; text<a id="ref1" href="c2.xhtml#note1">1</a></p><P ID="shout">é</P><span id="top">again</span><span id="top">and again</span></body></html>"#,
+        )
+        .file(
+            "OEBPS/c2.xhtml",
+            xhtml(r#"<aside id="note1" epub:type="footnote">Note</aside>"#),
+        )
+}
+
+#[test]
+fn anchors_per_chapter() {
+    let doc = fixture().open();
+    let anchors = doc.chapter_anchors(0).unwrap();
+
+    assert_eq!(3, anchors.len());
+    assert_eq!("h1", anchors["top"].element_name);
+    assert_eq!(0, anchors["top"].char_offset);
+    assert_eq!("a", anchors["ref1"].element_name);
+    assert_eq!(
+        "Title".len() + "Some &amp; text".len(),
+        anchors["ref1"].char_offset
+    );
+    assert_eq!("p", anchors["shout"].element_name);
+    assert_eq!(
+        anchors["ref1"].char_offset + 1,
+        anchors["shout"].char_offset
+    );
+
+    let notes = doc.chapter_anchors(1).unwrap();
+    assert_eq!(1, notes.len());
+    assert_eq!("aside", notes["note1"].element_name);
+    assert!(doc.has_anchor(1, "note1").unwrap());
+    assert!(!doc.has_anchor(1, "top").unwrap());
+}
+
+#[test]
+fn anchors_duplicates_keep_first() {
+    let doc = fixture().open();
+
+    assert_eq!(vec!["top".to_string()], doc.duplicate_anchors(0).unwrap());
+    assert!(doc.duplicate_anchors(1).unwrap().is_empty());
+    // Served from the cache the second time
+    assert_eq!(
+        doc.chapter_anchors(0).unwrap(),
+        doc.chapter_anchors(0).unwrap()
+    );
+}
+
+#[test]
+fn anchors_invalid_position() {
+    let doc = fixture().open();
+    assert!(matches!(
+        doc.chapter_anchors(2),
+        Err(ArchiveError::InvalidPosition)
+    ));
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use common::{opf, opf_with, xhtml, Fixture};
+use epub::anomalies::{HrefLocation, PathAnomalyKind};
+use epub::doc::EpubDoc;
+use epub::options::{ParseMode, ParseOptions};
+use std::io::Cursor;
+
+const NCX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <navMap>
+    <navPoint id="np-1" playOrder="1">
+      <navLabel><text>One</text></navLabel>
+      <content src="../Text/one.xhtml"/>
+    </navPoint>
+    <navPoint id="np-2" playOrder="2">
+      <navLabel><text>Two</text></navLabel>
+      <content src="../../../two.xhtml"/>
+    </navPoint>
+  </navMap>
+</ncx>"#;
+
+fn fixture() -> Fixture {
+    let manifest = r#"
+    <item id="ncx" href="Misc/toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="one" href="Text/one.xhtml" media-type="application/xhtml+xml"/>
+    <item id="two" href="Text\two.xhtml" media-type="application/xhtml+xml"/>
+    <item id="three" href="/OEBPS/Text/three.xhtml" media-type="application/xhtml+xml"/>
+    <item id="four" href="Text/chapter%20four.xhtml" media-type="application/xhtml+xml"/>
+    <item id="five" href="../../five.xhtml" media-type="application/xhtml+xml"/>
+    <item id="audio" href="https://example.com/audio.mp3" media-type="audio/mpeg"/>"#;
+    let guide = r#"<guide>
+    <reference type="text" title="Start" href="Text/one.xhtml#start"/>
+    <reference type="cover" title="Cover" href="Text\two.xhtml"/>
+  </guide>"#;
+    let spine = r#"<spine toc="ncx"><itemref idref="one"/></spine>"#;
+
+    Fixture::new(opf_with("2.0", "", "", manifest, spine, guide)).file("OEBPS/Misc/toc.ncx", NCX)
+}
+
+#[test]
+fn path_anomalies_counts() {
+    let doc = fixture().open();
+    let anomalies = doc.path_anomalies().unwrap();
+
+    let count = |location, kind| {
+        anomalies
+            .iter()
+            .filter(|a| a.location == location && a.kind == kind)
+            .count()
+    };
+
+    assert_eq!(1, count(HrefLocation::Manifest, PathAnomalyKind::Backslash));
+    assert_eq!(1, count(HrefLocation::Manifest, PathAnomalyKind::Absolute));
+    assert_eq!(
+        1,
+        count(HrefLocation::Manifest, PathAnomalyKind::PercentEncoded)
+    );
+    assert_eq!(
+        1,
+        count(HrefLocation::Manifest, PathAnomalyKind::EscapesRoot)
+    );
+    assert_eq!(1, count(HrefLocation::Manifest, PathAnomalyKind::Remote));
+    assert_eq!(1, count(HrefLocation::Guide, PathAnomalyKind::Backslash));
+    // `../Text/one.xhtml` is fine relative to the NCX, `../../../two.xhtml` is not.
+    assert_eq!(1, count(HrefLocation::Toc, PathAnomalyKind::EscapesRoot));
+    assert_eq!(7, anomalies.len());
+}
+
+#[test]
+fn path_anomalies_clean_book() {
+    let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    assert!(doc.path_anomalies().unwrap().is_empty());
+
+    let doc = EpubDoc::new("test.epub").unwrap();
+    let anomalies = doc.path_anomalies().unwrap();
+    assert_eq!(2, anomalies.len());
+    assert!(
+        anomalies
+            .iter()
+            .all(|a| a.location == HrefLocation::Manifest
+                && a.kind == PathAnomalyKind::PercentEncoded)
+    );
+}
+
+#[test]
+fn path_anomalies_of_undeclared_tocs() {
+    let toc_anomalies = |doc: &EpubDoc<_>| {
+        doc.path_anomalies()
+            .unwrap()
+            .into_iter()
+            .filter(|a| a.location == HrefLocation::Toc)
+            .map(|a| (a.href, a.kind))
+            .collect::<Vec<_>>()
+    };
+
+    // An NCX found by its media type, without a `toc` attribute on the spine
+    let manifest = r#"<item id="ncx" href="Misc/toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="one" href="Text/one.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine><itemref idref="one"/></spine>"#;
+    let doc = Fixture::new(opf("2.0", "", manifest, spine))
+        .file("OEBPS/Misc/toc.ncx", NCX)
+        .open();
+    assert_eq!(
+        vec![(
+            "../../../two.xhtml".to_string(),
+            PathAnomalyKind::EscapesRoot
+        )],
+        toc_anomalies(&doc)
+    );
+
+    // A nav without the `nav` property
+    let manifest = r#"<item id="contents" href="Text/nav.xhtml" media-type="application/xhtml+xml"/>
+    <item id="one" href="Text/one.xhtml" media-type="application/xhtml+xml"/>"#;
+    let nav = xhtml(r#"<nav epub:type="toc"><ol><li><a href="\one.xhtml">One</a></li></ol></nav>"#);
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/Text/nav.xhtml", nav)
+        .open();
+    assert_eq!(
+        vec![("\\one.xhtml".to_string(), PathAnomalyKind::Absolute)],
+        toc_anomalies(&doc)
+    );
+}
+
+#[test]
+fn path_anomalies_of_recovered_package() {
+    let manifest = r#"<item id="one" href="Text\one.xhtml" media-type="application/xhtml+xml"/>"#;
+    let package = opf(
+        "2.0",
+        "",
+        manifest,
+        r#"<spine><itemref idref="one"/></spine>"#,
+    )
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;");
+    let options = ParseOptions {
+        mode: ParseMode::Lenient,
+        ..Default::default()
+    };
+    let source = Fixture::new(package).build();
+    let doc = EpubDoc::from_reader_with_options(Cursor::new(source), options).unwrap();
+
+    let anomalies = doc.path_anomalies().unwrap();
+    assert_eq!(1, anomalies.len());
+    assert_eq!(PathAnomalyKind::Backslash, anomalies[0].kind);
+}
@@ -1,6 +1,6 @@
 use epub::archive::EpubArchive;
 use std::fs;
-use std::io::Write;
+use std::io::{Cursor, Write};
 
 #[test]
 fn archive_open() {
@@ -28,6 +28,28 @@ fn archive_entry_percent_encoding() {
     assert!(content.is_ok());
 }
 
+#[test]
+fn archive_contains() {
+    let mut archive = EpubArchive::new("test.epub").unwrap();
+
+    for name in [
+        "META-INF/container.xml",
+        "a%20%25%20encoded%20item.xml",
+        "a%20normal%20item.xml",
+        "a % encoded item.xml",
+    ] {
+        assert_eq!(
+            archive.get_entry(name).is_ok(),
+            archive.contains(name),
+            "{name}"
+        );
+        assert!(archive.contains(name), "{name}");
+    }
+
+    assert!(!archive.contains("META-INF/missing.xml"));
+    assert!(!archive.contains("a%20missing%20item.xml"));
+}
+
 #[test]
 fn archive_root_file() {
     let archive = EpubArchive::new("test.epub");
@@ -55,3 +77,122 @@ fn archive_bin_entry() {
     let resp = f.write_all(&content);
     assert!(resp.is_ok());
 }
+
+#[test]
+fn archive_read_entry_into() {
+    let mut archive = EpubArchive::new("test.epub").unwrap();
+    let mut buf = b"stale content".to_vec();
+
+    let read = archive
+        .read_entry_into("OEBPS/Text/titlepage.xhtml", &mut buf)
+        .unwrap();
+    assert_eq!(read, buf.len());
+    assert_eq!(
+        archive.get_entry("OEBPS/Text/titlepage.xhtml").unwrap(),
+        buf
+    );
+
+    assert!(archive.read_entry_into("not/here.xhtml", &mut buf).is_err());
+}
+
+/// A zip with a single stored `a.txt` entry of `hello`, whose central directory claims an
+/// uncompressed size of `u64::MAX` through a zip64 extra field.
+fn forged_size_zip() -> Vec<u8> {
+    let name = b"a.txt";
+    let data = b"hello";
+    let crc = 0x3610_a686_u32;
+
+    let mut zip = Vec::new();
+    // Local file header
+    zip.extend(0x0403_4b50_u32.to_le_bytes());
+    zip.extend([20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    zip.extend(crc.to_le_bytes());
+    zip.extend((data.len() as u32).to_le_bytes());
+    zip.extend((data.len() as u32).to_le_bytes());
+    zip.extend((name.len() as u16).to_le_bytes());
+    zip.extend(0_u16.to_le_bytes());
+    zip.extend(name);
+    zip.extend(data);
+
+    let central_offset = zip.len() as u32;
+    let mut extra = Vec::new();
+    extra.extend(0x0001_u16.to_le_bytes());
+    extra.extend(8_u16.to_le_bytes());
+    extra.extend(u64::MAX.to_le_bytes());
+
+    // Central directory record
+    zip.extend(0x0201_4b50_u32.to_le_bytes());
+    zip.extend([45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    zip.extend(crc.to_le_bytes());
+    zip.extend((data.len() as u32).to_le_bytes());
+    zip.extend(u32::MAX.to_le_bytes());
+    zip.extend((name.len() as u16).to_le_bytes());
+    zip.extend((extra.len() as u16).to_le_bytes());
+    zip.extend([0; 10]);
+    zip.extend(0_u32.to_le_bytes());
+    zip.extend(name);
+    zip.extend(&extra);
+    let central_size = zip.len() as u32 - central_offset;
+
+    // End of central directory
+    zip.extend(0x0605_4b50_u32.to_le_bytes());
+    zip.extend([0, 0, 0, 0, 1, 0, 1, 0]);
+    zip.extend(central_size.to_le_bytes());
+    zip.extend(central_offset.to_le_bytes());
+    zip.extend(0_u16.to_le_bytes());
+    zip
+}
+
+#[test]
+fn archive_forged_entry_size() {
+    let mut archive = EpubArchive::from_reader(Cursor::new(forged_size_zip())).unwrap();
+    let mut buf = Vec::new();
+
+    let read = archive.read_entry_into("a.txt", &mut buf).unwrap();
+    assert_eq!(read, 5);
+    assert_eq!(buf, b"hello");
+}
+
+/// A zip with a deflated `a.txt` and a stored `b.txt`.
+fn two_entry_zip() -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file("a.txt", zip::write::FileOptions::default())
+        .unwrap();
+    writer.write_all(&[b'a'; 1000]).unwrap();
+    let stored =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    writer.start_file("b.txt", stored).unwrap();
+    writer.write_all(b"bb").unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn archive_entry_sizes() {
+    let mut archive = EpubArchive::new("test.epub").unwrap();
+    let sizes = archive.entry_sizes();
+    assert_eq!(
+        Some(&(1186183, 1181285)),
+        sizes.get("OEBPS/Images/portada.png")
+    );
+    for (name, (size, _)) in &sizes {
+        assert_eq!(*size, archive.get_entry_size(name).unwrap(), "{name}");
+    }
+
+    // Only the central directory is read, so a broken local header doesn't matter
+    let mut zip = two_entry_zip();
+    zip[0..4].copy_from_slice(b"junk");
+    let archive = EpubArchive::from_reader(Cursor::new(zip)).unwrap();
+    let sizes = archive.entry_sizes();
+    assert_eq!(2, sizes.len());
+    assert_eq!(1000, sizes["a.txt"].0);
+    assert!(sizes["a.txt"].1 < 1000);
+    assert_eq!((2, 2), sizes["b.txt"]);
+
+    // Data prepended to the archive is skipped
+    let mut zip = b"prepended".to_vec();
+    zip.extend(two_entry_zip());
+    let mut archive = EpubArchive::from_reader(Cursor::new(zip)).unwrap();
+    assert_eq!((2, 2), archive.entry_sizes()["b.txt"]);
+    assert_eq!(b"bb".to_vec(), archive.get_entry("b.txt").unwrap());
+}
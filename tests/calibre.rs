@@ -0,0 +1,79 @@
+mod common;
+
+use common::{opf, Fixture};
+use epub::calibre::CalibreMetadata;
+
+/// The metadata of a book saved from a calibre 6 library, with two custom columns.
+const CALIBRE_METADATA: &str = r#"
+    <dc:creator opf:file-as="Dickens, Charles" opf:role="aut">Charles Dickens</dc:creator>
+    <dc:contributor opf:file-as="calibre" opf:role="bkp">calibre (6.29.0) [https://calibre-ebook.com]</dc:contributor>
+    <dc:identifier opf:scheme="calibre">3f2c7a9e-5b1d-4c8e-9a6f-1d2e3f4a5b6c</dc:identifier>
+    <meta name="calibre:author_link_map" content="{&quot;Charles Dickens&quot;: &quot;&quot;}"/>
+    <meta name="calibre:user_metadata:#genre" content="{&quot;table&quot;: &quot;custom_column_1&quot;, &quot;column&quot;: &quot;value&quot;, &quot;datatype&quot;: &quot;text&quot;, &quot;is_multiple&quot;: {}, &quot;name&quot;: &quot;Genre&quot;, &quot;#value#&quot;: &quot;Ghost story&quot;, &quot;#extra#&quot;: null}"/>
+    <meta name="calibre:user_metadata:#read" content="{&quot;table&quot;: &quot;custom_column_2&quot;, &quot;datatype&quot;: &quot;bool&quot;, &quot;name&quot;: &quot;Read&quot;, &quot;#value#&quot;: true}"/>
+    <meta name="calibre:series" content="Christmas Books"/>
+    <meta name="calibre:series_index" content="1.0"/>
+    <meta name="calibre:rating" content="8.0"/>
+    <meta name="calibre:timestamp" content="2023-12-24T18:30:12.345678+00:00"/>
+    <meta name="calibre:title_sort" content="Christmas Carol, A"/>"#;
+
+#[test]
+fn calibre_library_export() {
+    let doc = Fixture::new(opf("2.0", CALIBRE_METADATA, "", "<spine/>")).open();
+    let calibre = doc.calibre_metadata();
+
+    let timestamp = calibre.timestamp.unwrap();
+    assert_eq!(
+        (2023, Some(12), Some(24)),
+        (timestamp.year, timestamp.month, timestamp.day)
+    );
+    let time = timestamp.time.unwrap();
+    assert_eq!(
+        (18, 30, 12, Some(0)),
+        (time.hour, time.minute, time.second, time.utc_offset)
+    );
+    assert_eq!(Some(8.0), calibre.rating);
+    assert_eq!(Some("Christmas Carol, A"), calibre.title_sort.as_deref());
+    assert_eq!(
+        Some(r#"{"Charles Dickens": ""}"#),
+        calibre.author_link_map.as_deref()
+    );
+
+    assert_eq!(
+        vec!["#genre", "#read"],
+        calibre.user_metadata.keys().collect::<Vec<_>>()
+    );
+    assert!(calibre.user_metadata["#read"].ends_with(r##""#value#": true}"##));
+}
+
+#[test]
+fn missing_fields() {
+    let metadata = r#"
+    <meta name="calibre:rating" content="lots"/>
+    <meta name="calibre:timestamp" content="  "/>"#;
+    let doc = Fixture::new(opf("2.0", metadata, "", "<spine/>")).open();
+
+    assert_eq!(CalibreMetadata::default(), doc.calibre_metadata());
+}
+
+#[cfg(feature = "calibre")]
+#[test]
+fn user_metadata_json() {
+    let doc = Fixture::new(opf("2.0", CALIBRE_METADATA, "", "<spine/>")).open();
+    let calibre = doc.calibre_metadata();
+
+    let genre = calibre.user_metadata_json("#genre").unwrap().unwrap();
+    assert_eq!("Ghost story", genre["#value#"]);
+    assert_eq!("Genre", genre["name"]);
+    let read = calibre.user_metadata_json("#read").unwrap().unwrap();
+    assert_eq!(true, read["#value#"]);
+    assert!(calibre.user_metadata_json("#missing").is_none());
+
+    let metadata = r#"<meta name="calibre:user_metadata:#broken" content="{not json"/>"#;
+    let doc = Fixture::new(opf("2.0", metadata, "", "<spine/>")).open();
+    assert!(doc
+        .calibre_metadata()
+        .user_metadata_json("#broken")
+        .unwrap()
+        .is_err());
+}
@@ -0,0 +1,122 @@
+use epub::cancel::{CancelToken, ScanControl};
+use epub::doc::EpubDoc;
+use epub::error::ArchiveError;
+use epub::search::TextIndex;
+use std::sync::mpsc;
+use std::thread;
+
+const MOBY_DICK: &str = "tests/docs/herman-melville_moby-dick.epub";
+const WINNIE: &str = "tests/docs/winnie_the_pooh.epub";
+
+#[test]
+fn search_cancelled_from_another_thread() {
+    let doc = EpubDoc::new(MOBY_DICK).unwrap();
+    let token = CancelToken::new();
+
+    let (request, requests) = mpsc::channel::<()>();
+    let (ack, acks) = mpsc::channel::<()>();
+    let canceller = {
+        let token = token.clone();
+        thread::spawn(move || {
+            requests.recv().unwrap();
+            token.cancel();
+            ack.send(()).unwrap();
+        })
+    };
+
+    let mut progress = Vec::new();
+    let mut control = ScanControl::new()
+        .cancel_token(token.clone())
+        .on_progress(|done, total| {
+            progress.push((done, total));
+            if done == 2 {
+                request.send(()).unwrap();
+                acks.recv().unwrap();
+            }
+        });
+    let result = doc.search_with_control("whale", &mut control);
+    drop(control);
+    canceller.join().unwrap();
+
+    assert!(matches!(result, Err(ArchiveError::Cancelled)));
+    assert!(token.is_cancelled());
+    let total = doc.spine().len();
+    assert_eq!(vec![(1, total), (2, total)], progress);
+}
+
+#[test]
+fn cancelled_before_start() {
+    let doc = EpubDoc::new(WINNIE).unwrap();
+    let token = CancelToken::new();
+    token.cancel();
+
+    let mut calls = 0;
+    let mut control = ScanControl::new()
+        .cancel_token(token.clone())
+        .on_progress(|_, _| calls += 1);
+    assert!(matches!(
+        doc.search_with_control("pooh", &mut control),
+        Err(ArchiveError::Cancelled)
+    ));
+    assert!(matches!(
+        doc.unused_resources_with_control(&mut control),
+        Err(ArchiveError::Cancelled)
+    ));
+    drop(control);
+    assert_eq!(0, calls);
+
+    let mut index = TextIndex::new(&doc).unwrap();
+    let mut control = ScanControl::new().cancel_token(token);
+    assert!(matches!(
+        index.build_chapters_with_control(&doc, 3, &mut control),
+        Err(ArchiveError::Cancelled)
+    ));
+    assert_eq!(0, index.indexed_chapters());
+}
+
+#[test]
+fn progress_of_complete_scans() {
+    let doc = EpubDoc::new(WINNIE).unwrap();
+    let total = doc.spine().len();
+
+    let mut progress = Vec::new();
+    let mut control = ScanControl::new().on_progress(|done, total| progress.push((done, total)));
+    let hits = doc.search_with_control("pooh", &mut control).unwrap();
+    drop(control);
+    assert_eq!(doc.search("pooh").unwrap(), hits);
+    assert_eq!(
+        (1..=total).map(|done| (done, total)).collect::<Vec<_>>(),
+        progress
+    );
+
+    let mut index = TextIndex::new(&doc).unwrap();
+    let mut progress = Vec::new();
+    let mut control = ScanControl::new().on_progress(|done, total| progress.push((done, total)));
+    assert_eq!(
+        2,
+        index
+            .build_chapters_with_control(&doc, 2, &mut control)
+            .unwrap()
+    );
+    assert_eq!(
+        total - 2,
+        index
+            .build_chapters_with_control(&doc, total, &mut control)
+            .unwrap()
+    );
+    drop(control);
+    assert_eq!(
+        (1..=total).map(|done| (done, total)).collect::<Vec<_>>(),
+        progress
+    );
+
+    let mut progress = Vec::new();
+    let mut control = ScanControl::new().on_progress(|done, total| progress.push((done, total)));
+    let unused = doc.unused_resources_with_control(&mut control).unwrap();
+    drop(control);
+    assert_eq!(doc.unused_resources().unwrap(), unused);
+    let &(done, total) = progress.last().unwrap();
+    assert_eq!(done, total);
+    assert_eq!(doc.resources().len() - unused.len(), done);
+    assert!(progress.iter().all(|&(done, total)| done <= total));
+}
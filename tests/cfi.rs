@@ -0,0 +1,221 @@
+mod common;
+
+use common::Fixture;
+use epub::cfi::CfiBuilder;
+use epub::cfi::CfiStep;
+use epub::doc::EpubDoc;
+use epub::error::ArchiveError;
+
+#[test]
+fn cfi_for_chapter_without_itemref_ids() {
+    // metadata, manifest, spine -> the spine is the third element: /6
+    let doc = EpubDoc::new("test.epub").unwrap();
+
+    assert_eq!("epubcfi(/6/2!)", doc.cfi_for_chapter(0).unwrap());
+    assert_eq!("epubcfi(/6/8!)", doc.cfi_for_chapter(3).unwrap());
+    assert_eq!("epubcfi(/6/34!)", doc.cfi_for_chapter(16).unwrap());
+    assert_eq!(None, doc.cfi_for_chapter(17));
+}
+
+// Mirrors the example from the CFI specification, which epub.js reproduces as well.
+const SPEC_OPF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="uid">urn:uuid:fixture</dc:identifier>
+  </metadata>
+  <manifest>
+    <item id="titlepage" href="titlepage.xhtml" media-type="application/xhtml+xml"/>
+    <item id="chapter01" href="chapter01.xhtml" media-type="application/xhtml+xml"/>
+    <item id="chapter02" href="chapter02.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref id="titleref" idref="titlepage"/>
+    <itemref id="chap01ref" idref="chapter01"/>
+    <itemref idref="chapter02"/>
+  </spine>
+</package>"#;
+
+#[test]
+fn cfi_for_chapter_with_itemref_ids() {
+    let doc = Fixture::new(SPEC_OPF).open();
+
+    assert_eq!("epubcfi(/6/2[titleref]!)", doc.cfi_for_chapter(0).unwrap());
+    assert_eq!("epubcfi(/6/4[chap01ref]!)", doc.cfi_for_chapter(1).unwrap());
+    assert_eq!("epubcfi(/6/6!)", doc.cfi_for_chapter(2).unwrap());
+
+    let cfi = doc
+        .cfi_builder_for_chapter(1)
+        .unwrap()
+        .element(1, Some("body01"))
+        .element(4, Some("para05"))
+        .step(3, None)
+        .offset(10)
+        .build();
+    assert_eq!("epubcfi(/6/4[chap01ref]!/4[body01]/10[para05]/3:10)", cfi);
+}
+
+#[test]
+fn cfi_spine_position_follows_package_children() {
+    // A (non-standard) extra element before the spine shifts the spine step.
+    let opf = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0" unique-identifier="uid">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="uid">urn:uuid:fixture</dc:identifier>
+  </metadata>
+  <!-- comments are not elements -->
+  <manifest>
+    <item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <bindings/>
+  <spine><itemref idref="one"/></spine>
+</package>"#;
+    let doc = Fixture::new(opf).open();
+
+    assert_eq!("epubcfi(/8/2!)", doc.cfi_for_chapter(0).unwrap());
+}
+
+#[test]
+fn cfi_builder_escapes_assertions() {
+    let cfi = CfiBuilder::new().element(0, Some("a[b]^c,d")).build();
+    assert_eq!("epubcfi(/2[a^[b^]^^c^,d])", cfi);
+}
+
+fn step(index: usize, id: Option<&str>) -> CfiStep {
+    CfiStep {
+        index,
+        id: id.map(Into::into),
+    }
+}
+
+#[test]
+fn resolve_cfi_round_trip() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+
+    for i in 0..doc.get_num_pages() {
+        let cfi = doc.cfi_for_chapter(i).unwrap();
+        let location = doc.resolve_cfi(&cfi).unwrap();
+
+        assert_eq!(i, location.spine_index);
+        assert_eq!(doc.spine()[i].idref, location.spine_id);
+        assert!(location.steps.is_empty());
+        assert_eq!(None, location.offset);
+    }
+
+    let doc = Fixture::new(SPEC_OPF).open();
+    let cfi = doc
+        .cfi_builder_for_chapter(1)
+        .unwrap()
+        .element_path(&[1, 4])
+        .step(3, None)
+        .offset(10)
+        .build();
+    let location = doc.resolve_cfi(&cfi).unwrap();
+
+    assert_eq!(1, location.spine_index);
+    assert_eq!("chapter01", location.spine_id);
+    assert_eq!(
+        vec![step(4, None), step(10, None), step(3, None)],
+        location.steps
+    );
+    assert_eq!(Some(10), location.offset);
+}
+
+#[test]
+fn resolve_cfi_from_epubjs() {
+    let doc = Fixture::new(SPEC_OPF).open();
+
+    let location = doc
+        .resolve_cfi("epubcfi(/6/4[chap01ref]!/4[body01]/10[para05]/3:10)")
+        .unwrap();
+    assert_eq!(1, location.spine_index);
+    assert_eq!(
+        vec![
+            step(4, Some("body01")),
+            step(10, Some("para05")),
+            step(3, None)
+        ],
+        location.steps
+    );
+    assert_eq!(Some(4), location.steps[1].element_index());
+    assert_eq!(None, location.steps[2].element_index());
+    assert_eq!(Some(10), location.offset);
+
+    // Range, resolves to its start
+    let location = doc
+        .resolve_cfi("epubcfi(/6/4[chap01ref]!/4[body01]/10[para05],/2/1:1,/3:4)")
+        .unwrap();
+    assert_eq!(
+        vec![
+            step(4, Some("body01")),
+            step(10, Some("para05")),
+            step(2, None),
+            step(1, None)
+        ],
+        location.steps
+    );
+    assert_eq!(Some(1), location.offset);
+
+    // Text location assertion with side bias
+    let location = doc
+        .resolve_cfi("epubcfi(/6/2[titleref]!/4/2/1:0[;s=b])")
+        .unwrap();
+    assert_eq!(0, location.spine_index);
+    assert_eq!(Some(0), location.offset);
+
+    // Without indirection, pointing at the itemref itself
+    let location = doc.resolve_cfi("epubcfi(/6/6)").unwrap();
+    assert_eq!(2, location.spine_index);
+    assert_eq!("chapter02", location.spine_id);
+}
+
+#[test]
+fn resolve_cfi_prefers_id_assertion() {
+    let doc = Fixture::new(SPEC_OPF).open();
+
+    // The step points at the first itemref, the assertion at the second one
+    let location = doc.resolve_cfi("epubcfi(/6/2[chap01ref]!)").unwrap();
+    assert_eq!(1, location.spine_index);
+
+    // Unknown assertions fall back to the position
+    let location = doc.resolve_cfi("epubcfi(/6/6[unknown]!)").unwrap();
+    assert_eq!(2, location.spine_index);
+}
+
+#[test]
+fn resolve_cfi_escaped_assertion() {
+    let opf = SPEC_OPF.replace(r#"id="chap01ref""#, r#"id="a[b],c""#);
+    let doc = Fixture::new(&opf).open();
+
+    let cfi = doc.cfi_for_chapter(1).unwrap();
+    assert_eq!("epubcfi(/6/4[a^[b^]^,c]!)", cfi);
+    assert_eq!(1, doc.resolve_cfi(&cfi).unwrap().spine_index);
+}
+
+#[test]
+fn resolve_cfi_rejects_malformed_input() {
+    let doc = Fixture::new(SPEC_OPF).open();
+
+    for cfi in [
+        "",
+        "epubcfi(/6/4!",
+        "epubcfi(6/4!)",
+        "epubcfi(/6/x!)",
+        "epubcfi(/6/4[chap01ref!)",
+        "epubcfi(/6/4!/4!/2)",
+        "epubcfi(/6/4!/4:3/2)",
+        "epubcfi(/6/4:3)",
+        "epubcfi(/6/4!/4~2.5)",
+        "epubcfi(/6/4!/4@50:50)",
+        "epubcfi(/6/4!/4,/2)",
+        "epubcfi(/6!)",
+        "epubcfi(/6/4/2!)",
+        "epubcfi(/4/4!)",
+        "epubcfi(/6/8!)",
+        "epubcfi(/6/3!)",
+    ] {
+        assert!(
+            matches!(doc.resolve_cfi(cfi), Err(ArchiveError::InvalidCfi(_))),
+            "{cfi:?} should be rejected"
+        );
+    }
+}
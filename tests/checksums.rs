@@ -0,0 +1,43 @@
+mod common;
+
+use common::{opf, xhtml, Fixture};
+use epub::checksums::MISSING_CHECKSUM;
+
+const MANIFEST: &str = r#"
+    <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="c2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c3" href="c3.xhtml" media-type="application/xhtml+xml"/>"#;
+
+fn book(spine: &str) -> Fixture {
+    Fixture::new(opf("3.0", "", MANIFEST, spine))
+        .file("OEBPS/c1.xhtml", xhtml("<p>One</p>"))
+        .file("OEBPS/c2.xhtml", xhtml("<p>Two</p>"))
+}
+
+#[test]
+fn chapter_checksums_report_missing_entries() {
+    let doc = book(r#"<spine><itemref idref="c1"/><itemref idref="c3"/></spine>"#).open();
+    let checksums = doc.chapter_checksums();
+
+    assert_eq!(2, checksums.len());
+    assert_eq!("c1", checksums[0].0);
+    assert_ne!(MISSING_CHECKSUM, checksums[0].1);
+    assert_eq!(("c3".to_string(), MISSING_CHECKSUM), checksums[1]);
+}
+
+#[test]
+fn diff_chapters() {
+    let old = book(r#"<spine><itemref idref="c1"/><itemref idref="c2"/></spine>"#)
+        .open()
+        .chapter_checksums();
+
+    let doc = book(r#"<spine><itemref idref="c1"/><itemref idref="c3"/></spine>"#)
+        .file("OEBPS/c1.xhtml", xhtml("<p>One, revised</p>"))
+        .open();
+    let diff = doc.diff_chapters(&old);
+
+    assert_eq!(vec!["c3"], diff.added);
+    assert_eq!(vec!["c2"], diff.removed);
+    assert_eq!(vec!["c1"], diff.changed);
+    assert!(!diff.is_empty());
+}
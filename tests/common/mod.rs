@@ -0,0 +1,108 @@
+//! Helpers to build small in-memory epub fixtures for tests that need a very specific OPF.
+#![allow(dead_code)]
+
+use epub::doc::EpubDoc;
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+
+/// An epub which only exists in memory, with the root file at `OEBPS/content.opf`.
+pub struct Fixture {
+    files: Vec<(String, Vec<u8>)>,
+    modified: zip::DateTime,
+}
+
+impl Fixture {
+    pub fn new(opf: impl Into<String>) -> Self {
+        Fixture {
+            files: vec![
+                ("mimetype".into(), b"application/epub+zip".to_vec()),
+                (
+                    "META-INF/container.xml".into(),
+                    CONTAINER.as_bytes().to_vec(),
+                ),
+                ("OEBPS/content.opf".into(), opf.into().into_bytes()),
+            ],
+            modified: zip::DateTime::default(),
+        }
+    }
+
+    /// Add (or replace) a file in the archive.
+    pub fn file(mut self, path: &str, content: impl AsRef<[u8]>) -> Self {
+        self.files.retain(|(name, _)| name != path);
+        self.files.push((path.into(), content.as_ref().to_vec()));
+        self
+    }
+
+    /// Set the modification time of every file, 1980-01-01 00:00:00 by default.
+    pub fn modified(mut self, modified: zip::DateTime) -> Self {
+        self.modified = modified;
+        self
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .last_modified_time(self.modified);
+
+        for (name, content) in &self.files {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    pub fn open(&self) -> EpubDoc<Cursor<Vec<u8>>> {
+        EpubDoc::from_reader(Cursor::new(self.build())).unwrap()
+    }
+}
+
+/// Build a package document with the given version and section contents.
+pub fn opf(version: &str, metadata: &str, manifest: &str, spine: &str) -> String {
+    opf_with(version, "", metadata, manifest, spine, "")
+}
+
+/// Build a package document, with extra `package` attributes and trailing elements (e.g. a `guide`).
+pub fn opf_with(
+    version: &str,
+    package_attrs: &str,
+    metadata: &str,
+    manifest: &str,
+    spine: &str,
+    extra: &str,
+) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="{version}" unique-identifier="uid" {package_attrs}>
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+    <dc:identifier id="uid">urn:uuid:fixture</dc:identifier>
+    <dc:title>Fixture</dc:title>
+    {metadata}
+  </metadata>
+  <manifest>
+    {manifest}
+  </manifest>
+  {spine}
+  {extra}
+</package>"#
+    )
+}
+
+/// A minimal XHTML chapter with the given body.
+pub fn xhtml(body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head><title>Chapter</title></head>
+<body>{body}</body>
+</html>"#
+    )
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use common::{opf, Fixture};
+use epub::doc::EpubDoc;
+use epub::metadata::TitleKind;
+use epub::options::{MetadataDedup, ParseOptions};
+use std::io::Cursor;
+
+fn options(metadata_dedup: MetadataDedup) -> ParseOptions {
+    ParseOptions {
+        metadata_dedup,
+        ..Default::default()
+    }
+}
+
+#[cfg(feature = "encodings")]
+fn book2_titles(policy: MetadataDedup) -> (Vec<String>, Vec<String>) {
+    let doc = EpubDoc::new_with_options("tests/docs/book2.epub", options(policy)).unwrap();
+    let contents = |metadata: &std::collections::HashMap<String, Vec<epub::doc::MetadataNode>>| {
+        metadata["title"]
+            .iter()
+            .map(|node| node.content.clone())
+            .collect()
+    };
+
+    (contents(doc.metadata()), contents(doc.raw_metadata()))
+}
+
+#[cfg(feature = "encodings")]
+#[test]
+fn book2_keep_all() {
+    let doc = EpubDoc::new("tests/docs/book2.epub").unwrap();
+    assert_eq!(Some("Metamorphosis "), doc.mdata("title"));
+
+    let (titles, raw) = book2_titles(MetadataDedup::KeepAll);
+    assert_eq!(vec!["Metamorphosis ", "Metamorphosis2 "], titles);
+    assert_eq!(titles, raw);
+}
+
+#[cfg(feature = "encodings")]
+#[test]
+fn book2_first() {
+    let (titles, raw) = book2_titles(MetadataDedup::First);
+    assert_eq!(vec!["Metamorphosis "], titles);
+    assert_eq!(vec!["Metamorphosis ", "Metamorphosis2 "], raw);
+}
+
+#[cfg(feature = "encodings")]
+#[test]
+fn book2_last() {
+    let (titles, raw) = book2_titles(MetadataDedup::Last);
+    assert_eq!(vec!["Metamorphosis2 "], titles);
+    assert_eq!(vec!["Metamorphosis ", "Metamorphosis2 "], raw);
+}
+
+#[cfg(feature = "encodings")]
+#[test]
+fn book2_longest() {
+    let (titles, raw) = book2_titles(MetadataDedup::Longest);
+    assert_eq!(vec!["Metamorphosis2 "], titles);
+    assert_eq!(vec!["Metamorphosis ", "Metamorphosis2 "], raw);
+
+    let doc = EpubDoc::new_with_options("tests/docs/book2.epub", options(MetadataDedup::Longest))
+        .unwrap();
+    assert_eq!(1, doc.metadata()["creator"].len());
+}
+
+#[test]
+fn refinements_survive_dedup() {
+    let metadata = r##"
+    <dc:title id="main">A Long Main Title</dc:title>
+    <meta refines="#main" property="title-type">main</meta>
+    <dc:title id="sub">Short</dc:title>
+    <meta refines="#sub" property="title-type">subtitle</meta>
+    <dc:title id="ed">  The Longest Title Of Them All  </dc:title>
+    <meta refines="#ed" property="title-type">edition</meta>"##;
+    let fixture = Fixture::new(opf("3.0", metadata, "", "<spine/>"));
+    let open = |policy| {
+        EpubDoc::from_reader_with_options(Cursor::new(fixture.build()), options(policy)).unwrap()
+    };
+
+    let doc = open(MetadataDedup::Last);
+    assert_eq!(
+        Some("  The Longest Title Of Them All  "),
+        doc.mdata("title")
+    );
+    // Refining metas are never discarded, and still point at the right nodes
+    assert_eq!(3, doc.metadata()["title-type"].len());
+    // Along with the title of the fixture
+    assert_eq!(4, doc.raw_metadata()["title"].len());
+    let titles = doc.titles();
+    assert_eq!(1, titles.len());
+    assert_eq!(TitleKind::Edition, titles[0].kind);
+
+    let doc = open(MetadataDedup::Longest);
+    assert_eq!(Some("ed"), doc.mdata_full("title").unwrap().find_attr("id"));
+
+    let doc = open(MetadataDedup::First);
+    assert_eq!(Some("Fixture"), doc.mdata("title"));
+    assert_eq!(
+        vec!["title-type"],
+        doc.refinements_for("sub")
+            .iter()
+            .map(|r| r.property)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[cfg(feature = "encodings")]
+#[test]
+fn mutation_keeps_discarded() {
+    let mut doc =
+        EpubDoc::new_with_options("tests/docs/book2.epub", options(MetadataDedup::Last)).unwrap();
+    doc.set_mdata("title", "Metamorphosis3");
+    doc.add_mdata("title", epub::doc::MetadataNode::from_content("Added"));
+
+    let titles: Vec<_> = doc
+        .metadata_iter()
+        .filter(|(name, _)| *name == "title")
+        .map(|(_, node)| node.content.as_str())
+        .collect();
+    assert_eq!(vec!["Metamorphosis ", "Metamorphosis3", "Added"], titles);
+    assert_eq!(3, doc.raw_metadata()["title"].len());
+    assert_eq!(2, doc.metadata()["title"].len());
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use common::{opf_with, xhtml, Fixture};
+use epub::diff::{diff, MetadataChange, ResourceChange, SpineChange, TocChange};
+use epub::doc::EpubDoc;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+const MANIFEST: &str = r#"
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="c2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c3" href="c3.xhtml" media-type="application/xhtml+xml"/>"#;
+const SPINE: &str =
+    r#"<spine toc="ncx"><itemref idref="c1"/><itemref idref="c2"/><itemref idref="c3"/></spine>"#;
+
+fn ncx(labels: &[(&str, &str)]) -> String {
+    let points: String = labels
+        .iter()
+        .enumerate()
+        .map(|(i, (label, src))| {
+            format!(
+                r#"<navPoint id="n{i}" playOrder="{i}"><navLabel><text>{label}</text></navLabel><content src="{src}"/></navPoint>"#
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1"><navMap>{points}</navMap></ncx>"#
+    )
+}
+
+fn fixture() -> Fixture {
+    Fixture::new(opf_with("2.0", "", "", MANIFEST, SPINE, ""))
+        .file(
+            "OEBPS/toc.ncx",
+            ncx(&[("One", "c1.xhtml"), ("Two", "c2.xhtml")]),
+        )
+        .file("OEBPS/c1.xhtml", xhtml("<p>One</p>"))
+        .file("OEBPS/c2.xhtml", xhtml("<p>Two</p>"))
+        .file("OEBPS/c3.xhtml", xhtml("<p>Three</p>"))
+}
+
+#[test]
+fn diff_identical() {
+    let doc = fixture().open();
+    assert!(diff(&doc, &fixture().open()).is_empty());
+}
+
+#[test]
+fn diff_reports_intended_changes() {
+    let old = fixture().open();
+
+    let opf = old
+        .get_resource_str_by_path(&old.root_file)
+        .unwrap()
+        .replace("<dc:title>Fixture", "<dc:title>Revised")
+        .replace(
+            r#"<item id="c3" href="c3.xhtml" media-type="application/xhtml+xml"/>"#,
+            r#"<item id="c4" href="c4.xhtml" media-type="application/xhtml+xml"/>"#,
+        )
+        .replace(
+            SPINE,
+            r#"<spine toc="ncx"><itemref idref="c2"/><itemref idref="c1"/><itemref idref="c4"/></spine>"#,
+        );
+    let mut output = Cursor::new(Vec::new());
+    old.writer()
+        .replace(&old.root_file, opf)
+        .replace("OEBPS/c2.xhtml", xhtml("<p>Two, revised</p>"))
+        .replace("OEBPS/c4.xhtml", xhtml("<p>Four</p>"))
+        .replace(
+            "OEBPS/toc.ncx",
+            ncx(&[
+                ("One", "c1.xhtml"),
+                ("Second", "c2.xhtml"),
+                ("Four", "c4.xhtml"),
+            ]),
+        )
+        .save(&mut output)
+        .unwrap();
+    output.set_position(0);
+    let new = EpubDoc::from_reader(output).unwrap();
+
+    let diff = diff(&old, &new);
+    assert_eq!(
+        vec![MetadataChange {
+            name: "title".to_string(),
+            old: vec!["Fixture".to_string()],
+            new: vec!["Revised".to_string()],
+        }],
+        diff.metadata
+    );
+    assert_eq!(
+        vec![
+            ResourceChange::Modified {
+                id: "c2".to_string(),
+                path: PathBuf::from("OEBPS/c2.xhtml")
+            },
+            ResourceChange::Removed {
+                id: "c3".to_string(),
+                path: PathBuf::from("OEBPS/c3.xhtml")
+            },
+            ResourceChange::Added {
+                id: "c4".to_string(),
+                path: PathBuf::from("OEBPS/c4.xhtml")
+            },
+            ResourceChange::Modified {
+                id: "ncx".to_string(),
+                path: PathBuf::from("OEBPS/toc.ncx")
+            },
+        ],
+        diff.resources
+    );
+    assert_eq!(
+        vec![
+            SpineChange::Moved {
+                idref: "c1".to_string(),
+                from: 0,
+                to: 1
+            },
+            SpineChange::Removed {
+                idref: "c3".to_string(),
+                index: 2
+            },
+            SpineChange::Added {
+                idref: "c4".to_string(),
+                index: 2
+            },
+        ],
+        diff.spine
+    );
+    assert_eq!(
+        vec![
+            TocChange::Relabeled {
+                position: vec![1],
+                old: "Two".to_string(),
+                new: "Second".to_string()
+            },
+            TocChange::Added {
+                position: vec![2],
+                label: "Four".to_string()
+            },
+        ],
+        diff.toc
+    );
+
+    let printed = diff.to_string();
+    assert!(printed.contains("~ metadata title: [\"Fixture\"] -> [\"Revised\"]"));
+    assert!(printed.contains("- resource OEBPS/c3.xhtml"));
+    assert!(printed.contains("~ spine c1 moved from 0 to 1"));
+}
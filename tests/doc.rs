@@ -1,5 +1,9 @@
-use epub::doc::EpubDoc;
-use std::path::Path;
+mod common;
+
+use common::{opf, xhtml, Fixture};
+use epub::doc::{EpubDoc, NavPoint, RemoteResource, ResourceItem};
+use epub::error::ArchiveError;
+use std::path::{Path, PathBuf};
 
 #[test]
 fn doc_open() {
@@ -8,19 +12,19 @@ fn doc_open() {
     assert_eq!(Path::new("OEBPS"), doc.root_base);
     assert_eq!(Path::new("OEBPS/content.opf"), doc.root_file);
 
-    assert_eq!(23, doc.context.resources.len());
+    assert_eq!(23, doc.resources().len());
     {
-        let tpage = doc.context.resources.get("titlepage.xhtml");
+        let tpage = doc.resources().get("titlepage.xhtml");
         assert_eq!(tpage.unwrap().path, Path::new("OEBPS/Text/titlepage.xhtml"));
     }
 
     {
-        assert_eq!(17, doc.context.spine.len());
-        assert_eq!("titlepage.xhtml", doc.context.spine[0]);
+        assert_eq!(17, doc.spine().len());
+        assert_eq!("titlepage.xhtml", doc.spine()[0].idref);
     }
 
     {
-        let unique_identifier = doc.context.unique_identifier.clone();
+        let unique_identifier = doc.unique_identifier();
         assert_eq!(
             unique_identifier.unwrap(),
             "urn:uuid:09132750-3601-4d19-b3a4-55fdf8639849"
@@ -48,10 +52,14 @@ fn doc_open() {
             release_identifier.unwrap(),
             "urn:uuid:09132750-3601-4d19-b3a4-55fdf8639849@2015-08-10T18:12:03Z"
         );
+        assert_eq!(
+            doc.get_release_identifier(),
+            doc.release_identifier_strict()
+        );
     }
 
     {
-        let unique_identifier = doc2.context.unique_identifier.clone();
+        let unique_identifier = doc2.unique_identifier();
         assert_eq!(
             "http://metamorphosiskafka.pressbooks.com",
             unique_identifier.unwrap()
@@ -59,8 +67,12 @@ fn doc_open() {
     }
 
     {
-        let release_identifier = doc2.get_release_identifier();
-        assert_eq!(None, release_identifier);
+        // Without a modification date only the strict release identifier is missing
+        assert_eq!(None, doc2.release_identifier_strict());
+        assert_eq!(
+            Some("http://metamorphosiskafka.pressbooks.com"),
+            doc2.get_release_identifier().as_deref()
+        );
     }
 }
 
@@ -70,13 +82,698 @@ fn toc_test() {
     assert!(doc.is_ok());
     let doc = doc.unwrap();
 
-    assert!(!doc.context.toc.is_empty());
+    assert!(!doc.get_toc().is_empty());
 
     assert_eq!(doc.get_toc().get(2).unwrap().label, "Vestidor");
 
-    for nav in doc.context.toc.iter() {
-        let chapter = doc.resource_uri_to_chapter(&nav.content);
+    for nav in doc.get_toc().iter() {
+        let chapter = doc.resource_uri_to_chapter(nav.content.as_ref().unwrap());
         assert!(chapter.is_some());
         assert_eq!(nav.play_order, chapter.unwrap());
     }
 }
+
+#[test]
+fn go_to_toc_entry() {
+    let mut doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+
+    fn flatten(navs: &[NavPoint], out: &mut Vec<NavPoint>) {
+        for nav in navs {
+            out.push(nav.clone());
+            flatten(&nav.children, out);
+        }
+    }
+    let mut entries = Vec::new();
+    flatten(doc.get_toc(), &mut entries);
+    assert!(!entries.is_empty());
+
+    for nav in entries.iter().rev() {
+        let fragment = doc.go_to_toc_entry(nav).unwrap();
+        assert_eq!(None, fragment);
+
+        let current_id = doc.get_current_id().unwrap().to_string();
+        let content = nav.content.as_ref().unwrap();
+        assert_eq!(&doc.resources()[&current_id].path, content);
+        assert_eq!(
+            doc.resource_uri_to_chapter(content),
+            Some(doc.get_current_chapter())
+        );
+    }
+}
+
+#[test]
+fn go_to_toc_entry_fragment_and_errors() {
+    let mut doc = EpubDoc::new("test.epub").unwrap();
+    let mut nav = doc.get_toc()[3].clone();
+    nav.content = Some(Path::new("OEBPS/Text/003.xhtml").to_path_buf());
+    nav.fragment = Some("section-2".to_string());
+
+    assert_eq!(
+        Some("section-2".to_string()),
+        doc.go_to_toc_entry(&nav).unwrap()
+    );
+    assert_eq!(Some("003.xhtml"), doc.get_current_id());
+
+    // Out of spine resources are rejected, leaving the current chapter untouched
+    nav.content = Some(Path::new("OEBPS/Images/cc.png").to_path_buf());
+    assert!(matches!(
+        doc.go_to_toc_entry(&nav),
+        Err(ArchiveError::NotInSpine(_))
+    ));
+    assert_eq!(Some("003.xhtml"), doc.get_current_id());
+}
+
+#[test]
+fn has_resource() {
+    let manifest = r#"<item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>
+    <item id="missing" href="missing.png" media-type="image/png"/>
+    <item id="spaced" href="with%20space.css" media-type="text/css"/>"#;
+    let spine = r#"<spine><itemref idref="one"/></spine>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/one.xhtml", xhtml(""))
+        .file("OEBPS/with space.css", "p {}")
+        .open();
+
+    for id in ["one", "missing", "spaced", "not-in-manifest"] {
+        assert_eq!(doc.get_resource(id).is_some(), doc.has_resource(id), "{id}");
+    }
+    assert!(doc.has_resource("one"));
+    assert!(doc.has_resource("spaced"));
+    assert!(!doc.has_resource("missing"));
+    assert!(!doc.has_resource("not-in-manifest"));
+
+    assert!(doc.has_resource_path("OEBPS/content.opf"));
+    assert!(doc.has_resource_path("OEBPS/with%20space.css"));
+    assert!(!doc.has_resource_path("OEBPS/missing.png"));
+}
+
+#[test]
+fn doc_debug() {
+    #[derive(Debug)]
+    struct Wrapper(EpubDoc<std::io::BufReader<std::fs::File>>);
+
+    let wrapper = Wrapper(EpubDoc::new("test.epub").unwrap());
+    assert_eq!(17, wrapper.0.get_num_pages());
+    let debug = format!("{wrapper:?}");
+    assert!(debug.starts_with("Wrapper(EpubDoc {"));
+    assert!(debug.contains("spine_len: 17"));
+    assert!(debug.contains("cover_id: Some(\"portada.png\")"));
+
+    let archive = epub::archive::EpubArchive::new("test.epub").unwrap();
+    assert!(format!("{archive:?}").starts_with("EpubArchive { entries: Some("));
+}
+
+#[test]
+fn doc_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<EpubDoc<std::io::BufReader<std::fs::File>>>();
+    assert_send_sync::<EpubDoc<std::io::Cursor<Vec<u8>>>>();
+
+    let doc = std::sync::Arc::new(EpubDoc::new("test.epub").unwrap());
+    let handles: Vec<_> = (0..doc.get_num_pages())
+        .map(|chapter| {
+            let doc = doc.clone();
+            std::thread::spawn(move || {
+                let id = &doc.spine()[chapter].idref;
+                (chapter, doc.get_resource(id).unwrap())
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        let (chapter, content) = handle.join().unwrap();
+        let id = &doc.spine()[chapter].idref;
+        assert_eq!(doc.get_resource(id).unwrap(), content);
+    }
+}
+
+#[test]
+fn doc_into_parts() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+    let cover = doc.get_resource("portada.png").unwrap();
+
+    let (metadata, archive) = doc.into_parts();
+    assert_eq!(17, metadata.spine.len());
+
+    let doc = EpubDoc::from_parts(metadata, archive).unwrap();
+    assert_eq!(Path::new("OEBPS/content.opf"), doc.root_file);
+    assert_eq!(Some(cover), doc.get_resource("portada.png"));
+    assert_eq!(Some("Todo es mío"), doc.mdata("title"));
+
+    let reader = doc.into_inner();
+    let doc = EpubDoc::from_reader(reader).unwrap();
+    assert_eq!(17, doc.get_num_pages());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+#[allow(deprecated)]
+fn context_round_trip() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+
+    let json = serde_json::to_string(&doc.context).unwrap();
+    assert!(json.contains(r#""path":"OEBPS/Text/titlepage.xhtml""#));
+
+    let restored: epub::doc::EpubMetadata = serde_json::from_str(&json).unwrap();
+    assert_eq!(doc.context.toc[0].content, restored.toc[0].content);
+
+    // The path index isn't serialized, restoring the doc rebuilds it
+    let archive = epub::archive::EpubArchive::new("test.epub").unwrap();
+    let restored = EpubDoc::from_parts(restored, archive).unwrap();
+    assert_eq!(doc.context, restored.context);
+    assert_eq!(
+        Some("portada.png"),
+        restored.resource_id_by_path("OEBPS/Images/portada.png")
+    );
+}
+
+#[test]
+fn malformed_cover_meta() {
+    let manifest = r#"
+    <item id="cover-image" href="Images/cover.jpg" media-type="image/jpeg"/>
+    <item id="other" href="Images/Other/cover.jpg" media-type="image/jpeg"/>"#;
+    let open = |meta: &str| {
+        Fixture::new(opf("2.0", meta, manifest, "<spine/>"))
+            .file("OEBPS/Images/cover.jpg", "jpeg")
+            .open()
+    };
+
+    // `value` instead of `content`
+    let doc = open(r#"<meta name="cover" value="cover-image"/>"#);
+    assert_eq!(Some("cover-image"), doc.mdata("cover"));
+    assert_eq!(Some("cover-image"), doc.get_cover_id());
+    assert_eq!(Some(b"jpeg".to_vec()), doc.get_cover());
+
+    // The href instead of the id, relative to the package document or the root of the epub
+    for href in [
+        "Images/cover.jpg",
+        "./Images/cover.jpg",
+        "OEBPS/Images/cover.jpg",
+    ] {
+        let doc = open(&format!(r#"<meta name="cover" content="{href}"/>"#));
+        assert_eq!(Some("cover-image"), doc.get_cover_id(), "{href}");
+        assert_eq!(Some(b"jpeg".to_vec()), doc.get_cover());
+    }
+
+    for content in ["Images/missing.jpg", "", "/"] {
+        let doc = open(&format!(r#"<meta name="cover" content="{content}"/>"#));
+        assert_eq!(None, doc.get_cover_id(), "{content}");
+    }
+}
+
+#[test]
+fn toc_fragments() {
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="c2.xhtml" media-type="application/xhtml+xml"/>"#;
+    let itemrefs = r#"<itemref idref="c1"/><itemref idref="c2"/>"#;
+    let ncx = r##"<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1"><navMap>
+      <navPoint playOrder="1"><navLabel><text>Start</text></navLabel><content src="c1.xhtml"/></navPoint>
+      <navPoint playOrder="2"><navLabel><text>First</text></navLabel><content src="c1.xhtml#sec-1"/></navPoint>
+      <navPoint playOrder="3"><navLabel><text>Second</text></navLabel><content src="c1.xhtml#sec%202"/></navPoint>
+      <navPoint playOrder="4"><navLabel><text>Empty</text></navLabel><content src="c2.xhtml#"/></navPoint>
+    </navMap></ncx>"##;
+    let nav = r##"<nav epub:type="toc"><ol>
+      <li><a href="c1.xhtml">Start</a></li>
+      <li><a href="c1.xhtml#sec-1">First</a></li>
+      <li><a href="c1.xhtml#sec%202">Second</a></li>
+      <li><a href="c2.xhtml#">Empty</a></li>
+    </ol></nav>"##;
+
+    // EPUB2 books only have the NCX, the nav is preferred otherwise
+    let spine = format!(r#"<spine toc="ncx">{itemrefs}</spine>"#);
+    for version in ["2.0", "3.0"] {
+        let mut doc = Fixture::new(opf(version, "", manifest, &spine))
+            .file("OEBPS/toc.ncx", ncx)
+            .file("OEBPS/nav.xhtml", xhtml(nav))
+            .open();
+
+        let targets: Vec<_> = doc
+            .get_toc()
+            .iter()
+            .map(|nav| {
+                (
+                    doc.resource_uri_to_chapter(nav.content.as_ref().unwrap()),
+                    nav.fragment.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (Some(0), None),
+                (Some(0), Some("sec-1")),
+                (Some(0), Some("sec 2")),
+                (Some(1), None),
+            ],
+            targets,
+            "{version}"
+        );
+
+        let nav = doc.get_toc()[2].clone();
+        assert_eq!(Some(Path::new("OEBPS/c1.xhtml")), nav.content.as_deref());
+        assert_eq!(
+            Some("sec 2".to_string()),
+            doc.go_to_toc_entry(&nav).unwrap()
+        );
+    }
+}
+
+#[test]
+fn toc_relative_to_its_document() {
+    let manifest = r#"<item id="ncx" href="Misc/toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="nav" href="Text/nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="c1" href="Text/c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="Text/Part2/c2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="notes" href="notes.xhtml" media-type="application/xhtml+xml"/>"#;
+    let itemrefs = r#"<itemref idref="c1"/><itemref idref="c2"/><itemref idref="notes"/>"#;
+    let ncx = r##"<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1"><navMap>
+      <navPoint playOrder="1"><navLabel><text>One</text></navLabel><content src="../Text/c1.xhtml"/></navPoint>
+      <navPoint playOrder="2"><navLabel><text>Two</text></navLabel><content src="../Text/Part2/c2.xhtml#s"/></navPoint>
+      <navPoint playOrder="3"><navLabel><text>Notes</text></navLabel><content src="../notes.xhtml"/></navPoint>
+    </navMap></ncx>"##;
+    let nav = r##"<nav epub:type="toc"><ol>
+      <li><a href="c1.xhtml">One (nav)</a></li>
+      <li><a href="./Part2/c2.xhtml#s">Two</a></li>
+      <li><a href="../notes.xhtml">Notes</a></li>
+    </ol></nav>"##;
+
+    // EPUB2 books only have the NCX, the nav is preferred otherwise
+    let spine = format!(r#"<spine toc="ncx">{itemrefs}</spine>"#);
+    for version in ["2.0", "3.0"] {
+        let doc = Fixture::new(opf(version, "", manifest, &spine))
+            .file("OEBPS/Misc/toc.ncx", ncx)
+            .file("OEBPS/Text/nav.xhtml", xhtml(nav))
+            .open();
+
+        let paths: Vec<_> = doc
+            .get_toc()
+            .iter()
+            .map(|nav| nav.content.as_deref())
+            .collect();
+        assert_eq!(
+            vec![
+                Some(Path::new("OEBPS/Text/c1.xhtml")),
+                Some(Path::new("OEBPS/Text/Part2/c2.xhtml")),
+                Some(Path::new("OEBPS/notes.xhtml")),
+            ],
+            paths,
+            "{version}"
+        );
+        let from_nav = doc.get_toc()[0].label.ends_with("(nav)");
+        assert_eq!(version == "3.0", from_nav);
+        for (id, nav) in ["c1", "c2", "notes"].iter().zip(doc.get_toc()) {
+            assert_eq!(Some(&doc.resources()[*id].path), nav.content.as_ref());
+        }
+    }
+}
+
+#[test]
+fn dual_toc() {
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="c2.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine toc="ncx"><itemref idref="c1"/><itemref idref="c2"/></spine>"#;
+    let ncx = r##"<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1"><navMap>
+      <navPoint playOrder="1"><navLabel><text>Part</text></navLabel><content src="c1.xhtml"/></navPoint>
+      <navPoint playOrder="2"><navLabel><text>Chapter</text></navLabel><content src="c2.xhtml"/></navPoint>
+    </navMap></ncx>"##;
+    let nav = r##"<nav epub:type="toc"><ol>
+      <li><a href="c1.xhtml">Part</a><ol><li><a href="c2.xhtml">Chapter</a></li></ol></li>
+    </ol></nav>"##;
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/toc.ncx", ncx)
+        .file("OEBPS/nav.xhtml", xhtml(nav))
+        .open();
+
+    assert_eq!(2, doc.get_toc_ncx().len());
+    assert!(doc.get_toc_ncx()[0].children.is_empty());
+    assert_eq!(1, doc.get_toc_nav().len());
+    assert_eq!("Chapter", doc.get_toc_nav()[0].children[0].label);
+    assert_eq!(doc.get_toc_nav(), doc.get_toc().as_slice());
+
+    // A broken nav falls back to the NCX
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/toc.ncx", ncx)
+        .file("OEBPS/nav.xhtml", "<html")
+        .open();
+    assert!(doc.get_toc_nav().is_empty());
+    assert_eq!(doc.get_toc_ncx(), doc.get_toc().as_slice());
+    assert!(!doc.partial_failures().is_empty());
+}
+
+#[test]
+fn resources_by_mime() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+    let mut images: Vec<_> = doc.images().map(|(id, _)| id).collect();
+    images.sort();
+    assert_eq!(vec!["cc.png", "portada.png"], images);
+    assert_eq!(1, doc.stylesheets().count());
+    assert_eq!(19, doc.resources_by_mime("application/xhtml+xml").count());
+    assert_eq!(0, doc.fonts().count());
+
+    let manifest = r#"<item id="otf" href="a.otf" media-type="font/otf"/>
+    <item id="ttf" href="b.ttf" media-type="application/x-font-ttf"/>
+    <item id="legacy" href="c.otf" media-type="Application/vnd.ms-opentype"/>
+    <item id="css" href="style.css" media-type="TEXT/CSS; charset=utf-8"/>
+    <item id="svg" href="image.svg" media-type="image/svg+xml"/>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>")).open();
+
+    let mut fonts: Vec<_> = doc.fonts().map(|(id, _)| id).collect();
+    fonts.sort();
+    assert_eq!(vec!["legacy", "otf", "ttf"], fonts);
+    assert_eq!(
+        "style.css",
+        doc.stylesheets()
+            .next()
+            .unwrap()
+            .1
+            .path
+            .file_name()
+            .unwrap()
+    );
+    assert_eq!(1, doc.resources_by_mime("IMAGE/*").count());
+    assert_eq!(0, doc.resources_by_mime("image/svg").count());
+    assert_eq!(5, doc.resources_by_mime("*").count());
+}
+
+#[test]
+fn fallback_chain() {
+    let manifest = r#"<item id="page" href="page.svg" media-type="image/svg+xml" fallback="webp"/>
+    <item id="webp" href="page.webp" media-type="image/webp" fallback="png"/>
+    <item id="png" href="page.png" media-type="image/png"/>
+    <item id="a" href="a.dat" media-type="application/x-a" fallback="b"/>
+    <item id="b" href="b.dat" media-type="application/x-b" fallback="a"/>
+    <item id="dangling" href="c.dat" media-type="application/x-c" fallback="missing"/>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>")).open();
+
+    assert_eq!(Some("webp"), doc.resources()["page"].fallback.as_deref());
+    assert!(doc.resources()["page"].extra_attrs.is_empty());
+
+    let resolve = |id: &str, supported: &[&str]| {
+        doc.resolve_fallback_chain(id, |mime| supported.contains(&mime))
+            .map(|item| item.path.to_str().unwrap())
+    };
+    assert_eq!(Some("OEBPS/page.svg"), resolve("page", &["image/svg+xml"]));
+    assert_eq!(Some("OEBPS/page.png"), resolve("page", &["image/png"]));
+    assert_eq!(
+        Some("OEBPS/page.webp"),
+        resolve("webp", &["image/png", "image/webp"])
+    );
+    assert_eq!(None, resolve("page", &["image/gif"]));
+
+    // A cycle ends the chain, and so does a dangling reference
+    assert_eq!(Some("OEBPS/b.dat"), resolve("a", &["application/x-b"]));
+    assert_eq!(None, resolve("a", &["image/png"]));
+    assert_eq!(None, resolve("dangling", &["image/png"]));
+    assert_eq!(None, resolve("missing", &["image/png"]));
+}
+
+#[test]
+fn manifest_href_fragments() {
+    let manifest = r#"<item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="part1" href="notes.xhtml#part1" media-type="application/xhtml+xml"/>
+    <item id="part2" href="notes.xhtml#part2" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine><itemref idref="c1"/></spine>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/c1.xhtml", xhtml(r#"<a href="notes.xhtml#n1">1</a>"#))
+        .file("OEBPS/notes.xhtml", xhtml("<p id=\"n1\">Note</p>"))
+        .open();
+
+    for id in ["part1", "part2"] {
+        assert_eq!(Path::new("OEBPS/notes.xhtml"), doc.resources()[id].path);
+        assert!(doc.get_resource(id).is_some());
+    }
+    let warnings: Vec<_> = doc
+        .parse_warnings()
+        .iter()
+        .map(|warning| warning.message.as_str())
+        .collect();
+    assert_eq!(2, warnings.len());
+    assert!(warnings[0].contains("has a fragment"));
+
+    // A reference to the file reaches both items
+    assert!(doc.unused_resources().unwrap().is_empty());
+}
+
+#[test]
+fn manifest_href_dot_segments() {
+    let manifest = r#"<item id="c1" href="./c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="cover" href="../Images/cover.jpg" media-type="image/jpeg"/>
+    <item id="c2" href="Text/../Text/./c2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="css" href="../../style.css" media-type="text/css"/>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>"))
+        .file("OEBPS/c1.xhtml", xhtml("<p>One</p>"))
+        .file("Images/cover.jpg", "jpeg")
+        .file("OEBPS/Text/c2.xhtml", xhtml("<p>Two</p>"))
+        .file("style.css", "p {}")
+        .open();
+
+    let path = |id: &str| doc.resources()[id].path.to_str().unwrap();
+    assert_eq!("OEBPS/c1.xhtml", path("c1"));
+    assert_eq!("Images/cover.jpg", path("cover"));
+    assert_eq!("OEBPS/Text/c2.xhtml", path("c2"));
+    for id in ["c1", "cover", "c2"] {
+        assert!(doc.get_resource(id).is_some());
+    }
+
+    // Escaping the root of the epub is clamped to it
+    assert_eq!("style.css", path("css"));
+    assert_eq!(Some(b"p {}".to_vec()), doc.get_resource("css"));
+    assert_eq!(1, doc.parse_warnings().len());
+    assert!(doc.parse_warnings()[0].message.contains("outside the epub"));
+}
+
+#[test]
+fn manifest_absolute_hrefs() {
+    let manifest = r#"<item id="c1" href="/OEBPS/Text/c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="Text/c2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="cover" href="/Images/cover.jpg" media-type="image/jpeg"/>"#;
+    let spine = r#"<spine><itemref idref="c1"/><itemref idref="c2"/></spine>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/Text/c1.xhtml", xhtml("<p>One</p>"))
+        .file("OEBPS/Text/c2.xhtml", xhtml("<p>Two</p>"))
+        .file("Images/cover.jpg", "jpeg")
+        .open();
+
+    let path = |id: &str| doc.resources()[id].path.to_str().unwrap();
+    assert_eq!("OEBPS/Text/c1.xhtml", path("c1"));
+    assert_eq!("OEBPS/Text/c2.xhtml", path("c2"));
+    assert_eq!("Images/cover.jpg", path("cover"));
+    for id in ["c1", "c2", "cover"] {
+        assert!(doc.get_resource(id).is_some());
+    }
+    assert!(doc.parse_warnings().is_empty());
+}
+
+#[test]
+fn manifest_missing_media_types() {
+    let manifest = r#"<item id="c1" href="c1.xhtml"/>
+    <item id="c2" href="c2.xhtml" media-type="text/plain"/>
+    <item id="data" href="data.bin"/>"#;
+    let spine = r#"<spine><itemref idref="c1"/><itemref idref="c2"/></spine>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/c1.xhtml", xhtml("<p>One</p>"))
+        .file("OEBPS/c2.xhtml", xhtml("<p>Two</p>"))
+        .open();
+
+    // The chapter without a media type is kept, and so is its place in the spine
+    assert_eq!(2, doc.spine().len());
+    assert_eq!("c1", doc.spine()[0].idref);
+    assert_eq!("application/xhtml+xml", doc.resources()["c1"].mime);
+    assert!(doc.get_resource_str("c1").unwrap().contains("One"));
+    assert_eq!("application/octet-stream", doc.resources()["data"].mime);
+    assert_eq!(2, doc.parse_warnings().len());
+
+    // A declared media type is kept, even if it's wrong
+    let c2 = &doc.resources()["c2"];
+    assert_eq!("text/plain", c2.mime);
+    assert_eq!(Some("application/xhtml+xml"), c2.inferred_mime());
+    assert_eq!(None, doc.resources()["data"].inferred_mime());
+}
+
+#[test]
+fn inferred_mime_of_media() {
+    let manifest = r#"<item id="audio" href="audio.M4A" media-type="audio/mp4"/>
+    <item id="video" href="video.mp4" media-type="video/mp4"/>
+    <item id="mp3" href="audio.mp3" media-type="audio/mpeg"/>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>")).open();
+
+    assert_eq!(Some("audio/mp4"), doc.resources()["audio"].inferred_mime());
+    assert_eq!(Some("video/mp4"), doc.resources()["video"].inferred_mime());
+    assert_eq!(Some("audio/mpeg"), doc.resources()["mp3"].inferred_mime());
+}
+
+#[test]
+fn resource_classification() {
+    let item = |mime: &str| ResourceItem {
+        path: PathBuf::from("OEBPS/item"),
+        mime: mime.to_string(),
+        properties: Vec::new(),
+        media_overlay: None,
+        fallback: None,
+        size: None,
+        compressed_size: None,
+        extra_attrs: Vec::new(),
+    };
+    let classes = |item: &ResourceItem| {
+        [
+            ("document", item.is_document()),
+            ("image", item.is_image()),
+            ("stylesheet", item.is_stylesheet()),
+            ("font", item.is_font()),
+            ("audio", item.is_audio()),
+            ("video", item.is_video()),
+            ("script", item.is_script()),
+        ]
+        .into_iter()
+        .filter(|(_, is)| *is)
+        .map(|(class, _)| class)
+        .collect::<Vec<_>>()
+    };
+
+    let cases: &[(&str, &[&str])] = &[
+        ("application/xhtml+xml", &["document"]),
+        ("text/html; charset=utf-8", &["document"]),
+        ("image/svg+xml", &["document", "image"]),
+        ("IMAGE/JPEG", &["image"]),
+        ("image/tiff", &[]),
+        ("text/css", &["stylesheet"]),
+        ("font/otf", &["font"]),
+        ("font/woff2", &["font"]),
+        ("application/vnd.ms-opentype", &["font"]),
+        ("application/font-sfnt", &["font"]),
+        ("application/x-font-ttf", &["font"]),
+        ("application/x-font-truetype", &["font"]),
+        ("Application/Font-WOFF", &["font"]),
+        ("application/octet-stream", &[]),
+        ("audio/ogg; codecs=opus", &["audio"]),
+        ("audio/mpeg", &["audio"]),
+        ("video/webm", &["video"]),
+        ("text/javascript", &["script"]),
+        ("application/javascript", &["script"]),
+    ];
+    for (mime, expected) in cases {
+        assert_eq!(expected, &classes(&item(mime)), "{mime}");
+    }
+}
+
+#[test]
+fn resource_sizes() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+    let sizes = |id: &str| {
+        let item = &doc.resources()[id];
+        (item.size, item.compressed_size)
+    };
+    assert_eq!((Some(1_186_183), Some(1_181_285)), sizes("portada.png"));
+    assert_eq!((Some(1010), Some(612)), sizes("000.xhtml"));
+    assert_eq!((Some(188), Some(127)), sizes("stylesheet.css"));
+
+    let manifest = r#"<item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="missing" href="missing.xhtml" media-type="application/xhtml+xml"/>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>"))
+        .file("OEBPS/c1.xhtml", "0123456789")
+        .open();
+    assert_eq!(Some(10), doc.resources()["c1"].size);
+    assert!(doc.resources()["c1"].compressed_size.is_some());
+    assert_eq!(None, doc.resources()["missing"].size);
+    assert_eq!(None, doc.resources()["missing"].compressed_size);
+}
+
+#[test]
+fn remote_resources() {
+    let manifest = r#"<item id="c1" href="c1.xhtml" media-type="application/xhtml+xml" properties="remote-resources"/>
+    <item id="audio" href="https://example.com/audio/chapter1.mp3" media-type="audio/mpeg"/>"#;
+    let spine = r#"<spine><itemref idref="c1"/></spine>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file(
+            "OEBPS/c1.xhtml",
+            xhtml(r#"<audio src="https://example.com/audio/chapter1.mp3"/>"#),
+        )
+        .open();
+
+    assert_eq!(
+        vec![RemoteResource {
+            id: "audio".to_string(),
+            url: "https://example.com/audio/chapter1.mp3".to_string(),
+            mime: "audio/mpeg".to_string(),
+        }],
+        doc.remote_resources()
+    );
+    let audio = &doc.resources()["audio"];
+    assert!(audio.is_remote());
+    assert!(!doc.resources()["c1"].is_remote());
+    assert_eq!(None, audio.size);
+    assert!(doc.parse_warnings().is_empty());
+
+    assert_eq!(None, doc.get_resource("audio"));
+    let mut buf = Vec::new();
+    assert!(matches!(
+        doc.get_resource_into("audio", &mut buf),
+        Err(ArchiveError::RemoteResource(url)) if url == "https://example.com/audio/chapter1.mp3"
+    ));
+}
+
+#[test]
+fn resources_in_manifest_order() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+    let ids: Vec<_> = doc.resources().keys().map(String::as_str).collect();
+    assert_eq!(Some(&"ncx"), ids.first());
+    assert_eq!(Some(&"normal.xml"), ids.last());
+
+    let manifest = r#"<item id="z" href="z.xhtml" media-type="application/xhtml+xml"/>
+    <item id="a" href="a.xhtml" media-type="application/xhtml+xml"/>
+    <item id="m" href="m.xhtml" media-type="application/xhtml+xml"/>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>")).open();
+    let ids: Vec<_> = doc.resources().keys().map(String::as_str).collect();
+    assert_eq!(vec!["z", "a", "m"], ids);
+}
+
+#[test]
+fn resource_id_by_path() {
+    let manifest = r#"<item id="c1" href="Text/chapter%201.xhtml" media-type="application/xhtml+xml"/>
+    <item id="first" href="shared.xhtml" media-type="application/xhtml+xml"/>
+    <item id="second" href="shared.xhtml" media-type="text/html"/>"#;
+    let spine = r#"<spine><itemref idref="second"/><itemref idref="c1"/></spine>"#;
+    let mut doc = Fixture::new(opf("3.0", "", manifest, spine)).open();
+
+    assert_eq!(
+        Some("c1"),
+        doc.resource_id_by_path("OEBPS/Text/chapter%201.xhtml")
+    );
+    assert_eq!(
+        Some("c1"),
+        doc.resource_id_by_path("OEBPS/Text/chapter 1.xhtml")
+    );
+    assert_eq!(
+        Some("c1"),
+        doc.resource_id_by_path("OEBPS\\Text\\chapter 1.xhtml")
+    );
+    assert_eq!(
+        Some(1),
+        doc.resource_uri_to_chapter("OEBPS/Text/chapter 1.xhtml")
+    );
+    assert_eq!(None, doc.resource_id_by_path("Text/chapter 1.xhtml"));
+
+    // The first item with a path wins
+    assert_eq!(Some("first"), doc.resource_id_by_path("OEBPS/shared.xhtml"));
+    assert_eq!(
+        Some("application/xhtml+xml"),
+        doc.get_resource_mime_by_path("OEBPS/shared.xhtml")
+    );
+
+    // The index follows the changes to the manifest
+    doc.insert_resource("new", "OEBPS/new.xhtml", "application/xhtml+xml")
+        .unwrap();
+    assert_eq!(Some("new"), doc.resource_id_by_path("OEBPS/new.xhtml"));
+
+    let (mut metadata, archive) = doc.into_parts();
+    metadata.resources.shift_remove("first");
+    let doc = EpubDoc::from_parts(metadata, archive).unwrap();
+    assert_eq!(
+        Some("second"),
+        doc.resource_id_by_path("OEBPS/shared.xhtml")
+    );
+    assert_eq!(Some(0), doc.resource_uri_to_chapter("OEBPS/shared.xhtml"));
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "export")]
+
+use epub::doc::EpubDoc;
+use epub::export::{StructureExport, SCHEMA_VERSION};
+use std::path::Path;
+
+/// Compare the export of `epub` with the checked-in snapshot, set `UPDATE_SNAPSHOTS` to rewrite it instead.
+fn assert_snapshot(epub: &str, snapshot: &str) {
+    let doc = EpubDoc::new(epub).unwrap();
+    let export = doc.export_structure();
+    let json = export.to_json().unwrap();
+    let snapshot = Path::new("tests/snapshots").join(snapshot);
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&snapshot, format!("{json}\n")).unwrap();
+    }
+
+    let expected = std::fs::read_to_string(&snapshot).unwrap();
+    assert_eq!(expected.trim_end(), json);
+    assert_eq!(export, StructureExport::from_json(&expected).unwrap());
+}
+
+#[test]
+fn export_snapshot_test_epub() {
+    assert_snapshot("test.epub", "test.structure.json");
+}
+
+#[test]
+fn export_snapshot_winnie_the_pooh() {
+    assert_snapshot(
+        "tests/docs/winnie_the_pooh.epub",
+        "winnie_the_pooh.structure.json",
+    );
+}
+
+#[test]
+fn export_rejects_newer_schema() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+    let mut export = doc.export_structure();
+    assert_eq!(SCHEMA_VERSION, export.schema_version);
+
+    export.schema_version = SCHEMA_VERSION + 1;
+    let json = export.to_json().unwrap();
+    let error = StructureExport::from_json(&json).unwrap_err();
+    assert!(error.to_string().contains("schema version"));
+}
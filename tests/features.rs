@@ -0,0 +1,32 @@
+//! Behaviour which depends on the enabled cargo features.
+
+mod common;
+
+use common::{opf, Fixture};
+use epub::doc::EpubDoc;
+
+#[test]
+fn utf8_bom() {
+    let package = format!("\u{feff}{}", opf("2.0", "", "", "<spine/>"));
+    let doc = Fixture::new(package).open();
+    assert_eq!(Some("Fixture"), doc.mdata("title"));
+}
+
+#[test]
+#[cfg(feature = "encodings")]
+fn utf16_package() {
+    // book2.epub has its package document encoded in UTF-16
+    let doc = EpubDoc::new("tests/docs/book2.epub").unwrap();
+    assert!(doc.mdata("title").is_some());
+}
+
+#[test]
+#[cfg(not(feature = "encodings"))]
+fn utf16_package_without_encodings() {
+    use epub::error::ArchiveError;
+
+    let err = EpubDoc::new("tests/docs/book2.epub").unwrap_err();
+    assert!(matches!(err, ArchiveError::Xml(_)));
+    assert!(err.to_string().contains("UTF-16LE"));
+    assert!(err.to_string().contains("`encodings` feature"));
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use common::{opf, Fixture};
+use epub::doc::EpubDoc;
+
+#[test]
+fn generator_calibre() {
+    let metadata = r#"
+    <dc:contributor opf:role="bkp">calibre (5.3.0) [https://calibre-ebook.com]</dc:contributor>
+    <meta name="calibre:timestamp" content="2021-01-01T00:00:00+00:00"/>
+    <meta name="calibre:title_sort" content="Fixture"/>"#;
+    let doc = Fixture::new(opf("2.0", metadata, "", "<spine/>"))
+        .file("META-INF/calibre_bookmarks.txt", "")
+        .open();
+
+    let generator = doc.generator().unwrap();
+    assert_eq!("calibre", generator.name);
+    assert_eq!(Some("5.3.0"), generator.version.as_deref());
+    assert_eq!(
+        vec![
+            "meta \"calibre:timestamp\"",
+            "meta \"calibre:title_sort\"",
+            "contributor \"calibre (5.3.0) [https://calibre-ebook.com]\" with role bkp",
+            "file META-INF/calibre_bookmarks.txt",
+        ],
+        generator.evidence
+    );
+}
+
+#[test]
+fn generator_calibre_epub3_refines() {
+    let metadata = r##"
+    <dc:contributor id="contrib">calibre (6.1.0) [https://calibre-ebook.com]</dc:contributor>
+    <meta refines="#contrib" property="role" scheme="marc:relators">bkp</meta>"##;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    let generator = doc.generator().unwrap();
+    assert_eq!("calibre", generator.name);
+    assert_eq!(Some("6.1.0"), generator.version.as_deref());
+}
+
+#[test]
+fn generator_sigil() {
+    let metadata = r#"<meta name="Sigil version" content="1.9.3"/>"#;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    let generator = doc.generator().unwrap();
+    assert_eq!("Sigil", generator.name);
+    assert_eq!(Some("1.9.3"), generator.version.as_deref());
+    assert_eq!(
+        vec!["meta \"Sigil version\" = \"1.9.3\""],
+        generator.evidence
+    );
+}
+
+#[test]
+fn generator_indesign() {
+    let metadata = r#"<meta name="generator" content="Adobe InDesign 17.0 (Windows)"/>"#;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    let generator = doc.generator().unwrap();
+    assert_eq!("Adobe InDesign", generator.name);
+    assert_eq!(Some("17.0"), generator.version.as_deref());
+}
+
+#[test]
+fn generator_unknown() {
+    let doc = Fixture::new(opf("3.0", "", "", "<spine/>")).open();
+    assert_eq!(None, doc.generator());
+
+    // A bookmarks file alone isn't enough to blame calibre
+    #[cfg(feature = "encodings")]
+    assert_eq!(
+        None,
+        EpubDoc::new("tests/docs/book2.epub").unwrap().generator()
+    );
+
+    let doc = EpubDoc::new("tests/docs/herman-melville_moby-dick.epub").unwrap();
+    let generator = doc.generator().unwrap();
+    assert_eq!("Standard Ebooks", generator.name);
+    assert_eq!(Some("1.9.1"), generator.version.as_deref());
+}
+
+#[test]
+fn generator_pandoc() {
+    let metadata = r#"
+    <meta name="generator" content="pandoc 3.1.2"/>
+    <dc:format>application/epub+zip</dc:format>"#;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    let generator = doc.generator().unwrap();
+    assert_eq!("pandoc", generator.name);
+    assert_eq!(Some("3.1.2"), generator.version.as_deref());
+    assert_eq!(
+        vec![
+            "meta \"generator\" = \"pandoc 3.1.2\"",
+            "dc:format \"application/epub+zip\"",
+        ],
+        generator.evidence
+    );
+
+    // A format alone doesn't mean pandoc produced the book
+    let metadata = "<dc:format>application/epub+zip</dc:format>";
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+    assert_eq!(None, doc.generator());
+}
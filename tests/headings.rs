@@ -0,0 +1,66 @@
+#![cfg(feature = "rewrite")]
+
+mod common;
+
+use common::{opf, xhtml, Fixture};
+use std::path::PathBuf;
+
+#[test]
+fn toc_from_headings() {
+    let manifest = r#"
+        <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+        <item id="cover" href="cover.jpg" media-type="image/jpeg"/>
+        <item id="c2" href="c2.xhtml" media-type="application/xhtml+xml"/>
+        <item id="c3" href="c3.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine>
+        <itemref idref="c1"/><itemref idref="cover"/><itemref idref="c2"/><itemref idref="c3"/>
+    </spine>"#;
+    let mut doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file(
+            "OEBPS/c1.xhtml",
+            xhtml(
+                r#"<h1>Part   <em>One</em></h1><h2 id="ch1">Chapter 1</h2><h3 id="s1">Deep</h3>"#,
+            ),
+        )
+        .file("OEBPS/cover.jpg", [0xff, 0xd8])
+        .file(
+            "OEBPS/c2.xhtml",
+            xhtml(r#"<h2 id="ch2">Fish &amp; Chips</h2><h2></h2><h1 id="p2">Part Two</h1>"#),
+        )
+        .file("OEBPS/c3.xhtml", xhtml("<p>No headings</p>"))
+        .open();
+    assert!(doc.get_toc().is_empty());
+
+    let toc = doc.generate_toc_from_headings(2);
+    // Generating doesn't change the ToC of the book
+    assert!(doc.get_toc().is_empty());
+
+    assert_eq!(2, toc.len());
+    assert_eq!("Part One", toc[0].label);
+    assert_eq!(Some(PathBuf::from("OEBPS/c1.xhtml")), toc[0].content);
+    assert_eq!(None, toc[0].fragment);
+    let chapters: Vec<_> = toc[0]
+        .children
+        .iter()
+        .map(|nav| nav.label.as_str())
+        .collect();
+    assert_eq!(vec!["Chapter 1", "Fish & Chips"], chapters);
+    assert!(toc[0].children[0].children.is_empty());
+    assert_eq!(Some("ch2"), toc[0].children[1].fragment.as_deref());
+    assert_eq!(
+        Some(PathBuf::from("OEBPS/c2.xhtml")),
+        toc[0].children[1].content
+    );
+    assert_eq!("Part Two", toc[1].label);
+    assert!(doc.set_toc(toc).is_empty());
+    let orders: Vec<_> = doc.toc_flat().map(|(_, nav)| nav.play_order).collect();
+    assert_eq!(vec![1, 2, 3, 4], orders);
+
+    assert_eq!(
+        Some("s1"),
+        doc.generate_toc_from_headings(6)[0].children[0].children[0]
+            .fragment
+            .as_deref()
+    );
+    assert!(doc.generate_toc_from_headings(0).is_empty());
+}
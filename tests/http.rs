@@ -0,0 +1,88 @@
+mod common;
+
+use common::{opf, xhtml, Fixture};
+use epub::doc::EpubDoc;
+use std::io::Cursor;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn etag_format() {
+    let manifest = r#"<item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>"#;
+    let chapter = xhtml("<p>Hello</p>");
+    let doc = Fixture::new(opf(
+        "3.0",
+        "",
+        manifest,
+        r#"<spine><itemref idref="c1"/></spine>"#,
+    ))
+    .file("OEBPS/c1.xhtml", &chapter)
+    .open();
+
+    let crc = doc.chapter_checksums()[0].1;
+    assert_eq!(
+        Some(format!("\"{crc:08x}-{:x}\"", chapter.len())),
+        doc.resource_etag("c1")
+    );
+}
+
+#[test]
+fn etag_stable_until_replaced() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+    let etags = doc.resources_etag_map();
+    // Two resources of the manifest are missing from the archive
+    assert_eq!(doc.resources().len() - 2, etags.len());
+    for (id, etag) in &etags {
+        assert_eq!(Some(etag), doc.resource_etag(id).as_ref());
+    }
+
+    // Reopening doesn't change anything
+    let reopened = EpubDoc::new("test.epub").unwrap();
+    assert_eq!(etags, reopened.resources_etag_map());
+
+    let path = doc.resources()["portada.png"].path.clone();
+    let mut output = Cursor::new(Vec::new());
+    doc.writer()
+        .replace(&path, b"not a png".to_vec())
+        .save(&mut output)
+        .unwrap();
+    output.set_position(0);
+    let saved = EpubDoc::from_reader(output).unwrap();
+    let saved_etags = saved.resources_etag_map();
+
+    assert_ne!(etags["portada.png"], saved_etags["portada.png"]);
+    for (id, etag) in etags.iter().filter(|(id, _)| *id != "portada.png") {
+        assert_eq!(etag, &saved_etags[id], "{id}");
+    }
+}
+
+#[test]
+fn missing_resources() {
+    let manifest = r#"<item id="gone" href="gone.xhtml" media-type="application/xhtml+xml"/>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>")).open();
+
+    assert_eq!(None, doc.resource_etag("gone"));
+    assert_eq!(None, doc.resource_etag("unknown"));
+    assert_eq!(None, doc.resource_last_modified("gone"));
+    assert!(doc.resources_etag_map().is_empty());
+}
+
+#[test]
+fn last_modified() {
+    let manifest = r#"<item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>"#;
+    let fixture =
+        Fixture::new(opf("3.0", "", manifest, "<spine/>")).file("OEBPS/c1.xhtml", xhtml(""));
+
+    // The default zip time, the earliest MS-DOS time
+    let doc = fixture.open();
+    assert_eq!(
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(315_532_800)),
+        doc.resource_last_modified("c1")
+    );
+
+    let modified = zip::DateTime::from_date_and_time(2024, 2, 29, 13, 37, 42).unwrap();
+    let doc = fixture.modified(modified).open();
+    assert_eq!(
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_213_862)),
+        doc.resource_last_modified("c1")
+    );
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::{opf, Fixture};
+use epub::ibooks::ALL_PLATFORMS;
+
+const PATH: &str = "META-INF/com.apple.ibooks.display-options.xml";
+
+fn fixture() -> Fixture {
+    Fixture::new(opf("3.0", "", "", "<spine/>"))
+}
+
+#[test]
+fn per_platform_options() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<display_options>
+  <platform name="*">
+    <option name="specified-fonts">true</option>
+    <option name="fixed-layout">true</option>
+    <option name="orientation-lock">none</option>
+  </platform>
+  <platform name="iphone">
+    <option name="orientation-lock">landscape-only</option>
+    <option name="open-to-spread"> false </option>
+    <option>unnamed</option>
+  </platform>
+</display_options>"#;
+    let doc = fixture().file(PATH, xml).open();
+    let options = doc.ibooks_display_options().unwrap();
+
+    assert_eq!(2, options.platforms.len());
+    assert_eq!(Some(true), options.specified_fonts());
+    assert_eq!(Some(true), options.fixed_layout());
+    // Only set for the iPhone
+    assert_eq!(None, options.open_to_spread());
+    assert_eq!(Some(false), options.flag("iphone", "open-to-spread"));
+
+    assert_eq!(
+        Some("landscape-only"),
+        options.get("iphone", "orientation-lock")
+    );
+    assert_eq!(Some("none"), options.get("ipad", "orientation-lock"));
+    assert_eq!(Some("none"), options.get(ALL_PLATFORMS, "orientation-lock"));
+    assert_eq!(Some("true"), options.get("iphone", "specified-fonts"));
+    assert_eq!(1, options.warnings.len());
+}
+
+#[test]
+fn missing_or_malformed() {
+    assert_eq!(None, fixture().open().ibooks_display_options());
+
+    let doc = fixture()
+        .file(PATH, "<display_options><platform name=\"*\">")
+        .open();
+    let options = doc.ibooks_display_options().unwrap();
+    assert!(options.platforms.is_empty());
+    assert_eq!(1, options.warnings.len());
+    assert_eq!(None, options.specified_fonts());
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use common::{opf, xhtml, Fixture};
+use epub::doc::EpubDoc;
+use epub::language::{LanguageGuess, Script};
+
+/// A book declared as `language`, with the given chapters (`linear`, body).
+fn book(language: &str, chapters: &[(bool, &str)]) -> EpubDoc<std::io::Cursor<Vec<u8>>> {
+    let mut manifest = String::new();
+    let mut spine = String::from("<spine>");
+    for (i, (linear, _)) in chapters.iter().enumerate() {
+        manifest.push_str(&format!(
+            r#"<item id="c{i}" href="c{i}.xhtml" media-type="application/xhtml+xml"/>"#
+        ));
+        let linear = if *linear { "" } else { r#" linear="no""# };
+        spine.push_str(&format!(r#"<itemref idref="c{i}"{linear}/>"#));
+    }
+    spine.push_str("</spine>");
+
+    let metadata = format!("<dc:language>{language}</dc:language>");
+    let mut fixture = Fixture::new(opf("3.0", &metadata, &manifest, &spine));
+    for (i, (_, body)) in chapters.iter().enumerate() {
+        fixture = fixture.file(&format!("OEBPS/c{i}.xhtml"), xhtml(body));
+    }
+    fixture.open()
+}
+
+#[test]
+fn declared_language_mismatch() {
+    let russian = "<p>Все счастливые семьи похожи друг на друга, каждая несчастливая семья несчастлива по-своему.</p>";
+    let doc = book("en", &[(true, russian), (true, "<p>Chapter 2</p>")]);
+
+    assert_eq!(
+        LanguageGuess {
+            dominant_script: Some(Script::Cyrillic),
+            declared: Some("en".to_string()),
+            agreement: false,
+        },
+        doc.detect_content_language(2)
+    );
+
+    let doc = book("ru", &[(true, russian)]);
+    assert!(doc.detect_content_language(1).agreement);
+    // An explicit script subtag wins
+    let doc = book("sr-Latn", &[(true, russian)]);
+    assert!(!doc.detect_content_language(1).agreement);
+}
+
+#[test]
+fn japanese_mixes_han_and_kana() {
+    let doc = book(
+        "ja",
+        &[
+            (true, "<p>吾輩は猫である。名前はまだ無い。</p>"),
+            (true, "<p>どこで生れたかとんと見当がつかぬ。</p>"),
+        ],
+    );
+
+    let guess = doc.detect_content_language(2);
+    assert_eq!(Some(Script::Kana), guess.dominant_script);
+    assert!(guess.agreement);
+}
+
+#[test]
+fn sampling() {
+    let arabic = "<p>في البدء كان الكلمة</p>";
+    let hebrew = "<p>בראשית ברא אלהים את השמים ואת הארץ</p>";
+    let latin = "<p>In the beginning</p>";
+    // The non-linear chapter isn't sampled, and one sample takes the middle linear chapter
+    let doc = book(
+        "he",
+        &[
+            (true, latin),
+            (false, arabic),
+            (true, hebrew),
+            (true, latin),
+        ],
+    );
+    assert_eq!(
+        Some(Script::Hebrew),
+        doc.detect_content_language(1).dominant_script
+    );
+    assert_eq!(
+        Some(Script::Latin),
+        doc.detect_content_language(10).dominant_script
+    );
+
+    let guess = doc.detect_content_language(0);
+    assert_eq!(None, guess.dominant_script);
+    assert!(guess.agreement);
+
+    // Only the start of a chapter is read
+    let long = format!(
+        "<p>{}</p><p>{}</p>",
+        "Lorem ipsum. ".repeat(10_000),
+        "Кириллица ".repeat(20_000)
+    );
+    let doc = book("uk", &[(true, &long)]);
+    let guess = doc.detect_content_language(1);
+    assert_eq!(Some(Script::Latin), guess.dominant_script);
+    assert!(!guess.agreement);
+}
+
+#[test]
+fn real_books() {
+    let doc = EpubDoc::new("tests/docs/herman-melville_moby-dick.epub").unwrap();
+    let guess = doc.detect_content_language(5);
+    assert_eq!(Some(Script::Latin), guess.dominant_script);
+    assert!(guess.agreement);
+}
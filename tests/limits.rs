@@ -0,0 +1,213 @@
+mod common;
+
+use common::{opf, Fixture};
+use epub::doc::EpubDoc;
+use epub::options::{MetadataLimits, ParseOptions};
+use std::io::Cursor;
+
+/// A package document with a huge description, a meta with thousands of attributes, and thousands of subjects.
+fn pathological() -> Fixture {
+    let description = format!("<dc:description>{}</dc:description>", "é".repeat(1 << 20));
+    let attributes: String = (0..5000).map(|i| format!(r#" a{i}="{i}""#)).collect();
+    let meta = format!(r#"<meta name="noisy" content="value"{attributes}/>"#);
+    let subjects: String = (0..5000)
+        .map(|i| format!("<dc:subject>Subject {i}</dc:subject>"))
+        .collect();
+
+    Fixture::new(opf(
+        "2.0",
+        &format!("{description}{meta}{subjects}"),
+        "",
+        "<spine/>",
+    ))
+}
+
+fn open(fixture: &Fixture, metadata_limits: MetadataLimits) -> EpubDoc<Cursor<Vec<u8>>> {
+    let options = ParseOptions {
+        metadata_limits,
+        ..Default::default()
+    };
+    EpubDoc::from_reader_with_options(Cursor::new(fixture.build()), options).unwrap()
+}
+
+#[test]
+fn metadata_is_unlimited_by_default() {
+    let doc = open(&pathological(), MetadataLimits::default());
+
+    assert_eq!(2 << 20, doc.mdata("description").unwrap().len());
+    assert_eq!(5002, doc.mdata_full("noisy").unwrap().attr.len());
+    assert_eq!(5000, doc.metadata()["subject"].len());
+    assert!(doc.parse_warnings().is_empty());
+}
+
+#[test]
+fn metadata_limits_bound_the_parsed_metadata() {
+    let limits = MetadataLimits {
+        max_value_len: Some(1001),
+        max_attributes: Some(10),
+        max_entries_per_key: Some(100),
+    };
+    let doc = open(&pathological(), limits);
+
+    // Truncated at a character boundary
+    assert_eq!("é".repeat(500), doc.mdata("description").unwrap());
+    assert_eq!(10, doc.mdata_full("noisy").unwrap().attr.len());
+    assert_eq!(100, doc.metadata()["subject"].len());
+    assert_eq!(Some("Fixture"), doc.mdata("title"));
+
+    let warnings: Vec<_> = doc
+        .parse_warnings()
+        .iter()
+        .map(|warning| warning.message.as_str())
+        .collect();
+    assert_eq!(
+        vec![
+            "`description` metadata value truncated to 1000 bytes",
+            "`noisy` metadata kept 10 of its 5002 attributes",
+            "only the first 100 `subject` metadata entries are kept",
+        ],
+        warnings
+    );
+}
+
+#[test]
+fn recommended_metadata_limits_keep_regular_books_intact() {
+    let options = ParseOptions {
+        metadata_limits: MetadataLimits::recommended(),
+        ..Default::default()
+    };
+    let limited = EpubDoc::new_with_options("tests/docs/winnie_the_pooh.epub", options).unwrap();
+    let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+
+    assert_eq!(doc.metadata(), limited.metadata());
+    assert!(limited.parse_warnings().is_empty());
+}
+
+#[test]
+fn metadata_limits_bound_the_identifiers() {
+    let limits = MetadataLimits {
+        max_value_len: Some(100),
+        max_attributes: Some(10),
+        max_entries_per_key: Some(2),
+    };
+    let attributes: String = (0..50).map(|i| format!(r#" a{i}="{i}""#)).collect();
+    let others: String = (0..5)
+        .map(|i| format!("<dc:identifier>urn:isbn:{i}</dc:identifier>"))
+        .collect();
+    let package = opf("2.0", &others, "", "<spine/>").replacen(
+        r#"<dc:identifier id="uid">urn:uuid:fixture"#,
+        &format!(
+            r#"<dc:identifier id="uid"{attributes}>{}"#,
+            "x".repeat(1000)
+        ),
+        1,
+    );
+
+    // The unique identifier is limited like any other metadata
+    let doc = open(&Fixture::new(package.clone()), limits);
+    let unique = doc.unique_identifier_node().unwrap();
+    assert_eq!(100, unique.content.len());
+    assert_eq!(10, unique.attr.len());
+    assert_eq!(2, doc.identifiers().len());
+    let warnings: Vec<_> = doc
+        .parse_warnings()
+        .iter()
+        .map(|warning| warning.message.as_str())
+        .collect();
+    assert_eq!(
+        vec![
+            "`identifier` metadata value truncated to 100 bytes",
+            "`identifier` metadata kept 10 of its 51 attributes",
+            "only the first 2 `identifier` metadata entries are kept",
+        ],
+        warnings
+    );
+
+    // Identifiers without an id are never the unique one, even if the package doesn't name one
+    let package = package.replacen(r#" unique-identifier="uid""#, "", 1);
+    let doc = open(&Fixture::new(package), limits);
+    assert_eq!(None, doc.unique_identifier_node());
+    assert_eq!(2, doc.identifiers().len());
+}
+
+/// An NCX and a nav with `depth` levels, a single entry per level.
+fn deep_toc(depth: usize) -> Fixture {
+    let nav_points: String = (0..depth)
+        .map(|i| {
+            format!(
+                r#"<navPoint playOrder="{i}"><navLabel><text>{i}</text></navLabel><content src="c.xhtml#{i}"/>"#
+            )
+        })
+        .chain((0..depth).map(|_| "</navPoint>".to_string()))
+        .collect();
+    let ncx = format!(
+        r#"<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1"><navMap>{nav_points}</navMap></ncx>"#
+    );
+    let entries: String = (0..depth)
+        .map(|i| format!(r##"<ol><li><a href="c.xhtml#{i}">{i}</a>"##))
+        .chain((0..depth).map(|_| "</li></ol>".to_string()))
+        .collect();
+    let nav = common::xhtml(&format!(r#"<nav epub:type="toc">{entries}</nav>"#));
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="c" href="c.xhtml" media-type="application/xhtml+xml"/>"#;
+
+    Fixture::new(opf(
+        "3.0",
+        "",
+        manifest,
+        r#"<spine toc="ncx"><itemref idref="c"/></spine>"#,
+    ))
+    .file("OEBPS/toc.ncx", ncx)
+    .file("OEBPS/nav.xhtml", nav)
+}
+
+#[test]
+fn deep_toc_is_flattened() {
+    let doc = EpubDoc::from_reader(Cursor::new(deep_toc(100).build())).unwrap();
+
+    for toc in [doc.get_toc_ncx(), doc.get_toc_nav()] {
+        let mut last = toc;
+        for _ in 0..63 {
+            assert_eq!(1, last.len());
+            last = &last[0].children;
+        }
+        // The last level has every deeper entry, in order
+        let labels: Vec<_> = last.iter().map(|nav| nav.label.as_str()).collect();
+        assert_eq!(100 - 63, labels.len());
+        assert_eq!(("63", "99"), (labels[0], labels[labels.len() - 1]));
+        assert!(last.iter().all(|nav| nav.children.is_empty()));
+    }
+    assert_eq!(2, doc.parse_warnings().len());
+
+    let options = ParseOptions {
+        max_toc_depth: 3,
+        ..Default::default()
+    };
+    let doc = EpubDoc::from_reader_with_options(Cursor::new(deep_toc(5).build()), options).unwrap();
+    let labels: Vec<_> = doc
+        .toc_flat()
+        .map(|(depth, nav)| (depth, nav.label.as_str()))
+        .collect();
+    assert_eq!(
+        vec![(0, "0"), (1, "1"), (2, "2"), (2, "3"), (2, "4")],
+        labels
+    );
+
+    // Shallow enough ToCs are kept as is
+    let doc = EpubDoc::from_reader(Cursor::new(deep_toc(64).build())).unwrap();
+    assert_eq!(63, doc.toc_flat().map(|(depth, _)| depth).max().unwrap());
+    assert!(doc.parse_warnings().is_empty());
+}
+
+#[test]
+fn absurdly_deep_toc_is_refused() {
+    let doc = EpubDoc::from_reader(Cursor::new(deep_toc(10_000).build())).unwrap();
+
+    assert!(doc.get_toc().is_empty());
+    assert!(doc
+        .partial_failures()
+        .iter()
+        .all(|failure| failure.message.contains("nested deeper")));
+    assert!(!doc.partial_failures().is_empty());
+}
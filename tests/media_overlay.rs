@@ -0,0 +1,153 @@
+mod common;
+
+use common::{opf, opf_with, Fixture};
+use epub::media_overlay::{MediaDuration, MediaOverlayInfo};
+use std::time::Duration;
+
+const MANIFEST: &str = r#"
+    <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml" media-overlay="c1-overlay"/>
+    <item id="c1-overlay" href="c1.smil" media-type="application/smil+xml"/>
+    <item id="c2" href="c2.xhtml" media-type="application/xhtml+xml" media-overlay="c2-overlay"/>
+    <item id="c2-overlay" href="c2.smil" media-type="application/smil+xml"/>
+    <item id="c3-overlay" href="c3.smil" media-type="application/smil+xml"/>"#;
+
+fn duration(raw: &str, parsed: Option<Duration>) -> MediaDuration {
+    MediaDuration {
+        raw: raw.to_string(),
+        parsed,
+    }
+}
+
+#[test]
+fn read_aloud_book() {
+    let metadata = r##"
+    <meta property="media:duration">0:32:19.500</meta>
+    <meta property="media:duration" refines="#c1-overlay">0:20:00</meta>
+    <meta property="media:duration" refines="#c2-overlay">12:19.5</meta>
+    <meta property="media:duration" refines="#c3-overlay">soon</meta>
+    <meta property="media:duration" refines="#missing">1s</meta>
+    <meta property="media:narrator">Joe Speaker</meta>
+    <meta property="media:narrator">Jane Speaker</meta>
+    <meta property="media:active-class">-epub-media-overlay-active</meta>
+    <meta property="media:playback-active-class">-epub-media-overlay-playing</meta>"##;
+    let doc = Fixture::new(opf("3.0", metadata, MANIFEST, "<spine/>")).open();
+
+    let expected = MediaOverlayInfo {
+        duration: Some(duration(
+            "0:32:19.500",
+            Some(Duration::from_millis(1_939_500)),
+        )),
+        item_durations: [
+            (
+                "c1-overlay".to_string(),
+                duration("0:20:00", Some(Duration::from_secs(1200))),
+            ),
+            (
+                "c2-overlay".to_string(),
+                duration("12:19.5", Some(Duration::from_millis(739_500))),
+            ),
+            // Kept, even though it can't be parsed
+            ("c3-overlay".to_string(), duration("soon", None)),
+        ]
+        .into(),
+        narrators: vec!["Joe Speaker".to_string(), "Jane Speaker".to_string()],
+        active_class: Some("-epub-media-overlay-active".to_string()),
+        playback_active_class: Some("-epub-media-overlay-playing".to_string()),
+    };
+    assert_eq!(Some(expected), doc.media_overlay_info());
+}
+
+#[test]
+fn clock_values() {
+    let parse = |value: &str| {
+        let metadata = format!(r#"<meta property="media:duration">{value}</meta>"#);
+        let doc = Fixture::new(opf("3.0", &metadata, "", "<spine/>")).open();
+        doc.media_overlay_info().unwrap().duration.unwrap().parsed
+    };
+
+    assert_eq!(Some(Duration::from_secs(3 * 3600 + 62)), parse("3:01:02"));
+    assert_eq!(Some(Duration::from_secs(125 * 3600)), parse("125:00:00"));
+    assert_eq!(Some(Duration::from_millis(90_250)), parse("01:30.25"));
+    assert_eq!(Some(Duration::from_millis(12_500)), parse(" 12.5 "));
+    assert_eq!(Some(Duration::from_millis(12_500)), parse("12.5s"));
+    assert_eq!(Some(Duration::from_secs(5400)), parse("1.5h"));
+    assert_eq!(Some(Duration::from_secs(120)), parse("2min"));
+    assert_eq!(Some(Duration::from_millis(250)), parse("250ms"));
+    assert_eq!(Some(Duration::new(1, 123_456_789)), parse("1.1234567891"));
+
+    for malformed in [
+        "",
+        "1:60:00",
+        "1:2:03",
+        "0:00:60",
+        "1:00:00:00",
+        "-1",
+        "1.",
+        "1e3",
+        "1.5x",
+    ] {
+        assert_eq!(None, parse(malformed), "{malformed:?}");
+    }
+}
+
+#[test]
+fn custom_prefix_and_no_overlays() {
+    let metadata = r#"<meta property="mo:narrator">Narrator</meta>"#;
+    let doc = Fixture::new(opf_with(
+        "3.0",
+        r#"prefix="mo: http://www.idpf.org/epub/vocab/overlays/#""#,
+        metadata,
+        "",
+        "<spine/>",
+        "",
+    ))
+    .open();
+    assert_eq!(
+        vec!["Narrator"],
+        doc.media_overlay_info().unwrap().narrators
+    );
+
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+    assert_eq!(None, doc.media_overlay_info());
+}
+
+#[test]
+fn overlay_of_spine_items() {
+    let manifest = format!(
+        r#"{MANIFEST}
+    <item id="c3" href="c3.xhtml" media-type="application/xhtml+xml" media-overlay="c3-overlay"/>
+    <item id="c4" href="c4.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c5" href="c5.xhtml" media-type="application/xhtml+xml" media-overlay="missing"/>"#
+    );
+    let spine = r#"<spine>
+    <itemref idref="c1"/><itemref idref="c2"/><itemref idref="c3"/><itemref idref="c4"/><itemref idref="c5"/>
+    </spine>"#;
+    let doc = Fixture::new(opf("3.0", "", &manifest, spine)).open();
+
+    let overlay = |id: &str| Some(&doc.resources()[id]);
+    let overlays: Vec<_> = doc
+        .spine()
+        .iter()
+        .map(|item| doc.media_overlay_for(&item.idref))
+        .collect();
+    assert_eq!(
+        vec![
+            overlay("c1-overlay"),
+            overlay("c2-overlay"),
+            overlay("c3-overlay"),
+            None,
+            None
+        ],
+        overlays
+    );
+    assert_eq!(
+        Some("c1-overlay"),
+        doc.resources()["c1"].media_overlay.as_deref()
+    );
+    assert_eq!(
+        Some("missing"),
+        doc.resources()["c5"].media_overlay.as_deref()
+    );
+    assert!(doc.resources()["c1"].extra_attrs.is_empty());
+    assert_eq!(None, doc.media_overlay_for("unknown"));
+}
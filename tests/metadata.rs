@@ -0,0 +1,1114 @@
+mod common;
+
+use common::{opf, opf_with, Fixture};
+use epub::doc::{EpubDoc, MetaLink, MetadataNode, OwnedAttribute, OwnedName};
+use epub::error::ArchiveError;
+use epub::metadata::{
+    DateKind, IdScheme, PartialDate, SeriesInfo, SeriesSource, Subject, TextDirection, TimeOfDay,
+    TitleKind,
+};
+use epub::options::{MetadataLimits, ParseOptions};
+use std::io::Cursor;
+
+#[test]
+fn creators_epub2_attributes() {
+    let metadata = r#"
+    <dc:creator opf:role="aut" opf:file-as="Kafka, Franz">Franz Kafka</dc:creator>
+    <dc:creator opf:role="trl">David Wyllie</dc:creator>
+    <dc:contributor opf:role="bkp">calibre (5.3.0)</dc:contributor>"#;
+    let doc = Fixture::new(opf("2.0", metadata, "", "<spine/>")).open();
+
+    let creators = doc.creators();
+    assert_eq!(2, creators.len());
+    assert_eq!("Franz Kafka", creators[0].name);
+    assert_eq!(Some("Kafka, Franz"), creators[0].sort_name.as_deref());
+    assert_eq!(Some("aut"), creators[0].role.as_deref());
+    assert_eq!("David Wyllie", creators[1].name);
+    assert_eq!(None, creators[1].sort_name);
+    assert_eq!(Some("trl"), creators[1].role.as_deref());
+    assert_eq!(Some("trl"), creators[1].raw.find_attr("role"));
+
+    let contributors = doc.contributors();
+    assert_eq!(1, contributors.len());
+    assert_eq!(Some("bkp"), contributors[0].role.as_deref());
+}
+
+#[test]
+fn creators_epub3_refines() {
+    let metadata = r##"
+    <dc:creator id="author">Franz Kafka</dc:creator>
+    <meta refines="#author" property="role" scheme="marc:relators">aut</meta>
+    <meta refines="#author" property="file-as">Kafka, Franz</meta>
+    <dc:creator id="illustrator">Ottomar Starke</dc:creator>
+    <meta refines="#illustrator" property="role" scheme="marc:relators">ill</meta>
+    <dc:creator>Anonymous</dc:creator>
+    <dc:contributor id="translator">David Wyllie</dc:contributor>
+    <meta refines="#translator" property="role" scheme="marc:relators">trl</meta>"##;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    let creators = doc.creators();
+    let summary: Vec<_> = creators
+        .iter()
+        .map(|c| (c.name.as_str(), c.sort_name.as_deref(), c.role.as_deref()))
+        .collect();
+    assert_eq!(
+        vec![
+            ("Franz Kafka", Some("Kafka, Franz"), Some("aut")),
+            ("Ottomar Starke", None, Some("ill")),
+            ("Anonymous", None, None),
+        ],
+        summary
+    );
+
+    let contributors = doc.contributors();
+    assert_eq!("David Wyllie", contributors[0].name);
+    assert_eq!(Some("trl"), contributors[0].role.as_deref());
+}
+
+#[test]
+fn refinements() {
+    let metadata = r##"
+    <dc:title id="title1">Main Title</dc:title>
+    <meta refines="#title1" property="title-type">main</meta>
+    <dc:title id="title2">A Subtitle</dc:title>
+    <meta refines="#title2" property="title-type">subtitle</meta>
+    <meta refines="#title2" property="display-seq">2</meta>
+    <dc:creator id="creator">Jane Doe</dc:creator>
+    <meta id="role" refines="#creator" property="role" scheme="marc:relators">aut</meta>
+    <meta refines="#creator" property="display-seq">1</meta>
+    <meta refines="#role" property="alternate-script">著者</meta>
+    <meta refines="#missing" property="title-type">dangling</meta>
+    <meta refines="#" property="title-type">empty</meta>"##;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    let titles = &doc.metadata()["title"];
+    let subtitle = doc.refinements(&titles[2]);
+    let subtitle: Vec<_> = subtitle
+        .iter()
+        .map(|r| (r.property, r.node.content.as_str()))
+        .collect();
+    assert_eq!(
+        vec![("title-type", "subtitle"), ("display-seq", "2")],
+        subtitle
+    );
+    // The fixture title has no id, and thus no refinements
+    assert!(doc.refinements(&titles[0]).is_empty());
+
+    let creator = &doc.creators()[0];
+    let refinements = doc.refinements(creator.raw);
+    assert_eq!("role", refinements[0].property);
+    assert_eq!("aut", refinements[0].node.content);
+    assert_eq!("display-seq", refinements[1].property);
+    assert_eq!("1", refinements[1].node.content);
+
+    // Chained: a refinement of the role refinement
+    let chained = doc.refinements(refinements[0].node);
+    assert_eq!("alternate-script", chained[0].property);
+    assert_eq!("著者", chained[0].node.content);
+
+    assert_eq!("dangling", doc.refinements_for("missing")[0].node.content);
+    assert!(doc.refinements_for("nonexistent").is_empty());
+}
+
+#[test]
+fn titles_epub3_title_types() {
+    let metadata = r##"
+    <dc:title id="collection" xml:lang="en">The Sherlock Holmes Stories</dc:title>
+    <meta refines="#collection" property="title-type">collection</meta>
+    <meta refines="#collection" property="display-seq">2</meta>
+    <dc:title id="main" xml:lang="en"> A Study in Scarlet </dc:title>
+    <meta refines="#main" property="title-type">main</meta>
+    <meta refines="#main" property="display-seq">1</meta>
+    <dc:title id="edition">Annotated Edition</dc:title>
+    <meta refines="#edition" property="title-type">edition</meta>
+    <dc:title id="odd">Odd</dc:title>
+    <meta refines="#odd" property="title-type">alternative</meta>"##;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    let titles = doc.titles();
+    // The fixture adds its own untyped title first
+    assert_eq!(5, titles.len());
+    assert_eq!(TitleKind::Subtitle, titles[0].kind);
+    assert_eq!("The Sherlock Holmes Stories", titles[1].text);
+    assert_eq!(TitleKind::Collection, titles[1].kind);
+    assert_eq!(Some(2), titles[1].display_seq);
+    assert_eq!(Some("en"), titles[1].lang.as_deref());
+    assert_eq!("A Study in Scarlet", titles[2].text);
+    assert_eq!(TitleKind::Main, titles[2].kind);
+    assert_eq!(Some(1), titles[2].display_seq);
+    assert_eq!(TitleKind::Edition, titles[3].kind);
+    assert_eq!(None, titles[3].display_seq);
+    assert_eq!(None, titles[3].lang);
+    assert_eq!(TitleKind::Other("alternative".to_string()), titles[4].kind);
+
+    assert_eq!(Some("A Study in Scarlet"), doc.main_title());
+}
+
+#[test]
+fn titles_epub2() {
+    let metadata = "<dc:title>A Novel</dc:title>";
+    let doc = Fixture::new(opf("2.0", metadata, "", "<spine/>")).open();
+
+    let titles = doc.titles();
+    let kinds: Vec<_> = titles.iter().map(|t| (t.text.as_str(), &t.kind)).collect();
+    assert_eq!(
+        vec![
+            ("Fixture", &TitleKind::Main),
+            ("A Novel", &TitleKind::Subtitle)
+        ],
+        kinds
+    );
+    assert_eq!(Some("Fixture"), doc.main_title());
+
+    let doc = Fixture::new(opf("2.0", "", "", "<spine/>")).open();
+    assert_eq!(1, doc.titles().len());
+    assert_eq!(Some("Fixture"), doc.main_title());
+}
+
+#[test]
+fn identifiers_epub2_schemes() {
+    let metadata = r#"
+    <dc:identifier opf:scheme="ISBN">978-0-14-303943-3</dc:identifier>
+    <dc:identifier opf:scheme="calibre">a6e2b1c4-0000-4000-8000-000000000000</dc:identifier>
+    <dc:identifier>urn:ISBN:0-306-40615-2</dc:identifier>
+    <dc:identifier>urn:uuid:09132750-3601-4d19-b3a4-55fdf8639849</dc:identifier>
+    <dc:identifier>doi:10.1000/182</dc:identifier>
+    <dc:identifier>https://doi.org/10.1000/183</dc:identifier>
+    <dc:identifier>10.1000/184</dc:identifier>
+    <dc:identifier>urn:oclc:12345</dc:identifier>
+    <dc:identifier>9780143039433</dc:identifier>
+    <dc:identifier>9780143039434</dc:identifier>
+    <dc:identifier>09132750-3601-4d19-b3a4-55fdf8639849</dc:identifier>"#;
+    let doc = Fixture::new(opf("2.0", metadata, "", "<spine/>")).open();
+
+    let identifiers = doc.identifiers();
+    let summary: Vec<_> = identifiers
+        .iter()
+        .map(|i| (i.value.as_str(), i.scheme.clone()))
+        .collect();
+    assert_eq!(
+        vec![
+            ("fixture", Some(IdScheme::Uuid)),
+            ("9780143039433", Some(IdScheme::Isbn)),
+            (
+                "a6e2b1c4-0000-4000-8000-000000000000",
+                Some(IdScheme::Other("calibre".to_string()))
+            ),
+            ("0306406152", Some(IdScheme::Isbn)),
+            ("09132750-3601-4d19-b3a4-55fdf8639849", Some(IdScheme::Uuid)),
+            ("10.1000/182", Some(IdScheme::Doi)),
+            ("10.1000/183", Some(IdScheme::Doi)),
+            ("10.1000/184", Some(IdScheme::Doi)),
+            ("urn:oclc:12345", Some(IdScheme::Urn("oclc".to_string()))),
+            ("9780143039433", Some(IdScheme::Isbn)),
+            // Wrong check digit
+            ("9780143039434", None),
+            ("09132750-3601-4d19-b3a4-55fdf8639849", Some(IdScheme::Uuid)),
+        ],
+        summary
+    );
+
+    assert!(identifiers[0].is_unique_identifier);
+    assert_eq!(Some("uid"), identifiers[0].id_attr.as_deref());
+    assert!(identifiers[1..].iter().all(|i| !i.is_unique_identifier));
+}
+
+#[test]
+fn identifiers_epub3_identifier_type() {
+    let metadata = r##"
+    <dc:identifier id="isbn">978-1-4028-9462-6</dc:identifier>
+    <meta refines="#isbn" property="identifier-type" scheme="onix:codelist5">15</meta>
+    <dc:identifier id="doi">10.1000/182</dc:identifier>
+    <meta refines="#doi" property="identifier-type" scheme="onix:codelist5">06</meta>
+    <dc:identifier id="urn">urn:uuid:09132750-3601-4d19-b3a4-55fdf8639849</dc:identifier>
+    <meta refines="#urn" property="identifier-type" scheme="onix:codelist5">22</meta>
+    <dc:identifier id="gtin">9781402894626</dc:identifier>
+    <meta refines="#gtin" property="identifier-type" scheme="onix:codelist5">03</meta>"##;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    let identifiers = doc.identifiers();
+    assert_eq!("9781402894626", identifiers[1].value);
+    assert_eq!(Some(IdScheme::Isbn), identifiers[1].scheme);
+    assert_eq!(Some(IdScheme::Doi), identifiers[2].scheme);
+    assert_eq!(Some(IdScheme::Uuid), identifiers[3].scheme);
+    assert_eq!("09132750-3601-4d19-b3a4-55fdf8639849", identifiers[3].value);
+    assert_eq!(
+        Some(IdScheme::Other("03".to_string())),
+        identifiers[4].scheme
+    );
+    assert_eq!(Some("urn:uuid:fixture"), doc.unique_identifier());
+    assert_eq!(
+        vec![true, false, false, false, false],
+        identifiers
+            .iter()
+            .map(|i| i.is_unique_identifier)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn isbn() {
+    let isbn = |metadata: &str| {
+        Fixture::new(opf("2.0", metadata, "", "<spine/>"))
+            .open()
+            .isbn()
+    };
+
+    assert_eq!(
+        Some("9780306406157".to_string()),
+        isbn(r#"<dc:identifier opf:scheme="ISBN">978-0-306-40615-7</dc:identifier>"#)
+    );
+    assert_eq!(
+        Some("0306406152".to_string()),
+        isbn("<dc:identifier>urn:isbn:0 306 40615 2</dc:identifier>")
+    );
+    // ISBN-13 wins, wherever it is
+    assert_eq!(
+        Some("9780306406157".to_string()),
+        isbn(
+            "<dc:identifier>0306406152</dc:identifier>
+            <dc:identifier>9780306406157</dc:identifier>"
+        )
+    );
+    // Only the UUID of the fixture
+    assert_eq!(None, isbn(""));
+    // Wrong check digits, or not an ISBN at all
+    assert_eq!(
+        None,
+        isbn(
+            r#"<dc:identifier opf:scheme="ISBN">978-0-306-40615-8</dc:identifier>
+            <dc:identifier>urn:isbn:030640615X</dc:identifier>
+            <dc:identifier opf:scheme="ISBN">not an isbn</dc:identifier>"#
+        )
+    );
+    assert_eq!(
+        Some("0306406152".to_string()),
+        isbn(
+            r#"<dc:identifier opf:scheme="ISBN">9780306406158</dc:identifier>
+            <dc:identifier opf:scheme="ISBN">0-306-40615-2</dc:identifier>"#
+        )
+    );
+}
+
+#[test]
+fn dates_epub2_events() {
+    let metadata = r#"
+    <dc:date opf:event="creation">1998</dc:date>
+    <dc:date opf:event="publication">2011-03</dc:date>
+    <dc:date opf:event="modification">2012-01-31</dc:date>
+    <dc:date opf:event="original-publication">1915</dc:date>"#;
+    let doc = Fixture::new(opf("2.0", metadata, "", "<spine/>")).open();
+
+    let dates = doc.dates();
+    let kinds: Vec<_> = dates.iter().map(|date| date.kind.clone()).collect();
+    assert_eq!(
+        vec![
+            DateKind::Creation,
+            DateKind::Publication,
+            DateKind::Modification,
+            DateKind::Other("original-publication".to_string()),
+        ],
+        kinds
+    );
+
+    let created = dates[0].parsed.unwrap();
+    assert_eq!(
+        (1998, None, None),
+        (created.year, created.month, created.day)
+    );
+
+    let published = doc.publication_date().unwrap();
+    assert_eq!("2011-03", published.raw);
+    let published = published.parsed.unwrap();
+    assert_eq!(
+        (2011, Some(3), None),
+        (published.year, published.month, published.day)
+    );
+}
+
+#[test]
+fn dates_epub3_modified() {
+    let metadata = r#"
+    <dc:date>2000-01-01</dc:date>
+    <dc:date>circa 1900</dc:date>
+    <meta property="dcterms:modified">2016-02-29T12:34:56Z</meta>"#;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    let dates = doc.dates();
+    assert_eq!(3, dates.len());
+    assert_eq!(DateKind::Unspecified, dates[1].kind);
+    assert_eq!(None, dates[1].parsed);
+    assert_eq!(DateKind::Modification, dates[2].kind);
+    assert_eq!(
+        Some(TimeOfDay {
+            hour: 12,
+            minute: 34,
+            second: 56,
+            utc_offset: Some(0),
+        }),
+        dates[2].parsed.unwrap().time
+    );
+
+    // The first dc:date without an event
+    assert_eq!("2000-01-01", doc.publication_date().unwrap().raw);
+
+    let doc = Fixture::new(opf("3.0", "", "", "<spine/>")).open();
+    assert_eq!(None, doc.publication_date());
+}
+
+#[test]
+fn partial_date_parse() {
+    let date = |year, month, day| PartialDate {
+        year,
+        month,
+        day,
+        time: None,
+    };
+    assert_eq!(Some(date(1998, None, None)), PartialDate::parse("1998"));
+    assert_eq!(
+        Some(date(2011, Some(3), None)),
+        PartialDate::parse(" 2011-03 ")
+    );
+    assert_eq!(
+        Some(date(2011, Some(3), Some(14))),
+        PartialDate::parse("2011-03-14")
+    );
+    assert_eq!(
+        Some(date(2011, Some(3), Some(14))),
+        PartialDate::parse("20110314")
+    );
+
+    let time = |value| PartialDate::parse(value).and_then(|date| date.time);
+    assert_eq!(
+        Some(TimeOfDay {
+            hour: 8,
+            minute: 5,
+            second: 0,
+            utc_offset: None,
+        }),
+        time("2011-03-14T08:05")
+    );
+    assert_eq!(
+        Some(-330),
+        time("2011-03-14T08:05:09.123-05:30").unwrap().utc_offset
+    );
+    assert_eq!(
+        Some(120),
+        time("2011-03-14T08:05:09+0200").unwrap().utc_offset
+    );
+    assert_eq!(Some(60), time("2011-03-14T08:05:09+01").unwrap().utc_offset);
+
+    for invalid in [
+        "",
+        "98",
+        "2011-13",
+        "2011-3",
+        "2011-03-32",
+        "2011-03T10:00",
+        "11/03/2011",
+        "2011-03-14T25:00",
+        "2012-01-01T12:00+1é1",
+        "2012-01-01T1é:00",
+    ] {
+        assert_eq!(None, PartialDate::parse(invalid), "{invalid}");
+    }
+
+    // Reached through the accessors of untrusted metadata
+    let metadata = "<dc:date>2012-01-01T12:00+1é1</dc:date>";
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+    assert_eq!(None, doc.publication_date().unwrap().parsed);
+}
+
+#[test]
+fn languages_single() {
+    let doc = epub::doc::EpubDoc::new("test.epub").unwrap();
+    assert_eq!(vec!["es"], doc.languages());
+    assert_eq!(Some("es".to_string()), doc.primary_language());
+}
+
+#[test]
+fn languages_normalized() {
+    let metadata = r#"
+    <dc:language>en_us</dc:language>
+    <dc:language> ja </dc:language>
+    <dc:language>EN-US</dc:language>
+    <dc:language>ZH-hant_tw</dc:language>
+    <dc:language>es-419</dc:language>
+    <dc:language>de-CH-x-Phonebk</dc:language>
+    <dc:language>English (US)</dc:language>
+    <dc:language></dc:language>"#;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    assert_eq!(
+        vec![
+            "en-US",
+            "ja",
+            "zh-Hant-TW",
+            "es-419",
+            "de-CH-x-phonebk",
+            "English (US)"
+        ],
+        doc.languages()
+    );
+    assert_eq!(Some("en-US".to_string()), doc.primary_language());
+
+    let doc = Fixture::new(opf("3.0", "", "", "<spine/>")).open();
+    assert_eq!(None, doc.primary_language());
+}
+
+#[test]
+fn subjects_keywords() {
+    let metadata = r#"
+    <dc:subject>Fiction</dc:subject>
+    <dc:subject> Fantasy </dc:subject>
+    <dc:subject>Fiction</dc:subject>"#;
+    let doc = Fixture::new(opf("2.0", metadata, "", "<spine/>")).open();
+
+    let labels: Vec<_> = doc.subjects().into_iter().map(|s| s.label).collect();
+    assert_eq!(vec!["Fiction", "Fantasy", "Fiction"], labels);
+    assert!(doc
+        .subjects()
+        .iter()
+        .all(|s| s.authority.is_none() && s.term.is_none()));
+}
+
+#[test]
+fn subjects_bisac_refines() {
+    let metadata = r##"
+    <dc:subject id="s1">FICTION / Classics</dc:subject>
+    <meta refines="#s1" property="authority">BISAC</meta>
+    <meta refines="#s1" property="term">FIC004000</meta>
+    <dc:subject id="s2">Whaling -- Fiction</dc:subject>
+    <meta refines="#s2" property="authority">LCSH</meta>
+    <dc:subject>Sea stories</dc:subject>"##;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    assert_eq!(
+        vec![
+            Subject {
+                label: "FICTION / Classics".to_string(),
+                authority: Some("BISAC".to_string()),
+                term: Some("FIC004000".to_string()),
+            },
+            Subject {
+                label: "Whaling -- Fiction".to_string(),
+                authority: Some("LCSH".to_string()),
+                term: None,
+            },
+            Subject {
+                label: "Sea stories".to_string(),
+                authority: None,
+                term: None,
+            },
+        ],
+        doc.subjects()
+    );
+}
+
+#[test]
+fn series_calibre() {
+    let metadata = r#"
+    <meta name="calibre:series" content=" The Stormlight Archive "/>
+    <meta name="calibre:series_index" content="2.5"/>"#;
+    let doc = Fixture::new(opf("2.0", metadata, "", "<spine/>")).open();
+
+    assert_eq!(
+        Some(SeriesInfo {
+            name: "The Stormlight Archive".to_string(),
+            position: Some(2.5),
+            source: SeriesSource::Calibre,
+        }),
+        doc.series()
+    );
+}
+
+#[test]
+fn series_belongs_to_collection() {
+    let metadata = r##"
+    <meta name="calibre:series" content="Calibre Series"/>
+    <meta name="calibre:series_index" content="3"/>
+    <meta property="belongs-to-collection" id="c1">Award Winners</meta>
+    <meta refines="#c1" property="collection-type">anthology</meta>
+    <meta property="belongs-to-collection" id="c2">The Wheel of Time</meta>
+    <meta refines="#c2" property="collection-type">series</meta>
+    <meta refines="#c2" property="group-position">0.5</meta>"##;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    assert_eq!(
+        Some(SeriesInfo {
+            name: "The Wheel of Time".to_string(),
+            position: Some(0.5),
+            source: SeriesSource::Collection,
+        }),
+        doc.series()
+    );
+
+    let metadata = r#"
+    <meta property="belongs-to-collection">Discworld</meta>
+    <meta name="calibre:series_index" content="not a number"/>"#;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+    let series = doc.series().unwrap();
+    assert_eq!("Discworld", series.name);
+    assert_eq!(None, series.position);
+}
+
+#[test]
+fn prefixed_metadata_names() {
+    // Everything prefixed, with the Dublin Core terms also given as old-style metas
+    let fixture = Fixture::new(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<opf:package xmlns:opf="http://www.idpf.org/2007/opf" xmlns:dc="http://purl.org/dc/elements/1.1/" version="3.0" unique-identifier="uid">
+  <opf:metadata>
+    <dc:identifier id="uid">urn:uuid:prefixed</dc:identifier>
+    <dc:title>Prefixed</dc:title>
+    <opf:meta name="dc:creator" content="Old Tool"/>
+    <opf:meta property="dc:publisher">Some Publisher</opf:meta>
+    <opf:meta property="dcterms:modified">2020-01-01T00:00:00Z</opf:meta>
+    <opf:meta name="cover" content="cover-image"/>
+  </opf:metadata>
+  <opf:manifest/>
+  <opf:spine/>
+</opf:package>"#,
+    );
+    let doc = fixture.open();
+
+    for (name, content) in [
+        ("title", "Prefixed"),
+        ("creator", "Old Tool"),
+        ("publisher", "Some Publisher"),
+    ] {
+        assert_eq!(Some(content), doc.mdata(name));
+        assert_eq!(Some(content), doc.mdata(&format!("dc:{name}")));
+        assert_eq!(
+            Some("http://purl.org/dc/elements/1.1/"),
+            doc.mdata_full(name).unwrap().namespace.as_deref()
+        );
+    }
+    assert!(!doc.metadata().contains_key("dc:creator"));
+
+    assert_eq!(Some("2020-01-01T00:00:00Z"), doc.mdata("dcterms:modified"));
+    assert_eq!(
+        Some("http://purl.org/dc/terms/"),
+        doc.mdata_full("dcterms:modified")
+            .unwrap()
+            .namespace
+            .as_deref()
+    );
+    assert_eq!(Some("cover-image"), doc.mdata("cover"));
+    assert_eq!(None, doc.mdata_full("cover").unwrap().namespace);
+}
+
+#[test]
+fn metadata_by_language() {
+    let metadata = r#"
+    <dc:title xml:lang="ja">吾輩は猫である</dc:title>
+    <dc:title xml:lang="en-US">I Am a Cat</dc:title>
+    <dc:title xml:lang="EN-gb">I Am a Cat, Too</dc:title>
+    <dc:description xml:lang="ja">説明</dc:description>
+    <dc:description>Description</dc:description>
+    <dc:publisher xml:lang="en">Publisher</dc:publisher>"#;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    // The fixture title has no language
+    assert_eq!(vec!["ja", "en-US", "EN-gb"], doc.mdata_languages("title"));
+    assert_eq!(vec!["ja"], doc.mdata_languages("dc:description"));
+
+    assert_eq!(Some("吾輩は猫である"), doc.mdata_lang("title", "ja"));
+    assert_eq!(Some("I Am a Cat"), doc.mdata_lang("title", "en"));
+    assert_eq!(Some("I Am a Cat, Too"), doc.mdata_lang("title", "en-GB"));
+    // No exact match, but the language does
+    assert_eq!(Some("I Am a Cat"), doc.mdata_lang("title", "en-AU"));
+    // Neither matches, so the untagged title applies
+    assert_eq!(Some("Fixture"), doc.mdata_lang("title", "fr"));
+    assert_eq!(Some("Fixture"), doc.mdata_lang("title", "e"));
+
+    assert_eq!(Some("Description"), doc.mdata_lang("description", "en"));
+    assert_eq!(Some("説明"), doc.mdata_lang("description", "ja-JP"));
+    assert_eq!(None, doc.mdata_lang("publisher", "fr"));
+    assert_eq!(None, doc.mdata_lang("subject", "en"));
+}
+
+#[test]
+fn prefixed_properties_by_iri() {
+    const IBOOKS: &str = "http://vocabulary.itunes.apple.com/rdf/ibooks/vocabulary-extensions-1.0/";
+    let metadata = r#"
+    <meta property="ib:version">1.2</meta>
+    <meta property="schema:accessMode">textual</meta>
+    <meta property="belongs-to-collection">Series</meta>
+    <meta property="dcterms:modified">2024-01-01T00:00:00Z</meta>"#;
+    let package_attrs = format!(r#"prefix="ib: {IBOOKS}  broken:  custom: http://example.com/""#);
+    let doc = Fixture::new(opf_with(
+        "3.0",
+        &package_attrs,
+        metadata,
+        "",
+        "<spine/>",
+        "",
+    ))
+    .open();
+
+    assert_eq!(Some("1.2"), doc.mdata_by_iri(&format!("{IBOOKS}version")));
+    assert_eq!(
+        Some(format!("{IBOOKS}version")),
+        doc.resolve_property("ib:version")
+    );
+    // `broken:` has no IRI, and `ibooks:` isn't declared
+    assert_eq!(None, doc.resolve_property("broken:x"));
+    assert_eq!(None, doc.resolve_property("ibooks:version"));
+    assert_eq!(
+        Some("http://example.com/x".to_string()),
+        doc.resolve_property("custom:x")
+    );
+
+    // Reserved prefixes and the default vocabulary
+    assert_eq!(
+        Some("textual"),
+        doc.mdata_by_iri("http://schema.org/accessMode")
+    );
+    assert_eq!(
+        Some("2024-01-01T00:00:00Z"),
+        doc.mdata_by_iri("http://purl.org/dc/terms/modified")
+    );
+    assert_eq!(
+        Some("Series"),
+        doc.mdata_by_iri("http://idpf.org/epub/vocab/package/meta/#belongs-to-collection")
+    );
+    assert_eq!(
+        Some("Fixture"),
+        doc.mdata_by_iri("http://purl.org/dc/elements/1.1/title")
+    );
+    assert_eq!(
+        None,
+        doc.mdata_by_iri("http://idpf.org/epub/vocab/package/meta/#title")
+    );
+    assert_eq!(None, doc.mdata_by_iri("http://example.com/version"));
+}
+
+#[test]
+fn package_language_and_direction() {
+    let metadata = r#"
+    <dc:title id="he">ספר בראשית</dc:title>
+    <dc:title id="en" xml:lang="en" dir="ltr">Genesis</dc:title>
+    <dc:title id="unknown" xml:lang="">???</dc:title>"#;
+    let doc = Fixture::new(opf_with(
+        "3.0",
+        r#"xml:lang="he" dir="rtl""#,
+        metadata,
+        "",
+        "<spine/>",
+        "",
+    ))
+    .open();
+
+    assert_eq!(Some("he"), doc.package_lang());
+    assert_eq!(Some(TextDirection::Rtl), doc.package_dir());
+
+    let titles = doc.titles();
+    let lang_dir: Vec<_> = titles
+        .iter()
+        .map(|title| (title.lang.as_deref(), title.dir))
+        .collect();
+    assert_eq!(
+        vec![
+            // The fixture title
+            (Some("he"), Some(TextDirection::Rtl)),
+            (Some("he"), Some(TextDirection::Rtl)),
+            (Some("en"), Some(TextDirection::Ltr)),
+            (None, Some(TextDirection::Rtl)),
+        ],
+        lang_dir
+    );
+
+    // Untagged titles are in the package language now
+    assert_eq!(vec!["he", "en"], doc.mdata_languages("title"));
+    assert_eq!(Some("Fixture"), doc.mdata_lang("title", "he-IL"));
+    assert_eq!(Some("???"), doc.mdata_lang("title", "fr"));
+
+    // Without the attributes
+    let doc = Fixture::new(opf("3.0", "", "", "<spine/>")).open();
+    assert_eq!(None, doc.package_lang());
+    assert_eq!(None, doc.package_dir());
+    assert_eq!(None, doc.titles()[0].dir);
+}
+
+#[test]
+fn metadata_link_records() {
+    const ONIX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ONIXMessage release="3.0" xmlns="http://ns.editeur.org/onix/3.0/reference">
+  <Product><RecordReference>fixture</RecordReference></Product>
+</ONIXMessage>"#;
+    let metadata = r#"
+    <link rel="record" href="meta/onix.xml#product" media-type="application/xml" properties="onix"/>
+    <link rel="record" href="https://example.com/marc.xml" media-type="application/marcxml+xml"/>
+    <link rel="record" href="../missing.xml"/>
+    <link href="no-rel.xml"/>"#;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>"))
+        .file("OEBPS/meta/onix.xml", ONIX)
+        .open();
+
+    let links = doc.meta_links();
+    assert_eq!(3, links.len());
+    assert_eq!(
+        MetaLink {
+            rel: "record".to_string(),
+            href: "meta/onix.xml#product".to_string(),
+            media_type: Some("application/xml".to_string()),
+            properties: Some("onix".to_string()),
+        },
+        links[0]
+    );
+    assert!(!links[0].is_remote());
+    assert!(links[1].is_remote());
+
+    assert_eq!(ONIX.as_bytes(), doc.get_meta_record(&links[0]).unwrap());
+    assert!(matches!(
+        doc.get_meta_record(&links[1]),
+        Err(ArchiveError::RemoteResource(href)) if href == "https://example.com/marc.xml"
+    ));
+    assert!(doc.get_meta_record(&links[2]).is_err());
+    // Links are still part of the raw metadata
+    assert_eq!(4, doc.metadata()["link"].len());
+}
+
+#[test]
+fn alternate_scripts() {
+    let metadata = r##"
+    <dc:creator id="creator">Natsume Soseki</dc:creator>
+    <meta refines="#creator" property="role" scheme="marc:relators">aut</meta>
+    <meta refines="#creator" property="alternate-script" xml:lang="ja">夏目 漱石</meta>
+    <meta refines="#creator" property="alternate-script" xml:lang="ja-Latn">Natsume Sōseki</meta>
+    <meta refines="#creator" property="alternate-script">なつめ そうせき</meta>
+    <dc:title id="title" xml:lang="en">I Am a Cat</dc:title>
+    <meta refines="#title" property="alternate-script" xml:lang="ja"> 吾輩は猫である </meta>
+    <dc:contributor>Aiko Ito</dc:contributor>"##;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    let creators = doc.creators();
+    assert_eq!(Some("aut"), creators[0].role.as_deref());
+    assert_eq!(
+        vec![
+            ("ja".to_string(), "夏目 漱石".to_string()),
+            ("ja-Latn".to_string(), "Natsume Sōseki".to_string()),
+            // Without a language of its own nor of the package
+            (String::new(), "なつめ そうせき".to_string()),
+        ],
+        creators[0].alternate_scripts
+    );
+    assert!(doc.contributors()[0].alternate_scripts.is_empty());
+
+    let title = doc
+        .titles()
+        .into_iter()
+        .find(|title| title.text == "I Am a Cat")
+        .unwrap();
+    assert_eq!(
+        vec![("ja".to_string(), "吾輩は猫である".to_string())],
+        title.alternate_scripts
+    );
+}
+
+#[test]
+fn release_identifier_fallbacks() {
+    let open = |metadata: &str| {
+        let opf = opf("3.0", metadata, "", "<spine/>")
+            // Drop the identifier of the fixture, each case brings its own
+            .replace(
+                r#"<dc:identifier id="uid">urn:uuid:fixture</dc:identifier>"#,
+                "",
+            );
+        Fixture::new(opf).open()
+    };
+
+    // EPUB3, with everything the specification requires
+    let doc = open(
+        r#"<dc:identifier id="uid">urn:isbn:9780000000002</dc:identifier>
+        <meta property="dcterms:modified">2024-05-01T12:00:00Z</meta>"#,
+    );
+    let expected = Some("urn:isbn:9780000000002@2024-05-01T12:00:00Z".to_string());
+    assert_eq!(expected, doc.get_release_identifier());
+    assert_eq!(expected, doc.release_identifier_strict());
+
+    // EPUB2, with a modification event
+    let doc = open(
+        r#"<dc:identifier id="uid">urn:isbn:9780000000002</dc:identifier>
+        <dc:date opf:event="publication">2001</dc:date>
+        <dc:date opf:event="modification">2010-02-03</dc:date>"#,
+    );
+    let modified = doc.modified_date().unwrap();
+    assert_eq!(DateKind::Modification, modified.kind);
+    assert_eq!(Some(2010), modified.parsed.map(|date| date.year));
+    assert_eq!(
+        Some("urn:isbn:9780000000002@2010-02-03".to_string()),
+        doc.get_release_identifier()
+    );
+    assert_eq!(None, doc.release_identifier_strict());
+
+    // No modification date and no unique identifier, the first identifier is all there is
+    let doc = open(r#"<dc:identifier>  </dc:identifier><dc:identifier>isbn-only</dc:identifier>"#);
+    assert_eq!(None, doc.modified_date());
+    assert_eq!(Some("isbn-only".to_string()), doc.get_release_identifier());
+    assert_eq!(None, doc.release_identifier_strict());
+
+    // No identifier at all
+    let doc = open(r#"<meta property="dcterms:modified">2024-05-01T12:00:00Z</meta>"#);
+    assert_eq!(None, doc.get_release_identifier());
+    assert_eq!(None, doc.release_identifier_strict());
+}
+
+#[test]
+fn unique_identifier_resolution() {
+    let open = |metadata: &str| {
+        let opf = opf("3.0", metadata, "", "<spine/>").replace(
+            r#"<dc:identifier id="uid">urn:uuid:fixture</dc:identifier>"#,
+            "",
+        );
+        Fixture::new(opf).open()
+    };
+
+    // Declared after other identifiers, one of which only differs in case
+    let doc = open(
+        r#"<dc:identifier id="isbn">urn:isbn:9780000000002</dc:identifier>
+        <dc:identifier id="UID">urn:uuid:wrong</dc:identifier>
+        <dc:identifier id="uid" opf:scheme="UUID">urn:uuid:right</dc:identifier>"#,
+    );
+    assert_eq!(Some("urn:uuid:right"), doc.unique_identifier());
+    let node = doc.unique_identifier_node().unwrap();
+    assert_eq!(Some("UUID"), node.find_attr("scheme"));
+    let unique: Vec<_> = doc
+        .identifiers()
+        .into_iter()
+        .filter(|identifier| identifier.is_unique_identifier)
+        .map(|identifier| identifier.id_attr)
+        .collect();
+    assert_eq!(vec![Some("uid".to_string())], unique);
+
+    // Whitespace around the id
+    let doc = open(r#"<dc:identifier id=" uid ">urn:uuid:spaced</dc:identifier>"#);
+    assert_eq!(Some("urn:uuid:spaced"), doc.unique_identifier());
+    assert!(doc.identifiers()[0].is_unique_identifier);
+
+    // Dropped by the metadata limits, but still known
+    let metadata = format!(
+        "{}<dc:identifier id=\"uid\">urn:uuid:last</dc:identifier>",
+        "<dc:identifier>filler</dc:identifier>".repeat(3)
+    );
+    let opf = opf("3.0", &metadata, "", "<spine/>").replace(
+        r#"<dc:identifier id="uid">urn:uuid:fixture</dc:identifier>"#,
+        "",
+    );
+    let options = ParseOptions {
+        metadata_limits: MetadataLimits {
+            max_entries_per_key: Some(2),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let doc =
+        EpubDoc::from_reader_with_options(Cursor::new(Fixture::new(opf).build()), options).unwrap();
+    assert_eq!(2, doc.metadata()["identifier"].len());
+    assert_eq!(Some("urn:uuid:last"), doc.unique_identifier());
+
+    // No match at all
+    let doc = open(r#"<dc:identifier id="other">urn:uuid:other</dc:identifier>"#);
+    assert_eq!(None, doc.unique_identifier());
+    assert_eq!(None, doc.unique_identifier_node());
+}
+
+#[cfg(feature = "encodings")]
+#[test]
+fn metadata_in_document_order() {
+    let doc = EpubDoc::new("tests/docs/book2.epub").unwrap();
+
+    let order: Vec<_> = doc
+        .metadata_iter()
+        .map(|(name, node)| (name, node.content.trim()))
+        .filter(|(name, _)| *name != "description")
+        .collect();
+    assert_eq!(
+        vec![
+            ("title", "Metamorphosis"),
+            ("title", "Metamorphosis2"),
+            ("language", "en"),
+            ("identifier", "http://metamorphosiskafka.pressbooks.com"),
+            ("creator", "Franz Kafka"),
+            ("publisher", "PressBooks.com"),
+            ("cover", "cover-image"),
+        ],
+        order
+    );
+
+    // Every element is in the map as well
+    let elements = doc.metadata_iter().count();
+    let in_map: usize = doc.metadata().values().map(Vec::len).sum();
+    assert_eq!(in_map, elements);
+}
+
+#[test]
+fn metadata_order_interleaved() {
+    let metadata = r##"
+    <dc:creator id="a">A</dc:creator>
+    <meta refines="#a" property="role">aut</meta>
+    <dc:creator id="b">B</dc:creator>
+    <meta refines="#b" property="role">ill</meta>"##;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+
+    let order: Vec<_> = doc
+        .metadata_iter()
+        .map(|(name, node)| format!("{name}={}", node.content))
+        .collect();
+    assert_eq!(
+        vec![
+            "identifier=urn:uuid:fixture",
+            "title=Fixture",
+            "creator=A",
+            "role=aut",
+            "creator=B",
+            "role=ill"
+        ],
+        order
+    );
+}
+
+#[test]
+fn container_metadata() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metadata xmlns="http://www.idpf.org/2013/metadata" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <dc:identifier id="release">urn:uuid:container</dc:identifier>
+  <dc:publisher xml:lang="en">Distributor Inc.</dc:publisher>
+  <meta property="dcterms:modified">2023-03-03T03:03:03Z</meta>
+  <meta name="distributor:channel" content="retail"/>
+</metadata>"#;
+    let fixture = Fixture::new(opf("3.0", "", "", "<spine/>"));
+    assert_eq!(None, fixture.open().container_metadata());
+
+    let doc = fixture.file("META-INF/metadata.xml", xml).open();
+    let metadata = doc.container_metadata().unwrap();
+    assert_eq!(4, metadata.len());
+
+    let identifier = &metadata["identifier"][0];
+    assert_eq!("urn:uuid:container", identifier.content);
+    assert_eq!(Some("release"), identifier.find_attr("id"));
+    assert_eq!(
+        Some("http://purl.org/dc/elements/1.1/"),
+        identifier.namespace.as_deref()
+    );
+    assert_eq!(Some("en"), metadata["publisher"][0].find_attr("lang"));
+    assert_eq!(
+        "2023-03-03T03:03:03Z",
+        metadata["dcterms:modified"][0].content
+    );
+    assert_eq!("retail", metadata["distributor:channel"][0].content);
+
+    // Cached, and separate from the package metadata
+    assert!(std::ptr::eq(metadata, doc.container_metadata().unwrap()));
+    assert_eq!(Some("urn:uuid:fixture"), doc.mdata("identifier"));
+}
+
+#[test]
+fn metadata_mutation() {
+    let metadata = r##"
+    <dc:title id="main">Typo'd Title</dc:title>
+    <meta refines="#main" property="title-type">main</meta>
+    <dc:title>Subtitle</dc:title>
+    <meta name="cover" content="cover"/>"##;
+    let manifest = r#"<item id="cover" href="cover.jpg" media-type="image/jpeg"/>"#;
+    let mut doc = Fixture::new(opf("3.0", metadata, manifest, "<spine/>")).open();
+
+    // Only the content of the first title changes
+    let previous = doc.set_mdata("title", "Fixed Title");
+    assert_eq!(Some("Fixture".to_string()), previous);
+    assert_eq!(Some("Fixed Title"), doc.mdata("title"));
+    assert_eq!(Some("Fixed Title"), doc.mdata("dc:title"));
+    let title = doc.mdata_full("title").unwrap();
+    assert_eq!(
+        Some("http://purl.org/dc/elements/1.1/"),
+        title.namespace.as_deref()
+    );
+    assert_eq!(3, doc.metadata()["title"].len());
+
+    // The refinements of a title don't depend on its content
+    let main = doc.metadata()["title"][1].clone();
+    assert_eq!("main", doc.refinements(&main)[0].node.content);
+
+    // The removed metadata is gone from the document order
+    assert_eq!(3, doc.remove_mdata("dc:title").len());
+    assert_eq!(None, doc.mdata("title"));
+    assert!(doc.metadata_iter().all(|(name, _)| name != "title"));
+
+    doc.add_mdata("title", MetadataNode::from_content("New Title"));
+    doc.add_mdata(
+        "title-type",
+        MetadataNode {
+            attr: vec![OwnedAttribute {
+                name: OwnedName {
+                    namespace: None,
+                    tag: "refines".to_string(),
+                },
+                value: "#main".into(),
+            }],
+            ..MetadataNode::from_content("main")
+        },
+    );
+    assert_eq!(Some("New Title"), doc.mdata("title"));
+    assert_eq!(
+        Some(&("title-type", "main")),
+        doc.metadata_iter()
+            .map(|(name, node)| (name, node.content.as_str()))
+            .collect::<Vec<_>>()
+            .last()
+    );
+    assert_eq!(2, doc.refinements_for("main").len());
+
+    // Derived state
+    assert_eq!(Some("cover"), doc.get_cover_id());
+    doc.set_mdata("cover", "missing");
+    assert_eq!(None, doc.get_cover_id());
+    doc.remove_mdata("cover");
+    doc.add_mdata("cover", MetadataNode::from_content("cover"));
+    assert_eq!(Some("cover"), doc.get_cover_id());
+
+    assert_eq!(
+        Some("urn:uuid:fixture".to_string()),
+        doc.set_mdata("identifier", "urn:uuid:changed")
+    );
+    assert_eq!(Some("urn:uuid:changed"), doc.unique_identifier());
+    assert_eq!(
+        Some("urn:uuid:changed"),
+        doc.unique_identifier_node()
+            .map(|node| node.content.as_str())
+    );
+    doc.remove_mdata("identifier");
+    assert_eq!(None, doc.unique_identifier());
+    assert!(doc.identifiers().is_empty());
+}
+
+#[test]
+fn description_as_text() {
+    let open = |description: &str| {
+        let metadata = format!("<dc:description>{description}</dc:description>");
+        Fixture::new(opf("3.0", &metadata, "", "<spine/>")).open()
+    };
+
+    // Markup escaped once, as it should be in XML
+    let doc = open(
+        "&lt;p&gt;A &lt;b&gt;bold&lt;/b&gt;   story&lt;/p&gt;&lt;p&gt;Second&amp;nbsp;line&lt;br/&gt;Third&lt;/p&gt;",
+    );
+    assert!(doc.mdata("description").unwrap().starts_with("<p>A <b>"));
+    assert_eq!(
+        Some("A bold story\nSecond\u{a0}line\nThird"),
+        doc.description_text().as_deref()
+    );
+
+    // Escaped twice
+    let doc = open("&amp;lt;div&amp;gt;Caf&amp;amp;eacute; &amp;amp; more&amp;lt;/div&amp;gt;");
+    assert_eq!(Some("Café & more"), doc.description_text().as_deref());
+
+    let doc = open("\n  A plain,\n  wrapped description. 1 &lt; 2\n");
+    assert_eq!(
+        Some("A plain, wrapped description. 1 < 2"),
+        doc.description_text().as_deref()
+    );
+
+    assert_eq!(None, open("&lt;p&gt; &lt;/p&gt;").description_text());
+    assert_eq!(None, open("").description_text());
+}
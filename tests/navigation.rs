@@ -0,0 +1,341 @@
+mod common;
+
+use common::{opf, xhtml, Fixture};
+use epub::doc::{EpubDoc, NavPoint};
+use epub::navigation::{Locator, Neighbors};
+use std::io::{Cursor, Read, Seek};
+
+const NAV: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<body>
+  <nav epub:type="toc">
+    <ol>
+      <li><a href="one.xhtml">One</a>
+        <ol>
+          <li><a href="one.xhtml#first">First</a></li>
+          <li><a href="one.xhtml#first">First, again</a></li>
+          <li><a href="one.xhtml#second">Second</a></li>
+        </ol>
+      </li>
+      <li><a href="notes.xhtml#note1">Note</a></li>
+      <li><a href="three.xhtml#middle">Three</a></li>
+    </ol>
+  </nav>
+</body>
+</html>"#;
+
+/// Spine: one, notes (non-linear), two (no ToC entries), three.
+fn fixture() -> EpubDoc<Cursor<Vec<u8>>> {
+    let manifest = r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>
+    <item id="notes" href="notes.xhtml" media-type="application/xhtml+xml"/>
+    <item id="two" href="two.xhtml" media-type="application/xhtml+xml"/>
+    <item id="three" href="three.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine>
+    <itemref idref="one"/>
+    <itemref idref="notes" linear="no"/>
+    <itemref idref="two"/>
+    <itemref idref="three"/>
+  </spine>"#;
+
+    Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/nav.xhtml", NAV)
+        .file(
+            "OEBPS/one.xhtml",
+            xhtml("<h1 id=\"first\"/><h1 id=\"second\"/>"),
+        )
+        .file("OEBPS/notes.xhtml", xhtml("<p id=\"note1\"/>"))
+        .file("OEBPS/two.xhtml", xhtml(""))
+        .file("OEBPS/three.xhtml", xhtml("<p id=\"middle\"/>"))
+        .open()
+}
+
+fn at(spine_index: usize, fragment: &str) -> Locator {
+    Locator {
+        spine_index,
+        fragment: Some(fragment.to_string()),
+    }
+}
+
+/// Walk the whole reading order from the start, checking `prev` mirrors `next`.
+fn walk<R: Read + Seek>(doc: &EpubDoc<R>) -> Vec<Locator> {
+    let mut output = vec![Locator::chapter(0)];
+
+    while let Some(next) = doc.reading_order_neighbors(output.last().unwrap()).next {
+        assert_eq!(
+            output.last(),
+            doc.reading_order_neighbors(&next).prev.as_ref()
+        );
+        output.push(next);
+    }
+
+    output
+}
+
+#[test]
+fn reading_order_includes_fragments() {
+    let doc = fixture();
+
+    assert_eq!(
+        vec![
+            Locator::chapter(0),
+            at(0, "first"),
+            at(0, "second"),
+            Locator::chapter(1),
+            at(1, "note1"),
+            Locator::chapter(2),
+            Locator::chapter(3),
+            at(3, "middle"),
+        ],
+        walk(&doc)
+    );
+}
+
+#[test]
+fn reading_order_linear_only() {
+    let mut doc = fixture();
+    doc.set_linear_navigation(true);
+
+    assert_eq!(
+        vec![
+            Locator::chapter(0),
+            at(0, "first"),
+            at(0, "second"),
+            Locator::chapter(2),
+            Locator::chapter(3),
+            at(3, "middle"),
+        ],
+        walk(&doc)
+    );
+
+    // Positions within a non-linear chapter can still navigate out of it
+    assert_eq!(
+        Neighbors {
+            prev: Some(at(0, "second")),
+            next: Some(at(1, "note1")),
+        },
+        doc.reading_order_neighbors(&Locator::chapter(1))
+    );
+}
+
+#[test]
+fn reading_order_edges() {
+    let doc = fixture();
+
+    assert_eq!(None, doc.reading_order_neighbors(&Locator::chapter(0)).prev);
+    assert_eq!(None, doc.reading_order_neighbors(&at(3, "middle")).next);
+    assert_eq!(
+        Neighbors::default(),
+        doc.reading_order_neighbors(&Locator::chapter(4))
+    );
+
+    // Unknown fragments behave like the start of the chapter
+    assert_eq!(
+        doc.reading_order_neighbors(&Locator::chapter(3)),
+        doc.reading_order_neighbors(&at(3, "unknown"))
+    );
+}
+
+#[test]
+fn reading_order_without_fragments() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+    let stops = walk(&doc);
+
+    assert_eq!(doc.get_num_pages(), stops.len());
+    assert!(stops.iter().all(|stop| stop.fragment.is_none()));
+}
+
+const BREADCRUMBS_NCX: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+<navMap>
+  <navPoint id="n1" playOrder="1"><navLabel><text>Part I</text></navLabel><content src="part1.xhtml"/>
+    <navPoint id="n2" playOrder="2"><navLabel><text>Chapter 1</text></navLabel><content src="ch1.xhtml"/>
+      <navPoint id="n3" playOrder="3"><navLabel><text>Section 1</text></navLabel><content src="ch1.xhtml#s1"/></navPoint>
+    </navPoint>
+  </navPoint>
+  <navPoint id="n4" playOrder="4"><navLabel><text>Part II</text></navLabel><content src="ch1.xhtml"/>
+    <navPoint id="n5" playOrder="5"><navLabel><text>Chapter 1, reprinted</text></navLabel><content src="ch1.xhtml"/></navPoint>
+    <navPoint id="n6" playOrder="6"><navLabel><text>Chapter 5</text></navLabel><content src="ch5.xhtml"/>
+      <navPoint id="n7" playOrder="7"><navLabel><text>Section 3</text></navLabel><content src="ch5.xhtml#s3"/></navPoint>
+    </navPoint>
+  </navPoint>
+  <navPoint id="n8" playOrder="8"><navLabel><text>Appendix</text></navLabel><content src="ch5.xhtml#s3"/>
+    <navPoint id="n9" playOrder="9"><navLabel><text>Extras</text></navLabel><content src="ch5.xhtml"/>
+      <navPoint id="n10" playOrder="10"><navLabel><text>Notes</text></navLabel><content src="ch5.xhtml"/>
+        <navPoint id="n11" playOrder="11"><navLabel><text>Section 1, annotated</text></navLabel><content src="ch1.xhtml#s1"/></navPoint>
+      </navPoint>
+    </navPoint>
+  </navPoint>
+</navMap>
+</ncx>"##;
+
+/// Several branches point at the same targets, at different depths.
+#[test]
+fn toc_breadcrumbs_prefer_deepest_then_earliest() {
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="part1" href="part1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch5" href="ch5.xhtml" media-type="application/xhtml+xml"/>
+    <item id="colophon" href="colophon.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine toc="ncx">
+    <itemref idref="part1"/>
+    <itemref idref="ch1"/>
+    <itemref idref="ch5"/>
+    <itemref idref="colophon"/>
+  </spine>"#;
+    let doc = Fixture::new(opf("2.0", "", manifest, spine))
+        .file("OEBPS/toc.ncx", BREADCRUMBS_NCX)
+        .open();
+    let labels =
+        |spine_index, fragment| NavPoint::path_labels(&doc.toc_breadcrumbs(spine_index, fragment));
+
+    assert_eq!(vec!["Part I"], labels(0, None));
+    // "Part II" is shallower, and "Chapter 1, reprinted" comes later at the same depth
+    assert_eq!(vec!["Part I", "Chapter 1"], labels(1, None));
+    // The annotated copy is deeper than the original
+    assert_eq!(
+        vec!["Appendix", "Extras", "Notes", "Section 1, annotated"],
+        labels(1, Some("s1"))
+    );
+    assert_eq!(
+        vec!["Part II", "Chapter 5", "Section 3"],
+        labels(2, Some("s3"))
+    );
+    // Unknown fragments fall back to the chapter
+    assert_eq!(
+        vec!["Appendix", "Extras", "Notes"],
+        labels(2, Some("missing"))
+    );
+    assert!(labels(3, None).is_empty());
+    assert!(labels(4, None).is_empty());
+
+    assert_eq!("Section 3", doc.toc_entry_for(2, Some("s3")).unwrap().label);
+    assert_eq!(None, doc.toc_entry_for(3, Some("s3")));
+}
+
+#[test]
+fn toc_flat_depths() {
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>"#;
+    let doc = Fixture::new(opf("2.0", "", manifest, r#"<spine toc="ncx"/>"#))
+        .file("OEBPS/toc.ncx", BREADCRUMBS_NCX)
+        .open();
+
+    let flat: Vec<_> = doc
+        .toc_flat()
+        .map(|(depth, nav)| format!("{}{}", "  ".repeat(depth), nav.label))
+        .collect();
+    assert_eq!(
+        vec![
+            "Part I",
+            "  Chapter 1",
+            "    Section 1",
+            "Part II",
+            "  Chapter 1, reprinted",
+            "  Chapter 5",
+            "    Section 3",
+            "Appendix",
+            "  Extras",
+            "    Notes",
+            "      Section 1, annotated",
+        ],
+        flat
+    );
+    // A view of the ToC itself
+    let (_, first) = doc.toc_flat().next().unwrap();
+    assert!(std::ptr::eq(&doc.get_toc()[0], first));
+
+    let doc = Fixture::new(opf("2.0", "", "", "<spine/>")).open();
+    assert_eq!(0, doc.toc_flat().count());
+}
+
+#[test]
+fn toc_entry_for_path_and_chapter() {
+    let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    let entry = doc.toc_entry_for_path("epub/text/chapter-1.xhtml").unwrap();
+    assert!(entry.label.contains("Winnie-the-Pooh and Some Bees"));
+    let chapter = doc
+        .resource_uri_to_chapter("epub/text/chapter-1.xhtml")
+        .unwrap();
+    assert_eq!(Some(entry), doc.toc_entry_for_chapter(chapter));
+    // Windows separators and percent-encoding are normalized
+    assert_eq!(
+        Some(entry),
+        doc.toc_entry_for_path("epub\\text\\chapter%2D1.xhtml")
+    );
+    assert_eq!(None, doc.toc_entry_for_path("epub/text/missing.xhtml"));
+    assert_eq!(None, doc.toc_entry_for_chapter(doc.spine().len()));
+
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="part1" href="part1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch5" href="ch5.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine toc="ncx">
+    <itemref idref="part1"/>
+    <itemref idref="ch1"/>
+    <itemref idref="ch5"/>
+  </spine>"#;
+    let doc = Fixture::new(opf("2.0", "", manifest, spine))
+        .file("OEBPS/toc.ncx", BREADCRUMBS_NCX)
+        .open();
+
+    // "Section 1, annotated" is deeper, but has a fragment
+    assert_eq!("Chapter 1", doc.toc_entry_for_chapter(1).unwrap().label);
+    assert_eq!(
+        "Notes",
+        doc.toc_entry_for_path("OEBPS/ch5.xhtml").unwrap().label
+    );
+
+    // Only entries with fragments point at the chapter
+    let ncx = BREADCRUMBS_NCX
+        .replace(r#"src="ch5.xhtml""#, r#"src="ch5.xhtml#top""#)
+        .replace(r#"src="ch1.xhtml""#, r#"src="ch1.xhtml#top""#);
+    let doc = Fixture::new(opf("2.0", "", manifest, spine))
+        .file("OEBPS/toc.ncx", ncx)
+        .open();
+    assert_eq!(
+        "Section 1, annotated",
+        doc.toc_entry_for_chapter(1).unwrap().label
+    );
+    assert_eq!(None, doc.toc_entry_for(1, None));
+}
+
+#[test]
+fn toc_spine_map_keeps_every_entry() {
+    let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    let map = doc.toc_spine_map();
+    assert_eq!(doc.toc_flat().count(), map.len());
+    for (flat_index, ((i, chapter), (_, nav))) in map.into_iter().zip(doc.toc_flat()).enumerate() {
+        assert_eq!(flat_index, i);
+        assert_eq!(
+            doc.resource_uri_to_chapter(nav.content.as_ref().unwrap()),
+            chapter
+        );
+    }
+
+    // ch5 is in the manifest but not in the spine
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="part1" href="part1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch5" href="ch5.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine toc="ncx"><itemref idref="part1"/><itemref idref="ch1"/></spine>"#;
+    let doc = Fixture::new(opf("2.0", "", manifest, spine))
+        .file("OEBPS/toc.ncx", BREADCRUMBS_NCX)
+        .open();
+    let chapters: Vec<_> = doc.toc_spine_map().into_iter().map(|(_, c)| c).collect();
+    assert_eq!(
+        vec![
+            Some(0),
+            Some(1),
+            Some(1),
+            Some(1),
+            Some(1),
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(1)
+        ],
+        chapters
+    );
+}
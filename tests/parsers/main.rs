@@ -1 +1,5 @@
+#[path = "../common/mod.rs"]
+mod common;
+
+mod v2;
 mod v3;
@@ -0,0 +1,331 @@
+use crate::common::{opf, Fixture};
+use epub::doc::EpubDoc;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+const NCX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <head>
+    <meta name="dtb:uid" content="urn:uuid:fixture"/>
+    <meta name="dtb:depth" content="1"/>
+  </head>
+  <docTitle><text>Fixture</text></docTitle>
+  <docAuthor><text>Jane Doe</text></docAuthor>
+  <navMap>
+    <navPoint id="one" playOrder="1">
+      <navLabel><text>One</text></navLabel>
+      <content src="one.xhtml"/>
+    </navPoint>
+  </navMap>
+</ncx>"#;
+
+#[test]
+fn spine_attributes() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+    assert_eq!(Some("ncx"), doc.toc_id());
+    assert_eq!(None, doc.spine_id());
+
+    let ncx = doc.ncx_metadata().unwrap();
+    assert_eq!(Some("Todo es mío"), ncx.title.as_deref());
+    assert_eq!(None, ncx.author);
+    assert_eq!(Some(3), ncx.depth);
+    assert_eq!(Some(0), ncx.total_page_count);
+    assert_eq!(Some(0), ncx.max_page_number);
+    assert_eq!(
+        Some("09132750-3601-4d19-b3a4-55fdf8639849"),
+        ncx.uid.as_deref()
+    );
+
+    let manifest = r#"<item id="toc-file" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine id="main-spine" toc="toc-file"><itemref idref="one"/></spine>"#;
+    let doc = Fixture::new(opf("2.0", "", manifest, spine))
+        .file("OEBPS/toc.ncx", NCX)
+        .open();
+
+    assert_eq!(Some("toc-file"), doc.toc_id());
+    assert_eq!(Some("main-spine"), doc.spine_id());
+    assert_eq!("One", doc.get_toc()[0].label);
+
+    let ncx = doc.ncx_metadata().unwrap();
+    assert_eq!(Some("Fixture"), ncx.title.as_deref());
+    assert_eq!(Some("Jane Doe"), ncx.author.as_deref());
+    assert_eq!(Some(1), ncx.depth);
+    assert_eq!(None, ncx.total_page_count);
+    assert_eq!(doc.unique_identifier(), ncx.uid.as_deref());
+}
+
+#[test]
+fn ncx_without_play_order() {
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>"#;
+    let open = |nav_map: &str| {
+        let ncx = format!(
+            r#"<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1"><navMap>{nav_map}</navMap></ncx>"#
+        );
+        Fixture::new(opf("2.0", "", manifest, r#"<spine toc="ncx"/>"#))
+            .file("OEBPS/toc.ncx", ncx)
+            .open()
+    };
+    let labels = |navs: &[epub::doc::NavPoint]| -> Vec<String> {
+        navs.iter()
+            .map(|nav| format!("{} {}", nav.play_order, nav.label))
+            .collect()
+    };
+
+    let doc = open(
+        r#"<navPoint id="c"><navLabel><text>Chapter 1</text></navLabel><content src="c1.xhtml"/>
+          <navPoint id="c1"><navLabel><text>Section 1</text></navLabel><content src="c1.xhtml#s1"/></navPoint>
+          <navPoint id="c2"><navLabel><text>Section 2</text></navLabel><content src="c1.xhtml#s2"/></navPoint>
+        </navPoint>
+        <navPoint id="no-content"><navLabel><text>Dropped</text></navLabel></navPoint>
+        <navPoint id="no-label"><content src="c3.xhtml"/></navPoint>
+        <navPoint id="d"><navLabel><text>Chapter 2</text></navLabel><content src="c2.xhtml"/></navPoint>"#,
+    );
+    let toc = doc.get_toc();
+    assert_eq!(vec!["0 Chapter 1", "3 Chapter 2"], labels(toc));
+    assert_eq!(vec!["0 Section 1", "1 Section 2"], labels(&toc[0].children));
+    assert_eq!(Some("s2"), toc[0].children[1].fragment.as_deref());
+
+    // Mixed with explicit play orders the document order is kept
+    let doc = open(
+        r#"<navPoint playOrder="5"><navLabel><text>A</text></navLabel><content src="a.xhtml"/></navPoint>
+        <navPoint><navLabel><text>B</text></navLabel><content src="b.xhtml"/></navPoint>
+        <navPoint playOrder="x"><navLabel><text>C</text></navLabel><content src="c.xhtml"/></navPoint>"#,
+    );
+    assert_eq!(vec!["5 A", "1 B", "2 C"], labels(doc.get_toc()));
+
+    // Explicit play orders are sorted, keeping the order of duplicates
+    let doc = open(
+        r#"<navPoint playOrder="3"><navLabel><text>C</text></navLabel><content src="c.xhtml"/></navPoint>
+        <navPoint playOrder="2"><navLabel><text>A</text></navLabel><content src="a.xhtml"/></navPoint>
+        <navPoint playOrder=" 2 "><navLabel><text>B</text></navLabel><content src="b.xhtml"/></navPoint>"#,
+    );
+    assert_eq!(vec!["2 A", "2 B", "3 C"], labels(doc.get_toc()));
+}
+
+#[test]
+fn ncx_id_and_class() {
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>"#;
+    let ncx = r#"<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1"><navMap>
+      <navPoint id="part-1" class="part" playOrder="1"><navLabel><text>Part One</text></navLabel><content src="p1.xhtml"/>
+        <navPoint id="ch-1" class="chapter" playOrder="2"><navLabel><text>Chapter 1</text></navLabel><content src="c1.xhtml"/></navPoint>
+      </navPoint>
+      <navPoint playOrder="3"><navLabel><text>Afterword</text></navLabel><content src="a.xhtml"/></navPoint>
+    </navMap></ncx>"#;
+    let doc = Fixture::new(opf("2.0", "", manifest, r#"<spine toc="ncx"/>"#))
+        .file("OEBPS/toc.ncx", ncx)
+        .open();
+
+    let toc = doc.get_toc();
+    assert_eq!(Some("part-1"), toc[0].id.as_deref());
+    assert_eq!(Some("part"), toc[0].kind.as_deref());
+    assert_eq!(Some("ch-1"), toc[0].children[0].id.as_deref());
+    assert_eq!(Some("chapter"), toc[0].children[0].kind.as_deref());
+    assert_eq!((None, None), (toc[1].id.as_deref(), toc[1].kind.as_deref()));
+}
+
+#[test]
+fn ncx_page_list() {
+    let ncx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <navMap>
+    <navPoint id="one" playOrder="1">
+      <navLabel><text>One</text></navLabel>
+      <content src="Text/one.xhtml"/>
+    </navPoint>
+  </navMap>
+  <pageList>
+    <pageTarget id="p2" type="normal" value="2" playOrder="3">
+      <navLabel><text>2</text></navLabel>
+      <content src="Text/one.xhtml#page%202"/>
+    </pageTarget>
+    <pageTarget id="p1" type="normal" value="1" playOrder="2">
+      <navLabel><text>1</text></navLabel>
+      <content src="Text/one.xhtml#page1"/>
+    </pageTarget>
+    <pageTarget id="p3" type="normal" value="3" playOrder="4">
+      <navLabel><text>3</text></navLabel>
+    </pageTarget>
+  </pageList>
+</ncx>"#;
+    let manifest = r#"<item id="ncx" href="nav/toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="one" href="Text/one.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine toc="ncx"><itemref idref="one"/></spine>"#;
+    let doc = Fixture::new(opf("2.0", "", manifest, spine))
+        .file("OEBPS/nav/toc.ncx", ncx)
+        .open();
+
+    let pages = doc.page_list();
+    assert_eq!(2, pages.len());
+    assert_eq!("1", pages[0].label);
+    assert_eq!(Some("page1"), pages[0].fragment.as_deref());
+    assert_eq!("2", pages[1].label);
+    assert_eq!(Some("page 2"), pages[1].fragment.as_deref());
+    // Resolved relative to the NCX, like the navPoints
+    for page in pages {
+        assert_eq!(doc.get_toc()[0].content.as_ref(), Some(&page.path));
+    }
+
+    // Without any playOrder the value decides the order
+    let ncx = ncx
+        .replace(r#" playOrder="3""#, "")
+        .replace(r#" playOrder="2""#, "");
+    let doc = Fixture::new(opf("2.0", "", manifest, spine))
+        .file("OEBPS/nav/toc.ncx", ncx)
+        .open();
+    let labels: Vec<_> = doc.page_list().iter().map(|p| p.label.as_str()).collect();
+    assert_eq!(vec!["1", "2"], labels);
+}
+
+#[test]
+fn ncx_without_spine_reference() {
+    let manifest = r#"<item id="toc-file" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>"#;
+    for spine in [
+        r#"<spine><itemref idref="one"/></spine>"#,
+        r#"<spine toc="missing"><itemref idref="one"/></spine>"#,
+    ] {
+        let doc = Fixture::new(opf("2.0", "", manifest, spine))
+            .file("OEBPS/toc.ncx", NCX)
+            .open();
+
+        assert_eq!(Some("toc-file"), doc.toc_id());
+        assert_eq!("One", doc.get_toc()[0].label);
+        assert!(doc.partial_failures().is_empty());
+        assert!(doc.parse_warnings()[0].message.contains("toc-file"));
+    }
+
+    // Found by its extension if the media type is wrong
+    let manifest = manifest.replace("application/x-dtbncx+xml", "text/xml");
+    let doc = Fixture::new(opf(
+        "2.0",
+        "",
+        &manifest,
+        r#"<spine><itemref idref="one"/></spine>"#,
+    ))
+    .file("OEBPS/toc.ncx", NCX)
+    .open();
+    assert_eq!("One", doc.get_toc()[0].label);
+
+    // The first NCX of the manifest wins
+    let manifest = r#"<item id="z-ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="a-ncx" href="other.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>"#;
+    let doc = Fixture::new(opf(
+        "2.0",
+        "",
+        manifest,
+        r#"<spine><itemref idref="one"/></spine>"#,
+    ))
+    .file("OEBPS/toc.ncx", NCX)
+    .open();
+    assert_eq!(Some("z-ncx"), doc.toc_id());
+}
+
+#[test]
+fn ncx_label_whitespace_and_markup() {
+    let ncx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <navMap>
+    <navPoint id="one" playOrder="1">
+      <navLabel><text><span>Italic</span> <span>Title</span></text></navLabel>
+      <content src="one.xhtml"/>
+    </navPoint>
+    <navPoint id="two" playOrder="2">
+      <navLabel>
+        <text>
+          Chapter
+          Two
+        </text>
+      </navLabel>
+      <content src="two.xhtml"/>
+    </navPoint>
+  </navMap>
+</ncx>"#;
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>"#;
+    let doc = Fixture::new(opf("2.0", "", manifest, r#"<spine toc="ncx"/>"#))
+        .file("OEBPS/toc.ncx", ncx)
+        .open();
+
+    let toc = doc.get_toc();
+    assert_eq!("Italic Title", toc[0].label);
+    assert_eq!("Chapter Two", toc[1].label);
+}
+
+#[test]
+fn ncx_duplicate_play_orders() {
+    let ncx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <navMap>
+    <navPoint playOrder="2"><navLabel><text>Two</text></navLabel><content src="two.xhtml"/></navPoint>
+    <navPoint playOrder="1"><navLabel><text>One</text></navLabel><content src="one.xhtml"/></navPoint>
+    <navPoint playOrder="1"><navLabel><text>Cover</text></navLabel><content src="cover.xhtml"/></navPoint>
+    <navPoint playOrder="2"><navLabel><text>Two</text></navLabel><content src="two.xhtml"/></navPoint>
+  </navMap>
+</ncx>"#;
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>"#;
+    let doc = Fixture::new(opf("2.0", "", manifest, r#"<spine toc="ncx"/>"#))
+        .file("OEBPS/toc.ncx", ncx)
+        .open();
+
+    // Entries sharing a play order keep their document order
+    let toc = doc.get_toc();
+    let labels: Vec<_> = toc.iter().map(|nav| nav.label.as_str()).collect();
+    assert_eq!(vec!["One", "Cover", "Two", "Two"], labels);
+
+    // Identical entries from different places in the document aren't equal
+    assert_ne!(toc[0], toc[1]);
+    assert_ne!(toc[2], toc[3]);
+    assert_eq!(toc[2], toc[2].clone());
+    let unique: HashSet<_> = toc.iter().collect();
+    assert_eq!(4, unique.len());
+
+    // The ordering agrees with equality, and falls back to the document order
+    assert_eq!(Ordering::Less, toc[0].cmp(&toc[1]));
+    assert_eq!(Ordering::Less, toc[2].cmp(&toc[3]));
+    assert_eq!(Ordering::Equal, toc[2].cmp(&toc[2].clone()));
+    let mut sorted = toc.clone();
+    sorted.reverse();
+    sorted.sort();
+    assert_eq!(toc, &sorted);
+}
+
+#[test]
+fn ncx_interleaved_play_orders() {
+    let ncx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <navMap>
+    <navPoint playOrder="3"><navLabel><text>Part II</text></navLabel><content src="part2.xhtml"/>
+      <navPoint playOrder="5"><navLabel><text>Chapter 3</text></navLabel><content src="ch3.xhtml"/></navPoint>
+      <navPoint playOrder="4"><navLabel><text>Chapter 2</text></navLabel><content src="ch2.xhtml"/></navPoint>
+      <navPoint playOrder="4"><navLabel><text>Chapter 2b</text></navLabel><content src="ch2b.xhtml"/></navPoint>
+    </navPoint>
+    <navPoint playOrder="1"><navLabel><text>Part I</text></navLabel><content src="part1.xhtml"/>
+      <navPoint playOrder="9"><navLabel><text>Chapter 1</text></navLabel><content src="ch1.xhtml"/></navPoint>
+      <navPoint><navLabel><text>Interlude</text></navLabel><content src="interlude.xhtml"/></navPoint>
+      <navPoint playOrder="2"><navLabel><text>Chapter 1b</text></navLabel><content src="ch1b.xhtml"/></navPoint>
+    </navPoint>
+    <navPoint playOrder="3"><navLabel><text>Appendix</text></navLabel><content src="appendix.xhtml"/></navPoint>
+  </navMap>
+</ncx>"#;
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>"#;
+    let doc = Fixture::new(opf("2.0", "", manifest, r#"<spine toc="ncx"/>"#))
+        .file("OEBPS/toc.ncx", ncx)
+        .open();
+
+    let labels = |toc: &[epub::doc::NavPoint]| -> Vec<String> {
+        toc.iter().map(|nav| nav.label.clone()).collect()
+    };
+    let toc = doc.get_toc();
+    // Sorted by play order, ties keep their document order
+    assert_eq!(vec!["Part I", "Part II", "Appendix"], labels(toc));
+    assert_eq!(
+        vec!["Chapter 2", "Chapter 2b", "Chapter 3"],
+        labels(&toc[1].children)
+    );
+    // A missing play order keeps the siblings in document order
+    assert_eq!(
+        vec!["Chapter 1", "Interlude", "Chapter 1b"],
+        labels(&toc[0].children)
+    );
+}
@@ -1,4 +1,7 @@
+use crate::common::{opf, opf_with, xhtml, Fixture};
 use epub::doc::EpubDoc;
+use epub::error::ArchiveError;
+use std::path::PathBuf;
 
 #[test]
 fn correct_table_of_contents() {
@@ -6,19 +9,96 @@ fn correct_table_of_contents() {
     let doc = EpubDoc::new(input_file).unwrap();
 
     assert!(
-        !doc.context.toc.is_empty(),
+        !doc.get_toc().is_empty(),
         "Table of contents is empty:\n{:#?}",
-        doc.context
+        doc.resources()
     );
 
     let labels = doc
-        .context
-        .toc
-        .into_iter()
-        .map(|i| i.label)
+        .get_toc()
+        .iter()
+        .map(|i| i.label.clone())
         .collect::<Vec<_>>();
 
-    assert!(labels.contains(&"Titlepage".to_string()),)
+    assert!(labels.contains(&"Titlepage".to_string()),);
+    // The chapters are only nested under the half title, not repeated at the top level
+    assert_eq!(7, labels.len());
+    assert!(!labels.iter().any(|label| label.starts_with("I: ")));
+
+    let part = &doc.get_toc()[4];
+    assert_eq!("Winnie-the-Pooh", part.label);
+    assert_eq!(10, part.children.len());
+    assert_eq!(
+        "I: In Which We Are Introduced to Winnie-the-Pooh and Some Bees, and the Stories Begin",
+        part.children[0].label
+    );
+    assert_eq!(
+        Some(PathBuf::from("epub/text/chapter-10.xhtml")),
+        part.children[9].content
+    );
+    assert!(part.children.iter().all(|nav| nav.children.is_empty()));
+    // Play order follows the document
+    assert_eq!(4, part.play_order);
+    assert_eq!(5, part.children[0].play_order);
+    assert_eq!(15, doc.get_toc()[5].play_order);
+}
+
+#[test]
+fn nested_table_of_contents() {
+    let manifest = r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="c2.xhtml" media-type="application/xhtml+xml"/>"#;
+    let nav = r##"<nav epub:type="toc"><h1>Contents</h1><ol>
+      <li><span>Part One</span><ol>
+        <li><a href="c1.xhtml">Chapter <em>1</em></a><ol>
+          <li><a href="c1.xhtml#s1">Section 1.1</a></li>
+          <li><a href="c1.xhtml#s2">Section 1.2</a></li>
+        </ol></li>
+      </ol></li>
+      <li><a href="c2.xhtml">Chapter 2</a></li>
+    </ol></nav>"##;
+    let doc = Fixture::new(opf(
+        "3.0",
+        "",
+        manifest,
+        r#"<spine><itemref idref="c1"/><itemref idref="c2"/></spine>"#,
+    ))
+    .file("OEBPS/nav.xhtml", xhtml(nav))
+    .open();
+
+    fn flatten(navs: &[epub::doc::NavPoint], depth: usize, out: &mut Vec<String>) {
+        for nav in navs {
+            out.push(
+                format!(
+                    "{}{} {} {}{}",
+                    "-".repeat(depth),
+                    nav.play_order,
+                    nav.label,
+                    nav.content
+                        .as_ref()
+                        .map_or(String::new(), |content| content.display().to_string()),
+                    nav.fragment
+                        .as_ref()
+                        .map_or(String::new(), |fragment| format!("#{fragment}"))
+                )
+                .trim_end()
+                .to_string(),
+            );
+            flatten(&nav.children, depth + 1, out);
+        }
+    }
+    let mut entries = Vec::new();
+    flatten(doc.get_toc(), 0, &mut entries);
+    assert_eq!(
+        vec![
+            "0 Part One",
+            "-1 Chapter 1 OEBPS/c1.xhtml",
+            "--2 Section 1.1 OEBPS/c1.xhtml#s1",
+            "--3 Section 1.2 OEBPS/c1.xhtml#s2",
+            "4 Chapter 2 OEBPS/c2.xhtml",
+        ],
+        entries
+    );
 }
 
 #[test]
@@ -33,12 +113,334 @@ fn correct_cover() {
     );
 
     let cover = doc.get_cover().unwrap();
-    let mime = doc
-        .context
-        .resources
-        .get(doc.get_cover_id().unwrap())
-        .unwrap();
+    let mime = doc.resources().get(doc.get_cover_id().unwrap()).unwrap();
 
     assert!(!cover.is_empty());
     assert_eq!(mime.mime, "image/svg+xml");
 }
+
+#[test]
+fn page_list() {
+    let manifest = r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="c1" href="Text/c1.xhtml" media-type="application/xhtml+xml"/>"#;
+    let nav = r##"<nav epub:type="toc"><ol><li><a href="Text/c1.xhtml">One</a></li></ol></nav>
+    <nav epub:type="page-list" hidden=""><ol>
+      <li><a href="Text/c1.xhtml#page-i">i</a></li>
+      <li><a href="Text/c1.xhtml#page1"><span>1</span></a></li>
+      <li><a>2</a></li>
+    </ol></nav>"##;
+    let doc = Fixture::new(opf(
+        "3.0",
+        "",
+        manifest,
+        r#"<spine><itemref idref="c1"/></spine>"#,
+    ))
+    .file("OEBPS/nav.xhtml", xhtml(nav))
+    .file("OEBPS/Text/c1.xhtml", xhtml(""))
+    .open();
+
+    assert_eq!(Some("3.0"), doc.epub_version());
+    let pages = doc.page_list();
+    assert_eq!(2, pages.len());
+    assert_eq!("i", pages[0].label);
+    assert_eq!(PathBuf::from("OEBPS/Text/c1.xhtml"), pages[0].path);
+    assert_eq!(Some("page-i"), pages[0].fragment.as_deref());
+    assert_eq!("1", pages[1].label);
+
+    let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    assert!(doc.page_list().is_empty());
+}
+
+#[test]
+fn page_list_of_nav_and_ncx() {
+    let ncx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <navMap>
+    <navPoint><navLabel><text>One</text></navLabel><content src="Text/c1.xhtml"/></navPoint>
+  </navMap>
+  <pageList>
+    <pageTarget value="1"><navLabel><text>1 (ncx)</text></navLabel><content src="Text/c1.xhtml#p1"/></pageTarget>
+  </pageList>
+</ncx>"#;
+    let manifest = r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="c1" href="Text/c1.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine toc="ncx"><itemref idref="c1"/></spine>"#;
+    let toc = r#"<nav epub:type="toc"><ol><li><a href="Text/c1.xhtml">One</a></li></ol></nav>"#;
+    let page_list = r##"<nav epub:type="page-list"><ol>
+      <li><a href="Text/c1.xhtml#p1">1 (nav)</a></li>
+      <li><a href="Text/c1.xhtml#p2">2 (nav)</a></li>
+    </ol></nav>"##;
+
+    // The nav takes precedence
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/toc.ncx", ncx)
+        .file("OEBPS/nav.xhtml", xhtml(&format!("{toc}{page_list}")))
+        .open();
+    let labels: Vec<_> = doc
+        .page_list()
+        .iter()
+        .map(|page| page.label.as_str())
+        .collect();
+    assert_eq!(vec!["1 (nav)", "2 (nav)"], labels);
+
+    // The NCX is the fallback
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/toc.ncx", ncx)
+        .file("OEBPS/nav.xhtml", xhtml(toc))
+        .open();
+    let labels: Vec<_> = doc
+        .page_list()
+        .iter()
+        .map(|page| page.label.as_str())
+        .collect();
+    assert_eq!(vec!["1 (ncx)"], labels);
+}
+
+#[test]
+fn dictionary_collections() {
+    let manifest = r#"
+    <item id="dict" href="dict/entries.xhtml" media-type="application/xhtml+xml"/>
+    <item id="skm" href="dict/keys.xml" media-type="application/vnd.epub.search-key-map+xml" properties="search-key-map"/>
+    <item id="index" href="index.xhtml" media-type="application/xhtml+xml"/>"#;
+    let collections = r##"
+  <collection role="dictionary" id="main-dictionary">
+    <metadata><dc:title xmlns:dc="http://purl.org/dc/elements/1.1/">Dictionary</dc:title></metadata>
+    <link href="dict/keys.xml"/>
+    <link href="dict/entries.xhtml#a"/>
+    <link href="https://example.com/remote.xhtml"/>
+  </collection>
+  <collection role="index">
+    <link href="index.xhtml"/>
+    <collection role="index-group">
+      <link href="index.xhtml#b"/>
+    </collection>
+  </collection>"##;
+    let doc = Fixture::new(opf_with(
+        "3.0",
+        "",
+        "",
+        manifest,
+        r#"<spine><itemref idref="dict"/></spine>"#,
+        collections,
+    ))
+    .open();
+
+    let collections = doc.collections();
+    assert_eq!(2, collections.len());
+
+    let dictionary = &collections[0];
+    assert_eq!("dictionary", dictionary.role);
+    assert_eq!(Some("main-dictionary"), dictionary.id.as_deref());
+    assert_eq!(
+        vec![
+            PathBuf::from("OEBPS/dict/keys.xml"),
+            PathBuf::from("OEBPS/dict/entries.xhtml")
+        ],
+        dictionary.links
+    );
+    // The links point at the manifest
+    for link in &dictionary.links {
+        assert!(doc.get_resource_mime_by_path(link).is_some(), "{link:?}");
+    }
+    assert!(dictionary.nested.is_empty());
+
+    let index = &collections[1];
+    assert_eq!("index", index.role);
+    assert_eq!(None, index.id);
+    assert_eq!(1, index.nested.len());
+    assert_eq!("index-group", index.nested[0].role);
+    assert_eq!(
+        vec![PathBuf::from("OEBPS/index.xhtml")],
+        index.nested[0].links
+    );
+}
+
+#[test]
+fn nav_id_and_type() {
+    let manifest =
+        r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#;
+    let nav = r##"<nav epub:type="toc"><ol>
+      <li id="part-1" epub:type="part"><a href="p1.xhtml">Part One</a><ol>
+        <li><a id="ch-1" epub:type="chapter" href="c1.xhtml">Chapter 1</a></li>
+      </ol></li>
+      <li><a href="a.xhtml">Afterword</a></li>
+    </ol></nav>"##;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>"))
+        .file("OEBPS/nav.xhtml", xhtml(nav))
+        .open();
+
+    let toc = doc.get_toc();
+    assert_eq!(Some("part-1"), toc[0].id.as_deref());
+    assert_eq!(Some("part"), toc[0].kind.as_deref());
+    assert_eq!(Some("ch-1"), toc[0].children[0].id.as_deref());
+    assert_eq!(Some("chapter"), toc[0].children[0].kind.as_deref());
+    assert_eq!((None, None), (toc[1].id.as_deref(), toc[1].kind.as_deref()));
+}
+
+#[test]
+fn nav_without_property() {
+    let manifest = r#"<item id="contents" href="Text/toc.xhtml" media-type="application/xhtml+xml"/>
+    <item id="nav" href="Text/nav.xhtml" media-type="application/xhtml+xml"/>
+    <item id="one" href="Text/one.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine><itemref idref="contents"/><itemref idref="one"/></spine>"#;
+    let nav = xhtml(r#"<nav epub:type="toc"><ol><li><a href="one.xhtml">One</a></li></ol></nav>"#);
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        // Named like a nav, but a plain chapter
+        .file("OEBPS/Text/toc.xhtml", xhtml("<p>Contents</p>"))
+        .file("OEBPS/Text/nav.xhtml", nav)
+        .file("OEBPS/Text/one.xhtml", xhtml(""))
+        .open();
+
+    assert_eq!(1, doc.get_toc().len());
+    assert_eq!("One", doc.get_toc()[0].label);
+    assert_eq!(
+        Some(PathBuf::from("OEBPS/Text/one.xhtml")),
+        doc.get_toc()[0].content
+    );
+    assert!(doc.parse_warnings()[0].message.contains("`nav`"));
+
+    // Nothing which looks like a nav, which isn't an error
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/Text/toc.xhtml", "<not xml")
+        .file("OEBPS/Text/one.xhtml", xhtml(""))
+        .open();
+    assert!(doc.get_toc().is_empty());
+    assert!(doc.partial_failures().is_empty());
+}
+
+#[test]
+fn nav_label_whitespace_and_markup() {
+    let manifest =
+        r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#;
+    let nav = r##"<nav epub:type="toc"><ol>
+      <li><a href="c1.xhtml"><span class="num">I.</span> <span class="i">Italic</span><span> Title</span></a></li>
+      <li><a href="c2.xhtml">
+            Chapter
+            Two
+          </a></li>
+    </ol></nav>"##;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>"))
+        .file("OEBPS/nav.xhtml", xhtml(nav))
+        .open();
+
+    let toc = doc.get_toc();
+    assert_eq!("I. Italic Title", toc[0].label);
+    assert_eq!("Chapter Two", toc[1].label);
+}
+
+#[test]
+fn invalid_nav_href() {
+    let manifest =
+        r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#;
+    let nav = r#"<nav epub:type="toc"><ol>
+      <li><a href="c%FF.xhtml">Broken</a></li>
+      <li><a href="c2.xhtml">Chapter Two</a></li>
+    </ol></nav>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>"))
+        .file("OEBPS/nav.xhtml", xhtml(nav))
+        .open();
+
+    assert_eq!(1, doc.get_toc().len());
+    assert_eq!("Chapter Two", doc.get_toc()[0].label);
+    assert_eq!(1, doc.parse_warnings().len());
+    assert!(doc.parse_warnings()[0].message.contains("c%FF.xhtml"));
+}
+
+#[test]
+fn span_part_headers() {
+    let manifest = r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="c2.xhtml" media-type="application/xhtml+xml"/>"#;
+    let nav = r##"<nav epub:type="toc"><ol>
+      <li><span>Part One</span><ol><li><a href="c1.xhtml#start">Chapter 1</a></li></ol></li>
+      <li><span>Empty Part</span><ol></ol></li>
+      <li><span>Part Two</span><ol><li><a href="c2.xhtml">Chapter 2</a></li></ol></li>
+    </ol></nav>"##;
+    let mut doc = Fixture::new(opf(
+        "3.0",
+        "",
+        manifest,
+        r#"<spine><itemref idref="c1"/><itemref idref="c2"/></spine>"#,
+    ))
+    .file("OEBPS/nav.xhtml", xhtml(nav))
+    .open();
+
+    let toc = doc.get_toc();
+    let parts: Vec<_> = toc.iter().map(|nav| nav.label.as_str()).collect();
+    assert_eq!(vec!["Part One", "Empty Part", "Part Two"], parts);
+    assert_eq!("Chapter 1", toc[0].children[0].label);
+    assert!(toc[1].children.is_empty());
+    assert_eq!("Chapter 2", toc[2].children[0].label);
+    // The headers have no target of their own
+    assert!(toc.iter().all(|part| part.content.is_none()));
+    assert!(toc.iter().all(|part| part.fragment.is_none()));
+    assert_eq!(Some("start"), toc[0].children[0].fragment.as_deref());
+
+    // The navigation helpers skip the headers
+    assert_eq!("Chapter 2", doc.toc_entry_for_chapter(1).unwrap().label);
+    assert_eq!("Chapter 1", doc.toc_entry_for_chapter(0).unwrap().label);
+    assert_eq!(vec![(1, Some(0)), (4, Some(1))], doc.toc_spine_map());
+    let header = doc.get_toc()[0].clone();
+    assert!(matches!(
+        doc.go_to_toc_entry(&header),
+        Err(ArchiveError::NoTarget(label)) if label == "Part One"
+    ));
+    let chapter = doc.get_toc()[2].children[0].clone();
+    assert_eq!(None, doc.go_to_toc_entry(&chapter).unwrap());
+    assert_eq!(1, doc.get_current_chapter());
+}
+
+#[test]
+fn hidden_nav_entries() {
+    let manifest =
+        r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#;
+    let nav = r##"<nav epub:type="toc"><ol>
+      <li><a href="c1.xhtml">Chapter 1</a></li>
+      <li hidden=""><a href="c2.xhtml">Chapter 2</a><ol><li><a href="c2.xhtml#s1">Section</a></li></ol></li>
+      <li><a href="c3.xhtml">Chapter 3</a></li>
+    </ol></nav>
+    <nav epub:type="page-list" hidden=""><ol>
+      <li><a href="c1.xhtml#p1">1</a></li>
+      <li><a href="c2.xhtml#p2">2</a></li>
+    </ol></nav>
+    <nav epub:type="landmarks"><ol>
+      <li><a epub:type="bodymatter" href="c1.xhtml">Start</a></li>
+    </ol></nav>"##;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>"))
+        .file("OEBPS/nav.xhtml", xhtml(nav))
+        .open();
+
+    // Hidden entries are still part of the ToC, along with their children
+    let toc = doc.get_toc();
+    assert_eq!(3, toc.len());
+    let hidden: Vec<_> = toc.iter().map(|nav| nav.hidden).collect();
+    assert_eq!(vec![false, true, false], hidden);
+    assert!(toc[1].children[0].hidden);
+
+    assert_eq!(2, doc.page_list().len());
+    assert!(doc.page_list().iter().all(|page| page.hidden));
+    assert!(!doc.landmarks()[0].hidden);
+}
+
+#[test]
+fn multiple_item_properties() {
+    let manifest = r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav scripted"/>
+    <item id="cover" href="cover.svg" media-type="image/svg+xml" properties="cover-image  svg"/>"#;
+    let nav = r#"<nav epub:type="toc"><ol><li><a href="c1.xhtml">Chapter 1</a></li></ol></nav>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, "<spine/>"))
+        .file("OEBPS/nav.xhtml", xhtml(nav))
+        .open();
+
+    assert_eq!(1, doc.get_toc().len());
+    assert_eq!(Some("cover"), doc.get_cover_id());
+
+    let nav = &doc.resources()["nav"];
+    assert_eq!(vec!["nav", "scripted"], nav.properties);
+    assert!(nav.has_property("scripted"));
+    assert!(!nav.has_property("nav scripted"));
+    assert_eq!(
+        vec!["cover-image", "svg"],
+        doc.resources()["cover"].properties
+    );
+}
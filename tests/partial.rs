@@ -0,0 +1,220 @@
+mod common;
+
+use common::{xhtml, Fixture};
+use epub::doc::{EpubDoc, OpfSection};
+use epub::error::ArchiveError;
+use epub::options::{ParseMode, ParseOptions};
+use std::io::Cursor;
+
+const NAV: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<body>
+  <nav epub:type="toc">
+    <ol>
+      <li><a href="one.xhtml">One</a></li>
+      <li><a href="two.xhtml">Two</a></li>
+    </ol>
+  </nav>
+</body>
+</html>"#;
+
+const METADATA: &str = r#"<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="uid">urn:uuid:fixture</dc:identifier>
+    <dc:title>Fixture</dc:title>
+  </metadata>"#;
+const MANIFEST: &str = r#"<manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="cover" href="cover.svg" media-type="image/svg+xml" properties="cover-image"/>
+    <item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>
+    <item id="two" href="two.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>"#;
+const SPINE: &str = r#"<spine><itemref idref="one"/><itemref idref="two"/></spine>"#;
+const GUIDE: &str = r#"<guide>
+    <reference type="text" title="Start" href="one.xhtml#start"/>
+    <reference title="Missing type" href="two.xhtml"/>
+  </guide>"#;
+
+/// Build a fixture where the given section is left out of the package document.
+fn fixture_without(section: Option<OpfSection>) -> Fixture {
+    let part = |s: OpfSection, content: &'static str| {
+        if section == Some(s) {
+            ""
+        } else {
+            content
+        }
+    };
+    let opf = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid">
+  {}
+  {}
+  {}
+</package>"#,
+        part(OpfSection::Metadata, METADATA),
+        part(OpfSection::Manifest, MANIFEST),
+        part(OpfSection::Spine, SPINE),
+    );
+
+    Fixture::new(opf)
+        .file("OEBPS/nav.xhtml", NAV)
+        .file("OEBPS/cover.svg", "<svg/>")
+        .file("OEBPS/one.xhtml", xhtml("<p>One</p>"))
+        .file("OEBPS/two.xhtml", xhtml("<p>Two</p>"))
+}
+
+fn open(fixture: &Fixture, mode: ParseMode) -> epub::error::Result<EpubDoc<Cursor<Vec<u8>>>> {
+    EpubDoc::from_reader_with_options(
+        Cursor::new(fixture.build()),
+        ParseOptions {
+            mode,
+            ..Default::default()
+        },
+    )
+}
+
+fn failed_sections(doc: &EpubDoc<Cursor<Vec<u8>>>) -> Vec<OpfSection> {
+    doc.partial_failures().iter().map(|f| f.section).collect()
+}
+
+#[test]
+fn complete_package_has_no_failures() {
+    let doc = open(&fixture_without(None), ParseMode::Strict).unwrap();
+    assert!(doc.partial_failures().is_empty());
+    assert_eq!(2, doc.get_toc().len());
+}
+
+#[test]
+fn missing_spine() {
+    let fixture = fixture_without(Some(OpfSection::Spine));
+    assert!(open(&fixture, ParseMode::Strict).is_err());
+
+    let mut doc = open(&fixture, ParseMode::Lenient).unwrap();
+    assert_eq!(vec![OpfSection::Spine], failed_sections(&doc));
+
+    // Everything not depending on the spine still works
+    assert_eq!(Some("Fixture"), doc.mdata("title"));
+    assert_eq!(b"<svg/>".to_vec(), doc.get_cover().unwrap());
+    assert_eq!(2, doc.get_toc().len());
+
+    let nav = doc.get_toc()[0].clone();
+    assert!(matches!(
+        doc.go_to_toc_entry(&nav),
+        Err(ArchiveError::SectionUnavailable(OpfSection::Spine))
+    ));
+}
+
+#[test]
+fn missing_manifest() {
+    let fixture = fixture_without(Some(OpfSection::Manifest));
+    assert!(open(&fixture, ParseMode::Strict).is_err());
+
+    let doc = open(&fixture, ParseMode::Lenient).unwrap();
+    assert_eq!(vec![OpfSection::Manifest], failed_sections(&doc));
+    assert_eq!(Some("Fixture"), doc.mdata("title"));
+    assert_eq!(2, doc.get_num_pages());
+    #[cfg(feature = "rewrite")]
+    assert!(matches!(
+        doc.get_page_with_epub_uris("one", "epub://"),
+        Err(ArchiveError::SectionUnavailable(OpfSection::Manifest))
+    ));
+}
+
+#[test]
+fn missing_metadata() {
+    let fixture = fixture_without(Some(OpfSection::Metadata));
+    assert!(open(&fixture, ParseMode::Strict).is_err());
+
+    let doc = open(&fixture, ParseMode::Lenient).unwrap();
+    assert_eq!(vec![OpfSection::Metadata], failed_sections(&doc));
+    assert_eq!(None, doc.mdata("title"));
+    assert_eq!(2, doc.get_num_pages());
+    assert!(doc.get_cover().is_some());
+}
+
+#[test]
+fn broken_toc_is_never_fatal() {
+    let fixture = fixture_without(None).file("OEBPS/nav.xhtml", "<html><body/></html>");
+
+    for mode in [ParseMode::Strict, ParseMode::Lenient] {
+        let doc = open(&fixture, mode).unwrap();
+        assert_eq!(vec![OpfSection::Toc], failed_sections(&doc));
+        assert!(doc.get_toc().is_empty());
+        assert_eq!(2, doc.get_num_pages());
+    }
+}
+
+#[test]
+fn malformed_guide() {
+    let opf = common::opf_with(
+        "2.0",
+        "",
+        "",
+        r#"<item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>"#,
+        r#"<spine><itemref idref="one"/></spine>"#,
+        GUIDE,
+    );
+    let doc = Fixture::new(opf).open();
+
+    assert_eq!(vec![OpfSection::Guide], failed_sections(&doc));
+    assert_eq!(1, doc.guide().len());
+    assert_eq!("text", doc.guide()[0].kind);
+    assert_eq!(Some("start"), doc.guide()[0].fragment.as_deref());
+}
+
+fn escaped_package(escape: impl Fn(&str) -> String) -> Fixture {
+    let opf = common::opf(
+        "2.0",
+        "<dc:creator>Fish &amp; Chips</dc:creator>",
+        r#"<item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>"#,
+        r#"<spine><itemref idref="one"/></spine>"#,
+    );
+
+    Fixture::new(escape(&opf)).file("OEBPS/one.xhtml", xhtml("<p>One</p>"))
+}
+
+fn entity_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn percent_encode(text: &str) -> String {
+    percent_encoding::utf8_percent_encode(text, percent_encoding::NON_ALPHANUMERIC).to_string()
+}
+
+#[test]
+fn entity_escaped_package() {
+    let fixture = escaped_package(entity_escape);
+    assert!(open(&fixture, ParseMode::Strict).is_err());
+
+    let doc = open(&fixture, ParseMode::Lenient).unwrap();
+    assert_eq!(Some("Fixture"), doc.mdata("title"));
+    assert_eq!(Some("Fish & Chips"), doc.mdata("creator"));
+    assert_eq!(1, doc.get_num_pages());
+
+    let warning = &doc.parse_warnings()[0];
+    assert_eq!(OpfSection::Package, warning.section);
+    assert!(warning.message.contains("HTML entity escaped"));
+}
+
+#[test]
+fn percent_encoded_package() {
+    let fixture = escaped_package(percent_encode);
+    assert!(open(&fixture, ParseMode::Strict).is_err());
+
+    let doc = open(&fixture, ParseMode::Lenient).unwrap();
+    assert_eq!(Some("Fixture"), doc.mdata("title"));
+    assert_eq!(Some("Fish & Chips"), doc.mdata("creator"));
+    assert_eq!(OpfSection::Package, doc.parse_warnings()[0].section);
+    assert!(doc.parse_warnings()[0].message.contains("percent-encoded"));
+}
+
+#[test]
+fn escaped_package_is_recovered_once() {
+    let fixture = escaped_package(|opf| entity_escape(&entity_escape(opf)));
+    assert!(open(&fixture, ParseMode::Lenient).is_err());
+
+    let fixture = escaped_package(|opf| percent_encode(&entity_escape(opf)));
+    assert!(open(&fixture, ParseMode::Lenient).is_err());
+}
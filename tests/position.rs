@@ -0,0 +1,105 @@
+use epub::doc::EpubDoc;
+use epub::error::ArchiveError;
+use epub::position::ReadingPosition;
+
+#[test]
+fn restore_position_on_fresh_doc() {
+    let mut doc = EpubDoc::new("test.epub").unwrap();
+    assert!(doc.set_current_chapter(5));
+    doc.set_current_fraction(0.25);
+    let position = doc.current_position().unwrap();
+
+    let mut fresh = EpubDoc::new("test.epub").unwrap();
+    fresh.restore_position(&position).unwrap();
+    assert_eq!(Some("004.xhtml"), fresh.get_current_id());
+    assert_eq!(Some(position), fresh.current_position());
+}
+
+#[test]
+fn restore_position_fallbacks() {
+    let mut doc = EpubDoc::new("test.epub").unwrap();
+
+    // Unknown id, but a valid index
+    let position = ReadingPosition {
+        spine_id: "removed.xhtml".into(),
+        spine_index: 3,
+        fraction: 2.0,
+    };
+    doc.restore_position(&position).unwrap();
+    assert_eq!(Some("002.xhtml"), doc.get_current_id());
+    assert_eq!(1.0, doc.current_position().unwrap().fraction);
+
+    // e.g. a position stored by a buggy reader
+    for fraction in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+        doc.restore_position(&ReadingPosition {
+            fraction,
+            ..position.clone()
+        })
+        .unwrap();
+        assert_eq!(0.0, doc.current_position().unwrap().fraction);
+    }
+
+    // The id takes precedence over the index
+    let position = ReadingPosition {
+        spine_id: "010.xhtml".into(),
+        spine_index: 3,
+        fraction: 0.0,
+    };
+    doc.restore_position(&position).unwrap();
+    assert_eq!(11, doc.get_current_chapter());
+
+    let position = ReadingPosition {
+        spine_id: "removed.xhtml".into(),
+        spine_index: 300,
+        fraction: 0.0,
+    };
+    assert!(matches!(
+        doc.restore_position(&position),
+        Err(ArchiveError::InvalidPosition)
+    ));
+    assert_eq!(11, doc.get_current_chapter());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn reading_position_serde_round_trip() {
+    let mut doc = EpubDoc::new("test.epub").unwrap();
+    doc.set_current_chapter(7);
+    doc.set_current_fraction(0.75);
+
+    let json = serde_json::to_string(&doc.current_position().unwrap()).unwrap();
+    let position: ReadingPosition = serde_json::from_str(&json).unwrap();
+
+    let mut fresh = EpubDoc::new("test.epub").unwrap();
+    fresh.restore_position(&position).unwrap();
+    assert_eq!(Some("006.xhtml"), fresh.get_current_id());
+    assert_eq!(0.75, fresh.current_position().unwrap().fraction);
+}
+
+#[test]
+fn progress_is_monotonic() {
+    let doc = EpubDoc::new("tests/docs/charles-dickens_a-christmas-carol.epub").unwrap();
+    let sizes = doc.spine_sizes();
+    assert_eq!(doc.get_num_pages(), sizes.len());
+    assert!(sizes.iter().all(|&size| size > 0));
+    assert_eq!(sizes, doc.spine_sizes());
+
+    let mut previous = 0.0;
+    for chapter in 0..doc.get_num_pages() {
+        for within in [0.0, 0.25, 0.5, 1.0] {
+            let progress = doc.progress(chapter, within);
+            assert!(progress >= previous, "{progress} < {previous}");
+            previous = progress;
+        }
+        assert!(doc.progress(chapter, 0.0) < doc.progress(chapter, 1.0));
+    }
+
+    assert_eq!(0.0, doc.progress(0, 0.0));
+    assert!((doc.progress(doc.get_num_pages() - 1, 1.0) - 1.0).abs() < f32::EPSILON);
+    assert_eq!(1.0, doc.progress(doc.get_num_pages(), 0.0));
+    assert_eq!(doc.progress(2, 1.0), doc.progress(3, 0.0));
+
+    for within in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+        assert_eq!(doc.progress(2, 0.0), doc.progress(2, within));
+    }
+}
@@ -16,13 +16,14 @@ fn read_doc() {
 
     {
         println!("resources:\n");
-        for (k, v) in doc.context.resources.iter() {
+        for (k, v) in doc.resources().iter() {
             println!("{}: {}\n * {}\n", k, v.mime, v.path.display());
         }
     }
 }
 
 #[test]
+#[cfg(feature = "encodings")]
 fn read_different_format_epubs() {
     // Read all the epubs in the /epubfiles directory.
     // These are formatted with UTF16/UTF8, borrowed from: https://github.com/tkanai/epub-testfiles
@@ -42,6 +43,7 @@ fn read_different_format_epubs() {
 }
 
 #[test]
+#[cfg(feature = "encodings")]
 fn bad_epub() {
     //book2.epub has a opf encoded in UTF-16
     //It also has malformed toc, manifest and guide entries, as well as multiple metadata entries
@@ -49,7 +51,7 @@ fn bad_epub() {
     let doc = EpubDoc::new(input_file);
     assert!(doc.is_ok());
     let doc = doc.unwrap();
-    if let Some(titles) = doc.context.metadata.get("title") {
+    if let Some(titles) = doc.metadata().get("title") {
         assert_eq!(
             titles.iter().map(|i| i.content.clone()).collect::<Vec<_>>(),
             vec!["Metamorphosis ".to_string(), "Metamorphosis2 ".to_string()]
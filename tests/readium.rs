@@ -0,0 +1,46 @@
+#![cfg(feature = "readium")]
+
+use epub::doc::EpubDoc;
+use epub::readium::{href_for_path, RwpmManifest};
+use std::path::Path;
+
+/// Compare the manifest of `epub` with the checked-in snapshot, set `UPDATE_SNAPSHOTS` to rewrite it instead.
+fn assert_snapshot(epub: &str, snapshot: &str) {
+    let doc = EpubDoc::new(epub).unwrap();
+    let manifest = doc.to_rwpm();
+    let json = manifest.to_json().unwrap();
+    let snapshot = Path::new("tests/snapshots").join(snapshot);
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&snapshot, format!("{json}\n")).unwrap();
+    }
+
+    let expected = std::fs::read_to_string(&snapshot).unwrap();
+    assert_eq!(expected.trim_end(), json);
+
+    let parsed: RwpmManifest = serde_json::from_str(&expected).unwrap();
+    assert_eq!(manifest, parsed);
+
+    for (id, resource) in doc.resources() {
+        let href = href_for_path(&resource.path);
+        let link = manifest.reading_order.iter().chain(&manifest.resources);
+        assert_eq!(1, link.filter(|link| link.href == href).count(), "{href}");
+
+        if doc.has_resource(id) {
+            assert!(doc.get_resource_by_href(&href).is_ok(), "{href}");
+        }
+    }
+}
+
+#[test]
+fn rwpm_snapshot_test_epub() {
+    assert_snapshot("test.epub", "test.rwpm.json");
+}
+
+#[test]
+fn rwpm_snapshot_winnie_the_pooh() {
+    assert_snapshot(
+        "tests/docs/winnie_the_pooh.epub",
+        "winnie_the_pooh.rwpm.json",
+    );
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use common::{opf, xhtml, Fixture};
+use epub::rendering::ChapterFeatures;
+use epub::text::{RubyMode, TextOptions};
+
+const MANIFEST: &str = r#"
+    <item id="ruby" href="ruby.xhtml" media-type="application/xhtml+xml"/>
+    <item id="inline" href="inline.xhtml" media-type="application/xhtml+xml"/>
+    <item id="plain" href="plain.xhtml" media-type="application/xhtml+xml"/>
+    <item id="css" href="style/vertical.css" media-type="text/css"/>"#;
+const SPINE: &str =
+    r#"<spine><itemref idref="ruby"/><itemref idref="inline"/><itemref idref="plain"/></spine>"#;
+
+const RUBY_CHAPTER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="ja">
+<head><link rel="stylesheet" type="text/css" href="style/vertical.css"/></head>
+<body>
+<p><ruby>吾輩<rp>(</rp><rt>わがはい</rt><rp>)</rp></ruby>は猫である。</p>
+<p><ruby>名<rt>な</rt>前<rt>まえ</rt></ruby>はまだ無い。</p>
+</body>
+</html>"#;
+
+fn fixture() -> Fixture {
+    Fixture::new(opf("3.0", "", MANIFEST, SPINE))
+        .file("OEBPS/ruby.xhtml", RUBY_CHAPTER)
+        .file(
+            "OEBPS/style/vertical.css",
+            "html { -epub-writing-mode: vertical-rl; writing-mode: vertical-rl; }",
+        )
+        .file(
+            "OEBPS/inline.xhtml",
+            xhtml(r#"<style>p { color: red }</style><p style="writing-mode:vertical-lr">縦</p><p dir="rtl">abc</p>"#),
+        )
+        .file("OEBPS/plain.xhtml", xhtml("<p>Plain</p>"))
+}
+
+#[test]
+fn chapter_text_ruby_modes() {
+    let doc = fixture().open();
+    let text = |ruby| {
+        doc.chapter_text_with_options(0, &TextOptions { ruby })
+            .unwrap()
+    };
+
+    assert_eq!(
+        "吾輩は猫である。\n名前はまだ無い。",
+        doc.chapter_text(0).unwrap()
+    );
+    assert_eq!(
+        "吾輩は猫である。\n名前はまだ無い。",
+        text(RubyMode::BaseOnly)
+    );
+    assert_eq!(
+        "わがはいは猫である。\nなまえはまだ無い。",
+        text(RubyMode::ReadingOnly)
+    );
+    assert_eq!(
+        "吾輩(わがはい)は猫である。\n名(な)前(まえ)はまだ無い。",
+        text(RubyMode::Bracketed)
+    );
+}
+
+#[test]
+fn chapter_features() {
+    let doc = fixture().open();
+
+    assert_eq!(
+        ChapterFeatures {
+            has_ruby: true,
+            has_vertical_css: true,
+            has_bdo_or_dir_overrides: false,
+        },
+        doc.chapter_features(0).unwrap()
+    );
+    assert_eq!(
+        ChapterFeatures {
+            has_ruby: false,
+            has_vertical_css: true,
+            has_bdo_or_dir_overrides: true,
+        },
+        doc.chapter_features(1).unwrap()
+    );
+    assert_eq!(ChapterFeatures::default(), doc.chapter_features(2).unwrap());
+    assert!(doc.chapter_features(3).is_err());
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::{opf, opf_with, xhtml, Fixture};
+use epub::doc::EpubDoc;
+use epub::rendition::{Flow, Layout, Orientation, Rendition, Spread};
+
+#[test]
+fn pre_paginated_comic() {
+    let metadata = r##"
+    <meta property="rendition:layout">pre-paginated</meta>
+    <meta property="rendition:orientation">landscape</meta>
+    <meta property="rendition:spread"> both </meta>
+    <meta property="rendition:flow">paginated</meta>
+    <meta refines="#p2" property="rendition:layout">reflowable</meta>"##;
+    let manifest = r#"
+        <item id="p1" href="p1.xhtml" media-type="application/xhtml+xml"/>
+        <item id="p2" href="p2.xhtml" media-type="application/xhtml+xml"/>
+        <item id="p3" href="p3.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine>
+        <itemref idref="p1" properties="page-spread-right"/>
+        <itemref idref="p2" properties="rendition:page-spread-center rendition:layout-reflowable"/>
+        <itemref idref="p3" properties="rendition:spread-none other:thing"/>
+    </spine>"#;
+    let doc = Fixture::new(opf("3.0", metadata, manifest, spine))
+        .file("OEBPS/p1.xhtml", xhtml("<img src='p1.jpg'/>"))
+        .open();
+
+    assert_eq!(
+        Rendition {
+            layout: Layout::PrePaginated,
+            orientation: Orientation::Landscape,
+            spread: Spread::Both,
+            flow: Flow::Paginated,
+        },
+        doc.rendition()
+    );
+    assert!(doc.is_fixed_layout());
+
+    assert_eq!(vec!["page-spread-right"], doc.spine_rendition_overrides(0));
+    assert_eq!(
+        vec!["page-spread-center", "layout-reflowable"],
+        doc.spine_rendition_overrides(1)
+    );
+    assert_eq!(vec!["spread-none"], doc.spine_rendition_overrides(2));
+    assert!(doc.spine_rendition_overrides(3).is_empty());
+}
+
+#[test]
+fn reflowable_book() {
+    let doc = EpubDoc::new("tests/docs/herman-melville_moby-dick.epub").unwrap();
+    assert_eq!(Rendition::default(), doc.rendition());
+    assert!(!doc.is_fixed_layout());
+
+    let metadata = r#"
+    <meta property="rendition:layout">reflowable</meta>
+    <meta property="rendition:spread">sideways</meta>"#;
+    let doc = Fixture::new(opf("3.0", metadata, "", "<spine/>")).open();
+    assert_eq!(Rendition::default(), doc.rendition());
+}
+
+#[test]
+fn declared_prefix() {
+    let metadata = r#"
+    <meta property="rnd:layout">pre-paginated</meta>
+    <meta property="other:orientation">portrait</meta>"#;
+    let manifest = r#"<item id="p1" href="p1.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine><itemref idref="p1" properties="rnd:page-spread-left"/></spine>"#;
+    let package =
+        r#"prefix="other: http://example.com/vocab#  rnd: http://www.idpf.org/vocab/rendition/#""#;
+    let doc = Fixture::new(opf_with("3.0", package, metadata, manifest, spine, "")).open();
+
+    assert!(doc.is_fixed_layout());
+    assert_eq!(Orientation::Auto, doc.rendition().orientation);
+    assert_eq!(vec!["page-spread-left"], doc.spine_rendition_overrides(0));
+}
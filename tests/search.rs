@@ -0,0 +1,155 @@
+mod common;
+
+use common::{opf, Fixture};
+use epub::doc::EpubDoc;
+use epub::search::{SearchHit, TextIndex};
+
+const DICKENS: &str = "tests/docs/charles-dickens_a-christmas-carol.epub";
+
+#[test]
+fn chapter_text_extraction() {
+    let chapter = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml">
+<head><title>Not included</title><style>p { color: red; }</style></head>
+<body>
+  <h1>Caf&eacute; &amp; Bar</h1>
+  <!-- <p>commented out</p> -->
+  <p>First   line,
+     still the <em>first</em> paragraph.<br/>After a break &#8212; &#x41;&unknown; a &lt; b &lt;&gt; c < d</p>
+  <script type="text/javascript">var x = "<p>";</script>
+  <div><p>Nested</p></div>
+  <p><![CDATA[raw & <kept>]]></p>
+</body>
+</html>"#;
+    let manifest = r#"<item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine><itemref idref="one"/></spine>"#;
+    let doc = Fixture::new(opf("3.0", "", manifest, spine))
+        .file("OEBPS/one.xhtml", chapter)
+        .open();
+
+    assert_eq!(
+        "Café & Bar\n\
+         First line, still the first paragraph.\n\
+         After a break — A&unknown; a < b <> c < d\n\
+         Nested\n\
+         raw & <kept>",
+        doc.chapter_text(0).unwrap()
+    );
+    assert!(doc.chapter_text(1).is_err());
+}
+
+#[test]
+fn index_matches_naive_search() {
+    let doc = EpubDoc::new(DICKENS).unwrap();
+    let index = TextIndex::build(&doc).unwrap();
+    assert!(index.is_complete());
+    assert_eq!(doc.get_num_pages(), index.indexed_chapters());
+
+    for query in [
+        "Scrooge",
+        "MARLEY",
+        "humbug",
+        "the",
+        "Ghost of Christmas",
+        "not in this book",
+    ] {
+        let hits = doc.search(query).unwrap();
+        assert_eq!(hits, index.search(query), "{query}");
+    }
+
+    let hits = index.search("humbug");
+    assert!(!hits.is_empty());
+    for SearchHit {
+        spine_index,
+        offset,
+    } in hits
+    {
+        let text = doc.chapter_text(spine_index).unwrap().to_lowercase();
+        assert_eq!("humbug", &text[offset..offset + "humbug".len()]);
+    }
+
+    assert!(index.search("").is_empty());
+}
+
+#[test]
+fn index_builds_incrementally() {
+    let doc = EpubDoc::new(DICKENS).unwrap();
+    let complete = TextIndex::build(&doc).unwrap();
+
+    let mut index = TextIndex::new(&doc).unwrap();
+    assert_eq!(0, index.indexed_chapters());
+    assert!(index.search("scrooge").is_empty());
+
+    assert_eq!(3, index.build_chapters(&doc, 3).unwrap());
+    assert!(!index.is_complete());
+    let partial = index.search("scrooge");
+    assert!(partial.iter().all(|hit| hit.spine_index < 3));
+    assert!(complete.search("scrooge").starts_with(&partial));
+
+    while index.build_chapters(&doc, 2).unwrap() > 0 {}
+    assert!(index.is_complete());
+    assert_eq!(complete, index);
+    assert_eq!(0, index.build_chapters(&doc, 10).unwrap());
+}
+
+#[test]
+fn index_validity() {
+    let doc = EpubDoc::new(DICKENS).unwrap();
+    let index = TextIndex::new(&doc).unwrap();
+
+    assert!(index.is_valid_for(&EpubDoc::new(DICKENS).unwrap()));
+    assert!(!index.is_valid_for(&EpubDoc::new("test.epub").unwrap()));
+    assert_eq!(
+        doc.content_hash().unwrap(),
+        EpubDoc::new(DICKENS).unwrap().content_hash().unwrap()
+    );
+    assert_eq!(
+        doc.get_release_identifier().as_deref(),
+        index.release_identifier()
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn index_round_trip() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+    let mut index = TextIndex::new(&doc).unwrap();
+    index.build_chapters(&doc, 5).unwrap();
+
+    let json = serde_json::to_string(&index).unwrap();
+    let mut restored: TextIndex = serde_json::from_str(&json).unwrap();
+    assert_eq!(index, restored);
+    assert!(restored.is_valid_for(&doc));
+
+    restored.build_chapters(&doc, usize::MAX).unwrap();
+    assert_eq!(TextIndex::build(&doc).unwrap(), restored);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn index_rejects_invalid_offsets() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+    let index = TextIndex::build(&doc).unwrap();
+    let mut json: serde_json::Value = serde_json::to_value(&index).unwrap();
+    let text_len = json["text"].as_str().unwrap().len();
+
+    let corrupt = |json: &serde_json::Value, offsets: serde_json::Value| {
+        let mut json = json.clone();
+        json["chapter_offsets"] = offsets;
+        serde_json::from_value::<TextIndex>(json)
+    };
+    // Past the end of the text
+    assert!(corrupt(&json, serde_json::json!([0, text_len + 1])).is_err());
+    // Out of order
+    assert!(corrupt(&json, serde_json::json!([10, 5])).is_err());
+    // More chapters than the spine has
+    let too_many = vec![0; doc.spine().len() + 1];
+    assert!(corrupt(&json, serde_json::json!(too_many)).is_err());
+
+    // Within a character
+    json["text"] = "añb".into();
+    assert!(corrupt(&json, serde_json::json!([0, 2])).is_err());
+    let restored = corrupt(&json, serde_json::json!([0, 3])).unwrap();
+    assert_eq!(1, restored.search("b").len());
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use common::{opf_with, xhtml, Fixture};
+use epub::doc::{EpubDoc, GuideReference, Landmark};
+use epub::semantics::SemanticLocation;
+use std::path::PathBuf;
+
+#[test]
+fn guide_and_landmark_aliases() {
+    use SemanticLocation::*;
+
+    // (EPUB2 guide type, EPUB3 landmark type, normalized)
+    let pairs = [
+        ("cover", "cover", Cover),
+        ("title-page", "titlepage", TitlePage),
+        ("toc", "toc", Toc),
+        ("index", "index", Index),
+        ("glossary", "glossary", Glossary),
+        ("acknowledgements", "acknowledgments", Acknowledgements),
+        ("bibliography", "bibliography", Bibliography),
+        ("colophon", "colophon", Colophon),
+        ("copyright-page", "copyright-page", CopyrightPage),
+        ("dedication", "dedication", Dedication),
+        ("epigraph", "epigraph", Epigraph),
+        ("foreword", "foreword", Foreword),
+        ("loi", "loi", ListOfIllustrations),
+        ("lot", "lot", ListOfTables),
+        ("notes", "endnotes", Notes),
+        ("notes", "rearnotes", Notes),
+        ("notes", "footnotes", Notes),
+        ("preface", "preface", Preface),
+        ("text", "bodymatter", BodyMatter),
+    ];
+
+    for (guide, landmark, expected) in pairs {
+        assert_eq!(expected, SemanticLocation::from_type(guide), "{guide}");
+        assert_eq!(
+            expected,
+            SemanticLocation::from_type(landmark),
+            "{landmark}"
+        );
+        assert_eq!(
+            expected,
+            SemanticLocation::from_type(&guide.to_uppercase()),
+            "{guide}"
+        );
+    }
+
+    let landmark_only = [
+        ("halftitlepage", HalfTitlePage),
+        ("introduction", Introduction),
+        ("prologue", Prologue),
+        ("epilogue", Epilogue),
+        ("afterword", Afterword),
+        ("conclusion", Conclusion),
+        ("appendix", Appendix),
+        ("imprint", Imprint),
+        ("errata", Errata),
+        ("loa", ListOfAudio),
+        ("lov", ListOfVideos),
+        ("frontmatter", FrontMatter),
+        ("backmatter", BackMatter),
+    ];
+    for (landmark, expected) in landmark_only {
+        assert_eq!(
+            expected,
+            SemanticLocation::from_type(landmark),
+            "{landmark}"
+        );
+    }
+
+    assert_eq!(
+        Other("other.afterword".to_string()),
+        SemanticLocation::from_type("other.afterword")
+    );
+    assert_eq!(
+        Other("z3998:fiction".to_string()),
+        SemanticLocation::from_type("z3998:fiction")
+    );
+}
+
+#[test]
+fn most_specific_semantic() {
+    let reference = |kind: &str| GuideReference {
+        kind: kind.to_string(),
+        title: None,
+        path: PathBuf::new(),
+        fragment: None,
+        hidden: false,
+    };
+
+    assert_eq!(
+        SemanticLocation::TitlePage,
+        reference("frontmatter titlepage").semantic()
+    );
+    assert_eq!(
+        SemanticLocation::BodyMatter,
+        reference("bodymatter z3998:fiction").semantic()
+    );
+    assert_eq!(
+        SemanticLocation::Dedication,
+        reference("z3998:verse dedication frontmatter").semantic()
+    );
+    assert_eq!(
+        SemanticLocation::Other("z3998:verse".to_string()),
+        reference("z3998:verse").semantic()
+    );
+    assert_eq!(
+        vec![SemanticLocation::FrontMatter, SemanticLocation::TitlePage],
+        reference("frontmatter titlepage").semantics()
+    );
+}
+
+#[test]
+fn landmarks_from_nav() {
+    let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    assert!(!doc.landmarks().is_empty());
+
+    let title = doc.semantic_page(&SemanticLocation::TitlePage).unwrap();
+    assert_eq!(PathBuf::from("epub/text/titlepage.xhtml"), title.path);
+    assert_eq!("frontmatter titlepage", title.kind);
+    assert_eq!(Some("Titlepage"), title.title.as_deref());
+
+    let start = doc.start_reading_position().unwrap();
+    assert_eq!(
+        Some(start),
+        doc.resource_uri_to_chapter("epub/text/chapter-1.xhtml")
+    );
+}
+
+#[test]
+fn reading_start_from_guide() {
+    let manifest = r#"<item id="cover" href="Text/cover.xhtml" media-type="application/xhtml+xml"/>
+    <item id="one" href="Text/one.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine><itemref idref="cover"/><itemref idref="one"/></spine>"#;
+    let guide = r#"<guide>
+    <reference type="cover" title="Cover" href="Text/cover.xhtml"/>
+    <reference type="text" title="Start" href="Text/one.xhtml#start"/>
+  </guide>"#;
+    let doc = Fixture::new(opf_with("2.0", "", "", manifest, spine, guide))
+        .file("OEBPS/Text/cover.xhtml", xhtml(""))
+        .file("OEBPS/Text/one.xhtml", xhtml("<p id=\"start\"/>"))
+        .open();
+
+    assert_eq!(Some(1), doc.start_reading_position());
+    let start = doc.semantic_page(&SemanticLocation::BodyMatter).unwrap();
+    assert_eq!(Some("start"), start.fragment.as_deref());
+    assert!(doc.landmarks().is_empty());
+
+    let doc = EpubDoc::new("test.epub").unwrap();
+    assert_eq!(None, doc.start_reading_position());
+}
+
+#[test]
+fn landmarks_relative_to_nav() {
+    let manifest = r#"<item id="nav" href="nav/nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="cover" href="Text/cover.xhtml" media-type="application/xhtml+xml"/>
+    <item id="one" href="Text/one.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine><itemref idref="cover"/><itemref idref="one"/></spine>"#;
+    let nav = xhtml(
+        r#"<nav epub:type="landmarks"><ol>
+      <li><a epub:type="cover" href="../Text/cover.xhtml">Cover</a></li>
+      <li><a epub:type="bodymatter" href="../Text/one.xhtml#start">Start</a></li>
+    </ol></nav>
+    <nav epub:type="page-list"><ol>
+      <li><a href="../Text/one.xhtml#p1">1</a></li>
+    </ol></nav>"#,
+    );
+    let doc = Fixture::new(opf_with("3.0", "", "", manifest, spine, ""))
+        .file("OEBPS/nav/nav.xhtml", nav)
+        .file("OEBPS/Text/cover.xhtml", xhtml(""))
+        .file("OEBPS/Text/one.xhtml", xhtml("<p id=\"start\"/>"))
+        .open();
+
+    let landmarks: &[Landmark] = doc.landmarks();
+    assert_eq!(2, landmarks.len());
+    assert_eq!(PathBuf::from("OEBPS/Text/cover.xhtml"), landmarks[0].path);
+    assert_eq!(PathBuf::from("OEBPS/Text/one.xhtml"), landmarks[1].path);
+    assert_eq!(Some("start"), landmarks[1].fragment.as_deref());
+    assert_eq!(Some(1), doc.start_reading_position());
+
+    let pages = doc.page_list();
+    assert_eq!(PathBuf::from("OEBPS/Text/one.xhtml"), pages[0].path);
+}
@@ -0,0 +1,179 @@
+mod common;
+
+use common::{opf, xhtml, Fixture};
+use epub::doc::{EpubDoc, OpfSection};
+use epub::error::ArchiveError;
+use epub::options::{MediaTypeSniffing, ParseOptions};
+use epub::sniff::sniff_mime;
+use std::io::Cursor;
+
+const JPEG: &[u8] = b"\xff\xd8\xff\xe0\x00\x10JFIF\x00";
+
+fn fixture() -> Fixture {
+    let manifest = r#"
+        <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+        <item id="cover" href="cover.png" media-type="image/png"/>
+        <item id="font" href="font.woff" media-type="application/font-woff"/>
+        <item id="style" href="style.css" media-type="text/css"/>"#;
+
+    Fixture::new(opf(
+        "3.0",
+        "",
+        manifest,
+        r#"<spine><itemref idref="c1"/></spine>"#,
+    ))
+    .file("OEBPS/c1.xhtml", xhtml("<p>One</p>"))
+    .file("OEBPS/cover.png", JPEG)
+    .file("OEBPS/font.woff", b"wOFF\x00\x01\x00\x00")
+    .file("OEBPS/style.css", "p { margin: 0 }")
+}
+
+fn open_with(sniffing: MediaTypeSniffing) -> EpubDoc<Cursor<Vec<u8>>> {
+    let options = ParseOptions {
+        media_type_sniffing: sniffing,
+        ..Default::default()
+    };
+    EpubDoc::from_reader_with_options(Cursor::new(fixture().build()), options).unwrap()
+}
+
+#[test]
+fn sniff_resource() {
+    let doc = fixture().open();
+
+    let cover = doc.sniff_resource_mime("cover").unwrap();
+    assert_eq!("image/png", cover.declared);
+    assert_eq!(Some("image/jpeg"), cover.detected.as_deref());
+    assert!(!cover.agrees);
+
+    // Legacy aliases agree with the detected type
+    let font = doc.sniff_resource_mime("font").unwrap();
+    assert_eq!(Some("font/woff"), font.detected.as_deref());
+    assert!(font.agrees);
+
+    let chapter = doc.sniff_resource_mime("c1").unwrap();
+    assert_eq!(Some("application/xhtml+xml"), chapter.detected.as_deref());
+    assert!(chapter.agrees);
+
+    let style = doc.sniff_resource_mime("style").unwrap();
+    assert_eq!(None, style.detected);
+    assert!(style.agrees);
+
+    assert!(matches!(
+        doc.sniff_resource_mime("nope"),
+        Err(ArchiveError::InvalidId)
+    ));
+
+    let mislabeled = doc.mislabeled_resources();
+    assert_eq!(1, mislabeled.len());
+    assert_eq!("cover", mislabeled[0].0);
+}
+
+#[test]
+fn sniff_on_open() {
+    let doc = open_with(MediaTypeSniffing::Off);
+    assert!(doc.parse_warnings().is_empty());
+
+    let doc = open_with(MediaTypeSniffing::Warn);
+    assert_eq!(Some("image/png"), doc.get_resource_mime("cover"));
+    assert_eq!(1, doc.parse_warnings().len());
+    assert_eq!(OpfSection::Manifest, doc.parse_warnings()[0].section);
+    assert!(doc.parse_warnings()[0].message.contains("cover"));
+
+    let doc = open_with(MediaTypeSniffing::Correct);
+    assert_eq!(Some("image/jpeg"), doc.get_resource_mime("cover"));
+    assert_eq!(1, doc.parse_warnings().len());
+    assert!(doc.mislabeled_resources().is_empty());
+}
+
+#[test]
+fn sniff_warnings_in_manifest_order() {
+    let manifest = r#"
+        <item id="second" href="b.png" media-type="image/png"/>
+        <item id="first" href="a.png" media-type="image/png"/>"#;
+    let options = ParseOptions {
+        media_type_sniffing: MediaTypeSniffing::Warn,
+        ..Default::default()
+    };
+    let source = Fixture::new(opf("3.0", "", manifest, "<spine/>"))
+        .file("OEBPS/b.png", JPEG)
+        .file("OEBPS/a.png", JPEG)
+        .build();
+    let doc = EpubDoc::from_reader_with_options(Cursor::new(source), options).unwrap();
+
+    let warnings = doc.parse_warnings();
+    assert_eq!(2, warnings.len());
+    assert!(warnings[0].message.contains("`second`"));
+    assert!(warnings[1].message.contains("`first`"));
+}
+
+#[test]
+fn sniff_prefer_sniffed_mime() {
+    let mut doc = fixture().open();
+    assert_eq!(Some("image/png"), doc.get_resource_mime("cover"));
+
+    doc.set_prefer_sniffed_mime(true);
+    assert!(doc.prefer_sniffed_mime());
+    assert_eq!(Some("image/jpeg"), doc.get_resource_mime("cover"));
+    assert_eq!(
+        Some("image/jpeg"),
+        doc.get_resource_mime_by_path("OEBPS/cover.png")
+    );
+    // Agreeing aliases are kept as declared
+    assert_eq!(Some("application/font-woff"), doc.get_resource_mime("font"));
+    assert_eq!(Some("text/css"), doc.get_resource_mime("style"));
+}
+
+#[test]
+fn sniff_signatures() {
+    let cases: &[(&[u8], Option<&str>)] = &[
+        (b"\x89PNG\r\n\x1a\n\x00", Some("image/png")),
+        (JPEG, Some("image/jpeg")),
+        (b"GIF87a", Some("image/gif")),
+        (b"RIFF\x24\x00\x00\x00WEBPVP8 ", Some("image/webp")),
+        (b"RIFF\x24\x00\x00\x00WAVEfmt ", None),
+        (b"OTTO\x00\x0a", Some("font/otf")),
+        (b"\x00\x01\x00\x00\x00\x0a", Some("font/ttf")),
+        (b"wOF2\x00\x01", Some("font/woff2")),
+        (b"ID3\x04\x00", Some("audio/mpeg")),
+        (b"\xff\xfb\x90\x00", Some("audio/mpeg")),
+        (b"\x00\x00\x00\x20ftypM4A \x00", Some("audio/mp4")),
+        (b"\x00\x00\x00\x20ftypisom\x00", Some("video/mp4")),
+        (b"", None),
+    ];
+
+    for (bytes, expected) in cases {
+        assert_eq!(*expected, sniff_mime(bytes), "{bytes:?}");
+    }
+}
+
+#[test]
+fn sniff_markup() {
+    let cases: &[(&str, Option<&str>)] = &[
+        (
+            "\u{feff}<?xml version=\"1.0\"?>\n<!-- <html> -->\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>",
+            Some("image/svg+xml"),
+        ),
+        ("<svg:svg xmlns:svg=\"http://www.w3.org/2000/svg\"/>", Some("image/svg+xml")),
+        (
+            "<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\"><body/></html>",
+            Some("application/xhtml+xml"),
+        ),
+        ("<!doctype html>\n<HTML><body></body></HTML>", Some("text/html")),
+        ("<?xml version=\"1.0\"?><ncx version=\"2005-1\"/>", Some("application/x-dtbncx+xml")),
+        ("<?xml version=\"1.0\"?><package/>", None),
+        ("<!-- unterminated", None),
+        ("plain text", None),
+    ];
+
+    for (text, expected) in cases {
+        assert_eq!(*expected, sniff_mime(text.as_bytes()), "{text}");
+    }
+}
+
+#[test]
+fn sniff_test_docs() {
+    for path in ["test.epub", "tests/docs/winnie_the_pooh.epub"] {
+        let doc = EpubDoc::new(path).unwrap();
+        assert!(doc.mislabeled_resources().is_empty(), "{path}");
+    }
+}
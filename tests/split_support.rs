@@ -0,0 +1,303 @@
+mod common;
+
+use common::{opf, xhtml, Fixture};
+use epub::doc::{EpubDoc, OwnedAttribute};
+use epub::error::ArchiveError;
+use epub::navigation::Locator;
+use epub::split_support::SplitRange;
+use std::io::Cursor;
+
+const DICKENS: &str = "tests/docs/charles-dickens_a-christmas-carol.epub";
+
+fn reopen(output: Cursor<Vec<u8>>) -> EpubDoc<Cursor<Vec<u8>>> {
+    EpubDoc::from_reader(Cursor::new(output.into_inner())).unwrap()
+}
+
+#[test]
+fn split_dickens_chapter() {
+    let mut doc = EpubDoc::new(DICKENS).unwrap();
+    let chapter = doc
+        .get_resource_str_by_path("epub/text/chapter-1.xhtml")
+        .unwrap();
+
+    // Split between two paragraphs, halfway through the chapter
+    let paragraphs: Vec<_> = chapter.match_indices("</p>").map(|(i, _)| i + 4).collect();
+    let split = paragraphs[paragraphs.len() / 2];
+    let body = chapter.find("<section").unwrap();
+    let first = format!("{}\n\t\t</section>\n\t</body>\n</html>", &chapter[..split]);
+    let second = format!(
+        "{}<section id=\"chapter-1-part-2\">{}",
+        &chapter[..body],
+        &chapter[split..]
+    );
+
+    doc.set_current_chapter(6);
+    doc.insert_resource(
+        "chapter-1-2.xhtml",
+        "epub/text/chapter-1-2.xhtml",
+        "application/xhtml+xml",
+    )
+    .unwrap();
+    doc.insert_spine_item(4, "chapter-1-2.xhtml", true).unwrap();
+    assert_eq!(7, doc.get_current_chapter());
+    let ranges = [
+        SplitRange {
+            path: "epub/text/chapter-1.xhtml".into(),
+            start_id: None,
+        },
+        SplitRange {
+            path: "epub/text/chapter-1-2.xhtml".into(),
+            start_id: Some("chapter-1-part-2".into()),
+        },
+    ];
+    assert_eq!(
+        0,
+        doc.retarget_toc("epub/text/chapter-1.xhtml", &ranges)
+            .unwrap()
+    );
+
+    let mut output = Cursor::new(Vec::new());
+    let report = doc
+        .writer()
+        .replace("epub/text/chapter-1.xhtml", first.as_str())
+        .replace("epub/text/chapter-1-2.xhtml", second.as_str())
+        .save(&mut output)
+        .unwrap();
+    assert!(report.rewritten.contains(&"epub/content.opf".to_string()));
+
+    let mut saved = reopen(output);
+    assert_eq!(12, saved.get_num_pages());
+    assert_eq!(Some(5), saved.resource_id_to_chapter("chapter-1-2.xhtml"));
+    assert_eq!(
+        Some("application/xhtml+xml"),
+        saved.get_resource_mime("chapter-1-2.xhtml")
+    );
+    assert_eq!(
+        first,
+        saved
+            .get_resource_str_by_path("epub/text/chapter-1.xhtml")
+            .unwrap()
+    );
+    assert_eq!(
+        second,
+        saved
+            .get_resource_str_by_path("epub/text/chapter-1-2.xhtml")
+            .unwrap()
+    );
+
+    // The new half is part of the reading order
+    let neighbors = saved.reading_order_neighbors(&Locator::chapter(5));
+    assert_eq!(Some(Locator::chapter(4)), neighbors.prev);
+    assert_eq!(Some(Locator::chapter(6)), neighbors.next);
+
+    // The ToC still resolves, the later chapters moved one place
+    let toc: Vec<_> = flatten(saved.get_toc()).into_iter().cloned().collect();
+    let stave = |label: &str| {
+        toc.iter()
+            .find(|nav| nav.label.contains(label))
+            .unwrap()
+            .clone()
+    };
+    saved.go_to_toc_entry(&stave("Marley")).unwrap();
+    assert_eq!(4, saved.get_current_chapter());
+    saved.go_to_toc_entry(&stave("First of the Three")).unwrap();
+    assert_eq!(6, saved.get_current_chapter());
+    saved.go_to_toc_entry(&stave("The End of It")).unwrap();
+    assert_eq!(9, saved.get_current_chapter());
+}
+
+fn flatten(points: &[epub::doc::NavPoint]) -> Vec<&epub::doc::NavPoint> {
+    points
+        .iter()
+        .flat_map(|point| std::iter::once(point).chain(flatten(&point.children)))
+        .collect()
+}
+
+const NCX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <navMap>
+    <navPoint id="n1" playOrder="1"><navLabel><text>Start</text></navLabel><content src="text/one.xhtml"/></navPoint>
+    <navPoint id="n2" playOrder="2"><navLabel><text>Middle</text></navLabel><content src="text/one.xhtml#b"/>
+      <navPoint id="n3" playOrder="3"><navLabel><text>End</text></navLabel><content src="text/one.xhtml#c"/></navPoint>
+    </navPoint>
+    <navPoint id="n4" playOrder="4"><navLabel><text>Two</text></navLabel><content src="text/two.xhtml"/></navPoint>
+  </navMap>
+</ncx>"#;
+
+fn split_fixture() -> Fixture {
+    let manifest = r#"
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="one" href="text/one.xhtml" media-type="application/xhtml+xml"/>
+    <item id="two" href="text/two.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine =
+        r#"<spine toc="ncx"><itemref idref="one"/><itemref idref="two" linear="no"/></spine>"#;
+
+    Fixture::new(opf("2.0", "", manifest, spine))
+        .file("OEBPS/toc.ncx", NCX)
+        .file(
+            "OEBPS/text/one.xhtml",
+            xhtml(r#"<p id="a">A</p><p id="b">B</p><p id="c">C</p>"#),
+        )
+        .file("OEBPS/text/two.xhtml", xhtml("<p>Two</p>"))
+}
+
+#[test]
+fn retarget_toc_fragments() {
+    let mut doc = split_fixture().open();
+    doc.insert_resource("one-b", "OEBPS/text/one-b.xhtml", "application/xhtml+xml")
+        .unwrap();
+    doc.insert_spine_item(0, "one-b", true).unwrap();
+
+    let ranges = [
+        SplitRange {
+            path: "OEBPS/text/one.xhtml".into(),
+            start_id: None,
+        },
+        SplitRange {
+            path: "OEBPS/text/one-b.xhtml".into(),
+            start_id: Some("b".into()),
+        },
+    ];
+    assert_eq!(
+        2,
+        doc.retarget_toc("OEBPS/text/one.xhtml", &ranges).unwrap()
+    );
+
+    let targets = |doc: &EpubDoc<_>| -> Vec<String> {
+        flatten(doc.get_toc())
+            .iter()
+            .map(|nav| {
+                let content = nav.content.as_ref().unwrap().display();
+                match &nav.fragment {
+                    Some(fragment) => format!("{content}#{fragment}"),
+                    None => content.to_string(),
+                }
+            })
+            .collect()
+    };
+    let expected = vec![
+        "OEBPS/text/one.xhtml",
+        "OEBPS/text/one-b.xhtml#b",
+        "OEBPS/text/one-b.xhtml#c",
+        "OEBPS/text/two.xhtml",
+    ];
+    assert_eq!(expected, targets(&doc));
+
+    let mut output = Cursor::new(Vec::new());
+    doc.writer()
+        .replace("OEBPS/text/one.xhtml", xhtml(r#"<p id="a">A</p>"#))
+        .replace(
+            "OEBPS/text/one-b.xhtml",
+            xhtml(r#"<p id="b">B</p><p id="c">C</p>"#),
+        )
+        .save(&mut output)
+        .unwrap();
+
+    let mut saved = reopen(output);
+    assert_eq!(expected, targets(&saved));
+    let ncx = saved.get_resource_str_by_path("OEBPS/toc.ncx").unwrap();
+    assert!(ncx.contains(r#"<content src="text/one-b.xhtml#c"/>"#));
+
+    let spine: Vec<_> = saved
+        .spine()
+        .iter()
+        .map(|item| (item.idref.as_str(), item.linear))
+        .collect();
+    assert_eq!(vec![("one", true), ("one-b", true), ("two", false)], spine);
+
+    let end = saved.get_toc()[1].children[0].clone();
+    assert_eq!(Some("c".to_string()), saved.go_to_toc_entry(&end).unwrap());
+    assert_eq!(1, saved.get_current_chapter());
+    #[cfg(feature = "rewrite")]
+    assert!(saved.has_anchor(1, "c").unwrap());
+}
+
+#[test]
+fn mutation_errors() {
+    let mut doc = split_fixture().open();
+
+    assert!(matches!(
+        doc.insert_resource("one", "OEBPS/text/other.xhtml", "application/xhtml+xml"),
+        Err(ArchiveError::DuplicateId(id)) if id == "one"
+    ));
+    assert!(matches!(
+        doc.insert_spine_item(0, "missing", true),
+        Err(ArchiveError::InvalidId)
+    ));
+    assert!(matches!(
+        doc.insert_spine_item(2, "two", true),
+        Err(ArchiveError::InvalidPosition)
+    ));
+    assert_eq!(0, doc.retarget_toc("OEBPS/text/one.xhtml", &[]).unwrap());
+
+    // Nothing changed, so the package document is copied as-is
+    let mut output = Cursor::new(Vec::new());
+    let report = doc.writer().save(&mut output).unwrap();
+    assert!(report.rewritten.is_empty());
+}
+
+#[test]
+fn vendor_attributes_round_trip() {
+    let package = r#"xmlns:kobo="http://kobobooks.com/ns""#;
+    let manifest = r#"
+    <item id="one" href="one.xhtml" media-type="application/xhtml+xml" kobo:chapter="1" fallback="two"/>
+    <item id="two" href="two.xhtml" media-type="application/xhtml+xml" data-origin="scan"/>"#;
+    let spine = r#"<spine>
+    <itemref idref="one" kobo:page-turn="swipe" custom="yes"/>
+    <itemref idref="two" xmlns:ibooks="http://vocabulary.itunes.apple.com/rdf/ibooks/vocabulary-extensions-1.0/" ibooks:scroll-axis="vertical" linear="no"/>
+  </spine>"#;
+    let mut doc = Fixture::new(common::opf_with("3.0", package, "", manifest, spine, ""))
+        .file("OEBPS/one.xhtml", xhtml("<p>One</p>"))
+        .file("OEBPS/two.xhtml", xhtml("<p>Two</p>"))
+        .open();
+
+    let attributes = |attrs: &[OwnedAttribute]| {
+        attrs
+            .iter()
+            .map(|attr| {
+                (
+                    attr.name.namespace.clone(),
+                    attr.name.tag.clone(),
+                    attr.value.to_string(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let kobo = Some("http://kobobooks.com/ns".to_string());
+    assert_eq!(
+        vec![(kobo.clone(), "chapter".to_string(), "1".to_string())],
+        attributes(&doc.resources()["one"].extra_attrs)
+    );
+    assert_eq!(Some("two"), doc.resources()["one"].fallback.as_deref());
+    assert_eq!(
+        vec![
+            (kobo, "page-turn".to_string(), "swipe".to_string()),
+            (None, "custom".to_string(), "yes".to_string()),
+        ],
+        attributes(&doc.spine()[0].extra_attrs)
+    );
+    assert_eq!(1, doc.spine()[1].extra_attrs.len());
+    let resources = doc.resources().clone();
+    let spine = doc.spine().to_vec();
+
+    // Regenerating the spine keeps the attributes
+    doc.insert_resource("three", "OEBPS/three.xhtml", "application/xhtml+xml")
+        .unwrap();
+    doc.insert_spine_item(0, "three", true).unwrap();
+    let mut output = Cursor::new(Vec::new());
+    doc.writer()
+        .replace("OEBPS/three.xhtml", xhtml("<p>Three</p>"))
+        .save(&mut output)
+        .unwrap();
+
+    let saved = reopen(output);
+    assert_eq!(3, saved.spine().len());
+    assert_eq!(
+        spine[..],
+        [&saved.spine()[..1], &saved.spine()[2..]].concat()
+    );
+    assert!(saved.spine()[1].extra_attrs.is_empty());
+    for (id, item) in &resources {
+        assert_eq!(item, &saved.resources()[id]);
+    }
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::{opf_with, xhtml, Fixture};
+use epub::doc::EpubDoc;
+
+fn fixture() -> Fixture {
+    let metadata = r#"
+    <meta name="cover" content="cover"/>
+    <link rel="record" href="record.xml" media-type="application/marc"/>"#;
+    let manifest = r#"
+        <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+        <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+        <item id="c1" href="Text/c1.xhtml" media-type="application/xhtml+xml"/>
+        <item id="notes" href="Text/notes.xhtml" media-type="application/xhtml+xml"/>
+        <item id="style" href="Styles/style.css" media-type="text/css"/>
+        <item id="extra" href="Styles/extra.css" media-type="text/css"/>
+        <item id="font" href="Fonts/serif.woff" media-type="font/woff"/>
+        <item id="old-font" href="Fonts/old.woff" media-type="font/woff"/>
+        <item id="cover" href="Images/cover.jpg" media-type="image/jpeg"/>
+        <item id="figure" href="Images/figure%201.png" media-type="image/png"/>
+        <item id="inline" href="Images/inline.png" media-type="image/png"/>
+        <item id="orphan" href="Images/orphan.png" media-type="image/png"/>
+        <item id="record" href="record.xml" media-type="application/marc"/>"#;
+    let spine = r#"<spine toc="ncx"><itemref idref="c1"/></spine>"#;
+    let chapter = r##"<link rel="stylesheet" href="../Styles/style.css"/>
+        <p>See <a href="notes.xhtml#n1">the note</a> and <a href="#top">the top</a>,
+        or <a href="https://example.com/Images/orphan.png">a remote copy</a>.</p>
+        <img src="../Images/figure%201.png?v=2"/>
+        <p style="background: url('../Images/inline.png')">Inline</p>"##;
+
+    Fixture::new(opf_with("3.0", "", metadata, manifest, spine, ""))
+        .file("OEBPS/toc.ncx", "<ncx/>")
+        .file("OEBPS/nav.xhtml", xhtml("<nav/>"))
+        .file("OEBPS/Text/c1.xhtml", xhtml(chapter))
+        .file(
+            "OEBPS/Text/notes.xhtml",
+            xhtml(r#"<aside id="n1">Note</aside>"#),
+        )
+        .file(
+            "OEBPS/Styles/style.css",
+            "@font-face { src: url(\"../Fonts/serif.woff\") }",
+        )
+        .file("OEBPS/Styles/extra.css", "@import 'style.css';")
+        .file("OEBPS/Images/figure 1.png", "png")
+}
+
+#[test]
+fn unused_resources() {
+    let doc = fixture().open();
+    let unused: Vec<_> = doc
+        .unused_resources()
+        .unwrap()
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+
+    assert_eq!(vec!["extra", "old-font", "orphan"], unused);
+}
+
+#[test]
+fn unused_resources_test_docs() {
+    let doc = EpubDoc::new("tests/docs/winnie_the_pooh.epub").unwrap();
+    let unused = doc.unused_resources().unwrap();
+    assert!(unused
+        .iter()
+        .all(|(id, _)| !doc.spine().iter().any(|item| &item.idref == id)));
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use common::{opf, xhtml, Fixture};
+use epub::archive::EntryLayout;
+use epub::doc::EpubDoc;
+use std::io::{Cursor, Seek, SeekFrom, Write};
+use std::panic::{self, AssertUnwindSafe};
+use zip::CompressionMethod;
+
+fn raw_entry<'a>(archive: &'a [u8], entry: &EntryLayout) -> &'a [u8] {
+    &archive[entry.header_offset as usize..(entry.data_offset + entry.compressed_size) as usize]
+}
+
+fn save_with_title(doc: &EpubDoc<Cursor<Vec<u8>>>, from: &str, to: &str) -> (Vec<u8>, Vec<String>) {
+    let opf = doc.get_resource_str_by_path(&doc.root_file).unwrap();
+    assert!(opf.contains(from));
+
+    let mut output = Cursor::new(Vec::new());
+    let report = doc
+        .writer()
+        .replace(&doc.root_file, opf.replace(from, to))
+        .save(&mut output)
+        .unwrap();
+
+    (output.into_inner(), report.rewritten)
+}
+
+#[test]
+fn passthrough_keeps_unchanged_entries_identical() {
+    let source = std::fs::read("test.epub").unwrap();
+    let doc = EpubDoc::from_reader(Cursor::new(source.clone())).unwrap();
+    let (output, rewritten) = save_with_title(&doc, "Todo es mío", "Todo es tuyo");
+
+    // test.epub has its mimetype in the wrong place, so that has to be moved to the front
+    let opf_name = doc.root_file.to_string_lossy().to_string();
+    assert_eq!(vec!["mimetype".to_string(), opf_name.clone()], rewritten);
+
+    let saved = EpubDoc::from_reader(Cursor::new(output.clone())).unwrap();
+    assert_eq!(Some("Todo es tuyo"), saved.mdata("title"));
+
+    let mut source_layout = doc.archive_layout().unwrap();
+    source_layout.sort_by_key(|e| e.header_offset);
+    let mut output_layout = saved.archive_layout().unwrap();
+    output_layout.sort_by_key(|e| e.header_offset);
+
+    let mimetype = &output_layout[0];
+    assert_eq!("mimetype", mimetype.name);
+    assert_eq!(0, mimetype.header_offset);
+    assert_eq!(CompressionMethod::Stored, mimetype.compression);
+    assert_eq!(b"mimetypeapplication/epub+zip", &output[30..58]);
+
+    // Same order, minus the moved mimetype
+    let names = |layout: &[EntryLayout]| -> Vec<String> {
+        layout
+            .iter()
+            .map(|e| e.name.clone())
+            .filter(|n| n != "mimetype")
+            .collect()
+    };
+    assert_eq!(names(&source_layout), names(&output_layout[1..]));
+
+    for original in &source_layout {
+        let saved = output_layout
+            .iter()
+            .find(|e| e.name == original.name)
+            .unwrap();
+
+        if original.name == opf_name || original.name == "mimetype" {
+            assert_ne!(raw_entry(&source, original), raw_entry(&output, saved));
+            continue;
+        }
+
+        assert_eq!(original.compressed_size, saved.compressed_size);
+        assert_eq!(original.crc32, saved.crc32);
+        assert_eq!(
+            raw_entry(&source, original),
+            raw_entry(&output, saved),
+            "{} differs",
+            original.name
+        );
+    }
+}
+
+fn clean_fixture() -> Fixture {
+    let manifest = r#"<item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>"#;
+    let spine = r#"<spine><itemref idref="one"/></spine>"#;
+
+    Fixture::new(opf("3.0", "", manifest, spine)).file("OEBPS/one.xhtml", xhtml("<p>One</p>"))
+}
+
+#[test]
+fn passthrough_without_changes_is_identical() {
+    let source = clean_fixture().build();
+    let doc = EpubDoc::from_reader(Cursor::new(source.clone())).unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let report = doc.writer().save(&mut output).unwrap();
+
+    assert!(report.rewritten.is_empty());
+    assert_eq!(source, output.into_inner());
+}
+
+/// A writer which panics as soon as anything is written to it.
+struct PanickingWriter;
+
+impl Write for PanickingWriter {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        panic!("write failed")
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for PanickingWriter {
+    fn seek(&mut self, _: SeekFrom) -> std::io::Result<u64> {
+        Ok(0)
+    }
+}
+
+#[test]
+fn archive_survives_a_panicking_save() {
+    let doc = clean_fixture().open();
+
+    let saved = panic::catch_unwind(AssertUnwindSafe(|| doc.writer().save(PanickingWriter)));
+    assert!(saved.is_err());
+
+    assert!(doc
+        .get_resource_str_by_path("OEBPS/one.xhtml")
+        .unwrap()
+        .contains("<p>One</p>"));
+    let mut output = Cursor::new(Vec::new());
+    assert!(doc.writer().save(&mut output).unwrap().rewritten.is_empty());
+}
+
+#[test]
+fn passthrough_only_rewrites_replaced_entries() {
+    let doc = clean_fixture().open();
+    let (output, rewritten) = save_with_title(&doc, "<dc:title>Fixture", "<dc:title>Changed");
+
+    assert_eq!(vec!["OEBPS/content.opf".to_string()], rewritten);
+    let saved = EpubDoc::from_reader(Cursor::new(output)).unwrap();
+    assert_eq!(Some("Changed"), saved.mdata("title"));
+}
+
+#[test]
+fn writer_adds_new_entries() {
+    let doc = clean_fixture().open();
+
+    let mut output = Cursor::new(Vec::new());
+    let report = doc
+        .writer()
+        .replace("OEBPS/extra.css", "p {}")
+        .save(&mut output)
+        .unwrap();
+    assert_eq!(vec!["OEBPS/extra.css".to_string()], report.rewritten);
+
+    output.set_position(0);
+    let saved = EpubDoc::from_reader(output).unwrap();
+    assert_eq!(
+        "p {}",
+        saved.get_resource_str_by_path("OEBPS/extra.css").unwrap()
+    );
+}
+
+#[test]
+fn recompressed_save() {
+    let doc = EpubDoc::new("test.epub").unwrap();
+
+    let mut output = Cursor::new(Vec::new());
+    let report = doc.writer().passthrough(false).save(&mut output).unwrap();
+    let layout = doc.archive_layout().unwrap();
+    assert_eq!(layout.len(), report.rewritten.len());
+    assert_eq!("mimetype", report.rewritten[0]);
+
+    output.set_position(0);
+    let saved = EpubDoc::from_reader(output).unwrap();
+    assert_eq!(doc.spine(), saved.spine());
+    assert_eq!(doc.get_cover(), saved.get_cover());
+
+    let saved_layout = saved.archive_layout().unwrap();
+    let mimetype = saved_layout.iter().find(|e| e.name == "mimetype").unwrap();
+    assert_eq!(0, mimetype.header_offset);
+}